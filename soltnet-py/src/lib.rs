@@ -0,0 +1,67 @@
+//! Python module over [`soltnet::embed`]. Arguments and results are JSON
+//! strings, as in `embed`; errors are raised as `ValueError`.
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+use soltnet::embed;
+
+fn py_result(result: anyhow::Result<String>) -> PyResult<String> {
+    result.map_err(|err| PyValueError::new_err(format!("{err:#}")))
+}
+
+/// Packs a `data` value of a template with `params` (a JSON array of
+/// strings); returns the bytes as a JSON hex string.
+#[pyfunction]
+#[pyo3(signature = (data, params = "[]"))]
+fn pack(data: &str, params: &str) -> PyResult<String> {
+    py_result(embed::pack(data, params))
+}
+
+/// Decodes `0x` hex `data` with a data `schema`; returns the filled-in schema.
+#[pyfunction]
+fn unpack(data: &str, schema: &str) -> PyResult<String> {
+    py_result(embed::unpack(data, schema))
+}
+
+/// Resolves every transaction of a template or batch.
+#[pyfunction]
+#[pyo3(signature = (template, params = "[]"))]
+fn parse_template(template: &str, params: &str) -> PyResult<String> {
+    py_result(embed::parse_template(template, params))
+}
+
+/// Fetches transaction `signature` from `source` and returns it as a template.
+#[pyfunction]
+fn parse_tx(py: Python<'_>, source: &str, signature: &str) -> PyResult<String> {
+    py_result(py.detach(|| embed::parse_tx(source, signature)))
+}
+
+/// Parses a raw transaction or block saved by `dump-tx`/`dump-block`.
+#[pyfunction]
+fn parse_raw(path: &str) -> PyResult<String> {
+    py_result(embed::parse_raw(path))
+}
+
+/// Dumps account `address` of `source` into the directory `to_path`.
+#[pyfunction]
+fn dump_account(py: Python<'_>, source: &str, address: &str, to_path: &str) -> PyResult<String> {
+    py_result(py.detach(|| embed::dump_account(source, address, to_path)))
+}
+
+/// Executes a single-transaction template on `target`.
+#[pyfunction]
+#[pyo3(signature = (target, template, params = "[]"))]
+fn exec_tx(py: Python<'_>, target: &str, template: &str, params: &str) -> PyResult<String> {
+    py_result(py.detach(|| embed::exec_tx(target, template, params)))
+}
+
+#[pymodule]
+fn soltnet_py(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(pack, module)?)?;
+    module.add_function(wrap_pyfunction!(unpack, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_template, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_tx, module)?)?;
+    module.add_function(wrap_pyfunction!(parse_raw, module)?)?;
+    module.add_function(wrap_pyfunction!(dump_account, module)?)?;
+    module.add_function(wrap_pyfunction!(exec_tx, module)?)?;
+    Ok(())
+}
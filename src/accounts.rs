@@ -5,16 +5,91 @@ pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey =
 pub const SYSTEM_PROGRAM_ID: Pubkey = Pubkey::from_str_const("11111111111111111111111111111111");
 pub const TOKEN_PROGRAM_ID: Pubkey =
     Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
-#[allow(dead_code)]
 pub const TOKEN_2022_PROGRAM_ID: Pubkey =
     Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
 pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
     Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
-#[allow(dead_code)]
+/// Mint of wrapped SOL; closing its token accounts returns the SOL.
+pub const NATIVE_MINT_ID: Pubkey =
+    Pubkey::from_str_const("So11111111111111111111111111111111111111112");
 pub const LOOKUP_TABLE_PROGRAM_ID: Pubkey =
     Pubkey::from_str_const("AddressLookupTab1e1111111111111111111111111");
 
-#[allow(dead_code)]
+pub const VOTE_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("Vote111111111111111111111111111111111111111");
+pub const STAKE_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("Stake11111111111111111111111111111111111111");
+pub const METADATA_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+pub const BUBBLEGUM_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("BGUMAp9Gq7iTEuizy4pqaxsTyUCBK68MDfK752saRPUY");
+pub const SPL_ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+pub const SPL_NOOP_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+pub const MPL_ACCOUNT_COMPRESSION_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("mcmt6YrQEMKw8Mw43FmpRLmf7BqRnFMKmAcbxE3xkAW");
+pub const MPL_NOOP_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("mnoopTCrg4p8ry25e4bcWA9XZjbNjMTfgYVGGEdRsf3");
+pub const RENT_SYSVAR_ID: Pubkey =
+    Pubkey::from_str_const("SysvarRent111111111111111111111111111111111");
+
+/// Sysvars by the names templates use for them.
+pub const SYSVARS: [(&str, Pubkey); 12] = [
+    (
+        "clock",
+        Pubkey::from_str_const("SysvarC1ock11111111111111111111111111111111"),
+    ),
+    (
+        "epoch_rewards",
+        Pubkey::from_str_const("SysvarEpochRewards1111111111111111111111111"),
+    ),
+    (
+        "epoch_schedule",
+        Pubkey::from_str_const("SysvarEpochSchedu1e111111111111111111111111"),
+    ),
+    (
+        "fees",
+        Pubkey::from_str_const("SysvarFees111111111111111111111111111111111"),
+    ),
+    (
+        "instructions",
+        Pubkey::from_str_const("Sysvar1nstructions1111111111111111111111111"),
+    ),
+    (
+        "last_restart_slot",
+        Pubkey::from_str_const("SysvarLastRestartS1ot1111111111111111111111"),
+    ),
+    (
+        "recent_blockhashes",
+        Pubkey::from_str_const("SysvarRecentB1ockHashes11111111111111111111"),
+    ),
+    ("rent", RENT_SYSVAR_ID),
+    (
+        "rewards",
+        Pubkey::from_str_const("SysvarRewards111111111111111111111111111111"),
+    ),
+    (
+        "slot_hashes",
+        Pubkey::from_str_const("SysvarS1otHashes111111111111111111111111111"),
+    ),
+    (
+        "slot_history",
+        Pubkey::from_str_const("SysvarS1otHistory11111111111111111111111111"),
+    ),
+    (
+        "stake_history",
+        Pubkey::from_str_const("SysvarStakeHistory1111111111111111111111111"),
+    ),
+];
+
+pub fn sysvar_id(name: &str) -> Option<Pubkey> {
+    SYSVARS
+        .iter()
+        .find(|(sysvar, _)| *sysvar == name)
+        .map(|(_, id)| *id)
+}
+
 pub const NATIVE_PROGRAMS: [Pubkey; 6] = [
     COMPUTE_BUDGET_PROGRAM_ID,
     SYSTEM_PROGRAM_ID,
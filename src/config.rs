@@ -1,16 +1,41 @@
 use std::{
-    collections::HashMap,
-    fs,
+    collections::{BTreeMap, HashMap},
+    fs, io,
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    str::FromStr,
 };
 
 use anyhow::{Context, Result, anyhow};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signer::Signer};
+
+use crate::tools::dump::read_manifest;
+use crate::tools::output::open_input;
+use crate::tx_format::json_tx::parse_keypair;
 
 const CONFIG_DEPLOY: &str = "deploy.sh";
 const CONFIG_DOCKERFILE: &str = "Dockerfile.testnet";
 const CONFIG_DOCKERCOMPOSE: &str = "docker-compose.yml";
+const CONFIG_OVERRIDES: &str = "overrides.json";
+const CONFIG_SYSVARS: &str = "sysvars.json";
+/// Upgrade authority of overridden programs unless one is given
+pub const NO_UPGRADE_AUTHORITY: &str = "none";
+
+/// Local build loaded at a program's address instead of its dumped ELF.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProgramOverride {
+    pub so: PathBuf,
+    pub upgrade_authority: String,
+}
+
+/// Sysvar parameters the validator starts with instead of its defaults.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidatorSysvars {
+    /// Epoch length (`--slots-per-epoch`, disables warmup epochs)
+    pub slots_per_epoch: Option<u64>,
+}
 
 fn repo_root() -> PathBuf {
     Path::new(env!("CARGO_MANIFEST_DIR"))
@@ -35,6 +60,48 @@ fn test_ledger_path() -> PathBuf {
     container_path().join("test-ledger")
 }
 
+fn faucet_keypair_path() -> PathBuf {
+    test_ledger_path().join("faucet-keypair.json")
+}
+
+/// Keypair configured for the Solana CLI (`keypair_path` in its config),
+/// falling back to `~/.config/solana/id.json`.
+fn cli_keypair_path() -> Option<PathBuf> {
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let solana_config = home.join(".config/solana");
+    let configured = fs::read_to_string(solana_config.join("cli/config.yml"))
+        .ok()
+        .and_then(|config| {
+            config
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("keypair_path:"))
+                .map(|path| PathBuf::from(path.trim().trim_matches(['\'', '"'])))
+        });
+    Some(configured.unwrap_or_else(|| solana_config.join("id.json")))
+}
+
+/// Values of the `$local:` template variables: `payer`/`payer_keypair` from
+/// `payer` (the profile keypair) or the Solana CLI keypair,
+/// `faucet`/`faucet_keypair` from the local validator ledger and `rpc` for
+/// the target RPC URL. Keypairs that cannot be read are left out.
+pub fn local_vars(rpc_url: &str, payer: Option<PathBuf>) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+    vars.insert("rpc".to_string(), rpc_url.to_string());
+    let keypairs = [
+        ("payer", payer.or_else(cli_keypair_path)),
+        ("faucet", Some(faucet_keypair_path())),
+    ];
+    for (name, path) in keypairs {
+        let Some(path) = path else { continue };
+        let path = path.display().to_string();
+        if let Ok(keypair) = parse_keypair(&serde_json::Value::String(path.clone()), &[]) {
+            vars.insert(name.to_string(), keypair.pubkey().to_string());
+            vars.insert(format!("{name}_keypair"), path);
+        }
+    }
+    vars
+}
+
 fn load_template(name: &str) -> Result<String> {
     let path = template_path().join(name);
     fs::read_to_string(&path).with_context(|| format!("failed to read template {path:?}"))
@@ -49,7 +116,7 @@ fn render_template(template: &str, values: &HashMap<String, String>) -> Result<S
 }
 
 fn write_testnet_config(name: &str, content: &str) -> Result<()> {
-    println!("Update {name} config file");
+    say!("Update {name} config file");
     let path = container_path().join(name);
     fs::write(&path, content.trim()).with_context(|| format!("failed to write {path:?}"))
 }
@@ -71,7 +138,7 @@ fn docker_command(args: &[&str]) -> Result<()> {
 }
 
 pub fn stop_testnet_container() -> Result<()> {
-    println!("Stopping testnet container...");
+    say!("Stopping testnet container...");
     let compose_path = container_path().join(CONFIG_DOCKERCOMPOSE);
     docker_command(&["compose", "-f", &compose_path.to_string_lossy(), "down"])?;
     let _ = fs::remove_dir_all(test_ledger_path());
@@ -79,7 +146,7 @@ pub fn stop_testnet_container() -> Result<()> {
 }
 
 pub fn start_testnet_container() -> Result<()> {
-    println!("Starting testnet container...");
+    say!("Starting testnet container...");
     let compose_path = container_path().join(CONFIG_DOCKERCOMPOSE);
     docker_command(&[
         "compose",
@@ -92,6 +159,186 @@ pub fn start_testnet_container() -> Result<()> {
     Ok(())
 }
 
+/// Address and kind (`so` or `json`) of a dumped file, looking through a
+/// `.gz`/`.zst` compression suffix.
+fn dumped_file(path: &Path) -> Option<(String, &'static str)> {
+    let name = path.file_name()?.to_str()?;
+    let name = name
+        .strip_suffix(".gz")
+        .or_else(|| name.strip_suffix(".zst"))
+        .unwrap_or(name);
+    let (stem, extension) = name.rsplit_once('.')?;
+    let kind = match extension {
+        "so" => "so",
+        "json" => "json",
+        _ => return None,
+    };
+    Some((stem.to_string(), kind))
+}
+
+/// Copies a dumped file into the config, decompressing it if needed.
+fn copy_dumped(source: &Path, target: &Path) -> Result<()> {
+    let mut reader = open_input(source)?;
+    let mut file =
+        fs::File::create(target).with_context(|| format!("failed to create {target:?}"))?;
+    io::copy(&mut reader, &mut file).with_context(|| format!("failed to copy {source:?}"))?;
+    Ok(())
+}
+
+/// Lists `(address, file)` pairs of a dump directory, following the manifest
+/// of a typed layout or scanning `.so`/`.json` files of a flat one.
+fn dumped_files(input_path: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if let Some(manifest) = read_manifest(input_path)? {
+        return Ok(manifest
+            .into_iter()
+            .map(|(address, path)| (address, input_path.join(path)))
+            .collect());
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(input_path)? {
+        let path = entry?.path();
+        if let Some((address, _)) = dumped_file(&path) {
+            files.push((address, path));
+        }
+    }
+    Ok(files)
+}
+
+fn overrides_path() -> PathBuf {
+    container_path().join(CONFIG_OVERRIDES)
+}
+
+fn load_overrides() -> Result<BTreeMap<String, ProgramOverride>> {
+    let path = overrides_path();
+    match fs::read_to_string(&path) {
+        Ok(data) => {
+            serde_json::from_str(&data).with_context(|| format!("invalid JSON in {path:?}"))
+        }
+        Err(_) => Ok(BTreeMap::new()),
+    }
+}
+
+fn sysvars_path() -> PathBuf {
+    container_path().join(CONFIG_SYSVARS)
+}
+
+pub fn load_validator_sysvars() -> Result<ValidatorSysvars> {
+    let path = sysvars_path();
+    match fs::read_to_string(&path) {
+        Ok(data) => {
+            serde_json::from_str(&data).with_context(|| format!("invalid JSON in {path:?}"))
+        }
+        Err(_) => Ok(ValidatorSysvars::default()),
+    }
+}
+
+/// `solana-test-validator` flags loading `programs` and `accounts` from
+/// `./accounts`; overridden programs are deployed with the upgradeable
+/// loader, like programs cloned from mainnet, and replace any dumped account
+/// at their address.
+fn validator_flags(
+    programs: &[String],
+    accounts: &[String],
+    overrides: &BTreeMap<String, ProgramOverride>,
+    sysvars: &ValidatorSysvars,
+) -> String {
+    let mut flags = String::new();
+    if let Some(slots) = sysvars.slots_per_epoch {
+        flags.push_str(&format!("\\\n\t--slots-per-epoch {slots} "));
+    }
+    for addr in programs {
+        match overrides.get(addr) {
+            Some(program) => flags.push_str(&format!(
+                "\\\n\t--upgradeable-program {addr} ./accounts/{addr}.so {} ",
+                program.upgrade_authority
+            )),
+            None => flags.push_str(&format!("\\\n\t--bpf-program {addr} ./accounts/{addr}.so ")),
+        }
+    }
+    for addr in accounts
+        .iter()
+        .filter(|addr| !overrides.contains_key(*addr))
+    {
+        flags.push_str(&format!("\\\n\t--account {addr} ./accounts/{addr}.json "));
+    }
+    flags
+}
+
+/// Copies the local builds of overridden programs into `accounts_dir`.
+fn apply_overrides(
+    accounts_dir: &Path,
+    overrides: &BTreeMap<String, ProgramOverride>,
+    programs: &mut Vec<String>,
+) -> Result<()> {
+    for (address, program) in overrides {
+        say!("Overriding program {address} with {}", program.so.display());
+        fs::copy(&program.so, accounts_dir.join(format!("{address}.so")))
+            .with_context(|| format!("failed to copy {:?}", program.so))?;
+        if !programs.contains(address) {
+            programs.push(address.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Loads `so` at `address` in the testnet config, replacing the dumped
+/// program; the override is kept when the config is loaded again.
+pub fn override_program(address: &str, so: &Path, upgrade_authority: &str) -> Result<()> {
+    Pubkey::from_str(address).map_err(|_| anyhow!("Invalid program id: {address}"))?;
+    let so = fs::canonicalize(so).with_context(|| format!("failed to read {so:?}"))?;
+    let mut overrides = load_overrides()?;
+    overrides.insert(
+        address.to_string(),
+        ProgramOverride {
+            so,
+            upgrade_authority: upgrade_authority.to_string(),
+        },
+    );
+    fs::create_dir_all(container_path())?;
+    fs::write(overrides_path(), serde_json::to_string_pretty(&overrides)?)?;
+    rewrite_deploy_script(&overrides)?;
+    say!("Restart the testnet to load the new program");
+    Ok(())
+}
+
+/// Starts the validator with `sysvars`, keeping the loaded accounts.
+pub fn set_validator_sysvars(sysvars: &ValidatorSysvars) -> Result<()> {
+    fs::create_dir_all(container_path())?;
+    fs::write(sysvars_path(), serde_json::to_string_pretty(sysvars)?)?;
+    rewrite_deploy_script(&load_overrides()?)
+}
+
+/// Writes the deploy script again for the accounts already in the config.
+fn rewrite_deploy_script(overrides: &BTreeMap<String, ProgramOverride>) -> Result<()> {
+    let accounts_dir = accounts_path();
+    fs::create_dir_all(&accounts_dir)?;
+    let mut programs = Vec::new();
+    let mut accounts = Vec::new();
+    for (address, path) in dumped_files(&accounts_dir)? {
+        match dumped_file(&path).map(|(_, kind)| kind) {
+            Some("so") => programs.push(address),
+            _ => accounts.push(address),
+        }
+    }
+    apply_overrides(&accounts_dir, overrides, &mut programs)?;
+    write_deploy_script(&programs, &accounts, overrides)
+}
+
+fn write_deploy_script(
+    programs: &[String],
+    accounts: &[String],
+    overrides: &BTreeMap<String, ProgramOverride>,
+) -> Result<()> {
+    let deploy_template = load_template("deploy.sh.template")?;
+    let mut values = HashMap::new();
+    values.insert(
+        "FLAGS".to_string(),
+        validator_flags(programs, accounts, overrides, &load_validator_sysvars()?),
+    );
+    write_testnet_config(CONFIG_DEPLOY, &render_template(&deploy_template, &values)?)
+}
+
 pub fn set_testnet_config(accounts_path_input: Option<&Path>) -> Result<()> {
     let accounts_dir = accounts_path();
     let container_dir = container_path();
@@ -109,59 +356,34 @@ pub fn set_testnet_config(accounts_path_input: Option<&Path>) -> Result<()> {
     let mut accounts = Vec::new();
 
     if let Some(input_path) = accounts_path_input {
-        for entry in fs::read_dir(input_path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if let Some(ext) = path.extension().and_then(|v| v.to_str()) {
-                if ext == "so" || ext == "json" {
-                    let stem = path
-                        .file_stem()
-                        .and_then(|v| v.to_str())
-                        .unwrap_or_default();
-                    if ext == "so" {
-                        programs.push(stem.to_string());
-                    } else {
-                        accounts.push(stem.to_string());
-                    }
+        for (address, source) in dumped_files(input_path)? {
+            match dumped_file(&source).map(|(_, kind)| kind) {
+                Some("so") => {
+                    say!("Copying program {address}");
+                    copy_dumped(&source, &accounts_dir.join(format!("{address}.so")))?;
+                    programs.push(address);
+                }
+                Some("json") => {
+                    say!("Copying account {address}");
+                    copy_dumped(&source, &accounts_dir.join(format!("{address}.json")))?;
+                    accounts.push(address);
                 }
+                _ => eprintln!("Skipping {address}: unsupported file {source:?}"),
             }
         }
-
-        for program in &programs {
-            println!("Copying program {program}");
-            fs::copy(
-                input_path.join(format!("{program}.so")),
-                accounts_dir.join(format!("{program}.so")),
-            )?;
-        }
-
-        for account in &accounts {
-            println!("Copying account {account}");
-            fs::copy(
-                input_path.join(format!("{account}.json")),
-                accounts_dir.join(format!("{account}.json")),
-            )?;
-        }
     }
 
-    let program_flags: Vec<String> = programs
-        .iter()
-        .map(|addr| format!("\\\n\t--bpf-program {addr} ./accounts/{addr}.so "))
-        .collect();
-    let account_flags: Vec<String> = accounts
-        .iter()
-        .map(|addr| format!("\\\n\t--account {addr} ./accounts/{addr}.json "))
-        .collect();
-
-    let mut all_flags = Vec::new();
-    all_flags.extend(program_flags);
-    all_flags.extend(account_flags);
-
-    let deploy_template = load_template("deploy.sh.template")?;
-    let flags_rendered = all_flags.join("");
-    let mut values = HashMap::new();
-    values.insert("FLAGS".to_string(), flags_rendered);
-    write_testnet_config(CONFIG_DEPLOY, &render_template(&deploy_template, &values)?)?;
+    // Clearing the config also drops program overrides and sysvar settings.
+    let overrides = match accounts_path_input {
+        Some(_) => load_overrides()?,
+        None => {
+            let _ = fs::remove_file(overrides_path());
+            let _ = fs::remove_file(sysvars_path());
+            BTreeMap::new()
+        }
+    };
+    apply_overrides(&accounts_dir, &overrides, &mut programs)?;
+    write_deploy_script(&programs, &accounts, &overrides)?;
 
     let dockerfile_template = load_template("Dockerfile.testnet.template")?;
     write_testnet_config(CONFIG_DOCKERFILE, &dockerfile_template)?;
@@ -171,3 +393,51 @@ pub fn set_testnet_config(accounts_path_input: Option<&Path>) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    };
+
+    use super::{
+        NO_UPGRADE_AUTHORITY, ProgramOverride, ValidatorSysvars, dumped_file, validator_flags,
+    };
+
+    #[test]
+    fn dumped_files_look_through_compression() {
+        let kind = |name: &str| dumped_file(Path::new(name));
+        assert_eq!(kind("dir/Acc.json"), Some(("Acc".to_string(), "json")));
+        assert_eq!(kind("Acc.json.gz"), Some(("Acc".to_string(), "json")));
+        assert_eq!(kind("Prog.so.zst"), Some(("Prog".to_string(), "so")));
+        assert_eq!(kind("accounts.jsonl"), None);
+        assert_eq!(kind("notes.txt.gz"), None);
+    }
+
+    #[test]
+    fn overridden_programs_use_the_upgradeable_loader() {
+        let overrides = BTreeMap::from([(
+            "Prog".to_string(),
+            ProgramOverride {
+                so: PathBuf::from("/build/prog.so"),
+                upgrade_authority: NO_UPGRADE_AUTHORITY.to_string(),
+            },
+        )]);
+        let flags = validator_flags(
+            &["Prog".to_string(), "Other".to_string()],
+            &["Prog".to_string(), "Acc".to_string()],
+            &overrides,
+            &ValidatorSysvars {
+                slots_per_epoch: Some(432_000),
+            },
+        );
+        assert_eq!(
+            flags,
+            "\\\n\t--slots-per-epoch 432000 \
+             \\\n\t--upgradeable-program Prog ./accounts/Prog.so none \
+             \\\n\t--bpf-program Other ./accounts/Other.so \
+             \\\n\t--account Acc ./accounts/Acc.json "
+        );
+    }
+}
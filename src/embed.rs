@@ -0,0 +1,192 @@
+//! String-in, string-out entry points for driving the template engine from
+//! other languages: arguments and results are JSON and byte buffers are `0x`
+//! hex strings, so bindings only have to pass strings around. The commands
+//! behind them print nothing: the host's stdout is not ours to write to.
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use solana_sdk::signer::Signer;
+
+use crate::tx_format::{
+    data_format::{pack_data, unpack_data},
+    json_tx::{parse_tx_from_json, raw_txs_from_value},
+};
+#[cfg(feature = "cli")]
+use crate::{
+    network::NetworkCtx,
+    tools::{
+        dump,
+        json_output::set_quiet,
+        output::OutputOptions,
+        parse::{fetch_transaction, parse_raw_file},
+        tx::{ExecOptions, execute_json_transaction},
+    },
+    tx_format::parse_tx::parse_tx_to_json,
+};
+
+fn from_json<T: serde::de::DeserializeOwned>(text: &str, label: &str) -> Result<T> {
+    serde_json::from_str(text).with_context(|| format!("Invalid {label} JSON"))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Packs a `data` value of a template with `params` (a JSON array of
+/// strings); returns the bytes as a JSON hex string.
+pub fn pack(data: &str, params: &str) -> Result<String> {
+    let data: Value = from_json(data, "data")?;
+    let params: Vec<String> = from_json(params, "params")?;
+    Ok(json!(to_hex(&pack_data(&data, &params)?)).to_string())
+}
+
+/// Decodes hex `data` with a data `schema`; returns the filled-in schema.
+pub fn unpack(data: &str, schema: &str) -> Result<String> {
+    let bytes =
+        hex::decode(data.trim_start_matches("0x")).map_err(|_| anyhow!("Invalid hex data"))?;
+    let schema: Value = from_json(schema, "schema")?;
+    Ok(unpack_data(&bytes, &schema, 0)?.to_string())
+}
+
+/// Resolves every transaction of a template or batch: the instructions with
+/// their accounts and packed data, the signer pubkeys and lookup tables.
+pub fn parse_template(template: &str, params: &str) -> Result<String> {
+    let params: Vec<String> = from_json(params, "params")?;
+    let mut resolved = Vec::new();
+    for raw in raw_txs_from_value(from_json(template, "template")?)? {
+        let parsed = parse_tx_from_json(&raw, &params)?;
+        let instructions: Vec<Value> = parsed
+            .instructions
+            .iter()
+            .map(|ix| {
+                let accounts: Vec<Value> = ix
+                    .accounts
+                    .iter()
+                    .map(|acc| {
+                        json!({
+                            "pubkey": acc.pubkey.to_string(),
+                            "is_signer": acc.is_signer,
+                            "is_writable": acc.is_writable,
+                        })
+                    })
+                    .collect();
+                json!({
+                    "program_id": ix.program_id.to_string(),
+                    "accounts": accounts,
+                    "data": to_hex(&ix.data),
+                })
+            })
+            .collect();
+        let signers: Vec<String> = parsed
+            .signers
+            .iter()
+            .map(|signer| signer.pubkey().to_string())
+            .collect();
+        let lookup_tables: Vec<String> = parsed
+            .lookup_tables
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        resolved.push(json!({
+            "instructions": instructions,
+            "signers": signers,
+            "lookup_tables": lookup_tables,
+        }));
+    }
+    Ok(Value::Array(resolved).to_string())
+}
+
+/// Fetches transaction `signature` from `source` and returns it as a
+/// template, as `parse-tx` writes it.
+#[cfg(feature = "cli")]
+pub fn parse_tx(source: &str, signature: &str) -> Result<String> {
+    set_quiet(true);
+    let network = NetworkCtx::new(source, source);
+    let tx = fetch_transaction(&network, signature)?;
+    Ok(parse_tx_to_json(&tx)?.to_string())
+}
+
+/// Parses a raw transaction or block saved by `dump-tx`/`dump-block`.
+#[cfg(feature = "cli")]
+pub fn parse_raw(path: &str) -> Result<String> {
+    set_quiet(true);
+    Ok(parse_raw_file(path)?.to_string())
+}
+
+/// Dumps account `address` of `source` into the directory `to_path`, as
+/// `dump` writes it; returns the directory.
+#[cfg(feature = "cli")]
+pub fn dump_account(source: &str, address: &str, to_path: &str) -> Result<String> {
+    set_quiet(true);
+    let network = NetworkCtx::new(source, source);
+    dump::dump_account(&network, address, to_path, OutputOptions::default())?;
+    Ok(json!({ "path": to_path }).to_string())
+}
+
+/// Executes a single-transaction template on `target`, paid by its first
+/// signer; returns the slot, blockhash, latency and compute units.
+#[cfg(feature = "cli")]
+pub fn exec_tx(target: &str, template: &str, params: &str) -> Result<String> {
+    set_quiet(true);
+    let params: Vec<String> = from_json(params, "params")?;
+    let batch = raw_txs_from_value(from_json(template, "template")?)?;
+    let [raw] = batch.as_slice() else {
+        return Err(anyhow!(
+            "exec_tx runs a single transaction, got {}",
+            batch.len()
+        ));
+    };
+    let parsed = parse_tx_from_json(raw, &params)?;
+    let network = NetworkCtx::new(target, target);
+    let report = execute_json_transaction(&network, parsed, &ExecOptions::default())?;
+    Ok(json!({
+        "slot": report.slot,
+        "blockhash": report.blockhash.to_string(),
+        "latency_ms": report.latency.as_millis() as u64,
+        "compute_units": report.compute_units,
+    })
+    .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::{pack, parse_template, unpack};
+
+    #[test]
+    fn packs_and_parses_through_json_strings() {
+        let data = json!({ "type": "object", "data": [
+            { "type": "u8", "data": 2 },
+            { "type": "u64", "data": "$1" }
+        ]});
+        let packed = pack(&data.to_string(), r#"["5"]"#).unwrap();
+        assert_eq!(packed, r#""0x020500000000000000""#);
+        let unpacked: Value = serde_json::from_str(
+            &unpack(
+                "0x0205000000",
+                &json!([{ "type": "u8" }, { "type": "u32" }]).to_string(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(unpacked[1]["data"], json!(5));
+
+        let template = json!({
+            "instructions": [{
+                "program_id": "11111111111111111111111111111111",
+                "accounts": [{ "pubkey": "$1", "is_writable": true }],
+                "data": "0x01"
+            }],
+            "signers": []
+        });
+        let account = solana_sdk::pubkey::Pubkey::new_unique().to_string();
+        let params = json!([account]).to_string();
+        let parsed: Value =
+            serde_json::from_str(&parse_template(&template.to_string(), &params).unwrap()).unwrap();
+        let ix = &parsed[0]["instructions"][0];
+        assert_eq!(ix["accounts"][0]["pubkey"], json!(account));
+        assert_eq!(ix["data"], json!("0x01"));
+        assert!(pack("{", "[]").is_err());
+    }
+}
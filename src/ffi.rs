@@ -0,0 +1,199 @@
+//! C ABI over [`crate::embed`]: every function takes a JSON request and
+//! returns a JSON response, `{"ok": <result>}` or `{"error": "<message>"}`,
+//! which the caller releases with [`soltnet_free_string`]. Panics are caught
+//! and reported as errors, as unwinding into the caller is undefined behavior.
+
+use std::{
+    any::Any,
+    ffi::{CStr, CString, c_char},
+    panic::{AssertUnwindSafe, catch_unwind},
+};
+
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::embed;
+
+/// String field `key` of the request.
+fn text(request: &Value, key: &str) -> Result<String> {
+    request
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Missing {key}"))
+}
+
+/// Field `key` of the request as JSON text.
+fn value(request: &Value, key: &str) -> Result<String> {
+    request
+        .get(key)
+        .filter(|value| !value.is_null())
+        .map(Value::to_string)
+        .ok_or_else(|| anyhow!("Missing {key}"))
+}
+
+/// `params` of the request as JSON, `[]` if absent.
+fn params(request: &Value) -> String {
+    request
+        .get("params")
+        .map_or_else(|| "[]".to_string(), Value::to_string)
+}
+
+fn response(result: Result<String>) -> *mut c_char {
+    let response = match result.and_then(|text| Ok(serde_json::from_str::<Value>(&text)?)) {
+        Ok(value) => json!({ "ok": value }),
+        Err(err) => json!({ "error": format!("{err:#}") }),
+    };
+    // JSON escapes control characters, so the text has no interior NUL.
+    CString::new(response.to_string())
+        .unwrap_or_default()
+        .into_raw()
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Reads the request and runs `handler` on it.
+///
+/// # Safety
+/// `request` must be null or a NUL-terminated string.
+unsafe fn handle(
+    request: *const c_char,
+    handler: impl FnOnce(&Value) -> Result<String>,
+) -> *mut c_char {
+    if request.is_null() {
+        return response(Err(anyhow!("Request is null")));
+    }
+    let body = unsafe { CStr::from_ptr(request) }.to_string_lossy();
+    let result = serde_json::from_str::<Value>(&body)
+        .map_err(|err| anyhow!("Invalid request JSON: {err}"))
+        .and_then(|request| {
+            catch_unwind(AssertUnwindSafe(|| handler(&request)))
+                .unwrap_or_else(|payload| Err(anyhow!("Panic: {}", panic_message(payload))))
+        });
+    response(result)
+}
+
+/// `{"data": <data value>, "params": [...]}` → the packed bytes as `0x` hex.
+///
+/// # Safety
+/// `request` must be null or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn soltnet_pack(request: *const c_char) -> *mut c_char {
+    unsafe {
+        handle(request, |req| {
+            embed::pack(&value(req, "data")?, &params(req))
+        })
+    }
+}
+
+/// `{"data": "0x...", "schema": <schema>}` → the decoded schema.
+///
+/// # Safety
+/// `request` must be null or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn soltnet_unpack(request: *const c_char) -> *mut c_char {
+    unsafe {
+        handle(request, |req| {
+            embed::unpack(&text(req, "data")?, &value(req, "schema")?)
+        })
+    }
+}
+
+/// `{"template": <template>, "params": [...]}` → the resolved transactions.
+///
+/// # Safety
+/// `request` must be null or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn soltnet_parse_template(request: *const c_char) -> *mut c_char {
+    unsafe {
+        handle(request, |req| {
+            embed::parse_template(&value(req, "template")?, &params(req))
+        })
+    }
+}
+
+/// `{"source": "mainnet", "signature": "..."}` → the transaction as a template.
+///
+/// # Safety
+/// `request` must be null or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn soltnet_parse_tx(request: *const c_char) -> *mut c_char {
+    unsafe {
+        handle(request, |req| {
+            embed::parse_tx(&text(req, "source")?, &text(req, "signature")?)
+        })
+    }
+}
+
+/// `{"target": "local", "template": <template>, "params": [...]}` → the
+/// slot, blockhash, latency and compute units of the executed transaction.
+///
+/// # Safety
+/// `request` must be null or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn soltnet_exec_tx(request: *const c_char) -> *mut c_char {
+    unsafe {
+        handle(request, |req| {
+            embed::exec_tx(
+                &text(req, "target")?,
+                &value(req, "template")?,
+                &params(req),
+            )
+        })
+    }
+}
+
+/// Releases a response returned by the functions above.
+///
+/// # Safety
+/// `response` must be null or a pointer returned by a `soltnet_*` function,
+/// released only once.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn soltnet_free_string(response: *mut c_char) {
+    if !response.is_null() {
+        drop(unsafe { CString::from_raw(response) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+
+    use serde_json::{Value, json};
+
+    use super::{handle, soltnet_free_string, soltnet_pack};
+
+    fn call(request: &str) -> Value {
+        let request = CString::new(request).unwrap();
+        unsafe {
+            let response = soltnet_pack(request.as_ptr());
+            let value = serde_json::from_str(&CStr::from_ptr(response).to_string_lossy()).unwrap();
+            soltnet_free_string(response);
+            value
+        }
+    }
+
+    #[test]
+    fn wraps_results_and_errors() {
+        let request = json!({ "data": { "type": "u16", "data": "$1" }, "params": ["258"] });
+        assert_eq!(call(&request.to_string()), json!({ "ok": "0x0201" }));
+        let hex = json!({ "data": "0x0a0b" });
+        assert_eq!(call(&hex.to_string()), json!({ "ok": "0x0a0b" }));
+        let error = call(r#"{"params": []}"#);
+        assert_eq!(error, json!({ "error": "Missing data" }));
+        assert!(call("not json")["error"].is_string());
+
+        let request = CString::new("{}").unwrap();
+        let response = unsafe { handle(request.as_ptr(), |_| panic!("boom")) };
+        let value: Value =
+            serde_json::from_str(&unsafe { CStr::from_ptr(response) }.to_string_lossy()).unwrap();
+        unsafe { soltnet_free_string(response) };
+        assert_eq!(value, json!({ "error": "Panic: boom" }));
+    }
+}
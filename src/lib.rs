@@ -0,0 +1,37 @@
+/// `println!` for human-readable output; with `--output json` it goes to
+/// stderr so stdout only carries the JSON result.
+#[cfg(feature = "cli")]
+#[macro_export]
+macro_rules! say {
+    () => {
+        $crate::tools::json_output::say(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::tools::json_output::say(format_args!($($arg)*))
+    };
+}
+
+pub mod accounts;
+#[cfg(feature = "cli")]
+pub mod config;
+pub mod embed;
+#[cfg(feature = "soltnet-ffi")]
+pub mod ffi;
+#[cfg(feature = "cli")]
+pub mod network;
+#[cfg(feature = "cli")]
+pub mod packs;
+#[cfg(feature = "cli")]
+pub mod profiles;
+#[cfg(feature = "cli")]
+pub mod rpc;
+#[cfg(feature = "cli")]
+pub mod settings;
+#[cfg(feature = "cli")]
+pub mod state;
+#[cfg(feature = "cli")]
+pub mod testnet;
+#[cfg(feature = "cli")]
+pub mod tools;
+pub mod tx_format;
+pub mod utils;
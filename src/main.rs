@@ -1,34 +1,264 @@
-mod accounts;
-mod config;
-mod tools;
-mod tx_format;
-mod utils;
-
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
-use anyhow::{anyhow, Result};
-use clap::{Parser, Subcommand};
+use anyhow::{Result, anyhow};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::api::config::RpcSendTransactionConfig;
+use solana_sdk::{hash::Hash, signer::Signer};
 
-use crate::config::{set_testnet_config, start_testnet_container, stop_testnet_container};
-use crate::tools::{
+use soltnet::config::{
+    NO_UPGRADE_AUTHORITY, local_vars, override_program, set_testnet_config,
+    start_testnet_container, stop_testnet_container,
+};
+use soltnet::network::{Commitment, NetworkCtx, RpcPreset, headers_from_env};
+use soltnet::packs::{PackRegistry, install_pack, packs_dir, resolve_template_path, set_installed};
+use soltnet::profiles::{PROFILE_KEYS, Profile, UserConfig, config_path};
+use soltnet::rpc::{RpcMocks, RpcRecorder};
+use soltnet::say;
+use soltnet::settings::Settings;
+use soltnet::state::StateStore;
+use soltnet::tools::{
+    alt::{materialize_lookup_tables, wait_for_lookup_table},
+    batch::{BatchOptions, run_batch},
+    bench::{BenchOptions, bench},
+    bundle::{BundleSources, apply_bundle, create_bundle},
+    compute::{CuBaseline, check_cu_baseline},
     data_format::set_data_format,
+    diff::diff_templates,
     dump::{
-        dump_account, dump_accounts_for_tx, dump_accounts_from_tx, dump_raw_block,
-        dump_raw_transaction,
+        DumpSummary, cancel_on_interrupt, dump_account, dump_accounts_for_tx,
+        dump_accounts_from_tx, dump_raw_block, dump_raw_transaction,
     },
-    parse::{create_json_from_tx, parse_block},
+    errors::ErrorMap,
+    events::{EventRegistry, EventsOutput},
+    fmt::{Shorthands, format_template},
+    hooks::run_hooks,
+    json_output::{OutputFormat, finish, push, record, reserve_stdout, set_output_format},
+    log_assert::LogAssertion,
+    merge::merge_templates,
+    migrate::migrate_template_file,
+    monitor::monitor,
+    output::{Compression, KeyStyle, Layout, OutputOptions, Overwrite},
+    parse::{
+        BlockParseOptions, DetailLevel, ParseSource, create_json_from_tx, parse_block, verify_parse,
+    },
+    pool::pool_info,
+    progress::{STEP_STARTED, emit, enable_event_stream},
+    prompt::prompt_missing_params,
+    replay::replay_route,
+    report::{accounts_report, emit_alt},
+    send_many::send_sol_many,
+    show::show,
+    sign::{combine_signatures, sign_message, sign_transaction, verify_signature},
+    simulate::{AccountOverride, simulate_json_transaction},
+    slots::fetch_next_epoch_start,
+    split::split_template,
+    stake::{StakeAuthority, dump_stake_accounts},
+    stats::{run_periodic, run_repeated},
+    style::init_color,
+    sysvars::check_sysvars,
+    token::{TokenMetadata, create_token, mint_nft},
+    transfer_checks::TransferChecks,
     tx::{
-        airdrop_sol, close_ata, create_ata, create_lookup_table, execute_json_transaction,
+        ExecOptions, airdrop_sol, close_ata, create_ata, create_lookup_table, encode_transactions,
         get_balance, get_token_balance, send_sol,
     },
+    verify::verify_program,
+    wallet::{keygen, make_wallets},
+};
+use soltnet::tx_format::json_tx::{
+    ParsedTransaction, load_parsed_tx_from_json, load_raw_tx_from_json, load_raw_txs_from_json,
+    parse_keypair, parse_tx_from_json,
 };
-use crate::tx_format::json_tx::load_parsed_tx_from_json;
+use soltnet::tx_format::params::{param_from_json, set_local_vars, set_state_vars};
+use soltnet::tx_format::pubkey::{parse_pubkey, parse_pubkey_str};
+use soltnet::tx_format::strict::set_strict;
+use soltnet::utils::{is_stdio, parse_duration, parse_ui_amount};
 
 #[derive(Parser)]
 #[command(name = "soltnet", version, about = "Solana Testnet Tool")]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Cluster to read state from (mainnet, devnet, testnet, local or an RPC URL; default mainnet)
+    #[arg(long, global = true)]
+    source: Option<String>,
+    /// Cluster to execute transactions on (mainnet, devnet, testnet, local or an RPC URL; default local)
+    #[arg(long, global = true)]
+    target: Option<String>,
+    /// Single cluster for both reads and transactions; replaces `--source` and `--target`
+    #[arg(short = 'u', long, global = true, conflicts_with_all = ["source", "target"])]
+    url: Option<String>,
+    /// Request pacing for the source RPC provider (batch size, concurrency, delays)
+    #[arg(long, global = true, value_enum, default_value_t = RpcPreset::Public)]
+    rpc_preset: RpcPreset,
+    /// Extra RPC header as `Name: value`, e.g. an API key (repeatable; also read from SOLTNET_RPC_HEADERS)
+    #[arg(long = "header", global = true)]
+    headers: Vec<String>,
+    /// Commitment of balance and account reads (transaction/block reads use at least confirmed; default confirmed)
+    #[arg(long, global = true, value_enum)]
+    commitment: Option<Commitment>,
+    /// Profile of ~/.config/soltnet/config.toml to take defaults from (default: its active profile)
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Record every RPC request/response to a cassette file
+    #[arg(long, global = true, conflicts_with = "replay_rpc")]
+    record_rpc: Option<PathBuf>,
+    /// Answer RPC requests from a recorded cassette instead of the network
+    #[arg(long, global = true)]
+    replay_rpc: Option<PathBuf>,
+    /// Disable colored output (also disabled by `NO_COLOR` or when stdout is not a terminal)
+    #[arg(long, global = true)]
+    no_color: bool,
+    /// Emit newline-delimited JSON progress events on stderr
+    #[arg(long, global = true)]
+    event_stream: bool,
+    /// Reject templates with fields soltnet does not read, e.g. a misspelled `is_signer` (default when `CI` is set)
+    #[arg(long, global = true)]
+    strict_templates: bool,
+    /// Print results as one JSON object on stdout (human-readable text goes to stderr)
+    #[arg(long = "output", global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
+}
+
+/// `sendTransaction` flags of the exec commands
+#[derive(Args)]
+struct SendArgs {
+    /// Times the RPC node retries sending the transaction to the leader
+    #[arg(long)]
+    max_retries: Option<usize>,
+    /// Commitment the preflight simulation runs at (defaults to confirmed)
+    #[arg(long, value_enum, conflicts_with = "skip_preflight")]
+    preflight_commitment: Option<Commitment>,
+    /// Send without the preflight simulation, so a failing transaction lands and its logs can be read
+    #[arg(long)]
+    skip_preflight: bool,
+    /// Commitment to wait for before reading the transaction back (defaults to confirmed)
+    #[arg(long, value_enum)]
+    confirm_commitment: Option<Commitment>,
+    /// Reject the send if the RPC node has not reached this slot
+    #[arg(long)]
+    min_context_slot: Option<u64>,
+}
+
+impl SendArgs {
+    /// Send config of these flags, falling back to the profile's defaults.
+    fn config(&self, profile: &Profile) -> RpcSendTransactionConfig {
+        RpcSendTransactionConfig {
+            skip_preflight: self.skip_preflight || profile.skip_preflight.unwrap_or_default(),
+            max_retries: self.max_retries.or(profile.max_retries),
+            preflight_commitment: self
+                .preflight_commitment
+                .map(|commitment| CommitmentConfig::from(commitment).commitment),
+            min_context_slot: self.min_context_slot,
+            ..Default::default()
+        }
+    }
+}
+
+/// Structured event output of exec-tx and parse-tx
+#[derive(Args)]
+struct EventsArgs {
+    /// Write the `Program data:` events of the transaction to this JSON file
+    #[arg(long)]
+    events: Option<PathBuf>,
+    /// Anchor IDL used to name and decode events (repeatable)
+    #[arg(long = "idl", requires = "events")]
+    idls: Vec<PathBuf>,
+}
+
+impl EventsArgs {
+    fn output(&self) -> Result<Option<EventsOutput>> {
+        let Some(path) = &self.events else {
+            return Ok(None);
+        };
+        Ok(Some(EventsOutput {
+            path: path.clone(),
+            registry: EventRegistry::load(&self.idls)?,
+        }))
+    }
+}
+
+/// Output flags shared by the dump and parse commands
+#[derive(Args)]
+struct OutputArgs {
+    /// Compress output with gzip (`.gz`)
+    #[arg(long, conflicts_with = "zstd")]
+    gzip: bool,
+    /// Compress output with zstd (`.zst`)
+    #[arg(long)]
+    zstd: bool,
+    /// Overwrite existing output files (the default; overrides an earlier --no-clobber)
+    #[arg(long, overrides_with = "no_clobber")]
+    force: bool,
+    /// Keep existing output files and skip writing them
+    #[arg(long, overrides_with = "force")]
+    no_clobber: bool,
+    /// Output file name, e.g. "{pubkey}_{slot}.json" (also {signature}, {timestamp})
+    #[arg(long)]
+    name_template: Option<String>,
+}
+
+impl OutputArgs {
+    fn options(&self, jsonl: bool) -> OutputOptions<'_> {
+        let compression = if self.gzip {
+            Compression::Gzip
+        } else if self.zstd {
+            Compression::Zstd
+        } else {
+            Compression::None
+        };
+        let overwrite = if self.no_clobber {
+            Overwrite::Skip
+        } else {
+            Overwrite::Force
+        };
+        OutputOptions {
+            jsonl,
+            compression,
+            overwrite,
+            name_template: self.name_template.as_deref(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Directory hooks of a template run in and resolve paths against; the
+/// current one for templates read from stdin or without a directory.
+fn template_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+/// Output directory of a dump or parse command; with `-` the output is
+/// written to stdout and progress text goes to stderr.
+fn output_target(output_path: Option<PathBuf>, output_dir: &Path) -> PathBuf {
+    let out = output_path.unwrap_or_else(|| output_dir.to_path_buf());
+    if is_stdio(&out) {
+        reserve_stdout();
+    }
+    out
+}
+
+fn check_dump_summary(summary: &DumpSummary, strict: bool) -> Result<()> {
+    summary.print();
+    if !summary.canceled.is_empty() {
+        return Err(anyhow!("Dump canceled"));
+    }
+    if strict && !summary.failed.is_empty() {
+        return Err(anyhow!(
+            "{} accounts could not be dumped",
+            summary.failed.len()
+        ));
+    }
+    Ok(())
 }
 
 #[derive(Subcommand)]
@@ -37,6 +267,20 @@ enum Commands {
     Load { accounts_path: PathBuf },
     /// Clear the local testnet configuration
     Clear,
+    /// Load a local build at a program's address in the testnet config, replacing the cloned program
+    OverrideProgram {
+        program_id: String,
+        so: PathBuf,
+        /// Upgrade authority of the overridden program
+        #[arg(long, default_value = NO_UPGRADE_AUTHORITY)]
+        upgrade_authority: String,
+    },
+    /// Compare the rent, epoch and fee sysvars of the source and the target
+    CheckSysvars {
+        /// Start the local testnet with the source's epoch schedule
+        #[arg(long)]
+        align: bool,
+    },
     /// Start the local testnet container
     Start,
     /// Stop the local testnet container
@@ -45,6 +289,117 @@ enum Commands {
     ExecTx {
         tx_json: PathBuf,
         params: Vec<String>,
+        /// Append a param read from a JSON file such as a parse-tx output, as `<file>:<path>` (repeatable)
+        #[arg(long = "params-from", value_name = "FILE:PATH")]
+        params_from: Vec<String>,
+        /// Fee payer keypair (file path or param), signs in addition to the template signers
+        #[arg(long)]
+        payer: Option<String>,
+        /// Error map or Anchor IDL used to decode custom program errors (repeatable)
+        #[arg(long = "error-map")]
+        error_maps: Vec<PathBuf>,
+        /// CU baseline file; records the template's CUs or checks them against it
+        #[arg(long)]
+        cu_baseline: Option<PathBuf>,
+        /// Allowed CU increase over the baseline, in percent
+        #[arg(long, default_value_t = 10.0)]
+        cu_tolerance: f64,
+        /// Only warn instead of failing when the CU baseline is exceeded
+        #[arg(long)]
+        cu_warn_only: bool,
+        /// Execute the template N times, each with a fresh blockhash
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        repeat: u64,
+        /// Print latency, slot and CU statistics for the runs
+        #[arg(long)]
+        stats: bool,
+        /// Log expectation `[!]<contains|regex|event>:<pattern>`, `!` forbids it (repeatable)
+        #[arg(long = "assert-log")]
+        log_assertions: Vec<LogAssertion>,
+        /// Hold the transaction until the target reaches this slot
+        #[arg(long, conflicts_with = "at_epoch_boundary")]
+        at_slot: Option<u64>,
+        /// Hold the transaction until the first slot of the next epoch
+        #[arg(long)]
+        at_epoch_boundary: bool,
+        /// Recreate lookup tables missing on the target with their addresses on the source
+        #[arg(long)]
+        auto_alt: bool,
+        /// Check SOL transfers before sending (recipient exists, sender stays rent exempt, recipient is no signer)
+        #[arg(long)]
+        transfer_checks: bool,
+        /// Allow transfers to accounts that do not exist yet
+        #[arg(long, requires = "transfer_checks")]
+        allow_unfunded_recipient: bool,
+        /// Burn the tokens left in token accounts the transaction closes instead of refusing to send it
+        #[arg(long)]
+        burn_remaining: bool,
+        /// Compute unit price in micro-lamports, set with a `set_cu_price` instruction
+        #[arg(long)]
+        priority_fee: Option<u64>,
+        /// Simulate the transaction on the target instead of sending it (no hooks, no state changes)
+        #[arg(long, conflicts_with_all = ["repeat", "stats", "cu_baseline", "at_slot", "at_epoch_boundary", "auto_alt"])]
+        simulate: bool,
+        /// Print the signed transaction in base64 instead of sending it (no hooks, no state changes)
+        #[arg(long, conflicts_with_all = ["repeat", "stats", "cu_baseline", "at_slot", "at_epoch_boundary", "auto_alt", "simulate"])]
+        encode_only: bool,
+        /// Also write the encoded transaction to this file, one per line for batch files
+        #[arg(long, value_name = "FILE", requires = "encode_only")]
+        encode_output: Option<PathBuf>,
+        /// Go on with the next transaction of a batch file after a failure
+        #[arg(long)]
+        keep_going: bool,
+        #[command(flatten)]
+        events: EventsArgs,
+        #[command(flatten)]
+        send: SendArgs,
+    },
+    /// Execute a template periodically (crank) and report aggregate results
+    Crank {
+        tx_json: PathBuf,
+        params: Vec<String>,
+        /// Period between runs, e.g. 500ms, 5s, 1m
+        #[arg(long, value_parser = parse_duration)]
+        every: Duration,
+        /// Number of runs; runs until interrupted when omitted
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        times: Option<u64>,
+        /// Fee payer keypair (file path or param), signs in addition to the template signers
+        #[arg(long)]
+        payer: Option<String>,
+        /// Error map or Anchor IDL used to decode custom program errors (repeatable)
+        #[arg(long = "error-map")]
+        error_maps: Vec<PathBuf>,
+    },
+    /// Simulate a template without sending it, optionally with overridden accounts
+    SimulateTx {
+        tx_json: PathBuf,
+        params: Vec<String>,
+        /// Fee payer keypair (file path or param), signs in addition to the template signers
+        #[arg(long)]
+        payer: Option<String>,
+        /// Error map or Anchor IDL used to decode custom program errors (repeatable)
+        #[arg(long = "error-map")]
+        error_maps: Vec<PathBuf>,
+        /// Account JSON (dump format) replacing the source state in a throwaway fork validator (repeatable)
+        #[arg(long = "override")]
+        overrides: Vec<PathBuf>,
+    },
+    /// Send a template at a target TPS from a pool of make-wallets wallets
+    Bench {
+        tx_json: PathBuf,
+        /// Wallet manifest written by make-wallets ($1 = pubkey, $2 = keypair path)
+        wallets_json: PathBuf,
+        params: Vec<String>,
+        /// Target transactions per second
+        #[arg(long, default_value_t = 10.0)]
+        tps: f64,
+        /// Benchmark duration in seconds
+        #[arg(long, default_value_t = 30)]
+        duration: u64,
+        /// Seconds to ramp up linearly to the target TPS
+        #[arg(long, default_value_t = 0)]
+        ramp_up: u64,
     },
     /// Retrieve SOL balance for an account
     Balance { pubkey: String },
@@ -53,12 +408,52 @@ enum Commands {
         pubkey: String,
         amount_sol: Option<String>,
     },
+    /// Generate keypair files and print their pubkeys
+    Keygen {
+        /// Keypair file, or the directory for several (default: `<pubkey>.json` here)
+        #[arg(long, short = 'o')]
+        outfile: Option<PathBuf>,
+        /// Only keep keypairs whose pubkey starts with this prefix
+        #[arg(long)]
+        vanity: Option<String>,
+        /// Number of keypairs
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        count: u64,
+    },
+    /// Generate funded keypairs and a wallets.json manifest
+    MakeWallets {
+        count: usize,
+        /// SOL airdropped to each wallet
+        #[arg(long, default_value = "1")]
+        sol: String,
+        /// Directory for keypair files and the manifest
+        #[arg(long, default_value = "./wallets")]
+        output_path: PathBuf,
+    },
     /// Transfer SOL between two accounts
     SendSol {
         from: String,
         to: String,
         amount_lamports: String,
         signer_keypair: String,
+        /// Allow sending to an account that does not exist yet
+        #[arg(long)]
+        allow_unfunded_recipient: bool,
+        /// Send without the recipient, rent and self-transfer checks
+        #[arg(long)]
+        skip_checks: bool,
+    },
+    /// Send SOL to every recipient of a JSON list, batching transfers into few transactions
+    SendSolMany {
+        /// `[{ "pubkey": ..., "amount": <lamports> }]`
+        recipients_json: PathBuf,
+        signer_keypair: String,
+        /// Allow sending to accounts that do not exist yet
+        #[arg(long)]
+        allow_unfunded_recipient: bool,
+        /// Send without the recipient, rent and self-transfer checks
+        #[arg(long)]
+        skip_checks: bool,
     },
     /// Create an associated token account
     CreateAta {
@@ -71,49 +466,308 @@ enum Commands {
         owner: String,
         mint: String,
         signer_keypair: String,
+        /// Burn the tokens left in the account before closing it
+        #[arg(long, alias = "burn")]
+        burn_remaining: bool,
     },
     /// Retrieve SPL token balance for an account
     TokenBalance { owner: String, mint: String },
+    /// Create an SPL token mint and mint an initial supply to the signer's ATA
+    CreateToken {
+        signer_keypair: String,
+        /// Number of decimals of the mint
+        #[arg(long, default_value_t = 9)]
+        decimals: u8,
+        /// Initial supply in whole tokens
+        #[arg(long, default_value = "0")]
+        supply: String,
+        /// Metaplex metadata name (enables metadata creation)
+        #[arg(long)]
+        name: Option<String>,
+        /// Metaplex metadata symbol
+        #[arg(long, default_value = "")]
+        symbol: String,
+        /// Metaplex metadata URI
+        #[arg(long, default_value = "")]
+        uri: String,
+    },
+    /// Mint an NFT (mint + metadata + master edition) to the signer or an owner
+    MintNft {
+        signer_keypair: String,
+        /// NFT name
+        #[arg(long)]
+        name: String,
+        /// NFT metadata URI
+        #[arg(long)]
+        uri: String,
+        /// NFT symbol
+        #[arg(long, default_value = "")]
+        symbol: String,
+        /// Wallet receiving the NFT (defaults to the signer)
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// Report the accounts referenced by a directory of templates and suggest lookup table members
+    AccountsReport {
+        templates_dir: PathBuf,
+        /// Write the suggested lookup table accounts for create-lookup-table
+        #[arg(long, value_name = "ACCOUNTS_JSON")]
+        emit_alt: Option<PathBuf>,
+    },
     /// Create an address lookup table using accounts JSON
     CreateLookupTable {
         accounts_json: PathBuf,
         signer_keypair: String,
+        /// Wait up to this long (e.g. 30s) until the table can be used by transactions
+        #[arg(long, value_parser = parse_duration)]
+        wait: Option<Duration>,
     },
-    /// Dump account or program data from mainnet
+    /// Dump account or program data from the source cluster
     Dump {
         pubkey: String,
         output_path: Option<PathBuf>,
+        /// Write files flat or into typed subdirectories with a manifest
+        #[arg(long, value_enum, default_value_t = Layout::Flat)]
+        layout: Layout,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Dump all stake accounts of an authority from the source cluster
+    DumpStakeAccounts {
+        authority: String,
+        output_path: Option<PathBuf>,
+        /// Match the authority only as staker or withdrawer (both by default)
+        #[arg(long, value_enum)]
+        role: Option<StakeAuthority>,
+        /// Write all accounts to one JSON Lines file
+        #[arg(long)]
+        jsonl: bool,
+        /// Write files flat or into typed subdirectories with a manifest
+        #[arg(long, value_enum, default_value_t = Layout::Flat)]
+        layout: Layout,
+        /// Exit with an error if any account could not be dumped
+        #[arg(long)]
+        strict: bool,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Compare the ELF of a program on the source cluster with a local build by hash
+    VerifyProgram {
+        program_id: String,
+        /// Locally built program
+        #[arg(long)]
+        so: PathBuf,
     },
     /// Dump all accounts touched by a transaction
     DumpFromTx {
         signature: String,
         output_path: Option<PathBuf>,
+        /// Write all accounts to one JSON Lines file
+        #[arg(long)]
+        jsonl: bool,
+        /// Write files flat or into typed subdirectories with a manifest
+        #[arg(long, value_enum, default_value_t = Layout::Flat)]
+        layout: Layout,
+        /// Exit with an error if any account could not be dumped
+        #[arg(long)]
+        strict: bool,
+        /// Also dump the account compression and noop programs of compressed NFT flows
+        #[arg(long)]
+        with_compression: bool,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Dump all accounts required by a transaction template
     DumpForTx {
         tx_json: PathBuf,
         output_path: Option<PathBuf>,
         params: Vec<String>,
+        /// Write all accounts to one JSON Lines file
+        #[arg(long)]
+        jsonl: bool,
+        /// Write files flat or into typed subdirectories with a manifest
+        #[arg(long, value_enum, default_value_t = Layout::Flat)]
+        layout: Layout,
+        /// Exit with an error if any account could not be dumped
+        #[arg(long)]
+        strict: bool,
+        #[command(flatten)]
+        output: OutputArgs,
     },
+    /// Capture every new transaction touching the accounts (parsed template plus dump)
+    Monitor {
+        #[arg(required = true)]
+        pubkeys: Vec<String>,
+        /// Directory receiving `<date>/<signature>/` captures
+        #[arg(long, default_value = "./monitor")]
+        output_path: PathBuf,
+        /// Seconds between polls
+        #[arg(long, default_value_t = 10)]
+        interval: u64,
+        /// Stop after capturing this many transactions
+        #[arg(long)]
+        max_txs: Option<usize>,
+    },
+    /// Dump, patch and replay an aggregator swap, reporting which leg fails
+    ReplayRoute {
+        signature: String,
+        /// Directory for the dumped accounts
+        #[arg(default_value = "./replay")]
+        output_path: PathBuf,
+        /// Simulate on the running target without restarting the local testnet
+        #[arg(long)]
+        no_restart: bool,
+    },
+    /// Show an account, transaction or block; the kind is detected from the pubkey, signature or slot
+    Show { id: String },
     /// Fetch a transaction and store its JSON representation
     ParseTx {
-        signature: String,
+        #[arg(required_unless_present = "from_file")]
+        signature: Option<String>,
+        /// With `--from-file` the only positional is the output path
+        #[arg(conflicts_with = "from_file")]
         output_path: Option<PathBuf>,
+        /// Parse a raw dump-tx file instead of fetching from the network
+        #[arg(long)]
+        from_file: Option<PathBuf>,
+        #[command(flatten)]
+        events: EventsArgs,
+        /// Key casing of the output: snake (`is_signer`, as templates) or camel (`isSigner`, as the RPC)
+        #[arg(long, value_enum)]
+        style: Option<KeyStyle>,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Parse/analyze a block by slot (accounts, balances, instructions)
     ParseBlock {
-        slot: String,
+        #[arg(required_unless_present = "from_file")]
+        slot: Option<String>,
+        /// With `--from-file` the only positional is the output path
+        #[arg(conflicts_with = "from_file")]
         output_path: Option<PathBuf>,
+        /// Parse a raw dump-block file instead of fetching from the network
+        #[arg(long)]
+        from_file: Option<PathBuf>,
+        /// Keep only failed transactions
+        #[arg(long)]
+        only_failed: bool,
+        /// Keep only transactions invoking this program, including via CPI (repeatable)
+        #[arg(long = "program")]
+        programs: Vec<String>,
+        /// Skip vote transactions
+        #[arg(long)]
+        exclude_votes: bool,
+        /// Transaction detail level requested from the RPC
+        #[arg(long, value_enum, default_value_t = DetailLevel::Full)]
+        details: DetailLevel,
+        /// Write one transaction per line (`<slot>.jsonl`)
+        #[arg(long)]
+        jsonl: bool,
+        /// Key casing of the output: snake (`is_signer`, as templates) or camel (`isSigner`, as the RPC)
+        #[arg(long, value_enum)]
+        style: Option<KeyStyle>,
+        #[command(flatten)]
+        output: OutputArgs,
     },
+    /// Re-run the parsers on a raw dump-tx/dump-block file and diff against expected output
+    VerifyParse {
+        raw_json: PathBuf,
+        expected_json: PathBuf,
+        /// Overwrite the expected output with the current parser result
+        #[arg(long)]
+        update: bool,
+    },
+    /// Structurally diff two templates or parse-tx outputs (instructions, accounts, data fields)
+    DiffTx { a: PathBuf, b: PathBuf },
+    /// Rewrite templates in canonical form (field order, account flags, data format keys)
+    FmtTx {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Expand shorthands into explicit instructions or collapse explicit ones into shorthands
+        #[arg(long, value_enum, default_value_t = Shorthands::Keep)]
+        shorthands: Shorthands,
+        /// Fail instead of rewriting when a template is not formatted
+        #[arg(long)]
+        check: bool,
+    },
+    /// Upgrade templates written for older soltnet versions to the current layout, in place
+    MigrateTx {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Fail instead of rewriting when a template needs migrating
+        #[arg(long)]
+        check: bool,
+    },
+    /// Combine several templates into one (shifted params, merged signers and compute budget)
+    MergeTx {
+        output: PathBuf,
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Keep param numbers as they are, so `$1` is the same value in every input
+        #[arg(long)]
+        share_params: bool,
+    },
+    /// Split an oversized template into several transactions, keeping `depends_on` runs together
+    SplitTx { input: PathBuf, output: PathBuf },
     /// Fetch a raw transaction response and store it as JSON
     DumpTx {
         signature: String,
         output_path: Option<PathBuf>,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     /// Fetch a raw block response and store it as JSON
     DumpBlock {
         slot: String,
         output_path: Option<PathBuf>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Sign a message (UTF-8 text or 0x-prefixed hex) with a keypair
+    SignMessage {
+        message: String,
+        /// Keypair file (keystore and Ledger signers are not supported)
+        signer_keypair: String,
+    },
+    /// Compile a template and sign it with the keypairs at hand, exporting the partially signed transaction in base64
+    SignTx {
+        tx_json: PathBuf,
+        params: Vec<String>,
+        /// Fee payer pubkey, when it is not the first template signer (e.g. a remote multisig member)
+        #[arg(long)]
+        payer: Option<String>,
+        /// Blockhash to sign with instead of the latest one of the target, or the nonce value with --nonce
+        /// (lookup tables of the template are still read from the target)
+        #[arg(long)]
+        blockhash: Option<String>,
+        /// Durable nonce account: prepends `AdvanceNonceAccount` and signs with its nonce, so the transaction does not expire
+        #[arg(long, value_name = "ACCOUNT")]
+        nonce: Option<String>,
+        /// Authority of the nonce account, a pubkey or keypair (defaults to the fee payer)
+        #[arg(long, value_name = "AUTHORITY", requires = "nonce")]
+        nonce_authority: Option<String>,
+        /// Also write the transaction to this file
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Merge partially signed transactions (base64 or files) and `<pubkey>=<signature>` pairs
+    CombineSigs {
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        /// Send the transaction to the target once every signature is present
+        #[arg(long)]
+        send: bool,
+        /// Also write the combined transaction to this file
+        #[arg(long, value_name = "FILE", conflicts_with = "send")]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        send_options: SendArgs,
+    },
+    /// Verify a message signature against a pubkey
+    VerifySignature {
+        pubkey: String,
+        message: String,
+        signature: String,
     },
     /// Apply a data format to an instruction inside a transaction JSON
     SetDataFormat {
@@ -121,149 +775,872 @@ enum Commands {
         format_json: PathBuf,
         program_id: String,
     },
+    /// Show reserves, price and fee of an AMM pool decoded with a protocol pack
+    PoolInfo {
+        pool: String,
+        /// Pack whose pool layout decodes the account
+        #[arg(long)]
+        pack: String,
+        /// Also dump the pool, its program, vaults and mints into this directory
+        #[arg(long)]
+        dump: Option<PathBuf>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Install and list protocol packs (program decoders, aliases, templates)
+    Pack {
+        #[command(subcommand)]
+        command: PackCommand,
+    },
+    /// Show the values earlier commands recorded for the target, usable as `$state:` variables
+    State {
+        /// Forget every recorded value
+        #[arg(long)]
+        clear: bool,
+    },
+    /// Show or change the profiles of ~/.config/soltnet/config.toml (`--profile` selects one)
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Share a testnet state (accounts, templates, data formats) as one archive
+    Bundle {
+        #[command(subcommand)]
+        command: BundleCommand,
+    },
 }
 
-fn parse_sol_to_lamports(input: &str) -> Result<u64> {
-    let cleaned = input.trim().replace('_', "");
-    if cleaned.is_empty() {
-        return Err(anyhow!("Invalid amount: empty string"));
-    }
-    if cleaned.starts_with('-') {
-        return Err(anyhow!("Amount must be non-negative"));
-    }
-
-    let mut parts = cleaned.split('.');
-    let whole_str = parts.next().unwrap_or("");
-    let frac_str = parts.next().unwrap_or("");
-    if parts.next().is_some() {
-        return Err(anyhow!("Invalid amount: too many decimal points"));
-    }
+#[derive(Subcommand)]
+enum PackCommand {
+    /// Install a pack from a directory, a `.tar`/`.tar.gz`/`.tar.zst` archive or an archive URL
+    Install { source: String },
+    /// List installed packs
+    List,
+}
 
-    if !whole_str.is_empty() && !whole_str.chars().all(|c| c.is_ascii_digit()) {
-        return Err(anyhow!("Invalid amount: {input}"));
-    }
-    if !frac_str.is_empty() && !frac_str.chars().all(|c| c.is_ascii_digit()) {
-        return Err(anyhow!("Invalid amount: {input}"));
-    }
-    if frac_str.len() > 9 {
-        return Err(anyhow!("Invalid amount: max 9 decimal places"));
-    }
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print one value of the profile, or all of them
+    Get { key: Option<String> },
+    /// Set a value of the profile (source, target, keypair, output_dir, commitment)
+    Set { key: String, value: String },
+    /// Make a profile the active one
+    Use { name: String },
+    /// List profiles
+    List,
+}
 
-    let whole: u64 = if whole_str.is_empty() {
-        0
-    } else {
-        whole_str.parse()?
-    };
+#[derive(Subcommand)]
+enum BundleCommand {
+    /// Pack a dump directory plus templates and formats into a `.tar`, `.tar.gz` or `.tar.zst`
+    Create {
+        archive: PathBuf,
+        accounts_path: PathBuf,
+        /// Directory of transaction templates to include
+        #[arg(long)]
+        templates: Option<PathBuf>,
+        /// Directory of data formats to include
+        #[arg(long)]
+        formats: Option<PathBuf>,
+    },
+    /// Unpack a bundle and load its accounts into the testnet config
+    Apply {
+        archive: PathBuf,
+        /// Directory the bundle is unpacked into
+        #[arg(default_value = "./bundle")]
+        output_path: PathBuf,
+    },
+}
 
-    let mut frac_padded = frac_str.to_string();
-    while frac_padded.len() < 9 {
-        frac_padded.push('0');
+/// With `--from-file` there is no signature/slot, so a lone positional is the output path.
+fn offline_positionals(
+    from_file: &Option<PathBuf>,
+    id: Option<String>,
+    output_path: Option<PathBuf>,
+) -> (Option<String>, Option<PathBuf>) {
+    match (from_file, id, output_path) {
+        (Some(_), Some(id), None) => (None, Some(PathBuf::from(id))),
+        (_, id, output_path) => (id, output_path),
     }
-    let frac: u64 = if frac_padded.is_empty() {
-        0
-    } else {
-        frac_padded.parse()?
-    };
-
-    let lamports = whole
-        .checked_mul(1_000_000_000)
-        .and_then(|v| v.checked_add(frac))
-        .ok_or_else(|| anyhow!("Amount is too large"))?;
-
-    Ok(lamports)
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    init_color(cli.no_color);
+    enable_event_stream(cli.event_stream);
+    set_output_format(cli.output_format);
+    set_strict(
+        cli.strict_templates || env::var_os("CI").is_some_and(|ci| !ci.is_empty() && ci != "false"),
+    );
+    emit(
+        STEP_STARTED,
+        serde_json::json!({ "step": matches.subcommand_name() }),
+    );
+    let result = run(cli);
+    finish(matches.subcommand_name(), &result);
+    result
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let config_file = config_path();
+    let user_config = UserConfig::load(&config_file);
+    // `config set` creates the profile it names.
+    let profile = if matches!(
+        cli.command,
+        Commands::Config {
+            command: ConfigCommand::Set { .. }
+        }
+    ) {
+        Profile::default()
+    } else {
+        user_config.profile(cli.profile.as_deref())?
+    };
+    let source = cli
+        .url
+        .clone()
+        .or(cli.source.clone())
+        .or(profile.source.clone())
+        .unwrap_or_else(|| "mainnet".to_string());
+    let target = cli
+        .url
+        .clone()
+        .or(cli.target.clone())
+        .or(profile.target.clone())
+        .unwrap_or_else(|| "local".to_string());
+    let mut network = NetworkCtx::new(&source, &target);
+    network.limits = cli.rpc_preset.limits();
+    network.commitment = cli.commitment.or(profile.commitment).unwrap_or_default();
+    let output_dir = profile
+        .output_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("."));
+    let env_headers = headers_from_env();
+    network.add_headers(env_headers.iter().chain(&cli.headers).map(String::as_str))?;
+    set_local_vars(local_vars(
+        &network.target,
+        profile.keypair.as_deref().map(PathBuf::from),
+    ));
+    let mut state = StateStore::for_instance(&network.target)?;
+    set_state_vars(state.vars());
+    set_installed(PackRegistry::load(&packs_dir()));
+    if let Some(path) = &cli.record_rpc {
+        network.recorder = Some(Arc::new(RpcRecorder::new(path)));
+    }
+    if let Some(path) = &cli.replay_rpc {
+        network.mocks = Some(Arc::new(RpcMocks::load(path)?));
+    }
 
     match cli.command {
         Commands::Load { accounts_path } => set_testnet_config(Some(&accounts_path))?,
         Commands::Clear => set_testnet_config(None)?,
+        Commands::OverrideProgram {
+            program_id,
+            so,
+            upgrade_authority,
+        } => override_program(&program_id, &so, &upgrade_authority)?,
+        Commands::CheckSysvars { align } => {
+            check_sysvars(&network, align)?;
+        }
         Commands::Start => start_testnet_container()?,
         Commands::Stop => stop_testnet_container()?,
-        Commands::ExecTx { tx_json, params } => {
-            let parsed = load_parsed_tx_from_json(&tx_json, &params)?;
-            execute_json_transaction(parsed, None)?;
+        Commands::ExecTx {
+            tx_json,
+            mut params,
+            params_from,
+            payer,
+            error_maps,
+            cu_baseline,
+            cu_tolerance,
+            cu_warn_only,
+            repeat,
+            stats,
+            log_assertions,
+            at_slot,
+            at_epoch_boundary,
+            auto_alt,
+            transfer_checks,
+            allow_unfunded_recipient,
+            burn_remaining,
+            priority_fee,
+            simulate,
+            encode_only,
+            encode_output,
+            keep_going,
+            events,
+            send,
+        } => {
+            for spec in &params_from {
+                params.push(param_from_json(spec)?);
+            }
+            let tx_json = resolve_template_path(&tx_json);
+            prompt_missing_params(&tx_json, &mut params)?;
+            let payer = payer
+                .map(|payer| parse_keypair(&serde_json::Value::String(payer), &params))
+                .transpose()?;
+            let mut options = ExecOptions {
+                payer: payer.as_ref().map(|keypair| keypair.pubkey()),
+                error_map: ErrorMap::load(&error_maps)?,
+                previous_blockhash: None,
+                send_config: send.config(&profile),
+                confirm_commitment: send.confirm_commitment.map(CommitmentConfig::from),
+                log_assertions,
+                events: events.output()?,
+                send_at_slot: match at_slot {
+                    Some(slot) => Some(slot),
+                    None if at_epoch_boundary => {
+                        Some(fetch_next_epoch_start(&network.target_client())?)
+                    }
+                    None => None,
+                },
+                default_lookup_tables: Settings::discover()?.lookup_tables(&network.target),
+                transfer_checks: TransferChecks {
+                    enabled: transfer_checks,
+                    allow_unfunded_recipient,
+                },
+                burn_remaining,
+            };
+            let transactions = load_raw_txs_from_json(&tx_json)?;
+            let prepare = |mut parsed: ParsedTransaction| {
+                if let Some(payer) = &payer {
+                    parsed.add_signer(payer.insecure_clone());
+                }
+                if let Some(micro_lamports) = priority_fee {
+                    parsed.set_cu_price(micro_lamports)?;
+                }
+                Ok(parsed)
+            };
+            if encode_only {
+                let parsed = transactions
+                    .iter()
+                    .map(|raw| prepare(parse_tx_from_json(raw, &params)?))
+                    .collect::<Result<Vec<_>>>()?;
+                return encode_transactions(&network, &parsed, &options, encode_output.as_deref());
+            }
+            if transactions.len() > 1 {
+                if simulate || auto_alt || repeat > 1 || cu_baseline.is_some() {
+                    return Err(anyhow!(
+                        "--simulate, --auto-alt, --repeat and --cu-baseline need a single transaction, {} holds {}",
+                        tx_json.display(),
+                        transactions.len()
+                    ));
+                }
+                let base_dir = template_dir(&tx_json);
+                return run_batch(
+                    &network,
+                    &transactions,
+                    base_dir,
+                    &params,
+                    prepare,
+                    &options,
+                    BatchOptions {
+                        keep_going,
+                        print_stats: stats,
+                    },
+                );
+            }
+            let alt_replacements = if auto_alt {
+                let parsed = load_parsed_tx_from_json(&tx_json, &params)?;
+                let authority = payer
+                    .as_ref()
+                    .or(parsed.signers.first())
+                    .ok_or_else(|| anyhow!("Missing transaction signer"))?;
+                materialize_lookup_tables(&network, &parsed.lookup_tables, authority)?
+            } else {
+                HashMap::new()
+            };
+            let load = || {
+                let mut parsed = prepare(load_parsed_tx_from_json(&tx_json, &params)?)?;
+                for table in &mut parsed.lookup_tables {
+                    if let Some(local) = alt_replacements.get(table) {
+                        *table = *local;
+                    }
+                }
+                Ok(parsed)
+            };
+            if simulate {
+                return simulate_json_transaction(
+                    &network,
+                    &tx_json,
+                    &params,
+                    load()?,
+                    options.payer,
+                    &[],
+                    &options.error_map,
+                );
+            }
+            let hooks = load_raw_tx_from_json(&tx_json)?.hooks.unwrap_or_default();
+            let base_dir = template_dir(&tx_json);
+            run_hooks(&network, "pre", &hooks.pre, base_dir, &params)?;
+            let run_stats = run_repeated(&network, repeat, load, &mut options)?;
+            run_hooks(&network, "post", &hooks.post, base_dir, &params)?;
+            if stats {
+                run_stats.print();
+            }
+            run_stats.check()?;
+            if let Some(path) = cu_baseline {
+                let consumed = run_stats
+                    .max_compute_units()
+                    .ok_or_else(|| anyhow!("Compute units not reported for the transaction"))?;
+                let baseline = CuBaseline {
+                    path: &path,
+                    tolerance_percent: cu_tolerance,
+                    warn_only: cu_warn_only,
+                };
+                check_cu_baseline(&baseline, &tx_json.display().to_string(), consumed)?;
+            }
+        }
+        Commands::Crank {
+            tx_json,
+            mut params,
+            every,
+            times,
+            payer,
+            error_maps,
+        } => {
+            let tx_json = resolve_template_path(&tx_json);
+            prompt_missing_params(&tx_json, &mut params)?;
+            let payer = payer
+                .map(|payer| parse_keypair(&serde_json::Value::String(payer), &params))
+                .transpose()?;
+            let mut options = ExecOptions {
+                payer: payer.as_ref().map(|keypair| keypair.pubkey()),
+                error_map: ErrorMap::load(&error_maps)?,
+                default_lookup_tables: Settings::discover()?.lookup_tables(&network.target),
+                ..Default::default()
+            };
+            let load = || {
+                let mut parsed = load_parsed_tx_from_json(&tx_json, &params)?;
+                if let Some(payer) = &payer {
+                    parsed.add_signer(payer.insecure_clone());
+                }
+                Ok(parsed)
+            };
+            let run_stats = run_periodic(&network, every, times, load, &mut options)?;
+            run_stats.print();
+            run_stats.check()?;
+        }
+        Commands::SimulateTx {
+            tx_json,
+            mut params,
+            payer,
+            error_maps,
+            overrides,
+        } => {
+            let tx_json = resolve_template_path(&tx_json);
+            prompt_missing_params(&tx_json, &mut params)?;
+            let payer = payer
+                .map(|payer| parse_keypair(&serde_json::Value::String(payer), &params))
+                .transpose()?;
+            let mut parsed = load_parsed_tx_from_json(&tx_json, &params)?;
+            if let Some(payer) = &payer {
+                parsed.add_signer(payer.insecure_clone());
+            }
+            let overrides = overrides
+                .iter()
+                .map(|path| AccountOverride::load(path))
+                .collect::<Result<Vec<_>>>()?;
+            simulate_json_transaction(
+                &network,
+                &tx_json,
+                &params,
+                parsed,
+                payer.as_ref().map(|keypair| keypair.pubkey()),
+                &overrides,
+                &ErrorMap::load(&error_maps)?,
+            )?;
         }
-        Commands::Balance { pubkey } => get_balance(&pubkey)?,
+        Commands::Bench {
+            tx_json,
+            wallets_json,
+            params,
+            tps,
+            duration,
+            ramp_up,
+        } => {
+            let options = BenchOptions {
+                tps,
+                duration: Duration::from_secs(duration),
+                ramp_up: Duration::from_secs(ramp_up),
+            };
+            bench(&network, tx_json, wallets_json, &params, &options)?;
+        }
+        Commands::Balance { pubkey } => get_balance(&network, &pubkey)?,
         Commands::Airdrop { pubkey, amount_sol } => {
             let amount = amount_sol.unwrap_or_else(|| "1".to_string());
-            let lamports = parse_sol_to_lamports(&amount)?;
-            airdrop_sol(&pubkey, lamports)?;
+            let lamports = parse_ui_amount(&amount, 9)?;
+            airdrop_sol(&network, &pubkey, lamports)?;
         }
+        Commands::Keygen {
+            outfile,
+            vanity,
+            count,
+        } => keygen(outfile.as_deref(), vanity.as_deref(), count as usize)?,
+        Commands::MakeWallets {
+            count,
+            sol,
+            output_path,
+        } => make_wallets(&network, count, parse_ui_amount(&sol, 9)?, output_path)?,
         Commands::SendSol {
             from,
             to,
             amount_lamports,
             signer_keypair,
+            allow_unfunded_recipient,
+            skip_checks,
         } => {
             let lamports: u64 = amount_lamports.replace('_', "").parse()?;
-            send_sol(&from, &to, lamports, &signer_keypair)?;
+            let checks = TransferChecks {
+                enabled: !skip_checks,
+                allow_unfunded_recipient,
+            };
+            send_sol(&network, &from, &to, lamports, &signer_keypair, checks)?;
+        }
+        Commands::SendSolMany {
+            recipients_json,
+            signer_keypair,
+            allow_unfunded_recipient,
+            skip_checks,
+        } => {
+            let checks = TransferChecks {
+                enabled: !skip_checks,
+                allow_unfunded_recipient,
+            };
+            send_sol_many(&network, &recipients_json, &signer_keypair, checks)?;
         }
         Commands::CreateAta {
             owner,
             mint,
             signer_keypair,
-        } => create_ata(&owner, &mint, &signer_keypair)?,
+        } => create_ata(&network, &owner, &mint, &signer_keypair)?,
         Commands::CloseAta {
             owner,
             mint,
             signer_keypair,
-        } => close_ata(&owner, &mint, &signer_keypair)?,
-        Commands::TokenBalance { owner, mint } => get_token_balance(&owner, &mint)?,
+            burn_remaining,
+        } => close_ata(&network, &owner, &mint, &signer_keypair, burn_remaining)?,
+        Commands::TokenBalance { owner, mint } => get_token_balance(&network, &owner, &mint)?,
+        Commands::CreateToken {
+            signer_keypair,
+            decimals,
+            supply,
+            name,
+            symbol,
+            uri,
+        } => {
+            let supply = parse_ui_amount(&supply, decimals as u32)?;
+            let metadata = name.map(|name| TokenMetadata { name, symbol, uri });
+            create_token(&network, &signer_keypair, decimals, supply, metadata)?;
+        }
+        Commands::MintNft {
+            signer_keypair,
+            name,
+            uri,
+            symbol,
+            owner,
+        } => mint_nft(
+            &network,
+            &signer_keypair,
+            owner.as_deref(),
+            TokenMetadata { name, symbol, uri },
+        )?,
+        Commands::AccountsReport {
+            templates_dir,
+            emit_alt: alt_path,
+        } => {
+            let report = accounts_report(&network, templates_dir)?;
+            report.print();
+            if let Some(path) = alt_path {
+                let count = emit_alt(&report, &path)?;
+                say!("Wrote {count} lookup table accounts to {}", path.display());
+            }
+        }
         Commands::CreateLookupTable {
             accounts_json,
             signer_keypair,
-        } => create_lookup_table(&accounts_json, &signer_keypair)?,
+            wait,
+        } => {
+            let table = create_lookup_table(&network, &accounts_json, &signer_keypair)?;
+            if let Some(timeout) = wait {
+                wait_for_lookup_table(&network.target_client(), &table, timeout)?;
+                say!("Lookup table {table} is ready");
+            }
+        }
         Commands::Dump {
             pubkey,
             output_path,
+            layout,
+            output,
+        } => {
+            let out = output_target(output_path, &output_dir);
+            let output = OutputOptions {
+                layout,
+                ..output.options(false)
+            };
+            dump_account(&network, &pubkey, out, output)?;
+        }
+        Commands::DumpStakeAccounts {
+            authority,
+            output_path,
+            role,
+            jsonl,
+            layout,
+            strict,
+            output,
         } => {
-            let out = output_path.unwrap_or_else(|| PathBuf::from("."));
-            dump_account(&pubkey, out)?;
+            let authority = parse_pubkey_str(&authority)?;
+            let out = output_target(output_path, &output_dir);
+            let output = OutputOptions {
+                layout,
+                ..output.options(jsonl)
+            };
+            cancel_on_interrupt()?;
+            let summary = dump_stake_accounts(&network, &authority, role, out, output)?;
+            check_dump_summary(&summary, strict)?;
         }
+        Commands::VerifyProgram { program_id, so } => verify_program(&network, &program_id, &so)?,
         Commands::DumpFromTx {
             signature,
             output_path,
+            jsonl,
+            layout,
+            strict,
+            with_compression,
+            output,
         } => {
-            let out = output_path.unwrap_or_else(|| PathBuf::from("."));
-            dump_accounts_from_tx(&signature, out)?;
+            let out = output_target(output_path, &output_dir);
+            let output = OutputOptions {
+                layout,
+                ..output.options(jsonl)
+            };
+            cancel_on_interrupt()?;
+            let summary =
+                dump_accounts_from_tx(&network, &signature, out, output, with_compression)?;
+            check_dump_summary(&summary, strict)?;
         }
         Commands::DumpForTx {
             tx_json,
             output_path,
-            params,
+            mut params,
+            jsonl,
+            layout,
+            strict,
+            output,
+        } => {
+            prompt_missing_params(&tx_json, &mut params)?;
+            let out = output_target(output_path, &output_dir);
+            let output = OutputOptions {
+                layout,
+                ..output.options(jsonl)
+            };
+            cancel_on_interrupt()?;
+            let summary = dump_accounts_for_tx(&network, tx_json, out, &params, output)?;
+            check_dump_summary(&summary, strict)?;
+        }
+        Commands::Monitor {
+            pubkeys,
+            output_path,
+            interval,
+            max_txs,
+        } => {
+            let addresses = pubkeys
+                .iter()
+                .map(|pubkey| parse_pubkey_str(pubkey))
+                .collect::<Result<Vec<_>>>()?;
+            monitor(
+                &network,
+                &addresses,
+                output_path,
+                Duration::from_secs(interval),
+                max_txs,
+            )?;
+        }
+        Commands::ReplayRoute {
+            signature,
+            output_path,
+            no_restart,
         } => {
-            let out = output_path.unwrap_or_else(|| PathBuf::from("."));
-            dump_accounts_for_tx(tx_json, out, &params)?;
+            replay_route(&network, &signature, output_path, !no_restart)?;
         }
+        Commands::Show { id } => show(&network, &id)?,
         Commands::ParseTx {
             signature,
             output_path,
+            from_file,
+            events,
+            style,
+            output,
+        } => {
+            let (signature, output_path) = offline_positionals(&from_file, signature, output_path);
+            let out = output_target(output_path, &output_dir);
+            let source = match (&from_file, &signature) {
+                (Some(path), _) => ParseSource::File(path),
+                (None, Some(signature)) => ParseSource::Rpc(signature),
+                (None, None) => return Err(anyhow!("Missing transaction signature")),
+            };
+            let events = events.output()?;
+            let output = OutputOptions {
+                style,
+                ..output.options(false)
+            };
+            create_json_from_tx(&network, source, out, output, events.as_ref())?;
+        }
+        Commands::ParseBlock {
+            slot,
+            output_path,
+            from_file,
+            only_failed,
+            programs,
+            exclude_votes,
+            details,
+            jsonl,
+            style,
+            output,
+        } => {
+            let (slot, output_path) = offline_positionals(&from_file, slot, output_path);
+            let out = output_target(output_path, &output_dir);
+            let source = match (&from_file, &slot) {
+                (Some(path), _) => ParseSource::File(path),
+                (None, Some(slot)) => ParseSource::Rpc(slot),
+                (None, None) => return Err(anyhow!("Missing block slot")),
+            };
+            let options = BlockParseOptions {
+                details,
+                exclude_votes,
+                only_failed,
+                programs,
+            };
+            let output = OutputOptions {
+                style,
+                ..output.options(jsonl)
+            };
+            parse_block(&network, source, out, &options, output)?;
+        }
+        Commands::VerifyParse {
+            raw_json,
+            expected_json,
+            update,
+        } => verify_parse(raw_json, expected_json, update)?,
+        Commands::DiffTx { a, b } => diff_templates(a, b)?,
+        Commands::MergeTx {
+            output,
+            inputs,
+            share_params,
+        } => merge_templates(output, &inputs, share_params)?,
+        Commands::SplitTx { input, output } => split_template(input, output)?,
+        Commands::FmtTx {
+            paths,
+            shorthands,
+            check,
         } => {
-            let out = output_path.unwrap_or_else(|| PathBuf::from("."));
-            create_json_from_tx(&signature, out)?;
+            for path in paths {
+                format_template(path, shorthands, check)?;
+            }
         }
-        Commands::ParseBlock { slot, output_path } => {
-            let out = output_path.unwrap_or_else(|| PathBuf::from("."));
-            parse_block(&slot, out)?;
+        Commands::MigrateTx { paths, check } => {
+            for path in paths {
+                migrate_template_file(path, check)?;
+            }
         }
         Commands::DumpTx {
             signature,
             output_path,
+            output,
+        } => {
+            let out = output_target(output_path, &output_dir);
+            dump_raw_transaction(&network, &signature, out, output.options(false))?;
+        }
+        Commands::DumpBlock {
+            slot,
+            output_path,
+            output,
         } => {
-            let out = output_path.unwrap_or_else(|| PathBuf::from("."));
-            dump_raw_transaction(&signature, out)?;
+            let out = output_target(output_path, &output_dir);
+            dump_raw_block(&network, &slot, out, output.options(false))?;
         }
-        Commands::DumpBlock { slot, output_path } => {
-            let out = output_path.unwrap_or_else(|| PathBuf::from("."));
-            dump_raw_block(&slot, out)?;
+        Commands::SignMessage {
+            message,
+            signer_keypair,
+        } => sign_message(&message, &signer_keypair)?,
+        Commands::SignTx {
+            tx_json,
+            mut params,
+            payer,
+            blockhash,
+            nonce,
+            nonce_authority,
+            out,
+        } => {
+            let tx_json = resolve_template_path(&tx_json);
+            prompt_missing_params(&tx_json, &mut params)?;
+            let mut parsed = load_parsed_tx_from_json(&tx_json, &params)?;
+            let payer = payer
+                .map(|payer| parse_pubkey(&serde_json::Value::String(payer), &params))
+                .transpose()?;
+            let blockhash = blockhash
+                .map(|hash| {
+                    hash.parse::<Hash>()
+                        .map_err(|_| anyhow!("Invalid blockhash: {hash}"))
+                })
+                .transpose()?;
+            let nonce = nonce
+                .map(|nonce| -> Result<_> {
+                    let nonce = parse_pubkey(&serde_json::Value::String(nonce), &params)?;
+                    // A keypair authority signs here, a pubkey one later with combine-sigs
+                    let authority = nonce_authority
+                        .map(|authority| {
+                            let authority = serde_json::Value::String(authority);
+                            match parse_keypair(&authority, &params) {
+                                Ok(keypair) => Ok(parsed.add_signer(keypair)),
+                                Err(_) => parse_pubkey(&authority, &params),
+                            }
+                        })
+                        .transpose()?;
+                    Ok((nonce, authority))
+                })
+                .transpose()?;
+            sign_transaction(&network, parsed, payer, blockhash, nonce, out.as_deref())?;
         }
+        Commands::CombineSigs {
+            inputs,
+            send,
+            out,
+            send_options,
+        } => combine_signatures(
+            &network,
+            &inputs,
+            send.then(|| send_options.config(&profile)),
+            send_options.confirm_commitment.map(CommitmentConfig::from),
+            out.as_deref(),
+        )?,
+        Commands::VerifySignature {
+            pubkey,
+            message,
+            signature,
+        } => verify_signature(&pubkey, &message, &signature)?,
         Commands::SetDataFormat {
             tx_json,
             format_json,
             program_id,
         } => set_data_format(tx_json, format_json, &program_id)?,
+        Commands::PoolInfo {
+            pool,
+            pack,
+            dump,
+            output,
+        } => {
+            let info = pool_info(&network, &parse_pubkey_str(&pool)?, &pack)?;
+            info.print();
+            if let Some(dump) = dump {
+                info.dump(&network, dump, output.options(false))?.print();
+            }
+        }
+        Commands::Pack { command } => match command {
+            PackCommand::Install { source } => {
+                let pack = install_pack(&source, &packs_dir())?;
+                say!(
+                    "Installed pack {} ({} programs, {} aliases) to {}",
+                    pack.name,
+                    pack.programs.len(),
+                    pack.aliases.len(),
+                    pack.root.display()
+                );
+            }
+            PackCommand::List => {
+                for pack in PackRegistry::load(&packs_dir()).packs {
+                    let version = pack.version.as_deref().unwrap_or("-");
+                    say!("{} {version}", pack.name);
+                    for (program_id, program) in &pack.programs {
+                        say!("  {program_id} {}", program.name);
+                    }
+                    let programs: HashMap<&String, &String> = pack
+                        .programs
+                        .iter()
+                        .map(|(program_id, program)| (program_id, &program.name))
+                        .collect();
+                    push(
+                        "packs",
+                        serde_json::json!({
+                            "name": pack.name,
+                            "version": pack.version,
+                            "programs": programs,
+                        }),
+                    );
+                }
+            }
+        },
+        Commands::State { clear } => {
+            if clear {
+                state.clear();
+                state.save()?;
+                say!("Cleared state of {}", network.target);
+            } else {
+                say!("State of {} ({})", network.target, state.path().display());
+                record("state", state.entries());
+                for (kind, values) in state.entries() {
+                    for (index, value) in values.iter().enumerate() {
+                        say!("  $state:{kind}.{index}  {value}");
+                    }
+                }
+            }
+        }
+        Commands::Config { command } => {
+            let name = user_config.profile_name(cli.profile.as_deref());
+            let mut user_config = user_config;
+            match command {
+                ConfigCommand::Get { key: Some(key) } => {
+                    let value = profile.get(&key)?;
+                    say!("{}", value.as_deref().unwrap_or_default());
+                    record(&key, value);
+                }
+                ConfigCommand::Get { key: None } => {
+                    say!("Profile {name} ({})", config_file.display());
+                    for key in PROFILE_KEYS {
+                        if let Some(value) = profile.get(key)? {
+                            say!("  {key} = {value}");
+                            record(key, value);
+                        }
+                    }
+                }
+                ConfigCommand::Set { key, value } => {
+                    user_config.set(&name, &key, &value)?;
+                    user_config.save(&config_file)?;
+                    say!("Set {key} of profile {name}");
+                }
+                ConfigCommand::Use { name } => {
+                    user_config.set_active(&name)?;
+                    user_config.save(&config_file)?;
+                    say!("Active profile: {name}");
+                }
+                ConfigCommand::List => {
+                    record("active", &name);
+                    record("profiles", user_config.profiles.keys().collect::<Vec<_>>());
+                    for profile_name in user_config.profiles.keys() {
+                        let marker = if *profile_name == name { "*" } else { " " };
+                        say!("{marker} {profile_name}");
+                    }
+                }
+            }
+        }
+        Commands::Bundle { command } => match command {
+            BundleCommand::Create {
+                archive,
+                accounts_path,
+                templates,
+                formats,
+            } => {
+                let sources = BundleSources {
+                    accounts: &accounts_path,
+                    templates: templates.as_deref(),
+                    formats: formats.as_deref(),
+                };
+                create_bundle(archive, &sources)?;
+            }
+            BundleCommand::Apply {
+                archive,
+                output_path,
+            } => apply_bundle(archive, output_path)?,
+        },
     }
 
     Ok(())
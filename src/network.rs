@@ -0,0 +1,271 @@
+use std::{
+    collections::HashMap,
+    env, fmt,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Result, anyhow};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::rpc_client::{RpcClient, RpcClientConfig};
+
+use crate::rpc::{HttpRpcSender, MockRpcSender, RecordingRpcSender, RpcMocks, RpcRecorder};
+
+pub const LOCAL_RPC_URL: &str = "http://127.0.0.1:8899";
+pub const MAINNET_RPC_URL: &str = "http://api.mainnet-beta.solana.com";
+pub const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
+pub const TESTNET_RPC_URL: &str = "https://api.testnet.solana.com";
+/// `;`-separated `Name: value` headers added to every RPC request
+pub const RPC_HEADERS_ENV: &str = "SOLTNET_RPC_HEADERS";
+
+/// Request pacing for an RPC provider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RpcLimits {
+    /// Accounts per `getMultipleAccounts` request
+    pub batch_size: usize,
+    /// Requests in flight at once
+    pub concurrency: usize,
+    /// Pause after each request
+    pub delay: Duration,
+}
+
+/// Provider presets for `--rpc-preset`; `public` keeps to the public RPC limits.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum RpcPreset {
+    #[default]
+    Public,
+    Helius,
+    Quicknode,
+}
+
+impl RpcPreset {
+    pub fn limits(self) -> RpcLimits {
+        match self {
+            RpcPreset::Public => RpcLimits {
+                batch_size: 100,
+                concurrency: 1,
+                delay: Duration::from_millis(500),
+            },
+            RpcPreset::Helius => RpcLimits {
+                batch_size: 100,
+                concurrency: 8,
+                delay: Duration::from_millis(20),
+            },
+            RpcPreset::Quicknode => RpcLimits {
+                batch_size: 100,
+                concurrency: 4,
+                delay: Duration::from_millis(100),
+            },
+        }
+    }
+}
+
+/// Commitment level for reads, selected with `--commitment`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Commitment {
+    Processed,
+    #[default]
+    Confirmed,
+    Finalized,
+}
+
+impl From<Commitment> for CommitmentConfig {
+    fn from(commitment: Commitment) -> Self {
+        match commitment {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+}
+
+/// Clusters a command talks to: `source` is where state is read from
+/// (dumps, parsing), `target` is where transactions are executed.
+#[derive(Clone, Debug)]
+pub struct NetworkCtx {
+    pub source: String,
+    pub target: String,
+    /// Answer RPC calls from these responses instead of the network
+    pub mocks: Option<Arc<RpcMocks>>,
+    /// Record every RPC exchange to a cassette
+    pub recorder: Option<Arc<RpcRecorder>>,
+    pub limits: RpcLimits,
+    /// Extra HTTP headers, e.g. provider API keys
+    pub headers: HeaderMap,
+    /// Commitment of account and balance reads
+    pub commitment: Commitment,
+    clients: ClientPool,
+}
+
+impl Default for NetworkCtx {
+    fn default() -> Self {
+        Self {
+            source: MAINNET_RPC_URL.to_string(),
+            target: LOCAL_RPC_URL.to_string(),
+            mocks: None,
+            recorder: None,
+            limits: RpcPreset::default().limits(),
+            headers: HeaderMap::new(),
+            commitment: Commitment::default(),
+            clients: ClientPool::default(),
+        }
+    }
+}
+
+impl NetworkCtx {
+    pub fn new(source: &str, target: &str) -> Self {
+        Self {
+            source: resolve_cluster(source),
+            target: resolve_cluster(target),
+            mocks: None,
+            recorder: None,
+            limits: RpcPreset::default().limits(),
+            headers: HeaderMap::new(),
+            commitment: Commitment::default(),
+            clients: ClientPool::default(),
+        }
+    }
+
+    /// Context whose clients are served entirely by `mocks`.
+    pub fn mocked(mocks: RpcMocks) -> Self {
+        Self {
+            mocks: Some(Arc::new(mocks)),
+            ..Self::default()
+        }
+    }
+
+    /// Adds `Name: value` headers to every RPC request; values are marked
+    /// sensitive and masked in error messages.
+    pub fn add_headers<'a>(&mut self, headers: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        for header in headers {
+            let (name, value) = header
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Invalid header (expected `Name: value`): {header}"))?;
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| anyhow!("Invalid header name: {}", name.trim()))?;
+            let mut value = HeaderValue::from_str(value.trim())
+                .map_err(|_| anyhow!("Invalid value for header {name}"))?;
+            value.set_sensitive(true);
+            self.headers.insert(name, value);
+        }
+        self.clients = ClientPool::default();
+        Ok(())
+    }
+
+    /// Commitment for account and balance reads.
+    pub fn read_commitment(&self) -> CommitmentConfig {
+        self.commitment.into()
+    }
+
+    /// Commitment for transaction and block reads, which do not support
+    /// `processed`.
+    pub fn history_commitment(&self) -> CommitmentConfig {
+        match self.commitment {
+            Commitment::Finalized => CommitmentConfig::finalized(),
+            _ => CommitmentConfig::confirmed(),
+        }
+    }
+
+    /// Waits out the configured delay after a source request.
+    pub fn pause(&self) {
+        thread::sleep(self.limits.delay);
+    }
+
+    pub fn source_client(&self) -> Arc<RpcClient> {
+        self.client(&self.source)
+    }
+
+    pub fn target_client(&self) -> Arc<RpcClient> {
+        self.client(&self.target)
+    }
+
+    /// Client for `url`, built on first use and shared by every later call
+    /// (and clone of this context), so keep-alive connections are reused.
+    fn client(&self, url: &str) -> Arc<RpcClient> {
+        let mut clients = self.clients.0.lock().unwrap();
+        clients
+            .entry(url.to_string())
+            .or_insert_with(|| Arc::new(self.new_client(url)))
+            .clone()
+    }
+
+    fn new_client(&self, url: &str) -> RpcClient {
+        let config = RpcClientConfig::with_commitment(CommitmentConfig::confirmed());
+        if let Some(mocks) = &self.mocks {
+            RpcClient::new_sender(MockRpcSender::new(url, mocks.clone()), config)
+        } else if let Some(recorder) = &self.recorder {
+            RpcClient::new_sender(
+                RecordingRpcSender::new(url, &self.headers, recorder.clone()),
+                config,
+            )
+        } else {
+            create_connection(url, &self.headers)
+        }
+    }
+}
+
+/// RPC clients of a context by URL.
+#[derive(Clone, Default)]
+struct ClientPool(Arc<Mutex<HashMap<String, Arc<RpcClient>>>>);
+
+impl fmt::Debug for ClientPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let clients = self.0.lock().unwrap();
+        f.debug_set().entries(clients.keys()).finish()
+    }
+}
+
+pub fn create_connection(url: &str, headers: &HeaderMap) -> RpcClient {
+    RpcClient::new_sender(
+        HttpRpcSender::new(url, headers),
+        RpcClientConfig::with_commitment(CommitmentConfig::confirmed()),
+    )
+}
+
+/// Headers from the `SOLTNET_RPC_HEADERS` environment variable.
+pub fn headers_from_env() -> Vec<String> {
+    env::var(RPC_HEADERS_ENV)
+        .map(|value| {
+            value
+                .split(';')
+                .map(str::trim)
+                .filter(|header| !header.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Maps cluster names (`mainnet`, `devnet`, `testnet`, `local`) to RPC URLs;
+/// anything else is used as a URL.
+pub fn resolve_cluster(value: &str) -> String {
+    match value {
+        "mainnet" | "mainnet-beta" | "m" => MAINNET_RPC_URL,
+        "devnet" | "d" => DEVNET_RPC_URL,
+        "testnet" | "t" => TESTNET_RPC_URL,
+        "local" | "localhost" | "l" => LOCAL_RPC_URL,
+        url => url,
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::NetworkCtx;
+
+    #[test]
+    fn reuses_clients_until_headers_change() {
+        let mut network = NetworkCtx::default();
+        let client = network.source_client();
+        assert!(Arc::ptr_eq(&client, &network.clone().source_client()));
+        assert!(!Arc::ptr_eq(&client, &network.target_client()));
+
+        network.add_headers(["x-api-key: secret"]).unwrap();
+        assert!(!Arc::ptr_eq(&client, &network.source_client()));
+    }
+}
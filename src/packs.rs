@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::tools::bundle::{list_files, read_tar_entries, safe_relative_path};
+use crate::tools::output::open_input;
+use crate::tx_format::data_format::{get_byte_length, unpack_data};
+
+pub const PACK_MANIFEST: &str = "pack.json";
+/// Overrides the directory packs are installed into
+pub const PACKS_DIR_ENV: &str = "SOLTNET_PACKS_DIR";
+
+static INSTALLED: OnceLock<PackRegistry> = OnceLock::new();
+
+/// Instruction or account layout of a program: matched by a hex
+/// `discriminator` prefix and/or the exact `size`, decoded with a data format.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PackLayout {
+    pub name: String,
+    #[serde(default)]
+    pub discriminator: Option<String>,
+    #[serde(default)]
+    pub size: Option<usize>,
+    pub format: Value,
+    /// Marks an account layout as an AMM pool for `pool-info`
+    #[serde(default)]
+    pub pool: Option<PoolFields>,
+}
+
+/// Names of the decoded fields `pool-info` reads from a pool account.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PoolFields {
+    /// Base and quote token vaults
+    pub vaults: [String; 2],
+    #[serde(default)]
+    pub fee_rate: Option<String>,
+    /// `fee_rate / fee_denominator` is the fee fraction
+    #[serde(default = "default_fee_denominator")]
+    pub fee_denominator: u64,
+    /// Q64.64 square root price of concentrated liquidity pools
+    #[serde(default)]
+    pub sqrt_price_x64: Option<String>,
+    #[serde(default)]
+    pub tick: Option<String>,
+}
+
+fn default_fee_denominator() -> u64 {
+    1_000_000
+}
+
+impl PackLayout {
+    fn matches(&self, data: &[u8]) -> bool {
+        let discriminator = self
+            .discriminator
+            .as_deref()
+            .map(|hex| hex::decode(hex.trim_start_matches("0x")).unwrap_or_default());
+        if self.size.is_none() && discriminator.is_none() {
+            return false;
+        }
+        self.size.is_none_or(|size| size == data.len())
+            && discriminator.is_none_or(|prefix| data.starts_with(&prefix))
+    }
+
+    /// Decodes `data` when the format covers it exactly.
+    fn decode(&self, data: &[u8]) -> Option<Value> {
+        let mut decoded = unpack_data(data, &self.format, 0).ok()?;
+        (get_byte_length(&decoded).ok()? == data.len()).then_some(())?;
+        if let Value::Object(map) = &mut decoded {
+            map.entry("name")
+                .or_insert_with(|| Value::String(self.name.clone()));
+        }
+        Some(decoded)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct PackProgram {
+    pub name: String,
+    #[serde(default)]
+    pub instructions: Vec<PackLayout>,
+    #[serde(default)]
+    pub accounts: Vec<PackLayout>,
+}
+
+impl PackProgram {
+    /// Decodes account data with the first matching account layout.
+    pub fn decode_account(&self, data: &[u8]) -> Option<(&PackLayout, Value)> {
+        self.accounts
+            .iter()
+            .filter(|layout| layout.matches(data))
+            .find_map(|layout| Some((layout, layout.decode(data)?)))
+    }
+}
+
+/// `pack.json`: decoders and aliases for a set of programs. Shorthand
+/// templates live in the `templates/` directory next to it.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Pack {
+    pub name: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Program id -> layouts
+    #[serde(default)]
+    pub programs: HashMap<String, PackProgram>,
+    /// Alias -> pubkey, usable wherever templates take a pubkey
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+impl Pack {
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = root.join(PACK_MANIFEST);
+        let data = fs::read_to_string(&path).with_context(|| format!("failed to read {path:?}"))?;
+        let mut pack: Pack =
+            serde_json::from_str(&data).with_context(|| format!("invalid pack in {path:?}"))?;
+        check_pack_name(&pack.name)?;
+        pack.root = root.to_path_buf();
+        Ok(pack)
+    }
+}
+
+/// A pack name is a single, non-empty directory name inside the packs directory.
+fn check_pack_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name.contains(['/', '\\'])
+        || safe_relative_path(name).is_err()
+        || Path::new(name).components().count() != 1
+    {
+        return Err(anyhow!("Invalid pack name: {name:?}"));
+    }
+    Ok(())
+}
+
+/// Installed packs.
+#[derive(Debug, Default)]
+pub struct PackRegistry {
+    pub packs: Vec<Pack>,
+}
+
+impl PackRegistry {
+    /// Loads every pack in `dir`, skipping (with a warning) invalid ones.
+    pub fn load(dir: &Path) -> Self {
+        let mut packs = Vec::new();
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let root = entry.path();
+            if root.join(PACK_MANIFEST).exists() {
+                match Pack::load(&root) {
+                    Ok(pack) => packs.push(pack),
+                    Err(error) => eprintln!("Warning: {error:#}"),
+                }
+            }
+        }
+        packs.sort_by(|a, b| a.name.cmp(&b.name));
+        Self { packs }
+    }
+
+    pub fn program(&self, program_id: &str) -> Option<&PackProgram> {
+        self.packs
+            .iter()
+            .find_map(|pack| pack.programs.get(program_id))
+    }
+
+    pub fn pack(&self, name: &str) -> Option<&Pack> {
+        self.packs.iter().find(|pack| pack.name == name)
+    }
+
+    pub fn alias(&self, name: &str) -> Option<Pubkey> {
+        self.packs
+            .iter()
+            .find_map(|pack| pack.aliases.get(name))
+            .and_then(|pubkey| Pubkey::from_str(pubkey).ok())
+    }
+
+    /// Decodes instruction data of a program with a registered layout.
+    pub fn decode_instruction(&self, program_id: &str, data: &[u8]) -> Option<Value> {
+        let program = self.program(program_id)?;
+        program
+            .instructions
+            .iter()
+            .filter(|layout| layout.matches(data))
+            .find_map(|layout| layout.decode(data))
+    }
+
+    /// Decodes account data owned by a program with a registered layout.
+    pub fn decode_account(&self, owner: &str, data: &[u8]) -> Option<Value> {
+        let (_, decoded) = self.program(owner)?.decode_account(data)?;
+        Some(decoded)
+    }
+
+    /// Path of a shorthand template written as `@<pack>/<template>`.
+    pub fn template(&self, reference: &str) -> Option<PathBuf> {
+        let (pack, template) = reference.strip_prefix('@')?.split_once('/')?;
+        let pack = self.pack(pack)?;
+        let path = pack.root.join("templates").join(template);
+        if path.exists() {
+            Some(path)
+        } else {
+            Some(path.with_extension("json"))
+        }
+    }
+}
+
+/// Registers the packs consulted by parsing and templates; only the first call has an effect.
+pub fn set_installed(registry: PackRegistry) {
+    let _ = INSTALLED.set(registry);
+}
+
+pub fn installed() -> Option<&'static PackRegistry> {
+    INSTALLED.get()
+}
+
+/// `$SOLTNET_PACKS_DIR`, or `~/.config/soltnet/packs`.
+pub fn packs_dir() -> PathBuf {
+    if let Some(dir) = env::var_os(PACKS_DIR_ENV) {
+        return PathBuf::from(dir);
+    }
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    home.join(".config/soltnet/packs")
+}
+
+/// Resolves `@<pack>/<template>` references to installed pack templates.
+pub fn resolve_template_path(path: &Path) -> PathBuf {
+    path.to_str()
+        .and_then(|reference| installed()?.template(reference))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Installs a pack from a directory, a `.tar`/`.tar.gz`/`.tar.zst` archive
+/// or an `http(s)` URL of an archive (downloaded with `curl`).
+pub fn install_pack(source: &str, packs_dir: &Path) -> Result<Pack> {
+    let files = if source.starts_with("http://") || source.starts_with("https://") {
+        let download =
+            tempfile::NamedTempFile::new().context("failed to create a download file")?;
+        let status = Command::new("curl")
+            .args(["-fsSL", "-o"])
+            .arg(download.path())
+            .arg(source)
+            .status()
+            .context("failed to run curl")?;
+        if !status.success() {
+            return Err(anyhow!(
+                "Failed to download {source}: curl exited with {status}"
+            ));
+        }
+        read_tar_entries(&mut open_input(download.path())?)?
+    } else {
+        let path = Path::new(source);
+        if path.is_dir() {
+            list_files(path)?
+                .into_iter()
+                .map(|file| {
+                    let name = file
+                        .strip_prefix(path)?
+                        .to_string_lossy()
+                        .replace('\\', "/");
+                    Ok((name, fs::read(&file)?))
+                })
+                .collect::<Result<Vec<_>>>()?
+        } else {
+            read_tar_entries(&mut open_input(path)?)?
+        }
+    };
+
+    // Archives may wrap the pack in a top-level directory.
+    let prefix = files
+        .iter()
+        .filter_map(|(name, _)| name.strip_suffix(PACK_MANIFEST))
+        .min_by_key(|prefix| prefix.len())
+        .ok_or_else(|| anyhow!("{source} has no {PACK_MANIFEST}"))?
+        .to_string();
+    let manifest = files
+        .iter()
+        .find(|(name, _)| *name == format!("{prefix}{PACK_MANIFEST}"))
+        .map(|(_, data)| data)
+        .ok_or_else(|| anyhow!("{source} has no {PACK_MANIFEST}"))?;
+    let name: String = serde_json::from_slice::<Value>(manifest)?["name"]
+        .as_str()
+        .ok_or_else(|| anyhow!("{PACK_MANIFEST} has no name"))?
+        .to_string();
+    // Validated before anything is removed: an empty name or one with
+    // separators would point at the packs directory itself or a nested one.
+    check_pack_name(&name)?;
+    let root = packs_dir.join(&name);
+    let entries = files
+        .iter()
+        .filter_map(|(name, data)| Some((name.strip_prefix(&prefix)?, data)))
+        .map(|(relative, data)| Ok((root.join(safe_relative_path(relative)?), data)))
+        .collect::<Result<Vec<_>>>()?;
+
+    match fs::remove_dir_all(&root) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => {
+            return Err(error).with_context(|| format!("failed to remove {root:?}"));
+        }
+        _ => {}
+    }
+    for (path, data) in entries {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, data).with_context(|| format!("failed to write {path:?}"))?;
+    }
+    Pack::load(&root)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::{PACK_MANIFEST, Pack, PackRegistry, install_pack};
+
+    #[test]
+    fn install_rejects_names_outside_the_packs_dir() {
+        let packs = tempfile::tempdir().unwrap();
+        fs::create_dir(packs.path().join("installed")).unwrap();
+        for name in ["", ".", "..", "installed/nested", "/abs"] {
+            let source = tempfile::tempdir().unwrap();
+            fs::write(
+                source.path().join(PACK_MANIFEST),
+                json!({ "name": name }).to_string(),
+            )
+            .unwrap();
+            let source = source.path().display().to_string();
+            assert!(install_pack(&source, packs.path()).is_err(), "{name:?}");
+        }
+        assert!(packs.path().join("installed").exists());
+    }
+
+    #[test]
+    fn decodes_matching_instruction_layouts() {
+        let pack: Pack = serde_json::from_value(json!({
+            "name": "amm",
+            "programs": {
+                "Amm1111111111111111111111111111111111111111": {
+                    "name": "Test AMM",
+                    "instructions": [{
+                        "name": "swap",
+                        "discriminator": "0x09",
+                        "format": { "type": "object", "data": [
+                            { "type": "u8", "name": "instruction" },
+                            { "type": "u64", "name": "amount_in" }
+                        ]}
+                    }]
+                }
+            },
+            "aliases": { "amm": "Amm1111111111111111111111111111111111111111" }
+        }))
+        .unwrap();
+        let registry = PackRegistry { packs: vec![pack] };
+        let program = "Amm1111111111111111111111111111111111111111";
+
+        let mut data = vec![9];
+        data.extend(500u64.to_le_bytes());
+        let decoded = registry.decode_instruction(program, &data).unwrap();
+        assert_eq!(decoded["name"], "swap");
+        assert_eq!(decoded["data"][1]["data"], 500);
+
+        data[0] = 1;
+        assert!(registry.decode_instruction(program, &data).is_none());
+        assert!(registry.decode_instruction(program, &[9, 1]).is_none());
+        assert_eq!(registry.alias("amm").unwrap().to_string(), program);
+    }
+}
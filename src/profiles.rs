@@ -0,0 +1,360 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use toml_edit::{DocumentMut, Item, Table};
+
+use crate::network::Commitment;
+
+/// Overrides the path of the user config file
+pub const CONFIG_FILE_ENV: &str = "SOLTNET_CONFIG";
+pub const DEFAULT_PROFILE: &str = "default";
+/// Values a profile can set.
+pub const PROFILE_KEYS: [&str; 7] = [
+    "source",
+    "target",
+    "keypair",
+    "output_dir",
+    "commitment",
+    "skip_preflight",
+    "max_retries",
+];
+
+/// Defaults of a set of commands, e.g. one per project or cluster.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    /// Cluster to read state from
+    pub source: Option<String>,
+    /// Cluster to execute transactions on
+    pub target: Option<String>,
+    /// Keypair used as `$local:payer`
+    pub keypair: Option<String>,
+    /// Output directory of dumps
+    pub output_dir: Option<PathBuf>,
+    pub commitment: Option<Commitment>,
+    /// Send transactions without the preflight simulation
+    pub skip_preflight: Option<bool>,
+    /// Times the RPC node retries sending a transaction to the leader
+    pub max_retries: Option<usize>,
+}
+
+impl Profile {
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(match key {
+            "source" => self.source.clone(),
+            "target" => self.target.clone(),
+            "keypair" => self.keypair.clone(),
+            "output_dir" => self
+                .output_dir
+                .as_ref()
+                .map(|dir| dir.display().to_string()),
+            "commitment" => self
+                .commitment
+                .and_then(|commitment| commitment.to_possible_value())
+                .map(|value| value.get_name().to_string()),
+            "skip_preflight" => self.skip_preflight.map(|skip| skip.to_string()),
+            "max_retries" => self.max_retries.map(|retries| retries.to_string()),
+            _ => return Err(unknown_key(key)),
+        })
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = value.to_string();
+        match key {
+            "source" => self.source = Some(value),
+            "target" => self.target = Some(value),
+            "keypair" => self.keypair = Some(value),
+            "output_dir" => self.output_dir = Some(PathBuf::from(value)),
+            "commitment" => {
+                self.commitment = Some(
+                    Commitment::from_str(&value, true)
+                        .map_err(|_| anyhow!("Invalid commitment: {value}"))?,
+                )
+            }
+            "skip_preflight" => {
+                self.skip_preflight = Some(
+                    value
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid skip_preflight: {value}"))?,
+                )
+            }
+            "max_retries" => {
+                self.max_retries = Some(
+                    value
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid max_retries: {value}"))?,
+                )
+            }
+            _ => return Err(unknown_key(key)),
+        }
+        Ok(())
+    }
+}
+
+fn unknown_key(key: &str) -> anyhow::Error {
+    anyhow!(
+        "Unknown profile key `{key}` (expected one of {})",
+        PROFILE_KEYS.join(", ")
+    )
+}
+
+/// User config file `~/.config/soltnet/config.toml`: named profiles and the
+/// active one. Changes are made to the parsed document, so comments and
+/// formatting of the file are kept.
+#[derive(Debug, Default)]
+pub struct UserConfig {
+    pub active: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+    doc: DocumentMut,
+    /// The file on disk could not be parsed and is moved aside on save.
+    unreadable: bool,
+}
+
+impl UserConfig {
+    /// Parses a config file. Invalid profiles or values are skipped with a
+    /// warning, so one typo does not stop every command.
+    pub fn parse(text: &str) -> Result<Self> {
+        let doc: DocumentMut = text.parse()?;
+        let mut config = UserConfig {
+            active: doc
+                .get("active")
+                .and_then(|item| item.as_str())
+                .map(str::to_string),
+            ..UserConfig::default()
+        };
+        if let Some(profiles) = doc.get("profiles") {
+            match profiles.as_table_like() {
+                Some(profiles) => {
+                    for (name, item) in profiles.iter() {
+                        match parse_profile(name, item) {
+                            Ok(profile) => {
+                                config.profiles.insert(name.to_string(), profile);
+                            }
+                            Err(error) => {
+                                eprintln!("Warning: skipping profile `{name}`: {error:#}")
+                            }
+                        }
+                    }
+                }
+                None => eprintln!("Warning: ignoring `profiles`, it must be a table"),
+            }
+        }
+        config.doc = doc;
+        Ok(config)
+    }
+
+    /// Loads the config file, or an empty config when there is none or it is
+    /// not valid TOML.
+    pub fn load(path: &Path) -> Self {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        Self::parse(&text).unwrap_or_else(|error| {
+            eprintln!("Warning: ignoring invalid config {path:?}: {error}");
+            Self {
+                unreadable: true,
+                ..Self::default()
+            }
+        })
+    }
+
+    /// Writes the config. A file that could not be parsed is kept next to it
+    /// as `<name>.bak` instead of being overwritten.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if self.unreadable {
+            let backup = path.with_extension("toml.bak");
+            fs::rename(path, &backup)
+                .with_context(|| format!("failed to move {path:?} to {backup:?}"))?;
+            eprintln!("Moved the invalid config to {backup:?}");
+        }
+        fs::write(path, self.doc.to_string()).with_context(|| format!("failed to write {path:?}"))
+    }
+
+    /// Sets `key` of the profile `name`, creating the profile when needed.
+    pub fn set(&mut self, name: &str, key: &str, value: &str) -> Result<()> {
+        let mut profile = self.profiles.get(name).cloned().unwrap_or_default();
+        profile.set(key, value)?;
+        let value = match key {
+            "skip_preflight" => toml_edit::value(profile.skip_preflight.unwrap_or_default()),
+            "max_retries" => toml_edit::value(profile.max_retries.unwrap_or_default() as i64),
+            _ => toml_edit::value(profile.get(key)?.unwrap_or_default()),
+        };
+        let profiles = self.doc.entry("profiles").or_insert_with(|| {
+            let mut table = Table::new();
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+        let table = profiles
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow!("`profiles` must be a table"))?
+            .entry(name)
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_like_mut()
+            .ok_or_else(|| anyhow!("`profiles.{name}` must be a table"))?;
+        table.insert(key, value);
+        self.profiles.insert(name.to_string(), profile);
+        Ok(())
+    }
+
+    /// Makes the defined profile `name` the active one.
+    pub fn set_active(&mut self, name: &str) -> Result<()> {
+        self.check_profile(name)?;
+        self.active = Some(name.to_string());
+        self.doc["active"] = toml_edit::value(name);
+        Ok(())
+    }
+
+    /// Name of the profile in use: `name` when given, else the active one.
+    pub fn profile_name(&self, name: Option<&str>) -> String {
+        name.map(str::to_string)
+            .or_else(|| self.active.clone())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+    }
+
+    /// The profile `name` (or the active one). Only the default profile may
+    /// be undefined, it is empty then.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let name = self.profile_name(name);
+        if name == DEFAULT_PROFILE {
+            return Ok(self.profiles.get(&name).cloned().unwrap_or_default());
+        }
+        self.check_profile(&name)?;
+        Ok(self.profiles[&name].clone())
+    }
+
+    fn check_profile(&self, name: &str) -> Result<()> {
+        if self.profiles.contains_key(name) {
+            return Ok(());
+        }
+        let defined: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+        Err(anyhow!(
+            "Unknown profile `{name}` (defined: {}); create it with `soltnet --profile {name} config set <key> <value>`",
+            if defined.is_empty() {
+                "none".to_string()
+            } else {
+                defined.join(", ")
+            }
+        ))
+    }
+}
+
+fn parse_profile(name: &str, item: &Item) -> Result<Profile> {
+    let table = item
+        .as_table_like()
+        .ok_or_else(|| anyhow!("`profiles.{name}` must be a table"))?;
+    let mut profile = Profile::default();
+    for (key, value) in table.iter() {
+        let value = match value.as_value() {
+            Some(toml_edit::Value::String(text)) => text.value().clone(),
+            Some(toml_edit::Value::Boolean(flag)) => flag.value().to_string(),
+            Some(toml_edit::Value::Integer(number)) => number.value().to_string(),
+            _ => return Err(anyhow!("`profiles.{name}.{key}` must be a string")),
+        };
+        profile.set(key, &value)?;
+    }
+    Ok(profile)
+}
+
+pub fn config_path() -> PathBuf {
+    if let Some(path) = env::var_os(CONFIG_FILE_ENV) {
+        return PathBuf::from(path);
+    }
+    let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    home.join(".config/soltnet/config.toml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserConfig;
+    use crate::network::Commitment;
+
+    #[test]
+    fn profiles_round_trip() {
+        let mut config = UserConfig::parse(
+            "active = \"dev\"\n\n[profiles.dev]\ntarget = \"devnet\"\ncommitment = \"finalized\"\n",
+        )
+        .unwrap();
+        let profile = config.profile(None).unwrap();
+        assert_eq!(profile.target.as_deref(), Some("devnet"));
+        assert_eq!(profile.commitment, Some(Commitment::Finalized));
+        assert!(config.profile(Some("other")).is_err());
+        assert_eq!(config.profile(Some("default")).unwrap().target, None);
+        assert!(config.set_active("other").is_err());
+
+        config.set("ci", "output_dir", "./out \"dir\"").unwrap();
+        config.set_active("ci").unwrap();
+        let reparsed = UserConfig::parse(&config.doc.to_string()).unwrap();
+        assert_eq!(reparsed.active.as_deref(), Some("ci"));
+        assert_eq!(
+            reparsed.profile(None).unwrap().get("output_dir").unwrap(),
+            Some("./out \"dir\"".to_string())
+        );
+
+        let sending =
+            UserConfig::parse("[profiles.dev]\nskip_preflight = true\nmax_retries = 0\n").unwrap();
+        let dev = sending.profile(Some("dev")).unwrap();
+        assert_eq!(dev.skip_preflight, Some(true));
+        assert_eq!(dev.max_retries, Some(0));
+    }
+
+    #[test]
+    fn invalid_profiles_are_skipped() {
+        let mut config = UserConfig::parse(
+            "[profiles.dev]\ncommitment = \"max\"\n\n[profiles.ci]\ntarget = \"devnet\"\n",
+        )
+        .unwrap();
+        assert!(config.profile(Some("dev")).is_err());
+        assert_eq!(
+            config.profile(Some("ci")).unwrap().target.as_deref(),
+            Some("devnet")
+        );
+        config.set("dev", "commitment", "confirmed").unwrap();
+        let fixed = UserConfig::parse(&config.doc.to_string()).unwrap();
+        assert_eq!(
+            fixed.profile(Some("dev")).unwrap().commitment,
+            Some(Commitment::Confirmed)
+        );
+    }
+
+    #[test]
+    fn set_keeps_comments() {
+        let text = "# my profiles\nactive = \"dev\" # current\n\n[profiles.dev]\n# devnet RPC\ntarget = \"devnet\"\n";
+        let mut config = UserConfig::parse(text).unwrap();
+        config.set("dev", "max_retries", "3").unwrap();
+        config.set("ci", "skip_preflight", "true").unwrap();
+        let saved = config.doc.to_string();
+        assert!(saved.starts_with(text), "{saved}");
+        assert!(saved.contains("max_retries = 3"), "{saved}");
+        assert!(
+            saved.contains("[profiles.ci]\nskip_preflight = true"),
+            "{saved}"
+        );
+    }
+
+    #[test]
+    fn unreadable_configs_are_moved_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, "[profiles.dev\n").unwrap();
+        let mut config = UserConfig::load(&path);
+        assert!(config.profiles.is_empty());
+        config.set("dev", "target", "devnet").unwrap();
+        config.save(&path).unwrap();
+        let reloaded = UserConfig::load(&path);
+        assert_eq!(
+            reloaded.profile(Some("dev")).unwrap().target.as_deref(),
+            Some("devnet")
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("config.toml.bak")).unwrap(),
+            "[profiles.dev\n"
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_rpc_client::{
+    api::{
+        client_error::{Error as ClientError, ErrorKind, Result as ClientResult},
+        request::{RpcError, RpcRequest, RpcResponseErrorData},
+    },
+    http_sender::HttpSender,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One recorded RPC call. Without `url` the entry answers calls to any
+/// endpoint, without `params` any call of the method.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RpcExchange {
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+    #[serde(default)]
+    pub result: Value,
+    /// Error of a failed call, replayed instead of `result`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcExchangeError>,
+}
+
+/// A recorded RPC error: JSON-RPC errors keep their code (their data is not
+/// recorded), transport errors only the message.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RpcExchangeError {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<i64>,
+    pub message: String,
+}
+
+impl RpcExchange {
+    fn response(&self) -> ClientResult<Value> {
+        match &self.error {
+            None => Ok(self.result.clone()),
+            Some(RpcExchangeError {
+                code: Some(code),
+                message,
+            }) => Err(RpcError::RpcResponseError {
+                code: *code,
+                message: message.clone(),
+                data: RpcResponseErrorData::Empty,
+            }
+            .into()),
+            Some(RpcExchangeError { message, .. }) => {
+                Err(ErrorKind::Custom(message.clone()).into())
+            }
+        }
+    }
+}
+
+impl From<&ClientError> for RpcExchangeError {
+    fn from(error: &ClientError) -> Self {
+        match error.kind() {
+            ErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => Self {
+                code: Some(*code),
+                message: message.clone(),
+            },
+            _ => Self {
+                code: None,
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+/// File-backed RPC responses: a JSON array of
+/// `{ method, url?, params?, result | error }`.
+///
+/// A call is answered only by entries of its method and endpoint with the
+/// same params, replayed in recorded order (the last one is reused once
+/// exhausted), or by entries without params.
+#[derive(Debug, Default)]
+pub struct RpcMocks {
+    exchanges: Vec<RpcExchange>,
+    used: Mutex<Vec<bool>>,
+}
+
+impl RpcMocks {
+    pub fn new(exchanges: Vec<RpcExchange>) -> Self {
+        let used = Mutex::new(vec![false; exchanges.len()]);
+        Self { exchanges, used }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        let exchanges = serde_json::from_str(&data)
+            .with_context(|| format!("invalid RPC mocks in {path:?}"))?;
+        Ok(Self::new(exchanges))
+    }
+
+    /// Finds the response for a call to `url`: the next unused entry with the
+    /// same params, then a params-less entry, and finally the last entry with
+    /// the same params. `None` when nothing was recorded for the call.
+    pub fn respond(&self, url: &str, method: &str, params: &Value) -> Option<ClientResult<Value>> {
+        let mut used = self.used.lock().unwrap();
+        let candidates = || {
+            self.exchanges.iter().enumerate().filter(|(_, ex)| {
+                ex.method == method && ex.url.as_ref().is_none_or(|ex_url| ex_url == url)
+            })
+        };
+        let same_params = |ex: &RpcExchange| ex.params.as_ref() == Some(params);
+
+        let next = candidates()
+            .find(|(index, ex)| !used[*index] && same_params(ex))
+            .or_else(|| candidates().find(|(_, ex)| ex.params.is_none()));
+        if let Some((index, ex)) = next {
+            used[index] = true;
+            return Some(ex.response());
+        }
+        candidates()
+            .rfind(|(_, ex)| same_params(ex))
+            .map(|(_, ex)| ex.response())
+    }
+}
+
+/// Cassette that every RPC exchange of a command is appended to.
+#[derive(Debug)]
+pub struct RpcRecorder {
+    path: PathBuf,
+    exchanges: Mutex<Vec<RpcExchange>>,
+}
+
+impl RpcRecorder {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            exchanges: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Appends an exchange and rewrites the cassette, so it is complete even
+    /// if the command fails afterwards.
+    fn record(&self, exchange: RpcExchange) -> Result<()> {
+        let mut exchanges = self.exchanges.lock().unwrap();
+        exchanges.push(exchange);
+        fs::write(&self.path, serde_json::to_string_pretty(&*exchanges)?)
+            .with_context(|| format!("failed to write {:?}", self.path))
+    }
+}
+
+/// HTTP transport sending extra headers. Secrets (header values and API keys
+/// in the URL) are masked in transport error messages.
+pub struct HttpRpcSender {
+    /// The HTTP client, or why it could not be built; every request then
+    /// fails with that error.
+    inner: std::result::Result<HttpSender, String>,
+    url: String,
+    secrets: Vec<String>,
+}
+
+impl HttpRpcSender {
+    pub fn new(url: &str, headers: &HeaderMap) -> Self {
+        let mut default_headers = HttpSender::default_headers();
+        default_headers.extend(headers.clone());
+        let inner = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .timeout(HTTP_TIMEOUT)
+            .pool_idle_timeout(HTTP_TIMEOUT)
+            .build()
+            .map(|client| HttpSender::new_with_client(url, client))
+            .map_err(|error| format!("Failed to build the RPC client: {error}"));
+
+        let mut secrets: Vec<String> = headers
+            .values()
+            .filter_map(|value| value.to_str().ok())
+            .filter(|value| value.len() >= MIN_SECRET_LEN)
+            .map(str::to_string)
+            .collect();
+        secrets.extend(url_secrets(url));
+        Self {
+            inner,
+            url: url.to_string(),
+            secrets,
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for HttpRpcSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let inner = self
+            .inner
+            .as_ref()
+            .map_err(|error| ErrorKind::Custom(error.clone()))?;
+        inner
+            .send(request, params)
+            .await
+            .map_err(|error| match error.kind() {
+                ErrorKind::Reqwest(_) => {
+                    ErrorKind::Custom(mask_secrets(&error.to_string(), &self.secrets)).into()
+                }
+                _ => error,
+            })
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner
+            .as_ref()
+            .map(RpcSender::get_transport_stats)
+            .unwrap_or_default()
+    }
+
+    fn url(&self) -> String {
+        mask_secrets(&self.url, &self.secrets)
+    }
+}
+
+/// Query parameters providers put API keys in.
+const KEY_PARAMS: [&str; 4] = ["api-key", "api_key", "token", "key"];
+
+/// Shorter values are not masked, so they cannot garble unrelated text.
+const MIN_SECRET_LEN: usize = 8;
+
+/// Path segments at least this long mixing letters and digits are taken as
+/// API keys (e.g. `/v2/<key>`).
+const MIN_PATH_KEY_LEN: usize = 20;
+
+fn is_path_key(segment: &str) -> bool {
+    segment.len() >= MIN_PATH_KEY_LEN
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && segment.chars().any(|c| c.is_ascii_digit())
+        && segment.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+/// API keys in a URL: values of the [`KEY_PARAMS`] query parameters and
+/// key-like path segments.
+fn url_secrets(url: &str) -> Vec<String> {
+    let (base, query) = url.split_once('?').unwrap_or((url, ""));
+    let path = base.split_once("://").map_or(base, |(_, rest)| rest);
+    let path_keys = path
+        .split('/')
+        .skip(1)
+        .filter(|segment| is_path_key(segment));
+    let query_keys = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(name, _)| KEY_PARAMS.contains(&name.to_ascii_lowercase().as_str()))
+        .map(|(_, value)| value);
+    path_keys
+        .chain(query_keys)
+        .filter(|secret| secret.len() >= MIN_SECRET_LEN)
+        .map(str::to_string)
+        .collect()
+}
+
+pub fn mask_secrets(text: &str, secrets: &[String]) -> String {
+    secrets
+        .iter()
+        .filter(|secret| !secret.is_empty())
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), "***")
+        })
+}
+
+/// `RpcSender` forwarding requests over HTTP and recording every response,
+/// errors included, with the endpoint URL (API keys masked).
+pub struct RecordingRpcSender {
+    inner: HttpRpcSender,
+    recorder: Arc<RpcRecorder>,
+}
+
+impl RecordingRpcSender {
+    pub fn new(url: &str, headers: &HeaderMap, recorder: Arc<RpcRecorder>) -> Self {
+        Self {
+            inner: HttpRpcSender::new(url, headers),
+            recorder,
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for RecordingRpcSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let response = self.inner.send(request, params.clone()).await;
+        let exchange = RpcExchange {
+            method: request.to_string(),
+            url: Some(self.inner.url()),
+            params: Some(params),
+            result: response.as_ref().cloned().unwrap_or_default(),
+            error: response.as_ref().err().map(RpcExchangeError::from),
+        };
+        if let Err(error) = self.recorder.record(exchange) {
+            eprintln!("Warning: {error:#}");
+        }
+        response
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+/// `RpcSender` answering every request from `RpcMocks` instead of the
+/// network; requests without a recorded response fail.
+pub struct MockRpcSender {
+    url: String,
+    mocks: Arc<RpcMocks>,
+    requests: AtomicUsize,
+}
+
+impl MockRpcSender {
+    pub fn new(url: &str, mocks: Arc<RpcMocks>) -> Self {
+        Self {
+            // Recorded URLs have their API keys masked
+            url: mask_secrets(url, &url_secrets(url)),
+            mocks,
+            requests: AtomicUsize::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl RpcSender for MockRpcSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let method = request.to_string();
+        self.mocks
+            .respond(&self.url, &method, &params)
+            .unwrap_or_else(|| {
+                Err(ErrorKind::Custom(format!("no mock response for {method} {params}")).into())
+            })
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats {
+            request_count: self.requests.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use serde_json::Value;
+
+    use super::{RpcExchange, RpcExchangeError, RpcMocks, mask_secrets, url_secrets};
+
+    const URL: &str = "http://127.0.0.1:8899";
+
+    fn result(mocks: &RpcMocks, url: &str, method: &str, params: Value) -> Option<Value> {
+        mocks
+            .respond(url, method, &params)
+            .map(|response| response.unwrap())
+    }
+
+    #[test]
+    fn respond_prefers_matching_params() {
+        let mocks = RpcMocks::new(vec![
+            RpcExchange {
+                method: "getBalance".to_string(),
+                result: json!(1),
+                ..Default::default()
+            },
+            RpcExchange {
+                method: "getBalance".to_string(),
+                params: Some(json!(["abc"])),
+                result: json!(2),
+                ..Default::default()
+            },
+        ]);
+        let balance = |params| result(&mocks, URL, "getBalance", params);
+        assert_eq!(balance(json!(["abc"])), Some(json!(2)));
+        assert_eq!(balance(json!(["def"])), Some(json!(1)));
+        assert_eq!(result(&mocks, URL, "getSlot", json!([])), None);
+    }
+
+    #[test]
+    fn respond_replays_in_recorded_order() {
+        let exchange = |params, result| RpcExchange {
+            method: "getSignatureStatuses".to_string(),
+            params: Some(params),
+            result,
+            ..Default::default()
+        };
+        let mocks = RpcMocks::new(vec![
+            exchange(json!(["a"]), json!(null)),
+            exchange(json!(["a"]), json!("confirmed")),
+            exchange(json!(["b"]), json!("other")),
+        ]);
+        let respond = |params| result(&mocks, URL, "getSignatureStatuses", params);
+        assert_eq!(respond(json!(["a"])), Some(json!(null)));
+        assert_eq!(respond(json!(["a"])), Some(json!("confirmed")));
+        assert_eq!(respond(json!(["a"])), Some(json!("confirmed")));
+        assert_eq!(respond(json!(["c"])), None);
+        assert_eq!(respond(json!(["b"])), Some(json!("other")));
+    }
+
+    #[test]
+    fn respond_matches_urls_and_replays_errors() {
+        let exchange = |url: &str, result, error| RpcExchange {
+            method: "getAccountInfo".to_string(),
+            url: Some(url.to_string()),
+            params: Some(json!(["a"])),
+            result,
+            error,
+        };
+        let mocks = RpcMocks::new(vec![
+            exchange("https://source.example.com", json!("source"), None),
+            exchange(
+                URL,
+                Value::Null,
+                Some(RpcExchangeError {
+                    code: Some(-32602),
+                    message: "Invalid param".to_string(),
+                }),
+            ),
+        ]);
+        let source = result(
+            &mocks,
+            "https://source.example.com",
+            "getAccountInfo",
+            json!(["a"]),
+        );
+        assert_eq!(source, Some(json!("source")));
+        let error = mocks
+            .respond(URL, "getAccountInfo", &json!(["a"]))
+            .unwrap()
+            .unwrap_err();
+        assert!(error.to_string().contains("Invalid param"), "{error}");
+        assert!(
+            mocks
+                .respond("http://other", "getAccountInfo", &json!(["a"]))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn masks_api_keys_from_urls() {
+        let url = "https://mainnet.example.com/?api-key=secret123&x=1&cluster=mainnet";
+        let secrets = url_secrets(url);
+        assert_eq!(secrets, ["secret123"]);
+        assert_eq!(
+            mask_secrets(&format!("error 1 for url ({url})"), &secrets),
+            "error 1 for url (https://mainnet.example.com/?api-key=***&x=1&cluster=mainnet)"
+        );
+
+        let url = "https://solana-mainnet.example.com/v2/AbCdEf0123456789xYz42";
+        let secrets = url_secrets(url);
+        assert_eq!(secrets, ["AbCdEf0123456789xYz42"]);
+        assert_eq!(
+            mask_secrets(url, &secrets),
+            "https://solana-mainnet.example.com/v2/***"
+        );
+        assert!(url_secrets("http://127.0.0.1:8899/?token=short").is_empty());
+    }
+}
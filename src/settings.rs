@@ -0,0 +1,123 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use solana_sdk::pubkey::Pubkey;
+use toml_edit::{DocumentMut, Item};
+
+use crate::network::resolve_cluster;
+use crate::tx_format::pubkey::parse_pubkey_str;
+
+/// Project settings file, looked up from the current directory upwards.
+pub const SETTINGS_FILE: &str = "soltnet.toml";
+
+/// Settings of one testnet instance, keyed in `soltnet.toml` by cluster name
+/// or RPC URL, e.g. `[instances.local]`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstanceSettings {
+    /// Lookup tables `exec-tx` attaches when they shorten a transaction
+    pub lookup_tables: Vec<Pubkey>,
+}
+
+#[derive(Debug, Default)]
+pub struct Settings {
+    /// Instance settings by RPC URL
+    pub instances: HashMap<String, InstanceSettings>,
+}
+
+impl Settings {
+    pub fn parse(text: &str) -> Result<Self> {
+        let doc: DocumentMut = text.parse()?;
+        let mut settings = Settings::default();
+        let Some(instances) = doc.get("instances") else {
+            return Ok(settings);
+        };
+        let instances = instances
+            .as_table_like()
+            .ok_or_else(|| anyhow!("`instances` must be a table"))?;
+        for (name, item) in instances.iter() {
+            let table = item
+                .as_table_like()
+                .ok_or_else(|| anyhow!("`instances.{name}` must be a table"))?;
+            let mut instance = InstanceSettings::default();
+            if let Some(tables) = table.get("lookup_tables") {
+                instance.lookup_tables = pubkeys(tables)
+                    .with_context(|| format!("Invalid `instances.{name}.lookup_tables`"))?;
+            }
+            settings.instances.insert(resolve_cluster(name), instance);
+        }
+        Ok(settings)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        Self::parse(&text).with_context(|| format!("Invalid settings in {path:?}"))
+    }
+
+    /// Loads the nearest `soltnet.toml`, or empty settings when there is none.
+    pub fn discover() -> Result<Self> {
+        match find_settings_file() {
+            Some(path) => Self::load(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn instance(&self, rpc_url: &str) -> Option<&InstanceSettings> {
+        self.instances.get(rpc_url)
+    }
+
+    pub fn lookup_tables(&self, rpc_url: &str) -> Vec<Pubkey> {
+        self.instance(rpc_url)
+            .map(|instance| instance.lookup_tables.clone())
+            .unwrap_or_default()
+    }
+}
+
+fn find_settings_file() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .map(|dir| dir.join(SETTINGS_FILE))
+        .find(|path| path.is_file())
+}
+
+fn pubkeys(item: &Item) -> Result<Vec<Pubkey>> {
+    item.as_array()
+        .ok_or_else(|| anyhow!("expected an array of pubkeys"))?
+        .iter()
+        .map(|value| {
+            let value = value
+                .as_str()
+                .ok_or_else(|| anyhow!("expected a pubkey string"))?;
+            parse_pubkey_str(value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::Settings;
+    use crate::network::{DEVNET_RPC_URL, LOCAL_RPC_URL};
+
+    #[test]
+    fn parses_instance_lookup_tables() {
+        let table = Pubkey::new_unique();
+        let settings = Settings::parse(&format!(
+            "[instances.local]\nlookup_tables = [\"{table}\"]\n\n\
+             [instances.\"https://rpc.example.com\"]\nlookup_tables = []\n"
+        ))
+        .unwrap();
+
+        assert_eq!(
+            settings.instance(LOCAL_RPC_URL).unwrap().lookup_tables,
+            [table]
+        );
+        assert!(settings.instance("https://rpc.example.com").is_some());
+        assert!(settings.instance(DEVNET_RPC_URL).is_none());
+        assert!(Settings::parse("[instances.local]\nlookup_tables = [1]").is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Disposable local testnet for Rust integration tests.
+//!
+//! ```no_run
+//! use soltnet::testnet::Testnet;
+//! use solana_sdk::signer::Signer;
+//!
+//! let testnet = Testnet::builder().fixtures("./fixtures/accounts").start()?;
+//! let balance = testnet.client().get_balance(&testnet.payer().pubkey())?;
+//! assert!(balance > 0);
+//! // The container is stopped when `testnet` is dropped.
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    signer::{Signer, keypair::Keypair},
+};
+
+use crate::config::{set_testnet_config, start_testnet_container, stop_testnet_container};
+use crate::network::{LOCAL_RPC_URL, NetworkCtx};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Options for [`Testnet::start`].
+pub struct TestnetBuilder {
+    fixtures: Option<PathBuf>,
+    rpc_url: String,
+    payer_lamports: u64,
+    startup_timeout: Duration,
+}
+
+impl Default for TestnetBuilder {
+    fn default() -> Self {
+        Self {
+            fixtures: None,
+            rpc_url: LOCAL_RPC_URL.to_string(),
+            payer_lamports: 100 * LAMPORTS_PER_SOL,
+            startup_timeout: STARTUP_TIMEOUT,
+        }
+    }
+}
+
+impl TestnetBuilder {
+    /// Dump directory (as written by `dump`/`dump-from-tx`) loaded into the validator.
+    pub fn fixtures(mut self, path: impl AsRef<Path>) -> Self {
+        self.fixtures = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// RPC URL of the validator, `http://127.0.0.1:8899` by default.
+    pub fn rpc_url(mut self, url: &str) -> Self {
+        self.rpc_url = url.to_string();
+        self
+    }
+
+    /// Lamports airdropped to the generated payer.
+    pub fn payer_lamports(mut self, lamports: u64) -> Self {
+        self.payer_lamports = lamports;
+        self
+    }
+
+    /// How long to wait for the validator to become healthy.
+    pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// Attaches to a validator that is already running, or starts the
+    /// testnet container with the fixtures loaded. A funded payer is created
+    /// either way.
+    pub fn start(self) -> Result<Testnet> {
+        let network = NetworkCtx::new(LOCAL_RPC_URL, &self.rpc_url);
+        let client = network.target_client();
+        let running = client.get_health().is_ok();
+        if running && self.fixtures.is_some() {
+            return Err(anyhow!(
+                "A validator is already running at {}; stop it to load fixtures",
+                self.rpc_url
+            ));
+        }
+
+        let testnet = Testnet {
+            network,
+            client,
+            payer: Keypair::new(),
+            owned: !running,
+        };
+        if testnet.owned {
+            set_testnet_config(self.fixtures.as_deref())?;
+            start_testnet_container()?;
+            testnet.wait_healthy(self.startup_timeout)?;
+        }
+        testnet.fund_payer(self.payer_lamports)?;
+        Ok(testnet)
+    }
+}
+
+/// Polls the validator at `url` until it reports healthy or `timeout` passes.
+pub fn wait_healthy(client: &RpcClient, url: &str, timeout: Duration) -> Result<()> {
+    let started = Instant::now();
+    while client.get_health().is_err() {
+        if started.elapsed() > timeout {
+            return Err(anyhow!(
+                "Validator at {url} did not become healthy within {}s",
+                timeout.as_secs()
+            ));
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
+
+/// Restarts the local testnet container with the accounts of a dump directory.
+pub fn restart_testnet(network: &NetworkCtx, accounts: &Path) -> Result<()> {
+    let _ = stop_testnet_container();
+    set_testnet_config(Some(accounts))?;
+    start_testnet_container()?;
+    wait_healthy(&network.target_client(), &network.target, STARTUP_TIMEOUT)
+}
+
+/// Running validator with a funded payer. A container started by the guard
+/// is stopped on drop; an attached validator is left running.
+pub struct Testnet {
+    network: NetworkCtx,
+    client: Arc<RpcClient>,
+    payer: Keypair,
+    owned: bool,
+}
+
+impl Testnet {
+    /// Starts or attaches to the local testnet with default options.
+    pub fn start() -> Result<Self> {
+        Self::builder().start()
+    }
+
+    pub fn builder() -> TestnetBuilder {
+        TestnetBuilder::default()
+    }
+
+    pub fn client(&self) -> &RpcClient {
+        &self.client
+    }
+
+    pub fn payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    /// Network context targeting the validator, for calling soltnet tools.
+    pub fn network(&self) -> &NetworkCtx {
+        &self.network
+    }
+
+    fn wait_healthy(&self, timeout: Duration) -> Result<()> {
+        wait_healthy(&self.client, &self.network.target, timeout)
+    }
+
+    fn fund_payer(&self, lamports: u64) -> Result<()> {
+        if lamports == 0 {
+            return Ok(());
+        }
+        let signature = self
+            .client
+            .request_airdrop(&self.payer.pubkey(), lamports)?;
+        self.client
+            .poll_for_signature_with_commitment(&signature, self.client.commitment())?;
+        Ok(())
+    }
+}
+
+impl Drop for Testnet {
+    fn drop(&mut self) {
+        if self.owned
+            && let Err(error) = stop_testnet_container()
+        {
+            eprintln!("Failed to stop testnet: {error:#}");
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use solana_address_lookup_table_interface::state::AddressLookupTable;
+use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, signer::keypair::Keypair};
+
+use crate::network::NetworkCtx;
+use crate::tools::tx::{create_lookup_table_with_addresses, fetch_lookup_table};
+
+const ALT_CACHE_FILE: &str = "soltnet-alts.json";
+/// How long `--auto-alt` waits for a recreated table to become usable
+const ALT_WARM_UP_TIMEOUT: Duration = Duration::from_secs(30);
+const ALT_POLL_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Local copies of source lookup tables per target, so repeated runs reuse
+/// the tables created by earlier ones.
+#[derive(Debug, Default)]
+pub struct AltCache {
+    path: PathBuf,
+    /// target URL -> source table -> local table
+    entries: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl AltCache {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = match fs::read_to_string(&path) {
+            Ok(data) => {
+                serde_json::from_str(&data).with_context(|| format!("invalid JSON in {path:?}"))?
+            }
+            Err(_) => BTreeMap::new(),
+        };
+        Ok(Self { path, entries })
+    }
+
+    pub fn get(&self, target: &str, table: &Pubkey) -> Option<Pubkey> {
+        self.entries
+            .get(target)?
+            .get(&table.to_string())?
+            .parse()
+            .ok()
+    }
+
+    pub fn insert(&mut self, target: &str, table: &Pubkey, local: &Pubkey) {
+        self.entries
+            .entry(target.to_string())
+            .or_default()
+            .insert(table.to_string(), local.to_string());
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(&self.entries)?)
+            .with_context(|| format!("failed to write {:?}", self.path))
+    }
+}
+
+/// Makes the lookup tables of a template usable on the target. Tables that
+/// are missing there are recreated (owned by `authority`) with the
+/// addresses they hold on the source; returns the replacement of each
+/// recreated table.
+pub fn materialize_lookup_tables(
+    network: &NetworkCtx,
+    tables: &[Pubkey],
+    authority: &Keypair,
+) -> Result<HashMap<Pubkey, Pubkey>> {
+    let target = network.target_client();
+    let source = network.source_client();
+    let mut cache = AltCache::load(std::env::temp_dir().join(ALT_CACHE_FILE))?;
+    let mut replacements = HashMap::new();
+    for table in tables {
+        if fetch_lookup_table(&target, table).is_ok() {
+            continue;
+        }
+        let addresses = lookup_table_addresses(&source, table)
+            .with_context(|| format!("Lookup table {table} is missing on the source"))?;
+        let cached = cache.get(&network.target, table).filter(|local| {
+            fetch_lookup_table(&target, local).is_ok_and(|copy| copy.addresses == addresses)
+        });
+        let local = match cached {
+            Some(local) => local,
+            None => {
+                say!(
+                    "Recreating lookup table {table} ({} accounts)",
+                    addresses.len()
+                );
+                let local = create_lookup_table_with_addresses(network, &addresses, authority)?;
+                wait_for_lookup_table(&target, &local, ALT_WARM_UP_TIMEOUT)?;
+                cache.insert(&network.target, table, &local);
+                cache.save()?;
+                local
+            }
+        };
+        say!("Using local lookup table {local} for {table}");
+        replacements.insert(*table, local);
+    }
+    Ok(replacements)
+}
+
+/// Waits until a lookup table is active and its last extension can be used,
+/// i.e. the target is past the slot of that extension.
+pub fn wait_for_lookup_table(client: &RpcClient, table: &Pubkey, timeout: Duration) -> Result<()> {
+    let started = Instant::now();
+    loop {
+        let account = client
+            .get_account_with_commitment(table, CommitmentConfig::confirmed())?
+            .value;
+        if let Some(account) = account {
+            let state = AddressLookupTable::deserialize(&account.data)
+                .map_err(|_| anyhow!("Failed to deserialize address lookup table {table}"))?;
+            let slot = client.get_slot_with_commitment(CommitmentConfig::confirmed())?;
+            if state.meta.deactivation_slot == u64::MAX && slot > state.meta.last_extended_slot {
+                return Ok(());
+            }
+        }
+        if started.elapsed() >= timeout {
+            return Err(anyhow!(
+                "Lookup table {table} is not usable after {}s",
+                timeout.as_secs()
+            ));
+        }
+        thread::sleep(ALT_POLL_INTERVAL);
+    }
+}
+
+/// Addresses of a lookup table, whether or not it is still active.
+fn lookup_table_addresses(client: &RpcClient, table: &Pubkey) -> Result<Vec<Pubkey>> {
+    let account = client.get_account(table)?;
+    let table = AddressLookupTable::deserialize(&account.data)
+        .map_err(|_| anyhow!("Failed to deserialize address lookup table {table}"))?;
+    Ok(table.addresses.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::AltCache;
+
+    #[test]
+    fn cache_round_trips_per_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alt-cache.json");
+        let (table, local) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut cache = AltCache::load(&path).unwrap();
+        cache.insert("http://127.0.0.1:8899", &table, &local);
+        cache.save().unwrap();
+
+        let cache = AltCache::load(&path).unwrap();
+        assert_eq!(cache.get("http://127.0.0.1:8899", &table), Some(local));
+        assert_eq!(cache.get("https://api.devnet.solana.com", &table), None);
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+
+use crate::network::NetworkCtx;
+use crate::tools::hooks::run_hooks;
+use crate::tools::progress::{STEP_STARTED, emit};
+use crate::tools::stats::RunStats;
+use crate::tools::style::failure;
+use crate::tools::tx::{ExecOptions, execute_json_transaction};
+use crate::tx_format::{
+    RawTransaction,
+    json_tx::{ParsedTransaction, parse_tx_from_json},
+};
+
+/// How `run_batch` handles failures and reports results.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BatchOptions {
+    /// Go on after a failed transaction and fail the batch at the end
+    pub keep_going: bool,
+    /// Print the results of the transactions as `--stats` does
+    pub print_stats: bool,
+}
+
+/// Executes the transactions of a batch file in order, each with its own
+/// hooks; `prepare` applies the command's payer and fee flags. Stops at the
+/// first failure unless `keep_going`.
+pub fn run_batch(
+    network: &NetworkCtx,
+    transactions: &[RawTransaction],
+    base_dir: &Path,
+    params: &[String],
+    prepare: impl Fn(ParsedTransaction) -> Result<ParsedTransaction>,
+    options: &ExecOptions,
+    batch: BatchOptions,
+) -> Result<()> {
+    let mut stats = RunStats::default();
+    let mut failed = 0;
+    for (index, raw) in transactions.iter().enumerate() {
+        let label = format!("Transaction {}/{}", index + 1, transactions.len());
+        emit(STEP_STARTED, json!({ "step": label }));
+        say!("{label}");
+        let hooks = raw.hooks.clone().unwrap_or_default();
+        let result = run_hooks(network, "pre", &hooks.pre, base_dir, params)
+            .and_then(|()| parse_tx_from_json(raw, params))
+            .and_then(&prepare)
+            .and_then(|parsed| execute_json_transaction(network, parsed, options))
+            .and_then(|report| {
+                run_hooks(network, "post", &hooks.post, base_dir, params)?;
+                Ok(report)
+            });
+        match result {
+            Ok(report) => stats.record(&report),
+            Err(err) if batch.keep_going => {
+                eprintln!("{}", failure(format!("{label} failed: {err:#}")));
+                stats.record_failure();
+                failed += 1;
+            }
+            Err(err) => return Err(err).with_context(|| format!("{label} failed")),
+        }
+    }
+    if batch.print_stats {
+        stats.print();
+    }
+    if failed > 0 {
+        return Err(anyhow!(
+            "{failed} of {} transactions failed",
+            transactions.len()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{hash::Hash, signature::Signature, signer::Signer};
+
+use crate::network::NetworkCtx;
+use crate::tools::stats::RunStats;
+use crate::tools::tx::{compile_transaction, fetch_lookup_tables};
+use crate::tools::wallet::load_wallet_manifest;
+use crate::tx_format::json_tx::{ParsedTransaction, load_parsed_tx_from_json};
+
+const BLOCKHASH_REFRESH: Duration = Duration::from_secs(1);
+const STATUS_POLL: Duration = Duration::from_secs(1);
+const STATUS_BATCH: usize = 256;
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct BenchOptions {
+    pub tps: f64,
+    pub duration: Duration,
+    pub ramp_up: Duration,
+}
+
+#[derive(Debug, Default)]
+struct BenchCounters {
+    sent: usize,
+    send_errors: usize,
+    /// Send error messages and how often each occurred.
+    send_error_kinds: BTreeMap<String, usize>,
+    confirmed: usize,
+    failed: usize,
+    latencies: Vec<Duration>,
+}
+
+/// Target rate at `elapsed`, growing linearly to `tps` over the ramp-up period.
+fn target_rate(options: &BenchOptions, elapsed: Duration) -> f64 {
+    if options.ramp_up.is_zero() || elapsed >= options.ramp_up {
+        options.tps
+    } else {
+        options.tps * elapsed.as_secs_f64() / options.ramp_up.as_secs_f64()
+    }
+}
+
+fn poll_statuses(
+    client: &solana_rpc_client::rpc_client::RpcClient,
+    pending: &mut Vec<(Signature, Instant)>,
+    counters: &mut BenchCounters,
+) -> Result<()> {
+    let mut still_pending = Vec::with_capacity(pending.len());
+    for chunk in pending.chunks(STATUS_BATCH) {
+        let signatures: Vec<Signature> = chunk.iter().map(|(sig, _)| *sig).collect();
+        let statuses = client.get_signature_statuses(&signatures)?.value;
+        for ((sig, sent_at), status) in chunk.iter().zip(statuses) {
+            match status {
+                Some(status) if status.err.is_some() => counters.failed += 1,
+                Some(status) if status.satisfies_commitment(CommitmentConfig::confirmed()) => {
+                    counters.confirmed += 1;
+                    counters.latencies.push(sent_at.elapsed());
+                }
+                _ => still_pending.push((*sig, *sent_at)),
+            }
+        }
+    }
+    *pending = still_pending;
+    Ok(())
+}
+
+/// Sends the template at a target TPS, rotating through the wallets of a
+/// `make-wallets` manifest. Each wallet is passed to the template as `$1`
+/// (pubkey) and `$2` (keypair path), followed by `params`.
+pub fn bench(
+    network: &NetworkCtx,
+    tx_path: impl AsRef<Path>,
+    wallets_path: impl AsRef<Path>,
+    params: &[String],
+    options: &BenchOptions,
+) -> Result<()> {
+    if options.tps <= 0.0 {
+        return Err(anyhow!("Target TPS must be positive"));
+    }
+    let wallets = load_wallet_manifest(wallets_path)?;
+    if wallets.is_empty() {
+        return Err(anyhow!("Wallet manifest is empty"));
+    }
+    if (wallets.len() as f64) < options.tps {
+        eprintln!(
+            "Warning: {} wallets for {} TPS, identical transactions may be rejected as duplicates",
+            wallets.len(),
+            options.tps
+        );
+    }
+
+    let templates = wallets
+        .iter()
+        .map(|wallet| {
+            let mut template_params = vec![wallet.pubkey.clone(), wallet.path.clone()];
+            template_params.extend_from_slice(params);
+            load_parsed_tx_from_json(&tx_path, &template_params)
+        })
+        .collect::<Result<Vec<ParsedTransaction>>>()?;
+
+    let client = network.target_client();
+    let lookup_accounts = fetch_lookup_tables(&client, &templates[0].lookup_tables)?;
+
+    let mut counters = BenchCounters::default();
+    let mut pending: Vec<(Signature, Instant)> = Vec::new();
+    let mut blockhash: Hash = client.get_latest_blockhash()?;
+    let mut blockhash_at = Instant::now();
+    let mut last_poll = Instant::now();
+    let mut last_report = Instant::now();
+    let mut next_wallet = 0;
+
+    say!(
+        "Benchmarking {} TPS for {:?} (ramp-up {:?}) with {} wallets...",
+        options.tps,
+        options.duration,
+        options.ramp_up,
+        wallets.len()
+    );
+
+    let started = Instant::now();
+    let mut last_tick = started;
+    let mut scheduled = 0.0;
+    while started.elapsed() < options.duration {
+        let now = Instant::now();
+        let elapsed = now - started;
+        if blockhash_at.elapsed() >= BLOCKHASH_REFRESH {
+            blockhash = client.get_latest_blockhash()?;
+            blockhash_at = Instant::now();
+        }
+
+        // Sends and status polls block, so credit the time that really passed.
+        scheduled += target_rate(options, elapsed) * (now - last_tick).as_secs_f64();
+        last_tick = now;
+        while scheduled >= 1.0 {
+            scheduled -= 1.0;
+            let template = &templates[next_wallet % templates.len()];
+            next_wallet += 1;
+            let payer = template
+                .signers
+                .first()
+                .ok_or_else(|| anyhow!("Missing transaction signer"))?
+                .pubkey();
+            let tx = compile_transaction(template, &payer, &lookup_accounts, blockhash)?;
+            match client.send_transaction(&tx) {
+                Ok(sig) => {
+                    counters.sent += 1;
+                    pending.push((sig, Instant::now()));
+                }
+                Err(err) => {
+                    counters.send_errors += 1;
+                    *counters
+                        .send_error_kinds
+                        .entry(err.to_string())
+                        .or_default() += 1;
+                }
+            }
+        }
+
+        if last_poll.elapsed() >= STATUS_POLL {
+            poll_statuses(&client, &mut pending, &mut counters)?;
+            last_poll = Instant::now();
+        }
+        if last_report.elapsed() >= Duration::from_secs(5) {
+            say!(
+                "t={:>3}s sent={} confirmed={} failed={} send_errors={}",
+                elapsed.as_secs(),
+                counters.sent,
+                counters.confirmed,
+                counters.failed,
+                counters.send_errors
+            );
+            last_report = Instant::now();
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let send_window = started.elapsed();
+
+    let drain_started = Instant::now();
+    while !pending.is_empty() && drain_started.elapsed() < DRAIN_TIMEOUT {
+        thread::sleep(STATUS_POLL);
+        poll_statuses(&client, &mut pending, &mut counters)?;
+    }
+
+    say!("Bench results:");
+    say!(
+        "Sent: {} (send errors {}), confirmed: {}, failed: {}, unconfirmed: {}",
+        counters.sent,
+        counters.send_errors,
+        counters.confirmed,
+        counters.failed,
+        pending.len()
+    );
+    for (error, count) in &counters.send_error_kinds {
+        eprintln!("Send error ({count}x): {error}");
+    }
+    say!(
+        "Achieved: {:.1} sent/s, {:.1} confirmed/s",
+        counters.sent as f64 / send_window.as_secs_f64(),
+        counters.confirmed as f64 / send_window.as_secs_f64()
+    );
+    let mut latency_stats = RunStats::default();
+    for latency in &counters.latencies {
+        latency_stats.record_latency(*latency);
+    }
+    latency_stats.print_latency();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BenchOptions, target_rate};
+    use std::time::Duration;
+
+    #[test]
+    fn target_rate_ramps_linearly() {
+        let options = BenchOptions {
+            tps: 100.0,
+            duration: Duration::from_secs(30),
+            ramp_up: Duration::from_secs(10),
+        };
+        assert_eq!(target_rate(&options, Duration::from_secs(0)), 0.0);
+        assert_eq!(target_rate(&options, Duration::from_secs(5)), 50.0);
+        assert_eq!(target_rate(&options, Duration::from_secs(20)), 100.0);
+    }
+}
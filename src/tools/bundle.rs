@@ -0,0 +1,246 @@
+use std::{
+    fs,
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::config::set_testnet_config;
+use crate::tools::output::{Compression, OutputOptions, open_input};
+
+const BUNDLE_MANIFEST: &str = "bundle.json";
+const ACCOUNTS_DIR: &str = "accounts";
+const TEMPLATES_DIR: &str = "templates";
+const FORMATS_DIR: &str = "formats";
+const BLOCK_SIZE: usize = 512;
+
+/// `bundle.json` at the root of a bundle archive.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct BundleManifest {
+    pub soltnet_version: String,
+    pub accounts: Vec<String>,
+    pub templates: Vec<String>,
+    pub formats: Vec<String>,
+}
+
+/// Directories packed into a bundle; only `accounts` is required.
+pub struct BundleSources<'a> {
+    pub accounts: &'a Path,
+    pub templates: Option<&'a Path>,
+    pub formats: Option<&'a Path>,
+}
+
+/// Packs the accounts dump (with its manifest), templates and data formats
+/// into one tar archive, compressed according to its `.zst`/`.gz` extension.
+pub fn create_bundle(archive: impl AsRef<Path>, sources: &BundleSources) -> Result<()> {
+    let archive = archive.as_ref();
+    let mut entries = Vec::new();
+    let mut manifest = BundleManifest {
+        soltnet_version: env!("CARGO_PKG_VERSION").to_string(),
+        ..Default::default()
+    };
+    let sections = [
+        (ACCOUNTS_DIR, Some(sources.accounts), &mut manifest.accounts),
+        (TEMPLATES_DIR, sources.templates, &mut manifest.templates),
+        (FORMATS_DIR, sources.formats, &mut manifest.formats),
+    ];
+    for (prefix, dir, listed) in sections {
+        let Some(dir) = dir else { continue };
+        for file in list_files(dir)? {
+            let relative = file.strip_prefix(dir)?.to_string_lossy().replace('\\', "/");
+            listed.push(relative.clone());
+            entries.push((format!("{prefix}/{relative}"), file));
+        }
+    }
+    if manifest.accounts.is_empty() {
+        return Err(anyhow!("No files in {}", sources.accounts.display()));
+    }
+
+    let output = OutputOptions {
+        compression: compression_for(archive),
+        ..Default::default()
+    };
+    let mut writer = output.create(archive)?;
+    let manifest_data = serde_json::to_vec_pretty(&manifest)?;
+    write_tar_entry(&mut writer, BUNDLE_MANIFEST, &manifest_data)?;
+    for (name, path) in &entries {
+        let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        write_tar_entry(&mut writer, name, &data)?;
+    }
+    writer.write_all(&[0; BLOCK_SIZE * 2])?;
+    writer.finish()?;
+
+    say!(
+        "Bundled {} accounts, {} templates and {} formats into {}",
+        manifest.accounts.len(),
+        manifest.templates.len(),
+        manifest.formats.len(),
+        archive.display()
+    );
+    Ok(())
+}
+
+/// Unpacks a bundle into `to_path` and loads its accounts into the testnet config.
+pub fn apply_bundle(archive: impl AsRef<Path>, to_path: impl AsRef<Path>) -> Result<()> {
+    let to_path = to_path.as_ref();
+    let mut reader = open_input(archive.as_ref())?;
+    let files = read_tar_entries(&mut reader)?;
+    if !files.iter().any(|(name, _)| name == BUNDLE_MANIFEST) {
+        return Err(anyhow!(
+            "{} is not a soltnet bundle (missing {BUNDLE_MANIFEST})",
+            archive.as_ref().display()
+        ));
+    }
+    for (name, data) in &files {
+        let path = to_path.join(safe_relative_path(name)?);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, data).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    say!("Unpacked {} files into {}", files.len(), to_path.display());
+    set_testnet_config(Some(&to_path.join(ACCOUNTS_DIR)))
+}
+
+fn compression_for(archive: &Path) -> Compression {
+    match archive.extension().and_then(|ext| ext.to_str()) {
+        Some("zst") => Compression::Zstd,
+        Some("gz") | Some("tgz") => Compression::Gzip,
+        _ => Compression::None,
+    }
+}
+
+pub fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let entries = fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(list_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Rejects archive paths that would escape the extraction directory.
+pub fn safe_relative_path(name: &str) -> Result<PathBuf> {
+    let path = Path::new(name);
+    if path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        Ok(path.to_path_buf())
+    } else {
+        Err(anyhow!("Unsafe path in bundle: {name}"))
+    }
+}
+
+/// Writes a regular file as a ustar entry.
+fn write_tar_entry(writer: &mut impl Write, name: &str, data: &[u8]) -> Result<()> {
+    let mut header = [0u8; BLOCK_SIZE];
+    let (prefix, name) = split_tar_name(name)?;
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+    header[136..148].copy_from_slice(b"00000000000\0");
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|byte| u32::from(*byte)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    writer.write_all(&header)?;
+    writer.write_all(data)?;
+    let padding = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+    writer.write_all(&vec![0; padding])?;
+    Ok(())
+}
+
+/// Splits a path into the ustar `prefix` (155 bytes) and `name` (100 bytes) fields.
+fn split_tar_name(path: &str) -> Result<(&str, &str)> {
+    if path.len() <= 100 {
+        return Ok(("", path));
+    }
+    path.match_indices('/')
+        .map(|(index, _)| (&path[..index], &path[index + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100)
+        .ok_or_else(|| anyhow!("Path too long for a bundle: {path}"))
+}
+
+/// Reads the regular files of a tar stream as `(path, contents)` pairs.
+pub fn read_tar_entries(reader: &mut impl Read) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+    let mut header = [0u8; BLOCK_SIZE];
+    loop {
+        reader
+            .read_exact(&mut header)
+            .context("Truncated bundle archive")?;
+        if header.iter().all(|byte| *byte == 0) {
+            return Ok(files);
+        }
+        let size = usize::from_str_radix(tar_field(&header[124..136]).trim(), 8)
+            .map_err(|_| anyhow!("Invalid entry size in bundle archive"))?;
+        let mut data = vec![0; size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE];
+        reader
+            .read_exact(&mut data)
+            .context("Truncated bundle archive")?;
+        data.truncate(size);
+
+        if matches!(header[156], b'0' | 0) {
+            let prefix = tar_field(&header[345..500]);
+            let name = tar_field(&header[..100]);
+            let path = if prefix.is_empty() {
+                name
+            } else {
+                format!("{prefix}/{name}")
+            };
+            files.push((path, data));
+        }
+    }
+}
+
+fn tar_field(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_tar_entries, safe_relative_path, write_tar_entry};
+
+    #[test]
+    fn tar_entries_round_trip() {
+        let long_name = format!("accounts/{}/{}.json", "d".repeat(80), "a".repeat(60));
+        let mut archive = Vec::new();
+        write_tar_entry(&mut archive, "bundle.json", b"{}").unwrap();
+        write_tar_entry(&mut archive, &long_name, &[7; 600]).unwrap();
+        archive.extend([0; 1024]);
+        assert_eq!(archive.len() % 512, 0);
+
+        let files = read_tar_entries(&mut archive.as_slice()).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0], ("bundle.json".to_string(), b"{}".to_vec()));
+        assert_eq!(files[1].0, long_name);
+        assert_eq!(files[1].1, vec![7; 600]);
+    }
+
+    #[test]
+    fn rejects_paths_escaping_the_bundle() {
+        assert!(safe_relative_path("accounts/a.json").is_ok());
+        assert!(safe_relative_path("../a.json").is_err());
+        assert!(safe_relative_path("/etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+use crate::accounts::{NATIVE_MINT_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+/// Token program `CloseAccount` instruction tag
+pub const TOKEN_CLOSE_ACCOUNT: u8 = 9;
+/// Token program `Burn` instruction tag
+pub const TOKEN_BURN: u8 = 8;
+/// Length of the fixed part of a token account (mint, owner, amount)
+const TOKEN_ACCOUNT_PREFIX: usize = 72;
+
+/// A token account closed by one of the instructions, e.g. an expanded
+/// `close_ata` shorthand.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TokenClose {
+    pub index: usize,
+    pub program_id: Pubkey,
+    pub account: Pubkey,
+    pub authority: Pubkey,
+}
+
+/// Mint, owner and amount of a token account that still holds tokens.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Remaining {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+/// Token accounts closed by the instructions. Accounts an earlier
+/// instruction writes to are left out: their balance before the transaction
+/// says nothing about the balance at the close (e.g. transfer, then close).
+pub fn token_closes(instructions: &[Instruction]) -> Vec<TokenClose> {
+    let written_before = |index: usize, account: &Pubkey| {
+        instructions[..index]
+            .iter()
+            .flat_map(|ix| &ix.accounts)
+            .any(|meta| meta.is_writable && meta.pubkey == *account)
+    };
+    instructions
+        .iter()
+        .enumerate()
+        .filter(|(_, ix)| {
+            ix.program_id == TOKEN_PROGRAM_ID || ix.program_id == TOKEN_2022_PROGRAM_ID
+        })
+        .filter(|(_, ix)| ix.data == [TOKEN_CLOSE_ACCOUNT] && ix.accounts.len() >= 3)
+        .filter(|(index, ix)| !written_before(*index, &ix.accounts[0].pubkey))
+        .map(|(index, ix)| TokenClose {
+            index,
+            program_id: ix.program_id,
+            account: ix.accounts[0].pubkey,
+            authority: ix.accounts[2].pubkey,
+        })
+        .collect()
+}
+
+/// Tokens left in a token account, `None` when it is empty or holds wrapped
+/// SOL (closing returns that SOL).
+fn remaining_tokens(data: &[u8]) -> Option<Remaining> {
+    let data = data.get(..TOKEN_ACCOUNT_PREFIX)?;
+    let mint = Pubkey::try_from(&data[..32]).ok()?;
+    let owner = Pubkey::try_from(&data[32..64]).ok()?;
+    let amount = u64::from_le_bytes(data[64..72].try_into().ok()?);
+    (amount > 0 && mint != NATIVE_MINT_ID).then_some(Remaining {
+        mint,
+        owner,
+        amount,
+    })
+}
+
+/// Refuses closes of token accounts in `remaining`, or burns their tokens
+/// right before the close when `burn_remaining`. The burn is signed by the
+/// account owner, which may differ from the close authority.
+pub fn burn_before_closes(
+    instructions: &mut Vec<Instruction>,
+    remaining: &HashMap<Pubkey, Remaining>,
+    burn_remaining: bool,
+) -> Result<()> {
+    for close in token_closes(instructions).into_iter().rev() {
+        let Some(tokens) = remaining.get(&close.account) else {
+            continue;
+        };
+        if !burn_remaining {
+            return Err(anyhow!(
+                "Token account {} still holds {} tokens; transfer them out or pass --burn-remaining",
+                close.account,
+                tokens.amount
+            ));
+        }
+        say!(
+            "Burning {} remaining tokens of {}",
+            tokens.amount,
+            close.account
+        );
+        let mut data = vec![TOKEN_BURN];
+        data.extend_from_slice(&tokens.amount.to_le_bytes());
+        let burn = Instruction {
+            program_id: close.program_id,
+            accounts: vec![
+                AccountMeta::new(close.account, false),
+                AccountMeta::new(tokens.mint, false),
+                AccountMeta::new_readonly(tokens.owner, true),
+            ],
+            data,
+        };
+        instructions.insert(close.index, burn);
+    }
+    Ok(())
+}
+
+/// Checks the token accounts the instructions close against the target: any
+/// still holding tokens is refused, or emptied with a burn first when
+/// `burn_remaining`.
+pub fn check_token_closes(
+    client: &RpcClient,
+    instructions: &mut Vec<Instruction>,
+    burn_remaining: bool,
+) -> Result<()> {
+    let mut remaining = HashMap::new();
+    for close in token_closes(instructions) {
+        let account = client
+            .get_account_with_commitment(&close.account, client.commitment())?
+            .value;
+        if let Some(tokens) = account.and_then(|account| remaining_tokens(&account.data)) {
+            remaining.insert(close.account, tokens);
+        }
+    }
+    burn_before_closes(instructions, &remaining, burn_remaining)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::{Remaining, TOKEN_BURN, TOKEN_CLOSE_ACCOUNT, burn_before_closes, token_closes};
+    use crate::accounts::TOKEN_PROGRAM_ID;
+    use crate::tx_format::{RawTransaction, json_tx::parse_tx_from_json};
+
+    #[test]
+    fn close_ata_shorthand_is_checked() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let raw: RawTransaction = serde_json::from_value(json!({
+            "instructions": [{
+                "program_id": "close_ata",
+                "owner": owner.to_string(),
+                "mint": mint.to_string()
+            }],
+            "signers": []
+        }))
+        .unwrap();
+        let parsed = parse_tx_from_json(&raw, &[]).unwrap();
+        let closes = token_closes(&parsed.instructions);
+        assert_eq!(closes.len(), 1);
+        assert_eq!(closes[0].authority, owner);
+
+        let tokens = Remaining {
+            mint,
+            owner,
+            amount: 5,
+        };
+        let remaining = HashMap::from([(closes[0].account, tokens)]);
+        let mut instructions = parsed.instructions.clone();
+        assert!(burn_before_closes(&mut instructions, &remaining, false).is_err());
+        assert!(burn_before_closes(&mut instructions, &HashMap::new(), false).is_ok());
+
+        burn_before_closes(&mut instructions, &remaining, true).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].data[0], TOKEN_BURN);
+        assert_eq!(instructions[0].data[1..], 5u64.to_le_bytes());
+        assert_eq!(instructions[0].accounts[1].pubkey, mint);
+        assert_eq!(instructions[0].accounts[2].pubkey, owner);
+        assert_eq!(instructions[1].data, [TOKEN_CLOSE_ACCOUNT]);
+    }
+
+    #[test]
+    fn transfer_then_close_is_not_checked() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let account =
+            json!({ "type": "ata", "owner": owner.to_string(), "mint": mint.to_string() });
+        let raw: RawTransaction = serde_json::from_value(json!({
+            "instructions": [
+                {
+                    "program_id": TOKEN_PROGRAM_ID.to_string(),
+                    "accounts": [
+                        { "pubkey": account, "is_signer": false, "is_writable": true },
+                        { "pubkey": Pubkey::new_unique().to_string(), "is_signer": false, "is_writable": true },
+                        { "pubkey": owner.to_string(), "is_signer": true, "is_writable": false }
+                    ],
+                    "data": { "type": "object", "data": [
+                        { "type": "u8", "data": 3 },
+                        { "type": "u64", "data": 5 }
+                    ] }
+                },
+                { "program_id": "close_ata", "owner": owner.to_string(), "mint": mint.to_string() }
+            ],
+            "signers": []
+        }))
+        .unwrap();
+        let parsed = parse_tx_from_json(&raw, &[]).unwrap();
+        assert!(token_closes(&parsed.instructions).is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+
+use crate::tools::json_output::record;
+use crate::utils::format_amount;
+
+/// Compute units consumed by one program invocation (top-level or CPI).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuEntry {
+    pub instruction: usize,
+    pub depth: usize,
+    pub program: String,
+    pub consumed: Option<u64>,
+}
+
+pub fn parse_invoke(line: &str) -> Option<(&str, usize)> {
+    let rest = line.strip_prefix("Program ")?;
+    let (program, depth) = rest.split_once(" invoke [")?;
+    let depth = depth.strip_suffix(']')?.parse().ok()?;
+    Some((program, depth))
+}
+
+fn parse_consumed(line: &str) -> Option<u64> {
+    let rest = line.strip_prefix("Program ")?;
+    let (_, rest) = rest.split_once(" consumed ")?;
+    let (consumed, rest) = rest.split_once(" of ")?;
+    rest.ends_with(" compute units")
+        .then(|| consumed.parse().ok())
+        .flatten()
+}
+
+pub fn is_invoke_end(line: &str) -> bool {
+    line.starts_with("Program ") && (line.ends_with(" success") || line.contains(" failed: "))
+}
+
+/// Builds the CU breakdown from "invoke [N]" / "consumed X of Y compute units" log lines.
+pub fn parse_cu_breakdown(logs: &[String]) -> Vec<CuEntry> {
+    let mut entries: Vec<CuEntry> = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    let mut instruction = 0;
+
+    for line in logs {
+        if let Some((program, depth)) = parse_invoke(line) {
+            if depth == 1 && !entries.is_empty() {
+                instruction += 1;
+            }
+            stack.push(entries.len());
+            entries.push(CuEntry {
+                instruction,
+                depth,
+                program: program.to_string(),
+                consumed: None,
+            });
+        } else if let Some(consumed) = parse_consumed(line) {
+            if let Some(&idx) = stack.last() {
+                entries[idx].consumed = Some(consumed);
+            }
+        } else if is_invoke_end(line) {
+            stack.pop();
+        }
+    }
+    entries
+}
+
+pub fn print_cu_breakdown(logs: &[String]) {
+    let entries = parse_cu_breakdown(logs);
+    if entries.is_empty() {
+        return;
+    }
+    say!("Compute units by instruction:");
+    for entry in entries {
+        let indent = "  ".repeat(entry.depth);
+        let label = if entry.depth == 1 {
+            format!("#{} {}", entry.instruction, entry.program)
+        } else {
+            format!("CPI {}", entry.program)
+        };
+        let consumed = entry
+            .consumed
+            .map(format_amount)
+            .unwrap_or_else(|| "n/a".to_string());
+        say!("{indent}{label:<56} {consumed:>10}");
+    }
+}
+
+/// Settings for comparing a run against a stored CU baseline file.
+pub struct CuBaseline<'a> {
+    pub path: &'a Path,
+    pub tolerance_percent: f64,
+    pub warn_only: bool,
+}
+
+fn exceeds_baseline(consumed: u64, baseline: u64, tolerance_percent: f64) -> bool {
+    consumed as f64 > baseline as f64 * (1.0 + tolerance_percent / 100.0)
+}
+
+/// Compares `consumed` with the baseline stored under `key`, recording it when absent.
+pub fn check_cu_baseline(baseline: &CuBaseline, key: &str, consumed: u64) -> Result<()> {
+    let mut entries: BTreeMap<String, u64> = if baseline.path.exists() {
+        let data = fs::read_to_string(baseline.path)
+            .with_context(|| format!("failed to read {:?}", baseline.path))?;
+        serde_json::from_str(&data)
+            .with_context(|| format!("invalid JSON in {:?}", baseline.path))?
+    } else {
+        BTreeMap::new()
+    };
+
+    let Some(&expected) = entries.get(key) else {
+        entries.insert(key.to_string(), consumed);
+        fs::write(baseline.path, serde_json::to_string_pretty(&entries)?)
+            .with_context(|| format!("failed to write {:?}", baseline.path))?;
+        record(
+            "cu_baseline",
+            json!({ "key": key, "consumed": consumed, "recorded": true }),
+        );
+        say!(
+            "CU baseline recorded for {key}: {}",
+            format_amount(consumed)
+        );
+        return Ok(());
+    };
+
+    record(
+        "cu_baseline",
+        json!({ "key": key, "consumed": consumed, "baseline": expected }),
+    );
+    if !exceeds_baseline(consumed, expected, baseline.tolerance_percent) {
+        say!(
+            "CU baseline ok for {key}: {} (baseline {})",
+            format_amount(consumed),
+            format_amount(expected)
+        );
+        return Ok(());
+    }
+
+    let message = format!(
+        "CU regression for {key}: {} exceeds baseline {} by more than {}%",
+        format_amount(consumed),
+        format_amount(expected),
+        baseline.tolerance_percent
+    );
+    if baseline.warn_only {
+        eprintln!("Warning: {message}");
+        Ok(())
+    } else {
+        Err(anyhow!(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CuEntry, exceeds_baseline, parse_cu_breakdown};
+
+    #[test]
+    fn exceeds_baseline_respects_tolerance() {
+        assert!(!exceeds_baseline(1_100, 1_000, 10.0));
+        assert!(exceeds_baseline(1_101, 1_000, 10.0));
+        assert!(exceeds_baseline(1_001, 1_000, 0.0));
+    }
+
+    #[test]
+    fn parse_cu_breakdown_tracks_cpi_depth() {
+        let logs: Vec<String> = [
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+            "Program ComputeBudget111111111111111111111111111111 success",
+            "Program Prog invoke [1]",
+            "Program log: hello",
+            "Program Token invoke [2]",
+            "Program Token consumed 4000 of 190000 compute units",
+            "Program Token success",
+            "Program Prog consumed 12000 of 200000 compute units",
+            "Program Prog success",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let entries = parse_cu_breakdown(&logs);
+        assert_eq!(
+            entries,
+            vec![
+                CuEntry {
+                    instruction: 0,
+                    depth: 1,
+                    program: "ComputeBudget111111111111111111111111111111".to_string(),
+                    consumed: None,
+                },
+                CuEntry {
+                    instruction: 1,
+                    depth: 1,
+                    program: "Prog".to_string(),
+                    consumed: Some(12000),
+                },
+                CuEntry {
+                    instruction: 1,
+                    depth: 2,
+                    program: "Token".to_string(),
+                    consumed: Some(4000),
+                },
+            ]
+        );
+    }
+}
@@ -27,7 +27,7 @@ pub fn set_data_format(
             let json = serde_json::to_string_pretty(&tx)?;
             std::fs::write(&tx_path, json)
                 .with_context(|| format!("failed to write {:?}", tx_path.as_ref()))?;
-            println!("Updated data format for instruction in program {program_id}");
+            say!("Updated data format for instruction in program {program_id}");
             return Ok(());
         }
     }
@@ -0,0 +1,301 @@
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::packs::installed;
+use crate::tools::style::diff_line;
+use crate::tx_format::{
+    RawAccountMeta, RawInstruction, RawTransaction, data_format::pack_data,
+    json_tx::load_raw_tx_from_json,
+};
+use crate::utils::json_diff;
+
+/// Structural diff of two templates (or `parse-tx` outputs). Instructions
+/// are aligned by program id, accounts by position; data is compared as
+/// bytes and reported per named field when a data format or an installed
+/// pack describes it.
+pub fn diff_templates(a: impl AsRef<Path>, b: impl AsRef<Path>) -> Result<()> {
+    let (a, b) = (a.as_ref(), b.as_ref());
+    let differences = diff_transactions(&load_raw_tx_from_json(a)?, &load_raw_tx_from_json(b)?);
+    if differences.is_empty() {
+        say!("No differences between {} and {}", a.display(), b.display());
+        return Ok(());
+    }
+    for difference in &differences {
+        say!("{}", diff_line(difference));
+    }
+    Err(anyhow!(
+        "{} and {} differ ({} differences)",
+        a.display(),
+        b.display(),
+        differences.len()
+    ))
+}
+
+pub fn diff_transactions(a: &RawTransaction, b: &RawTransaction) -> Vec<String> {
+    let mut out = Vec::new();
+    for step in align(&a.instructions, &b.instructions) {
+        match step {
+            Step::Removed(i) => out.push(format!(
+                "- instruction #{i} {}",
+                display(&a.instructions[i].program_id)
+            )),
+            Step::Added(j) => out.push(format!(
+                "+ instruction #{j} {}",
+                display(&b.instructions[j].program_id)
+            )),
+            Step::Matched(i, j) => {
+                let changes = diff_instruction(&a.instructions[i], &b.instructions[j]);
+                if !changes.is_empty() {
+                    out.push(format!(
+                        "~ instruction #{i} -> #{j} {}",
+                        display(&a.instructions[i].program_id)
+                    ));
+                    out.extend(changes.into_iter().map(|change| format!("    {change}")));
+                }
+            }
+        }
+    }
+    let tables = |tx: &RawTransaction| Value::from(tx.lookup_tables.clone().unwrap_or_default());
+    out.extend(
+        json_diff(&tables(a), &tables(b))
+            .into_iter()
+            .map(|change| format!("lookup tables: {change}")),
+    );
+    out
+}
+
+fn diff_instruction(a: &RawInstruction, b: &RawInstruction) -> Vec<String> {
+    let mut out = Vec::new();
+    for index in 0..a.accounts.len().max(b.accounts.len()) {
+        match (a.accounts.get(index), b.accounts.get(index)) {
+            (Some(old), Some(new)) => {
+                if old.pubkey != new.pubkey {
+                    out.push(format!(
+                        "account {index}: {} -> {}",
+                        display(&old.pubkey),
+                        display(&new.pubkey)
+                    ));
+                }
+                if old.is_signer != new.is_signer {
+                    out.push(format!(
+                        "account {index} signer: {} -> {}",
+                        old.is_signer, new.is_signer
+                    ));
+                }
+                if old.is_writable != new.is_writable {
+                    out.push(format!(
+                        "account {index} writable: {} -> {}",
+                        old.is_writable, new.is_writable
+                    ));
+                }
+            }
+            (Some(old), None) => out.push(format!("- account {index}: {}", describe(old))),
+            (None, Some(new)) => out.push(format!("+ account {index}: {}", describe(new))),
+            (None, None) => {}
+        }
+    }
+    out.extend(diff_data(&display(&a.program_id), &a.data, &b.data));
+    out
+}
+
+fn diff_data(program_id: &str, a: &Value, b: &Value) -> Vec<String> {
+    if a == b {
+        return Vec::new();
+    }
+    let (bytes_a, bytes_b) = (pack_data(a, &[]).ok(), pack_data(b, &[]).ok());
+    if bytes_a.is_some() && bytes_a == bytes_b {
+        return Vec::new();
+    }
+    let decode = |value: &Value, bytes: &Option<Vec<u8>>| match value {
+        Value::String(_) => bytes
+            .as_deref()
+            .and_then(|bytes| installed()?.decode_instruction(program_id, bytes))
+            .unwrap_or_else(|| value.clone()),
+        _ => value.clone(),
+    };
+    let (fields_a, fields_b) = (
+        named_fields(&decode(a, &bytes_a)),
+        named_fields(&decode(b, &bytes_b)),
+    );
+    if fields_a.is_empty() || fields_b.is_empty() {
+        let hex = |value: &Value, bytes: &Option<Vec<u8>>| match bytes {
+            Some(bytes) => format!("0x{}", hex::encode(bytes)),
+            None => display(value),
+        };
+        return vec![format!(
+            "data: {} -> {}",
+            hex(a, &bytes_a),
+            hex(b, &bytes_b)
+        )];
+    }
+
+    let mut out = Vec::new();
+    for (name, old) in &fields_a {
+        match fields_b.iter().find(|(other, _)| other == name) {
+            Some((_, new)) if new != old => {
+                out.push(format!("data {name}: {} -> {}", display(old), display(new)))
+            }
+            Some(_) => {}
+            None => out.push(format!("- data {name}: {}", display(old))),
+        }
+    }
+    for (name, new) in &fields_b {
+        if !fields_a.iter().any(|(other, _)| other == name) {
+            out.push(format!("+ data {name}: {}", display(new)));
+        }
+    }
+    out
+}
+
+/// `(dotted name, value)` of every named leaf of a data format value.
+fn named_fields(value: &Value) -> Vec<(String, Value)> {
+    fn collect(value: &Value, prefix: &str, out: &mut Vec<(String, Value)>) {
+        let children = match value {
+            Value::Array(items) => items,
+            Value::Object(map) => match map.get("data") {
+                Some(Value::Array(items)) => items,
+                Some(data) => {
+                    if let Some(name) = map.get("name").and_then(Value::as_str) {
+                        out.push((format!("{prefix}{name}"), data.clone()));
+                    }
+                    return;
+                }
+                None => return,
+            },
+            _ => return,
+        };
+        let prefix = match value.get("name").and_then(Value::as_str) {
+            Some(name) if value.get("type").and_then(Value::as_str) == Some("object") => {
+                format!("{prefix}{name}.")
+            }
+            _ => prefix.to_string(),
+        };
+        for child in children {
+            collect(child, &prefix, out);
+        }
+    }
+    let mut out = Vec::new();
+    // The top-level name is the instruction (or pack layout) name, not a field.
+    let children = match value.get("data") {
+        Some(Value::Array(_)) => &value["data"],
+        _ => value,
+    };
+    collect(children, "", &mut out);
+    out
+}
+
+fn describe(account: &RawAccountMeta) -> String {
+    let mut flags = Vec::new();
+    if account.is_signer {
+        flags.push("signer");
+    }
+    if account.is_writable {
+        flags.push("writable");
+    }
+    if flags.is_empty() {
+        display(&account.pubkey)
+    } else {
+        format!("{} ({})", display(&account.pubkey), flags.join(", "))
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Step {
+    Matched(usize, usize),
+    Removed(usize),
+    Added(usize),
+}
+
+/// Longest common subsequence of program ids.
+fn align(a: &[RawInstruction], b: &[RawInstruction]) -> Vec<Step> {
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if a[i].program_id == b[j].program_id {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut steps = Vec::new();
+    while i < a.len() && j < b.len() {
+        if a[i].program_id == b[j].program_id {
+            steps.push(Step::Matched(i, j));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            steps.push(Step::Removed(i));
+            i += 1;
+        } else {
+            steps.push(Step::Added(j));
+            j += 1;
+        }
+    }
+    steps.extend((i..a.len()).map(Step::Removed));
+    steps.extend((j..b.len()).map(Step::Added));
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::diff_transactions;
+    use crate::tx_format::RawTransaction;
+
+    #[test]
+    fn aligns_instructions_and_reports_changes() {
+        let transfer = |amount: u64, writable: bool| {
+            json!({
+                "program_id": "Prog111111111111111111111111111111111111111",
+                "accounts": [
+                    { "pubkey": "Src1111111111111111111111111111111111111111", "is_signer": true, "is_writable": true },
+                    { "pubkey": "Dst1111111111111111111111111111111111111111", "is_writable": writable }
+                ],
+                "data": { "type": "object", "name": "transfer", "data": [
+                    { "type": "u8", "name": "instruction", "data": 2 },
+                    { "type": "u64", "name": "amount", "data": amount }
+                ]}
+            })
+        };
+        let budget =
+            json!({ "program_id": "ComputeBudget111111111111111111111111111111", "data": "0x02" });
+        let memo = json!({ "program_id": "Memo1111111111111111111111111111111111111111", "data": "0x6869" });
+        let a: RawTransaction = serde_json::from_value(json!({
+            "instructions": [budget, transfer(5, true)],
+            "signers": []
+        }))
+        .unwrap();
+        let b: RawTransaction = serde_json::from_value(json!({
+            "instructions": [budget, memo, transfer(7, false)],
+            "signers": []
+        }))
+        .unwrap();
+
+        assert!(diff_transactions(&a, &a).is_empty());
+        let mut stamped = serde_json::to_value(&a).unwrap();
+        stamped["_soltnet"] = json!({ "slot": 7, "created_at": 1_700_000_000 });
+        let stamped: RawTransaction = serde_json::from_value(stamped).unwrap();
+        assert!(diff_transactions(&a, &stamped).is_empty());
+        assert_eq!(
+            diff_transactions(&a, &b),
+            [
+                "+ instruction #1 Memo1111111111111111111111111111111111111111",
+                "~ instruction #1 -> #2 Prog111111111111111111111111111111111111111",
+                "    account 1 writable: true -> false",
+                "    data amount: 5 -> 7",
+            ]
+        );
+    }
+}
@@ -1,18 +1,57 @@
-use std::{collections::HashSet, fs, path::Path, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{
+        Mutex, Once,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result, anyhow};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
-use solana_commitment_config::CommitmentConfig;
+use serde_json::{Map, Value, json};
 use solana_rpc_client::api::config::RpcTransactionConfig;
-use solana_sdk::pubkey::Pubkey;
+use solana_rpc_client::api::request::RpcRequest;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{account::Account, pubkey::Pubkey};
 use solana_transaction_status::{EncodedTransaction, UiMessage, UiTransactionEncoding};
 
-use crate::tools::tx::{MAINNET_RPC_URL, create_connection};
+use crate::accounts::{
+    BUBBLEGUM_PROGRAM_ID, LOOKUP_TABLE_PROGRAM_ID, MPL_ACCOUNT_COMPRESSION_PROGRAM_ID,
+    MPL_NOOP_PROGRAM_ID, SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID,
+    TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+use crate::network::NetworkCtx;
+use crate::tools::json_output::{push, record};
+use crate::tools::output::{JsonLinesWriter, Layout, OutputOptions, OutputWriter};
+use crate::tools::progress::{FILE_WRITTEN, RPC_RETRY, emit};
+use crate::tools::style::{failure, warning};
 use crate::tx_format::json_tx::load_parsed_tx_from_json;
+use crate::utils::{STAMP_KEY, is_stdio, stamp, warn_if_stale};
 
 const UPGRADEABLE_LOADER_ID: Pubkey =
     Pubkey::from_str_const("BPFLoaderUpgradeab1e11111111111111111111111");
+const LOADER_V4_ID: Pubkey = Pubkey::from_str_const("LoaderV411111111111111111111111111111111111");
+/// Loader-v4 program accounts start with the slot, authority and status
+const LOADER_V4_HEADER_LEN: usize = 48;
+const LOADER_V4_STATUS_OFFSET: usize = 40;
+const LOADER_V4_RETRACTED: u8 = 0;
 const ELF_MAGIC: [u8; 4] = [0x7f, 0x45, 0x4c, 0x46];
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+/// How often the retry backoff checks for an interrupt
+const CANCEL_POLL: Duration = Duration::from_millis(100);
+/// Rounds of re-reading chunks that were read at an older slot.
+const CHUNK_SLOT_ATTEMPTS: usize = 3;
+const MANIFEST_FILE: &str = "manifest.json";
+const MINT_LEN: usize = 82;
+const TOKEN_2022_ACCOUNT_TYPE_OFFSET: usize = 165;
+const TOKEN_2022_MINT_TYPE: u8 = 1;
+/// Bytes per `dataSlice` request when fetching program data
+const PROGRAM_DATA_CHUNK: usize = 256 * 1024;
 
 fn extract_elf_bytes(data: &[u8]) -> Option<Vec<u8>> {
     data.windows(ELF_MAGIC.len())
@@ -20,6 +59,22 @@ fn extract_elf_bytes(data: &[u8]) -> Option<Vec<u8>> {
         .map(|idx| data[idx..].to_vec())
 }
 
+/// Whether an account holds a program. Loader-v4 programs do not rely on
+/// the `executable` flag, so their status is checked instead.
+fn is_program(account: &Account) -> bool {
+    if account.owner == LOADER_V4_ID {
+        return account.data.len() > LOADER_V4_HEADER_LEN
+            && account.data[LOADER_V4_STATUS_OFFSET] != LOADER_V4_RETRACTED;
+    }
+    account.executable
+}
+
+/// ELF of a loader-v4 program, stored after its header.
+fn loader_v4_elf(data: &[u8]) -> Option<Vec<u8>> {
+    let elf = data.get(LOADER_V4_HEADER_LEN..)?;
+    elf.starts_with(&ELF_MAGIC).then(|| elf.to_vec())
+}
+
 fn try_get_upgradeable_program_data_address(data: &[u8]) -> Option<Pubkey> {
     if data.len() < 4 + 32 {
         return None;
@@ -48,98 +103,564 @@ fn serialize_account_info(
     })
 }
 
-pub fn dump_account(address: &str, to_path: impl AsRef<Path>) -> Result<()> {
-    fs::create_dir_all(&to_path)?;
+/// Set by Ctrl-C once `cancel_on_interrupt` is installed; dumps then stop
+/// starting new work and report what they did so far.
+static CANCELED: AtomicBool = AtomicBool::new(false);
 
-    let connection = create_connection(MAINNET_RPC_URL);
+/// Makes the first Ctrl-C cancel running dumps (which still write their
+/// summary and manifest) and a second one exit right away.
+pub fn cancel_on_interrupt() -> Result<()> {
+    static INSTALL: Once = Once::new();
+    let mut result = Ok(());
+    INSTALL.call_once(|| {
+        result = ctrlc::set_handler(|| {
+            if CANCELED.swap(true, Ordering::Relaxed) {
+                std::process::exit(130);
+            }
+            eprintln!(
+                "{}",
+                warning("Canceling the dump; press Ctrl-C again to exit now")
+            );
+        });
+    });
+    result.context("failed to install the Ctrl-C handler")
+}
+
+/// An account read from the source cluster and the slot it was read at.
+struct FetchedAccount {
+    address: String,
+    pubkey: Pubkey,
+    account: Account,
+    slot: u64,
+}
+
+fn fetch_account(
+    network: &NetworkCtx,
+    connection: &RpcClient,
+    address: &str,
+) -> Result<FetchedAccount> {
     let pubkey = Pubkey::from_str(address).map_err(|_| anyhow!("Invalid pubkey: {address}"))?;
-    let account = connection
-        .get_account(&pubkey)
-        .with_context(|| format!("Account not found: {address}"))?;
-
-    if account.executable {
-        println!("Dumping program {address}...");
-        let mut program_data = account.data.clone();
-        if account.owner == UPGRADEABLE_LOADER_ID {
-            if let Some(program_data_address) =
-                try_get_upgradeable_program_data_address(&account.data)
-            {
-                if let Ok(program_data_info) = connection.get_account(&program_data_address) {
-                    program_data = program_data_info.data;
+    let response = connection
+        .get_account_with_commitment(&pubkey, network.read_commitment())
+        .with_context(|| format!("Failed to fetch account: {address}"))?;
+    let account = response
+        .value
+        .ok_or_else(|| anyhow!("Account not found: {address}"))?;
+    Ok(FetchedAccount {
+        address: address.to_string(),
+        pubkey,
+        account,
+        slot: response.context.slot,
+    })
+}
+
+/// Fetches accounts with `getMultipleAccounts`, spreading batches over the
+/// preset's concurrency and pausing after each request. Returns the fetched
+/// accounts, the addresses that could not be fetched and those left unfetched
+/// because `cancel` was set.
+fn fetch_accounts(
+    network: &NetworkCtx,
+    addresses: &[String],
+    cancel: &AtomicBool,
+) -> (Vec<FetchedAccount>, Vec<String>, Vec<String>) {
+    let batches: Vec<&[String]> = addresses.chunks(network.limits.batch_size.max(1)).collect();
+    let next = AtomicUsize::new(0);
+    let fetched = Mutex::new(Vec::new());
+    let failed = Mutex::new(Vec::new());
+    let canceled = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..network.limits.concurrency.max(1).min(batches.len()) {
+            scope.spawn(|| {
+                let connection = network.source_client();
+                while let Some(batch) = batches.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if cancel.load(Ordering::Relaxed) {
+                        canceled.lock().unwrap().extend_from_slice(batch);
+                        continue;
+                    }
+                    let (batch_fetched, batch_failed) = fetch_batch(network, &connection, batch);
+                    fetched.lock().unwrap().extend(batch_fetched);
+                    failed.lock().unwrap().extend(batch_failed);
+                    network.pause();
+                }
+            });
+        }
+    });
+    // Batches finish in any order; sort so dumps and summaries are reproducible.
+    let mut fetched = fetched.into_inner().unwrap();
+    fetched.sort_by(|a: &FetchedAccount, b| a.address.cmp(&b.address));
+    let mut failed = failed.into_inner().unwrap();
+    failed.sort();
+    let mut canceled = canceled.into_inner().unwrap();
+    canceled.sort();
+    (fetched, failed, canceled)
+}
+
+fn fetch_batch(
+    network: &NetworkCtx,
+    connection: &RpcClient,
+    batch: &[String],
+) -> (Vec<FetchedAccount>, Vec<String>) {
+    let mut failed = Vec::new();
+    let mut valid = Vec::new();
+    for address in batch {
+        match Pubkey::from_str(address) {
+            Ok(pubkey) => valid.push((address, pubkey)),
+            Err(_) => {
+                eprintln!("Failed to dump account {address}: invalid pubkey");
+                failed.push(address.clone());
+            }
+        }
+    }
+
+    let pubkeys: Vec<Pubkey> = valid.iter().map(|(_, pubkey)| *pubkey).collect();
+    let response = match connection
+        .get_multiple_accounts_with_commitment(&pubkeys, network.read_commitment())
+    {
+        Ok(response) => response,
+        Err(error) => {
+            eprintln!("Failed to fetch {} accounts: {error}", pubkeys.len());
+            failed.extend(valid.into_iter().map(|(address, _)| address.clone()));
+            return (Vec::new(), failed);
+        }
+    };
+
+    let mut fetched = Vec::new();
+    for ((address, pubkey), account) in valid.into_iter().zip(response.value) {
+        match account {
+            Some(account) => fetched.push(FetchedAccount {
+                address: address.clone(),
+                pubkey,
+                account,
+                slot: response.context.slot,
+            }),
+            None => {
+                eprintln!("Failed to dump account {address}: account not found");
+                failed.push(address.clone());
+            }
+        }
+    }
+    (fetched, failed)
+}
+
+pub fn dump_account(
+    network: &NetworkCtx,
+    address: &str,
+    to_path: impl AsRef<Path>,
+    output: OutputOptions,
+) -> Result<()> {
+    output.check_account_names()?;
+    let connection = network.source_client();
+    let fetched = fetch_account(network, &connection, address)?;
+    write_account(
+        network,
+        &connection,
+        &fetched,
+        to_path.as_ref(),
+        output,
+        None,
+    )
+}
+
+/// `(offset, length)` ranges of `chunk` bytes (the last one shorter) covering `len` bytes.
+fn chunk_ranges(len: usize, chunk: usize) -> Vec<(usize, usize)> {
+    (0..len)
+        .step_by(chunk.max(1))
+        .map(|offset| (offset, chunk.min(len - offset)))
+        .collect()
+}
+
+/// Fetches one `dataSlice` of an account, read at `min_context_slot` or
+/// later, with the slot it was read at and the account size.
+fn fetch_data_slice(
+    network: &NetworkCtx,
+    connection: &RpcClient,
+    pubkey: &Pubkey,
+    (offset, length): (usize, usize),
+    min_context_slot: Option<u64>,
+) -> Result<(u64, u64, Vec<u8>)> {
+    let config = serde_json::json!({
+        "encoding": "base64",
+        "commitment": network.read_commitment().commitment,
+        "dataSlice": { "offset": offset, "length": length },
+        "minContextSlot": min_context_slot,
+    });
+    let response: serde_json::Value = connection.send(
+        RpcRequest::GetAccountInfo,
+        serde_json::json!([pubkey.to_string(), config]),
+    )?;
+    let slot = response["context"]["slot"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("RPC does not report the slot of {pubkey}"))?;
+    let value = &response["value"];
+    let space = value["space"]
+        .as_u64()
+        .ok_or_else(|| anyhow!("RPC does not report the size of {pubkey}"))?;
+    let data = value["data"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("Account not found: {pubkey}"))?;
+    Ok((slot, space, STANDARD.decode(data)?))
+}
+
+/// A fetched chunk with the slot it was read at.
+type Chunk = Option<(u64, Vec<u8>)>;
+
+/// Indices of the chunks that are missing or were read at another slot.
+fn stale_chunks(chunks: &[Chunk], slot: u64) -> Vec<usize> {
+    chunks
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| !matches!(chunk, Some((chunk_slot, _)) if *chunk_slot == slot))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Fetches the chunks at `indices` in parallel, at `slot` or later.
+fn fetch_chunks(
+    network: &NetworkCtx,
+    connection: &RpcClient,
+    pubkey: &Pubkey,
+    ranges: &[(usize, usize)],
+    indices: &[usize],
+    slot: u64,
+    chunks: &mut [Chunk],
+) {
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let chunks = Mutex::new(chunks);
+    thread::scope(|scope| {
+        for _ in 0..network.limits.concurrency.max(1).min(indices.len()) {
+            scope.spawn(|| {
+                while let Some(&index) = indices.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let range = ranges[index];
+                    let chunk = fetch_data_slice(network, connection, pubkey, range, Some(slot))
+                        .ok()
+                        .filter(|(_, _, data)| data.len() == range.1);
+                    chunks.lock().unwrap()[index] = chunk.map(|(slot, _, data)| (slot, data));
+                    let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                    eprint!("\rFetching {pubkey}: {done}/{} chunks", indices.len());
+                    network.pause();
                 }
+            });
+        }
+    });
+    eprintln!();
+}
+
+/// Fetches large account data (program data is often several MB) as
+/// parallel `dataSlice` requests with a progress line. Every chunk must be
+/// read at the same slot, so chunks read at an older slot than the newest
+/// one are fetched again. Returns `None` when the account is small, the RPC
+/// does not serve slices or the slots don't settle, so the caller falls back
+/// to a single `getAccountInfo`.
+fn fetch_data_in_chunks(
+    network: &NetworkCtx,
+    connection: &RpcClient,
+    pubkey: &Pubkey,
+) -> Option<Vec<u8>> {
+    let (mut slot, space, _) = fetch_data_slice(network, connection, pubkey, (0, 0), None).ok()?;
+    network.pause();
+    let ranges = chunk_ranges(usize::try_from(space).ok()?, PROGRAM_DATA_CHUNK);
+    if ranges.len() < 2 {
+        return None;
+    }
+    let mut chunks: Vec<Chunk> = vec![None; ranges.len()];
+    let mut indices: Vec<usize> = (0..ranges.len()).collect();
+    for _ in 0..CHUNK_SLOT_ATTEMPTS {
+        fetch_chunks(
+            network,
+            connection,
+            pubkey,
+            &ranges,
+            &indices,
+            slot,
+            &mut chunks,
+        );
+        if chunks.iter().any(Option::is_none) {
+            return None;
+        }
+        slot = chunks
+            .iter()
+            .flatten()
+            .map(|(chunk_slot, _)| *chunk_slot)
+            .fold(slot, u64::max);
+        indices = stale_chunks(&chunks, slot);
+        if indices.is_empty() {
+            return Some(
+                chunks
+                    .into_iter()
+                    .flatten()
+                    .flat_map(|(_, data)| data)
+                    .collect(),
+            );
+        }
+    }
+    eprintln!(
+        "{}",
+        warning(format!("Chunks of {pubkey} were read at different slots"))
+    );
+    None
+}
+
+/// ELF of a program account, read from its program data account for the
+/// upgradeable loader (which also holds migrated core BPF programs) and
+/// after the header for loader-v4.
+fn program_elf(
+    network: &NetworkCtx,
+    connection: &RpcClient,
+    address: &str,
+    account: &Account,
+) -> Result<Vec<u8>> {
+    if account.owner == LOADER_V4_ID {
+        return loader_v4_elf(&account.data)
+            .ok_or_else(|| anyhow!("Loader-v4 program {address} does not hold an ELF"));
+    }
+    let mut program_data = account.data.clone();
+    if account.owner == UPGRADEABLE_LOADER_ID
+        && let Some(program_data_address) = try_get_upgradeable_program_data_address(&account.data)
+    {
+        if let Some(data) = fetch_data_in_chunks(network, connection, &program_data_address) {
+            program_data = data;
+        } else {
+            let program_data_info = connection.get_account(&program_data_address);
+            network.pause();
+            if let Ok(program_data_info) = program_data_info {
+                program_data = program_data_info.data;
             }
         }
+    }
+    extract_elf_bytes(&program_data)
+        .ok_or_else(|| anyhow!("Program data not found or not ELF for: {address}"))
+}
+
+/// Fetches the deployed ELF of a program from the source cluster.
+pub fn fetch_program_elf(network: &NetworkCtx, address: &str) -> Result<Vec<u8>> {
+    let connection = network.source_client();
+    let fetched = fetch_account(network, &connection, address)?;
+    if !is_program(&fetched.account) {
+        return Err(anyhow!("{address} is not a program"));
+    }
+    program_elf(network, &connection, address, &fetched.account)
+}
+
+/// Writes a fetched account as `.so` (programs) or JSON. Non-program
+/// accounts go to `jsonl` instead of their own file when it is given.
+fn write_account(
+    network: &NetworkCtx,
+    connection: &RpcClient,
+    fetched: &FetchedAccount,
+    to_path: &Path,
+    output: OutputOptions,
+    jsonl: Option<&mut JsonLinesWriter<OutputWriter>>,
+) -> Result<()> {
+    let FetchedAccount {
+        address,
+        pubkey,
+        account,
+        slot,
+    } = fetched;
+    if !is_program(account)
+        && let Some(writer) = jsonl
+    {
+        return writer.write(&serialize_account_info(pubkey, account));
+    }
+
+    let vars = [("pubkey", address.clone()), ("slot", slot.to_string())];
+    let stem = output.stem(address, &vars)?;
+    if is_stdio(to_path) {
+        if is_program(account) {
+            return Err(anyhow!(
+                "{address} is a program, dumped as a .so file; pass an output directory"
+            ));
+        }
+        say!("Dumping account {address}...");
+        let payload = serialize_account_info(pubkey, account);
+        return output.write_json(to_path, &payload);
+    }
+
+    let dir = match output.layout {
+        Layout::Flat => to_path.to_path_buf(),
+        Layout::Typed => to_path.join(typed_subdir(account)),
+    };
+    fs::create_dir_all(&dir)?;
 
-        let elf_bytes = extract_elf_bytes(&program_data)
-            .ok_or_else(|| anyhow!("Program data not found or not ELF for: {address}"))?;
-        let out_path = to_path.as_ref().join(format!("{address}.so"));
+    let out_path = if is_program(account) {
+        say!("Dumping program {address}...");
+        let elf_bytes = program_elf(network, connection, address, account)?;
+        let out_path = dir.join(format!("{stem}.so"));
+        if !output.should_write(&out_path)? {
+            return Ok(());
+        }
         fs::write(&out_path, elf_bytes)?;
-        println!("Program dumped to {}", out_path.display());
+        emit(FILE_WRITTEN, serde_json::json!({ "path": out_path }));
+        push("files", &out_path);
+        say!("Program dumped to {}", out_path.display());
+        out_path
     } else {
-        println!("Dumping account {address}...");
-        let payload = serialize_account_info(&pubkey, &account);
-        let out_path = to_path.as_ref().join(format!("{address}.json"));
-        fs::write(&out_path, serde_json::to_string_pretty(&payload)?)?;
-        println!("Account dumped to {}", out_path.display());
-    }
+        say!("Dumping account {address}...");
+        let payload = serialize_account_info(pubkey, account);
+        let out_path = dir.join(output.file_name(&stem));
+        if !output.should_write(&out_path)? {
+            return Ok(());
+        }
+        output.write_json(&out_path, &payload)?;
+        say!("Account dumped to {}", out_path.display());
+        out_path
+    };
 
+    if output.layout == Layout::Typed {
+        record_in_manifest(to_path, address, &out_path, &network.source, *slot)?;
+    }
     Ok(())
 }
 
-fn add_account(set: &mut HashSet<String>, account: &str) {
+/// Subdirectory of the typed layout an account belongs to.
+fn typed_subdir(account: &Account) -> &'static str {
+    let is_token_program =
+        account.owner == TOKEN_PROGRAM_ID || account.owner == TOKEN_2022_PROGRAM_ID;
+    if is_program(account) {
+        "programs"
+    } else if is_token_program
+        && (account.data.len() == MINT_LEN
+            || account.data.get(TOKEN_2022_ACCOUNT_TYPE_OFFSET) == Some(&TOKEN_2022_MINT_TYPE))
+    {
+        "mints"
+    } else if account.owner == LOOKUP_TABLE_PROGRAM_ID {
+        "lookup_tables"
+    } else {
+        "accounts"
+    }
+}
+
+fn read_manifest_value(manifest_path: &Path) -> Result<Option<Map<String, Value>>> {
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(manifest_path)
+        .with_context(|| format!("failed to read {manifest_path:?}"))?;
+    serde_json::from_str(&data)
+        .map(Some)
+        .with_context(|| format!("invalid manifest {manifest_path:?}"))
+}
+
+/// Records `address -> relative path` in the dump directory manifest and
+/// stamps it with the latest slot an account was read at.
+fn record_in_manifest(
+    root: &Path,
+    address: &str,
+    path: &Path,
+    source: &str,
+    slot: u64,
+) -> Result<()> {
+    static MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = MANIFEST_LOCK.lock().unwrap();
+    let manifest_path = root.join(MANIFEST_FILE);
+    let mut manifest = read_manifest_value(&manifest_path)?.unwrap_or_default();
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    manifest.insert(
+        address.to_string(),
+        Value::String(relative.to_string_lossy().into_owned()),
+    );
+    let slot = manifest
+        .get(STAMP_KEY)
+        .and_then(|stamp| stamp["slot"].as_u64())
+        .map_or(slot, |stamped| stamped.max(slot));
+    manifest.insert(STAMP_KEY.to_string(), stamp(source, Some(slot), None));
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)
+        .with_context(|| format!("failed to write {manifest_path:?}"))
+}
+
+/// Reads the `address -> relative path` entries of the manifest of a typed
+/// dump directory, if there is one, warning when it is stale.
+pub fn read_manifest(root: &Path) -> Result<Option<BTreeMap<String, String>>> {
+    let manifest_path = root.join(MANIFEST_FILE);
+    let Some(manifest) = read_manifest_value(&manifest_path)? else {
+        return Ok(None);
+    };
+    if let Some(header) = manifest.get(STAMP_KEY) {
+        let stamped = json!({ STAMP_KEY: header });
+        warn_if_stale(&stamped, &manifest_path.display().to_string());
+    }
+    Ok(Some(
+        manifest
+            .into_iter()
+            .filter(|(address, _)| address != STAMP_KEY)
+            .filter_map(|(address, path)| Some((address, path.as_str()?.to_string())))
+            .collect(),
+    ))
+}
+
+fn add_account(set: &mut BTreeSet<String>, account: &str) {
     if !account.is_empty() {
         set.insert(account.to_string());
     }
 }
 
-pub fn dump_accounts_from_tx(signature: &str, to_path: impl AsRef<Path>) -> Result<()> {
-    let connection = create_connection(MAINNET_RPC_URL);
+/// Account compression programs (with the noop program they log through)
+/// used by a transaction, directly or through Bubblegum.
+fn compression_programs(keys: &[String]) -> Vec<(Pubkey, Pubkey)> {
+    let uses = |program: &Pubkey| keys.iter().any(|key| *key == program.to_string());
+    let families = [
+        (SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID),
+        (MPL_ACCOUNT_COMPRESSION_PROGRAM_ID, MPL_NOOP_PROGRAM_ID),
+    ];
+    let used: Vec<_> = families
+        .into_iter()
+        .filter(|(compression, _)| uses(compression))
+        .collect();
+    if used.is_empty() && uses(&BUBBLEGUM_PROGRAM_ID) {
+        return families.to_vec();
+    }
+    used
+}
+
+pub fn dump_accounts_from_tx(
+    network: &NetworkCtx,
+    signature: &str,
+    to_path: impl AsRef<Path>,
+    output: OutputOptions,
+    with_compression: bool,
+) -> Result<DumpSummary> {
+    let connection = network.source_client();
     let config = RpcTransactionConfig {
         encoding: Some(UiTransactionEncoding::JsonParsed),
-        commitment: Some(CommitmentConfig::confirmed()),
+        commitment: Some(network.history_commitment()),
         max_supported_transaction_version: Some(0),
     };
     let tx = connection
         .get_transaction_with_config(&signature.parse()?, config)
         .with_context(|| format!("Transaction not found: {signature}"))?;
 
-    let mut accounts = HashSet::new();
     let message = match &tx.transaction.transaction {
         EncodedTransaction::Json(tx) => &tx.message,
         _ => return Err(anyhow!("Transaction encoding is not JSON")),
     };
 
-    match message {
-        UiMessage::Parsed(msg) => {
-            for key in &msg.account_keys {
-                add_account(&mut accounts, &key.pubkey);
-            }
-        }
-        UiMessage::Raw(msg) => {
-            for key in &msg.account_keys {
-                add_account(&mut accounts, key);
-            }
-        }
-    }
+    // Keys in the order of the balance arrays: parsed messages already list
+    // loaded addresses, raw ones get them appended from the meta.
+    let mut keys: Vec<String> = match message {
+        UiMessage::Parsed(msg) => msg
+            .account_keys
+            .iter()
+            .map(|key| key.pubkey.clone())
+            .collect(),
+        UiMessage::Raw(msg) => msg.account_keys.clone(),
+    };
+    let mut closed = Vec::new();
+    let mut accounts = BTreeSet::new();
 
     if let Some(meta) = tx.transaction.meta {
         let loaded_addresses: Option<solana_transaction_status::UiLoadedAddresses> =
             meta.loaded_addresses.into();
-        if let Some(loaded) = loaded_addresses {
-            for key in loaded.writable.iter().chain(loaded.readonly.iter()) {
-                add_account(&mut accounts, key);
-            }
+        if let (UiMessage::Raw(_), Some(loaded)) = (message, loaded_addresses) {
+            keys.extend(loaded.writable.into_iter().chain(loaded.readonly));
         }
+        closed = closed_accounts(&keys, &meta.pre_balances, &meta.post_balances);
 
         let token_balances: Vec<solana_transaction_status::UiTransactionTokenBalance> =
             Option::<Vec<_>>::from(meta.pre_token_balances)
                 .unwrap_or_default()
                 .into_iter()
-                .chain(
-                    Option::<Vec<_>>::from(meta.post_token_balances)
-                        .unwrap_or_default()
-                        .into_iter(),
-                )
+                .chain(Option::<Vec<_>>::from(meta.post_token_balances).unwrap_or_default())
                 .collect();
 
         for balance in token_balances {
@@ -149,66 +670,284 @@ pub fn dump_accounts_from_tx(signature: &str, to_path: impl AsRef<Path>) -> Resu
             }
         }
     }
-
-    for account in accounts {
-        if let Err(error) = dump_account(&account, &to_path) {
-            eprintln!("Failed to dump account {account}: {error}");
+    for key in &keys {
+        add_account(&mut accounts, key);
+    }
+    // Lookup tables themselves, so versioned transactions can be replayed.
+    let lookups = match message {
+        UiMessage::Parsed(msg) => &msg.address_table_lookups,
+        UiMessage::Raw(msg) => &msg.address_table_lookups,
+    };
+    for lookup in lookups.iter().flatten() {
+        add_account(&mut accounts, &lookup.account_key);
+    }
+    let compression = compression_programs(&keys);
+    if !compression.is_empty() {
+        eprintln!(
+            "{}",
+            warning(
+                "Warning: the transaction uses account compression; Merkle trees are dumped at their \
+                 current root, so proofs taken from the transaction may no longer verify"
+            )
+        );
+        if with_compression {
+            for (program, noop) in compression {
+                add_account(&mut accounts, &program.to_string());
+                add_account(&mut accounts, &noop.to_string());
+            }
+        } else {
+            eprintln!("Use --with-compression to include the compression and noop programs");
         }
     }
 
-    Ok(())
+    let mut summary = dump_accounts(network, accounts, to_path, signature, output)?;
+    // Accounts the transaction closed no longer exist on the source cluster,
+    // so failing to dump them is expected rather than an error.
+    let (expected, failed) = summary
+        .failed
+        .into_iter()
+        .partition(|address| closed.contains(address));
+    summary.failed = failed;
+    summary.closed = expected;
+    Ok(summary)
+}
+
+/// Accounts funded before the transaction and left with zero lamports by it.
+fn closed_accounts(keys: &[String], pre_balances: &[u64], post_balances: &[u64]) -> Vec<String> {
+    keys.iter()
+        .zip(pre_balances.iter().zip(post_balances))
+        .filter(|(_, (pre, post))| **pre > 0 && **post == 0)
+        .map(|(key, _)| key.clone())
+        .collect()
 }
 
 pub fn dump_accounts_for_tx(
+    network: &NetworkCtx,
     path: impl AsRef<Path>,
     to_path: impl AsRef<Path>,
     params: &[String],
-) -> Result<()> {
+    output: OutputOptions,
+) -> Result<DumpSummary> {
     let tx = load_parsed_tx_from_json(&path, params)?;
 
-    let mut accounts = HashSet::new();
+    let mut accounts = BTreeSet::new();
     for instruction in tx.instructions {
         for account in instruction.accounts {
             add_account(&mut accounts, &account.pubkey.to_string());
         }
     }
 
-    for account in accounts {
-        if let Err(error) = dump_account(&account, &to_path) {
-            eprintln!("Failed to dump account {account}: {error}");
+    let name = path
+        .as_ref()
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("accounts");
+    dump_accounts(network, accounts, &to_path, name, output)
+}
+
+/// Outcome of dumping a set of accounts.
+#[derive(Debug, Default)]
+pub struct DumpSummary {
+    pub dumped: usize,
+    /// Accounts that still failed after the retry
+    pub failed: Vec<String>,
+    /// Accounts closed by the replayed transaction, which cannot be dumped
+    pub closed: Vec<String>,
+    /// Accounts not dumped because the dump was canceled
+    pub canceled: Vec<String>,
+}
+
+impl DumpSummary {
+    pub fn print(&self) {
+        record("dumped", self.dumped);
+        record("failed", &self.failed);
+        record("closed", &self.closed);
+        record("canceled", &self.canceled);
+        say!(
+            "Dumped {} accounts, {} failed",
+            self.dumped,
+            self.failed.len()
+        );
+        for address in &self.failed {
+            say!("  {} {address}", failure("failed:"));
+        }
+        if !self.closed.is_empty() {
+            say!(
+                "Skipped {} accounts closed by the transaction:",
+                self.closed.len()
+            );
+            for address in &self.closed {
+                say!("  closed: {address}");
+            }
+        }
+        if !self.canceled.is_empty() {
+            say!(
+                "{}",
+                warning(format!(
+                    "Canceled before {} accounts were dumped",
+                    self.canceled.len()
+                ))
+            );
         }
     }
+}
 
-    Ok(())
+/// Retries failed accounts once after a backoff, returning those that still
+/// fail and those not retried because `cancel` was set.
+fn retry_failed(
+    failed: Vec<String>,
+    cancel: &AtomicBool,
+    mut dump: impl FnMut(&str) -> Result<()>,
+) -> (Vec<String>, Vec<String>) {
+    if failed.is_empty() || cancel.load(Ordering::Relaxed) {
+        return (failed, Vec::new());
+    }
+    say!(
+        "Retrying {} failed accounts in {}s...",
+        failed.len(),
+        RETRY_BACKOFF.as_secs()
+    );
+    emit(
+        RPC_RETRY,
+        serde_json::json!({ "accounts": failed.len(), "backoff_ms": RETRY_BACKOFF.as_millis() }),
+    );
+    let started = Instant::now();
+    while started.elapsed() < RETRY_BACKOFF && !cancel.load(Ordering::Relaxed) {
+        thread::sleep(CANCEL_POLL);
+    }
+    let mut still_failed = Vec::new();
+    let mut canceled = Vec::new();
+    for address in failed {
+        if cancel.load(Ordering::Relaxed) {
+            canceled.push(address);
+        } else if let Err(error) = dump(&address) {
+            eprintln!("Failed to dump account {address}: {error}");
+            still_failed.push(address);
+        }
+    }
+    (still_failed, canceled)
 }
 
-pub fn dump_raw_transaction(signature: &str, to_path: impl AsRef<Path>) -> Result<()> {
-    let connection = create_connection(MAINNET_RPC_URL);
+/// Dumps every account of the set, fetching them in batches and retrying
+/// failures once at the end. With JSON Lines output, non-program accounts go
+/// to `<name>.jsonl` one per line; programs are still `.so` files. After a
+/// Ctrl-C (see `cancel_on_interrupt`) no new accounts are started and the
+/// summary lists the canceled ones.
+pub fn dump_accounts(
+    network: &NetworkCtx,
+    accounts: BTreeSet<String>,
+    to_path: impl AsRef<Path>,
+    name: &str,
+    output: OutputOptions,
+) -> Result<DumpSummary> {
+    let to_path = to_path.as_ref();
+    if is_stdio(to_path) && !output.jsonl {
+        return Err(anyhow!("Writing several accounts to stdout needs --jsonl"));
+    }
+    output.check_account_names()?;
+    let accounts: Vec<String> = accounts.into_iter().collect();
+    let total = accounts.len();
+
+    let mut jsonl_path = to_path.join(output.file_name(name));
+    let mut jsonl = None;
+    if output.jsonl {
+        let Some(path) = output.path_in(to_path, name)? else {
+            return Ok(DumpSummary::default());
+        };
+        jsonl = Some(JsonLinesWriter::new(output.create(&path)?));
+        jsonl_path = path;
+    } else {
+        fs::create_dir_all(to_path)?;
+    }
+
+    let connection = network.source_client();
+    let (fetched, mut failed, mut canceled) = fetch_accounts(network, &accounts, &CANCELED);
+    for account in &fetched {
+        if CANCELED.load(Ordering::Relaxed) {
+            canceled.push(account.address.clone());
+            continue;
+        }
+        if let Err(error) = write_account(
+            network,
+            &connection,
+            account,
+            to_path,
+            output,
+            jsonl.as_mut(),
+        ) {
+            eprintln!("Failed to dump account {}: {error}", account.address);
+            failed.push(account.address.clone());
+        }
+    }
+    let (failed, retries_canceled) = retry_failed(failed, &CANCELED, |address| {
+        let fetched = fetch_account(network, &connection, address)?;
+        write_account(
+            network,
+            &connection,
+            &fetched,
+            to_path,
+            output,
+            jsonl.as_mut(),
+        )
+    });
+
+    if let Some(writer) = jsonl {
+        writer.into_inner().finish()?;
+        say!("Accounts written to {}", jsonl_path.display());
+    }
+    canceled.extend(retries_canceled);
+    canceled.sort();
+    Ok(DumpSummary {
+        dumped: total - failed.len() - canceled.len(),
+        failed,
+        canceled,
+        ..Default::default()
+    })
+}
+
+pub fn dump_raw_transaction(
+    network: &NetworkCtx,
+    signature: &str,
+    to_path: impl AsRef<Path>,
+    output: OutputOptions,
+) -> Result<()> {
+    let connection = network.source_client();
     let config = RpcTransactionConfig {
         encoding: Some(UiTransactionEncoding::JsonParsed),
-        commitment: Some(CommitmentConfig::confirmed()),
+        commitment: Some(network.history_commitment()),
         max_supported_transaction_version: Some(0),
     };
     let tx = connection
         .get_transaction_with_config(&signature.parse()?, config)
         .with_context(|| format!("Transaction not found: {signature}"))?;
 
-    fs::create_dir_all(&to_path)?;
-    let file_path = to_path.as_ref().join(format!("{signature}.json"));
-    fs::write(&file_path, serde_json::to_string_pretty(&tx)?)?;
-    println!("Raw transaction dumped to {}", file_path.display());
+    let vars = [
+        ("signature", signature.to_string()),
+        ("slot", tx.slot.to_string()),
+    ];
+    let stem = output.stem(signature, &vars)?;
+    let Some(file_path) = output.path_in(to_path.as_ref(), &stem)? else {
+        return Ok(());
+    };
+    output.write_json(&file_path, &tx)?;
+    say!("Raw transaction dumped to {}", file_path.display());
     Ok(())
 }
 
-pub fn dump_raw_block(slot: &str, to_path: impl AsRef<Path>) -> Result<()> {
-    let connection = create_connection(MAINNET_RPC_URL);
+pub fn dump_raw_block(
+    network: &NetworkCtx,
+    slot: &str,
+    to_path: impl AsRef<Path>,
+    output: OutputOptions,
+) -> Result<()> {
+    let connection = network.source_client();
     let slot_num: u64 = slot.parse().map_err(|_| anyhow!("Invalid slot: {slot}"))?;
 
     let config = solana_rpc_client::api::config::RpcBlockConfig {
         encoding: Some(UiTransactionEncoding::JsonParsed),
         transaction_details: Some(solana_transaction_status::TransactionDetails::Full),
         rewards: Some(true),
-        commitment: Some(CommitmentConfig::confirmed()),
+        commitment: Some(network.history_commitment()),
         max_supported_transaction_version: Some(0),
     };
 
@@ -216,9 +955,149 @@ pub fn dump_raw_block(slot: &str, to_path: impl AsRef<Path>) -> Result<()> {
         .get_block_with_config(slot_num, config)
         .with_context(|| format!("Block not found: {slot}"))?;
 
-    fs::create_dir_all(&to_path)?;
-    let file_path = to_path.as_ref().join(format!("{slot_num}.json"));
-    fs::write(&file_path, serde_json::to_string_pretty(&block)?)?;
-    println!("Raw block dumped to {}", file_path.display());
+    let stem = output.stem(&slot_num.to_string(), &[("slot", slot_num.to_string())])?;
+    let Some(file_path) = output.path_in(to_path.as_ref(), &stem)? else {
+        return Ok(());
+    };
+    // The block response has no slot of its own; keep it for offline parsing.
+    let mut raw = serde_json::to_value(&block)?;
+    if let Value::Object(map) = &mut raw {
+        map.insert("slot".to_string(), slot_num.into());
+    }
+    output.write_json(&file_path, &raw)?;
+    say!("Raw block dumped to {}", file_path.display());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, sync::atomic::AtomicBool};
+
+    use serde_json::Value;
+
+    use solana_sdk::account::Account;
+
+    use super::{
+        LOADER_V4_HEADER_LEN, LOADER_V4_ID, chunk_ranges, closed_accounts, compression_programs,
+        dump_account, fetch_accounts, is_program, loader_v4_elf, retry_failed, stale_chunks,
+    };
+    use crate::accounts::{
+        BUBBLEGUM_PROGRAM_ID, SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID,
+    };
+    use crate::network::NetworkCtx;
+    use crate::rpc::RpcMocks;
+    use crate::tools::output::OutputOptions;
+
+    #[test]
+    fn dump_account_writes_mocked_account() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/rpc");
+        let network = NetworkCtx::mocked(RpcMocks::load(fixtures.join("account.json")).unwrap());
+        let dir = tempfile::tempdir().unwrap();
+
+        let address = "7epHow1uCBj2VnMfL3ThsbZSwDnnibmL9SJ2uTdQg8i6";
+        dump_account(&network, address, dir.path(), OutputOptions::default()).unwrap();
+
+        let dumped: Value = serde_json::from_str(
+            &fs::read_to_string(dir.path().join(format!("{address}.json"))).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(dumped["pubkey"], address);
+        assert_eq!(dumped["account"]["lamports"], 1461600);
+        assert_eq!(dumped["account"]["data"][0], "AQIDBAUGBwg=");
+    }
+
+    #[test]
+    fn fetch_accounts_reports_failures_in_order() {
+        let mut network = NetworkCtx::mocked(RpcMocks::default());
+        network.limits.batch_size = 1;
+        network.limits.concurrency = 4;
+        network.limits.delay = std::time::Duration::ZERO;
+        let addresses: Vec<String> = ["d", "c", "b", "a", "e"].map(String::from).to_vec();
+        let (fetched, failed, canceled) =
+            fetch_accounts(&network, &addresses, &AtomicBool::new(false));
+        assert!(fetched.is_empty() && canceled.is_empty());
+        assert_eq!(failed, ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn canceled_dumps_start_no_new_work() {
+        let mut network = NetworkCtx::mocked(RpcMocks::default());
+        network.limits.batch_size = 2;
+        network.limits.delay = std::time::Duration::ZERO;
+        let addresses: Vec<String> = ["c", "a", "b"].map(String::from).to_vec();
+        let cancel = AtomicBool::new(true);
+        let (fetched, failed, canceled) = fetch_accounts(&network, &addresses, &cancel);
+        assert!(fetched.is_empty() && failed.is_empty());
+        assert_eq!(canceled, ["a", "b", "c"]);
+
+        let retried = retry_failed(vec!["a".to_string()], &cancel, |_| {
+            panic!("canceled retries must not run")
+        });
+        assert_eq!(retried, (vec!["a".to_string()], Vec::new()));
+    }
+
+    #[test]
+    fn loader_v4_programs_are_read_after_the_header() {
+        let mut data = vec![0; LOADER_V4_HEADER_LEN];
+        data[40] = 1;
+        data.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2]);
+        let mut account = Account {
+            owner: LOADER_V4_ID,
+            data,
+            ..Account::default()
+        };
+        assert!(is_program(&account));
+        assert_eq!(
+            loader_v4_elf(&account.data).unwrap(),
+            [0x7f, b'E', b'L', b'F', 2]
+        );
+
+        account.data[40] = 0;
+        assert!(!is_program(&account));
+    }
+
+    #[test]
+    fn detects_compression_usage() {
+        let keys = |programs: &[&solana_sdk::pubkey::Pubkey]| -> Vec<String> {
+            programs.iter().map(|program| program.to_string()).collect()
+        };
+        assert!(compression_programs(&keys(&[&LOADER_V4_ID])).is_empty());
+        assert_eq!(
+            compression_programs(&keys(&[&SPL_ACCOUNT_COMPRESSION_PROGRAM_ID])),
+            [(SPL_ACCOUNT_COMPRESSION_PROGRAM_ID, SPL_NOOP_PROGRAM_ID)]
+        );
+        assert_eq!(
+            compression_programs(&keys(&[&BUBBLEGUM_PROGRAM_ID])).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn chunks_at_other_slots_are_stale() {
+        let chunks = vec![
+            Some((5, vec![1])),
+            None,
+            Some((4, vec![2])),
+            Some((5, vec![3])),
+        ];
+        assert_eq!(stale_chunks(&chunks, 5), [1, 2]);
+        assert_eq!(stale_chunks(&chunks, 6), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn chunk_ranges_cover_the_data() {
+        assert_eq!(chunk_ranges(10, 4), [(0, 4), (4, 4), (8, 2)]);
+        assert_eq!(chunk_ranges(8, 4), [(0, 4), (4, 4)]);
+        assert!(chunk_ranges(0, 4).is_empty());
+    }
+
+    #[test]
+    fn closed_accounts_lose_all_lamports() {
+        let keys: Vec<String> = ["payer", "closed", "created", "empty"]
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        let closed = closed_accounts(&keys, &[100, 50, 0, 0], &[90, 0, 20, 0]);
+        assert_eq!(closed, ["closed"]);
+    }
+}
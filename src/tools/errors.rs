@@ -0,0 +1,327 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+use solana_rpc_client::api::{
+    client_error::{Error as ClientError, ErrorKind},
+    request::{RpcError, RpcResponseErrorData},
+};
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    transaction::TransactionError,
+};
+
+use crate::accounts::TOKEN_PROGRAM_ID;
+use crate::tools::style::failure;
+
+const ANY_PROGRAM: &str = "*";
+
+const TOKEN_ERRORS: [&str; 20] = [
+    "NotRentExempt",
+    "InsufficientFunds",
+    "InvalidMint",
+    "MintMismatch",
+    "OwnerMismatch",
+    "FixedSupply",
+    "AlreadyInUse",
+    "InvalidNumberOfProvidedSigners",
+    "InvalidNumberOfRequiredSigners",
+    "UninitializedState",
+    "NativeNotSupported",
+    "NonNativeHasBalance",
+    "InvalidInstruction",
+    "InvalidState",
+    "Overflow",
+    "AuthorityTypeNotSupported",
+    "MintCannotFreeze",
+    "AccountFrozen",
+    "MintDecimalsMismatch",
+    "NonNativeNotSupported",
+];
+
+const ANCHOR_ERRORS: [(u32, &str); 40] = [
+    (100, "InstructionMissing"),
+    (101, "InstructionFallbackNotFound"),
+    (102, "InstructionDidNotDeserialize"),
+    (103, "InstructionDidNotSerialize"),
+    (2000, "ConstraintMut"),
+    (2001, "ConstraintHasOne"),
+    (2002, "ConstraintSigner"),
+    (2003, "ConstraintRaw"),
+    (2004, "ConstraintOwner"),
+    (2005, "ConstraintRentExempt"),
+    (2006, "ConstraintSeeds"),
+    (2007, "ConstraintExecutable"),
+    (2008, "ConstraintState"),
+    (2009, "ConstraintAssociated"),
+    (2010, "ConstraintAssociatedInit"),
+    (2011, "ConstraintClose"),
+    (2012, "ConstraintAddress"),
+    (2013, "ConstraintZero"),
+    (2014, "ConstraintTokenMint"),
+    (2015, "ConstraintTokenOwner"),
+    (3000, "AccountDiscriminatorAlreadySet"),
+    (3001, "AccountDiscriminatorNotFound"),
+    (3002, "AccountDiscriminatorMismatch"),
+    (3003, "AccountDidNotDeserialize"),
+    (3004, "AccountDidNotSerialize"),
+    (3005, "AccountNotEnoughKeys"),
+    (3006, "AccountNotMutable"),
+    (3007, "AccountOwnedByWrongProgram"),
+    (3008, "InvalidProgramId"),
+    (3009, "InvalidProgramExecutable"),
+    (3010, "AccountNotSigner"),
+    (3011, "AccountNotSystemOwned"),
+    (3012, "AccountNotInitialized"),
+    (3013, "AccountNotProgramData"),
+    (3014, "AccountNotAssociatedTokenAccount"),
+    (3015, "AccountSysvarMismatch"),
+    (3016, "AccountReallocExceedsLimit"),
+    (3017, "AccountDuplicateReallocs"),
+    (4100, "DeclaredProgramIdMismatch"),
+    (5000, "Deprecated"),
+];
+
+/// Custom program error names keyed by program id (`*` matches any program).
+#[derive(Debug, Default)]
+pub struct ErrorMap {
+    programs: HashMap<String, HashMap<u32, String>>,
+    /// Programs registered from an Anchor IDL; only these get the Anchor
+    /// framework error names.
+    anchor_programs: HashSet<String>,
+}
+
+fn parse_code(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(num) => num.as_u64().and_then(|v| u32::try_from(v).ok()),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => s.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+impl ErrorMap {
+    pub fn load(paths: &[impl AsRef<Path>]) -> Result<Self> {
+        let mut map = ErrorMap::default();
+        for path in paths {
+            let data = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {:?}", path.as_ref()))?;
+            let value: Value = serde_json::from_str(&data)
+                .with_context(|| format!("invalid JSON in {:?}", path.as_ref()))?;
+            map.register(&value)
+                .with_context(|| format!("invalid error map in {:?}", path.as_ref()))?;
+        }
+        Ok(map)
+    }
+
+    /// Registers either an Anchor IDL (`errors` array) or a
+    /// `{ "<program_id>": { "<code>": "<name>" } }` map.
+    pub fn register(&mut self, value: &Value) -> Result<()> {
+        let object = value
+            .as_object()
+            .ok_or_else(|| anyhow!("Error map must be an object"))?;
+
+        if let Some(errors) = object.get("errors").and_then(Value::as_array) {
+            let program = object
+                .get("address")
+                .or_else(|| object.get("metadata").and_then(|m| m.get("address")))
+                .and_then(Value::as_str)
+                .unwrap_or(ANY_PROGRAM)
+                .to_string();
+            self.anchor_programs.insert(program.clone());
+            let entries = self.programs.entry(program).or_default();
+            for error in errors {
+                let code = error.get("code").and_then(parse_code);
+                let name = error.get("name").and_then(Value::as_str);
+                if let (Some(code), Some(name)) = (code, name) {
+                    let label = match error.get("msg").and_then(Value::as_str) {
+                        Some(msg) => format!("{name}: {msg}"),
+                        None => name.to_string(),
+                    };
+                    entries.insert(code, label);
+                }
+            }
+            return Ok(());
+        }
+
+        for (program, codes) in object {
+            let codes = codes
+                .as_object()
+                .ok_or_else(|| anyhow!("Error codes for {program} must be an object"))?;
+            let entries = self.programs.entry(program.clone()).or_default();
+            for (code, name) in codes {
+                let code = parse_code(&Value::String(code.clone()))
+                    .ok_or_else(|| anyhow!("Invalid error code {code}"))?;
+                let name = name
+                    .as_str()
+                    .ok_or_else(|| anyhow!("Error name for {code} must be a string"))?;
+                entries.insert(code, name.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn lookup(&self, program_id: &str, code: u32) -> Option<String> {
+        for program in [program_id, ANY_PROGRAM] {
+            if let Some(name) = self
+                .programs
+                .get(program)
+                .and_then(|codes| codes.get(&code))
+            {
+                return Some(name.clone());
+            }
+        }
+        if program_id == TOKEN_PROGRAM_ID.to_string() {
+            return TOKEN_ERRORS.get(code as usize).map(|name| name.to_string());
+        }
+        let anchor = [program_id, ANY_PROGRAM]
+            .iter()
+            .any(|program| self.anchor_programs.contains(*program));
+        if !anchor || code < 100 {
+            return None;
+        }
+        ANCHOR_ERRORS
+            .iter()
+            .find(|(known, _)| *known == code)
+            .map(|(_, name)| format!("Anchor {name}"))
+    }
+}
+
+/// Returns the log lines produced by the top-level instruction at `index`.
+pub fn instruction_logs(logs: &[String], index: usize) -> &[String] {
+    let starts: Vec<usize> = logs
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.ends_with(" invoke [1]"))
+        .map(|(idx, _)| idx)
+        .collect();
+    match starts.get(index) {
+        Some(&start) => {
+            let end = starts.get(index + 1).copied().unwrap_or(logs.len());
+            &logs[start..end]
+        }
+        None => logs,
+    }
+}
+
+pub fn report_failure(
+    error: &TransactionError,
+    logs: &[String],
+    instructions: &[Instruction],
+    errors: &ErrorMap,
+) {
+    eprintln!("{}", failure(format!("Transaction failed: {error}")));
+    let TransactionError::InstructionError(index, ix_error) = error else {
+        for log in logs {
+            eprintln!("  {log}");
+        }
+        return;
+    };
+
+    let index = *index as usize;
+    let program_id = instructions
+        .get(index)
+        .map(|ix| ix.program_id.to_string())
+        .unwrap_or_default();
+    eprintln!("Failed instruction: #{index} (program {program_id})");
+
+    match ix_error {
+        InstructionError::Custom(code) => {
+            let name = errors
+                .lookup(&program_id, *code)
+                .unwrap_or_else(|| "unknown error".to_string());
+            eprintln!(
+                "{}",
+                failure(format!(
+                    "Error: custom program error {code} (0x{code:x}) {name}"
+                ))
+            );
+        }
+        other => eprintln!("{}", failure(format!("Error: {other}"))),
+    }
+
+    let excerpt = instruction_logs(logs, index);
+    if !excerpt.is_empty() {
+        eprintln!("Logs:");
+        for log in excerpt {
+            eprintln!("  {log}");
+        }
+    }
+}
+
+/// Extracts the transaction error and simulation logs from a failed send.
+pub fn send_failure_details(error: &ClientError) -> Option<(TransactionError, Vec<String>)> {
+    let tx_error = error.get_transaction_error()?;
+    let logs = match error.kind() {
+        ErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        }) => result.logs.clone().unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    Some((tx_error, logs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorMap, instruction_logs};
+    use crate::accounts::TOKEN_PROGRAM_ID;
+    use serde_json::json;
+
+    #[test]
+    fn error_map_reads_idl_and_plain_maps() {
+        let mut map = ErrorMap::default();
+        map.register(&json!({
+            "address": "Prog111111111111111111111111111111111111111",
+            "errors": [{"code": 6000, "name": "Overflow", "msg": "Math overflow"}]
+        }))
+        .expect("idl");
+        map.register(&json!({"Other11111111111111111111111111111111111111": {"0x1": "Bad"}}))
+            .expect("map");
+
+        assert_eq!(
+            map.lookup("Prog111111111111111111111111111111111111111", 6000),
+            Some("Overflow: Math overflow".to_string())
+        );
+        assert_eq!(
+            map.lookup("Other11111111111111111111111111111111111111", 1),
+            Some("Bad".to_string())
+        );
+        assert_eq!(
+            map.lookup(&TOKEN_PROGRAM_ID.to_string(), 1),
+            Some("InsufficientFunds".to_string())
+        );
+        assert_eq!(
+            map.lookup("Prog111111111111111111111111111111111111111", 2003),
+            Some("Anchor ConstraintRaw".to_string())
+        );
+        assert_eq!(map.lookup("Unknown", 2003), None);
+        assert_eq!(
+            map.lookup("Other11111111111111111111111111111111111111", 2003),
+            None
+        );
+        assert_eq!(super::parse_code(&json!(u64::MAX)), None);
+    }
+
+    #[test]
+    fn instruction_logs_selects_top_level_invoke() {
+        let logs: Vec<String> = [
+            "Program A invoke [1]",
+            "Program A success",
+            "Program B invoke [1]",
+            "Program C invoke [2]",
+            "Program B failed",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(instruction_logs(&logs, 1), &logs[2..]);
+        assert_eq!(instruction_logs(&logs, 0), &logs[..2]);
+    }
+}
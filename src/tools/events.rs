@@ -0,0 +1,337 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::{Map, Value, json};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::tools::compute::{is_invoke_end, parse_invoke};
+use crate::tools::log_assert::{PROGRAM_DATA_PREFIX, event_discriminator};
+
+/// Event of an Anchor IDL.
+#[derive(Debug)]
+struct EventSchema {
+    name: String,
+    discriminator: [u8; 8],
+    /// Field list of legacy IDLs; newer IDLs describe the event in `types`
+    fields: Option<Value>,
+}
+
+/// Event schemas and type definitions collected from Anchor IDLs.
+#[derive(Debug, Default)]
+pub struct EventRegistry {
+    events: Vec<EventSchema>,
+    types: HashMap<String, Value>,
+}
+
+impl EventRegistry {
+    pub fn load(paths: &[impl AsRef<Path>]) -> Result<Self> {
+        let mut registry = EventRegistry::default();
+        for path in paths {
+            let data = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {:?}", path.as_ref()))?;
+            let idl: Value = serde_json::from_str(&data)
+                .with_context(|| format!("invalid JSON in {:?}", path.as_ref()))?;
+            registry.register(&idl);
+        }
+        Ok(registry)
+    }
+
+    /// Registers the `events` and `types` of an Anchor IDL (legacy or 0.30+ format).
+    pub fn register(&mut self, idl: &Value) {
+        for ty in idl["types"].as_array().into_iter().flatten() {
+            if let Some(name) = ty["name"].as_str() {
+                self.types.insert(name.to_string(), ty["type"].clone());
+            }
+        }
+        for event in idl["events"].as_array().into_iter().flatten() {
+            let Some(name) = event["name"].as_str() else {
+                continue;
+            };
+            let discriminator = event["discriminator"]
+                .as_array()
+                .and_then(|bytes| {
+                    bytes
+                        .iter()
+                        .map(|byte| byte.as_u64().map(|byte| byte as u8))
+                        .collect::<Option<Vec<u8>>>()
+                })
+                .and_then(|bytes| bytes.try_into().ok())
+                .unwrap_or_else(|| event_discriminator(name));
+            self.events.push(EventSchema {
+                name: name.to_string(),
+                discriminator,
+                fields: event.get("fields").cloned(),
+            });
+        }
+    }
+
+    /// Name and decoded fields of `Program data:` bytes, if they match a registered event.
+    fn decode(&self, data: &[u8]) -> Option<(String, Option<Value>)> {
+        let event = self
+            .events
+            .iter()
+            .find(|event| data.starts_with(&event.discriminator))?;
+        let mut rest = &data[8..];
+        let fields = match &event.fields {
+            Some(fields) => decode_fields(fields, &mut rest, &self.types),
+            None => match self.types.get(&event.name) {
+                Some(ty) => decode_defined(ty, &mut rest, &self.types),
+                None => Err(anyhow!("No type for event {}", event.name)),
+            },
+        };
+        Some((event.name.clone(), fields.ok()))
+    }
+}
+
+/// Events emitted through `Program data:` logs with the emitting program,
+/// top-level instruction and invoke depth. Fields are decoded when the event
+/// is registered.
+pub fn extract_events(logs: &[String], registry: &EventRegistry) -> Vec<Value> {
+    let mut events = Vec::new();
+    let mut stack: Vec<(String, usize)> = Vec::new();
+    let mut instruction = 0;
+    let mut invoked = false;
+
+    for line in logs {
+        if let Some((program, depth)) = parse_invoke(line) {
+            if depth == 1 && invoked {
+                instruction += 1;
+            }
+            invoked = true;
+            stack.push((program.to_string(), depth));
+        } else if is_invoke_end(line) {
+            stack.pop();
+        } else if let Some(data) = line.strip_prefix(PROGRAM_DATA_PREFIX) {
+            let (program, depth) = stack.last().cloned().unwrap_or_default();
+            let decoded = STANDARD
+                .decode(data)
+                .ok()
+                .and_then(|bytes| registry.decode(&bytes));
+            let (name, fields) = decoded.unzip();
+            events.push(json!({
+                "instruction": instruction,
+                "program": program,
+                "depth": depth,
+                "name": name,
+                "fields": fields.flatten(),
+                "data": data,
+            }));
+        }
+    }
+    events
+}
+
+/// Destination of the structured events of an execution or parsed transaction.
+pub struct EventsOutput {
+    pub path: PathBuf,
+    pub registry: EventRegistry,
+}
+
+impl EventsOutput {
+    pub fn write(&self, signature: &str, slot: u64, logs: &[String]) -> Result<()> {
+        let events = extract_events(logs, &self.registry);
+        let document = json!({
+            "signature": signature,
+            "slot": slot,
+            "events": events,
+        });
+        fs::write(&self.path, serde_json::to_string_pretty(&document)?)
+            .with_context(|| format!("failed to write {:?}", self.path))?;
+        say!("{} events written to {}", events.len(), self.path.display());
+        Ok(())
+    }
+}
+
+fn decode_fields(
+    fields: &Value,
+    data: &mut &[u8],
+    types: &HashMap<String, Value>,
+) -> Result<Value> {
+    let fields = fields
+        .as_array()
+        .ok_or_else(|| anyhow!("Fields must be an array"))?;
+    let named = fields.iter().all(|field| field.get("name").is_some());
+    if named {
+        let mut object = Map::new();
+        for field in fields {
+            let name = field["name"].as_str().unwrap_or_default().to_string();
+            object.insert(name, decode_type(&field["type"], data, types)?);
+        }
+        Ok(Value::Object(object))
+    } else {
+        fields
+            .iter()
+            .map(|ty| decode_type(ty.get("type").unwrap_or(ty), data, types))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array)
+    }
+}
+
+fn decode_defined(ty: &Value, data: &mut &[u8], types: &HashMap<String, Value>) -> Result<Value> {
+    match ty["kind"].as_str() {
+        Some("struct") => match ty.get("fields") {
+            Some(fields) => decode_fields(fields, data, types),
+            None => Ok(Value::Null),
+        },
+        Some("enum") => {
+            let index = take(data, 1)?[0] as usize;
+            let variant = ty["variants"]
+                .get(index)
+                .ok_or_else(|| anyhow!("Unknown enum variant {index}"))?;
+            let name = variant["name"].as_str().unwrap_or_default();
+            match variant.get("fields") {
+                Some(fields) => Ok(json!({ name: decode_fields(fields, data, types)? })),
+                None => Ok(Value::String(name.to_string())),
+            }
+        }
+        Some("alias") => decode_type(&ty["value"], data, types),
+        _ => Err(anyhow!("Unsupported type definition")),
+    }
+}
+
+fn decode_type(ty: &Value, data: &mut &[u8], types: &HashMap<String, Value>) -> Result<Value> {
+    if let Some(name) = ty.as_str() {
+        return decode_primitive(name, data);
+    }
+    if let Some(inner) = ty.get("vec") {
+        let len = u32::from_le_bytes(take(data, 4)?.try_into()?) as usize;
+        return (0..len)
+            .map(|_| decode_type(inner, data, types))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array);
+    }
+    if let Some(inner) = ty.get("option") {
+        return match take(data, 1)?[0] {
+            0 => Ok(Value::Null),
+            _ => decode_type(inner, data, types),
+        };
+    }
+    if let Some([inner, len]) = ty.get("array").and_then(Value::as_array).map(Vec::as_slice) {
+        let len = len
+            .as_u64()
+            .ok_or_else(|| anyhow!("Invalid array length"))?;
+        return (0..len)
+            .map(|_| decode_type(inner, data, types))
+            .collect::<Result<Vec<_>>>()
+            .map(Value::Array);
+    }
+    if let Some(defined) = ty.get("defined") {
+        let name = defined
+            .as_str()
+            .or_else(|| defined["name"].as_str())
+            .ok_or_else(|| anyhow!("Invalid defined type"))?;
+        let definition = types
+            .get(name)
+            .ok_or_else(|| anyhow!("Unknown type {name}"))?;
+        return decode_defined(definition, data, types);
+    }
+    Err(anyhow!("Unsupported type {ty}"))
+}
+
+fn decode_primitive(name: &str, data: &mut &[u8]) -> Result<Value> {
+    macro_rules! number {
+        ($ty:ty) => {{
+            let bytes = take(data, std::mem::size_of::<$ty>())?;
+            <$ty>::from_le_bytes(bytes.try_into()?)
+        }};
+    }
+    Ok(match name {
+        "bool" => Value::Bool(take(data, 1)?[0] != 0),
+        "u8" => json!(number!(u8)),
+        "i8" => json!(number!(i8)),
+        "u16" => json!(number!(u16)),
+        "i16" => json!(number!(i16)),
+        "u32" => json!(number!(u32)),
+        "i32" => json!(number!(i32)),
+        "u64" => json!(number!(u64)),
+        "i64" => json!(number!(i64)),
+        "f32" => json!(number!(f32)),
+        "f64" => json!(number!(f64)),
+        "u128" => Value::String(number!(u128).to_string()),
+        "i128" => Value::String(number!(i128).to_string()),
+        "pubkey" | "publicKey" => {
+            let bytes: [u8; 32] = take(data, 32)?.try_into()?;
+            Value::String(Pubkey::new_from_array(bytes).to_string())
+        }
+        "string" => {
+            let len = u32::from_le_bytes(take(data, 4)?.try_into()?) as usize;
+            Value::String(String::from_utf8(take(data, len)?.to_vec())?)
+        }
+        "bytes" => {
+            let len = u32::from_le_bytes(take(data, 4)?.try_into()?) as usize;
+            Value::String(STANDARD.encode(take(data, len)?))
+        }
+        other => return Err(anyhow!("Unsupported type {other}")),
+    })
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(anyhow!("Event data too short"));
+    }
+    let (head, rest) = data.split_at(len);
+    *data = rest;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use serde_json::json;
+
+    use super::{EventRegistry, extract_events};
+    use crate::tools::log_assert::event_discriminator;
+
+    #[test]
+    fn extracts_and_decodes_registered_events() {
+        let mut registry = EventRegistry::default();
+        registry.register(&json!({
+            "events": [{ "name": "SwapEvent" }],
+            "types": [{
+                "name": "SwapEvent",
+                "type": { "kind": "struct", "fields": [
+                    { "name": "amount", "type": "u64" },
+                    { "name": "memo", "type": { "option": "string" } }
+                ]}
+            }]
+        }));
+        let mut data = event_discriminator("SwapEvent").to_vec();
+        data.extend(42u64.to_le_bytes());
+        data.extend([1, 2, 0, 0, 0]);
+        data.extend(b"hi");
+        let logs: Vec<String> = [
+            "Program Comp1111111111111111111111111111111111 invoke [1]",
+            "Program Comp1111111111111111111111111111111111 success",
+            "Program Swap111111111111111111111111111111111 invoke [1]",
+            "Program Pool111111111111111111111111111111111 invoke [2]",
+            &format!("Program data: {}", STANDARD.encode(&data)),
+            "Program Pool111111111111111111111111111111111 success",
+            "Program data: AAAAAAAAAAA=",
+            "Program Swap111111111111111111111111111111111 success",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let events = extract_events(&logs, &registry);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["instruction"], 1);
+        assert_eq!(
+            events[0]["program"],
+            "Pool111111111111111111111111111111111"
+        );
+        assert_eq!(events[0]["depth"], 2);
+        assert_eq!(events[0]["name"], "SwapEvent");
+        assert_eq!(events[0]["fields"], json!({ "amount": 42, "memo": "hi" }));
+        assert_eq!(
+            events[1]["program"],
+            "Swap111111111111111111111111111111111"
+        );
+        assert_eq!(events[1]["name"], json!(null));
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::accounts::{
+    ASSOCIATED_TOKEN_PROGRAM_ID, COMPUTE_BUDGET_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+use crate::tx_format::{
+    RawInstruction, RawTransaction, data_format::pack_data, json_tx::expand_shorthand,
+    strict::check_fields,
+};
+
+/// What `fmt-tx` does with shorthand instructions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shorthands {
+    /// Leave shorthand and explicit instructions as written
+    #[default]
+    Keep,
+    /// Replace shorthands with their explicit instructions
+    Expand,
+    /// Replace explicit instructions equal to a shorthand with the shorthand
+    Collapse,
+}
+
+/// Rewrites a template in canonical form: the layout of `parse-tx` output,
+/// with sorted keys and explicit account flags. With `check`, the file is left
+/// untouched and an error reports whether it is not canonical.
+pub fn format_template(path: impl AsRef<Path>, shorthands: Shorthands, check: bool) -> Result<()> {
+    let path = path.as_ref();
+    let original =
+        fs::read_to_string(path).with_context(|| format!("Error reading file {path:?}"))?;
+    let value: Value =
+        serde_json::from_str(&original).with_context(|| format!("Invalid JSON in {path:?}"))?;
+    check_fields(&value).with_context(|| format!("Not formatting {}", path.display()))?;
+    let tx: RawTransaction = serde_json::from_value(value)?;
+    let formatted = to_canonical_string(&canonicalize(tx, shorthands)?)?;
+
+    if formatted == original {
+        return Ok(());
+    }
+    if check {
+        return Err(anyhow!("{} is not formatted", path.display()));
+    }
+    fs::write(path, formatted).with_context(|| format!("failed to write {path:?}"))?;
+    say!("Formatted {}", path.display());
+    Ok(())
+}
+
+pub fn canonicalize(mut tx: RawTransaction, shorthands: Shorthands) -> Result<RawTransaction> {
+    for ix in &mut tx.instructions {
+        match shorthands {
+            Shorthands::Keep => {}
+            Shorthands::Expand => {
+                if let Some(expanded) = expand_shorthand(ix)? {
+                    *ix = expanded;
+                }
+            }
+            Shorthands::Collapse => {
+                if let Some(shorthand) = collapse(ix) {
+                    *ix = shorthand;
+                }
+            }
+        }
+    }
+    Ok(tx)
+}
+
+/// A template as `parse-tx` writes it: pretty-printed with sorted keys,
+/// without `lookup_tables` when there are none and without the empty `data`
+/// and `accounts` of shorthands.
+pub fn to_canonical_string(tx: &RawTransaction) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&to_canonical_value(tx)?)?)
+}
+
+pub fn to_canonical_value(tx: &RawTransaction) -> Result<Value> {
+    let mut value = serde_json::to_value(tx)?;
+    if let Some(map) = value.as_object_mut()
+        && map.get("lookup_tables") == Some(&Value::Null)
+    {
+        map.remove("lookup_tables");
+    }
+    let instructions = value["instructions"].as_array_mut().into_iter().flatten();
+    for (ix, raw) in instructions.zip(&tx.instructions) {
+        if let (Some(map), Ok(Some(_))) = (ix.as_object_mut(), expand_shorthand(raw)) {
+            map.remove("data");
+            map.remove("accounts");
+        }
+    }
+    Ok(value)
+}
+
+/// The shorthand expanding to exactly this instruction, if there is one.
+fn collapse(ix: &RawInstruction) -> Option<RawInstruction> {
+    let candidate = if ix.program_id == COMPUTE_BUDGET_PROGRAM_ID.to_string() {
+        let bytes = pack_data(&ix.data, &[]).ok()?;
+        match bytes.first()? {
+            3 => {
+                let micro_lamports = u64::from_le_bytes(bytes.get(1..9)?.try_into().ok()?);
+                shorthand("set_cu_price", json!({ "micro_lamports": micro_lamports }))
+            }
+            _ => {
+                let limit = u32::from_le_bytes(bytes.get(1..5)?.try_into().ok()?);
+                shorthand("set_cu_limit", json!({ "limit": limit }))
+            }
+        }
+    } else if ix.program_id == SYSTEM_PROGRAM_ID.to_string() {
+        let amount = match ix.data["data"][1]["data"].clone() {
+            Value::Null => {
+                let bytes = pack_data(&ix.data, &[]).ok()?;
+                json!(u64::from_le_bytes(bytes.get(4..12)?.try_into().ok()?))
+            }
+            amount => amount,
+        };
+        shorthand(
+            "transfer",
+            json!({
+                "from": ix.accounts.first()?.pubkey,
+                "to": ix.accounts.get(1)?.pubkey,
+                "amount": amount,
+            }),
+        )
+    } else if ix.program_id == ASSOCIATED_TOKEN_PROGRAM_ID.to_string() {
+        ata_shorthand("create_ata", &ix.accounts.get(1)?.pubkey)?
+    } else if ix.program_id == TOKEN_PROGRAM_ID.to_string() {
+        ata_shorthand("close_ata", &ix.accounts.first()?.pubkey)?
+    } else {
+        return None;
+    };
+    let expanded = expand_shorthand(&candidate).ok()??;
+    same_instruction(ix, &expanded).then_some(candidate)
+}
+
+fn shorthand(name: &str, fields: Value) -> RawInstruction {
+    RawInstruction {
+        program_id: name.into(),
+        data: Value::Null,
+        accounts: Vec::new(),
+        extra: fields.as_object().cloned().unwrap_or_default(),
+    }
+}
+
+fn ata_shorthand(name: &str, ata: &Value) -> Option<RawInstruction> {
+    (ata["type"] == "ata")
+        .then(|| shorthand(name, json!({ "owner": ata["owner"], "mint": ata["mint"] })))
+}
+
+/// Same program, accounts and data (compared as bytes when both pack).
+fn same_instruction(a: &RawInstruction, b: &RawInstruction) -> bool {
+    let accounts = |ix: &RawInstruction| serde_json::to_value(&ix.accounts).ok();
+    let same_data = a.data == b.data
+        || matches!(
+            (pack_data(&a.data, &[]), pack_data(&b.data, &[])),
+            (Ok(x), Ok(y)) if x == y
+        );
+    a.program_id == b.program_id && accounts(a) == accounts(b) && a.extra.is_empty() && same_data
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{Shorthands, canonicalize};
+    use crate::tx_format::RawTransaction;
+
+    #[test]
+    fn expands_and_collapses_shorthands() {
+        let owner = "Owner111111111111111111111111111111111111111";
+        let mint = "Mint1111111111111111111111111111111111111111";
+        let tx: RawTransaction = serde_json::from_value(json!({
+            "instructions": [
+                { "program_id": "set_cu_limit", "limit": 200000 },
+                { "program_id": "set_cu_price", "micro_lamports": 10000 },
+                { "program_id": "transfer", "from": owner, "to": mint, "amount": "$1" },
+                { "program_id": "create_ata", "owner": owner, "mint": mint },
+                { "program_id": "close_ata", "owner": owner, "mint": mint }
+            ],
+            "signers": []
+        }))
+        .unwrap();
+        let original = serde_json::to_value(&tx).unwrap();
+
+        let expanded = canonicalize(tx, Shorthands::Expand).unwrap();
+        assert!(
+            expanded
+                .instructions
+                .iter()
+                .all(|ix| ix.program_id.as_str().is_some_and(|id| id.len() > 30))
+        );
+        let collapsed = canonicalize(expanded, Shorthands::Collapse).unwrap();
+        assert_eq!(serde_json::to_value(&collapsed).unwrap(), original);
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    path::Path,
+    process::{Command, Output},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::network::NetworkCtx;
+use crate::tools::progress::{STEP_STARTED, emit};
+use crate::tools::tx::{ExecOptions, execute_json_transaction};
+use crate::tx_format::{RawHook, json_tx::load_parsed_tx_from_json, params::resolve_value};
+
+/// Runs the `stage` ("pre" or "post") hooks of a template in order. Shell
+/// commands run in `base_dir` and their output is printed with the stage as
+/// prefix; a failing hook aborts unless it sets `allow_failure`. Templates
+/// run by hooks do not run hooks of their own.
+pub fn run_hooks(
+    network: &NetworkCtx,
+    stage: &str,
+    hooks: &[RawHook],
+    base_dir: &Path,
+    params: &[String],
+) -> Result<()> {
+    for hook in hooks {
+        emit(
+            STEP_STARTED,
+            json!({ "step": format!("{stage} hook"), "run": hook.run, "template": hook.template }),
+        );
+        let result = match (&hook.run, &hook.template) {
+            (Some(command), None) => run_command(stage, command, base_dir),
+            (None, Some(template)) => {
+                let hook_params = hook_params(&hook.params, params);
+                say!("[{stage}] exec {template}");
+                load_parsed_tx_from_json(base_dir.join(template), &hook_params)
+                    .and_then(|parsed| {
+                        execute_json_transaction(network, parsed, &ExecOptions::default())
+                    })
+                    .map(|_| ())
+            }
+            _ => Err(anyhow!(
+                "A {stage} hook needs exactly one of `run` or `template`"
+            )),
+        };
+        match result {
+            Err(error) if hook.allow_failure => eprintln!("[{stage}] hook failed: {error:#}"),
+            result => result.with_context(|| format!("{stage} hook failed"))?,
+        }
+    }
+    Ok(())
+}
+
+/// Params of a template hook; `$N` and `$local:` values are resolved
+/// against the params of the declaring template.
+fn hook_params(values: &[Value], params: &[String]) -> Vec<String> {
+    values
+        .iter()
+        .map(|value| match resolve_value(value, params) {
+            Value::String(text) => text,
+            other => other.to_string(),
+        })
+        .collect()
+}
+
+fn run_command(stage: &str, command: &str, base_dir: &Path) -> Result<()> {
+    say!("[{stage}] $ {command}");
+    let output = shell(command)
+        .current_dir(base_dir)
+        .output()
+        .with_context(|| format!("failed to run `{command}`"))?;
+    print_output(stage, &output);
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("`{command}` exited with status {}", output.status))
+    }
+}
+
+fn shell(command: &str) -> Command {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    shell.arg(command);
+    shell
+}
+
+fn print_output(stage: &str, output: &Output) {
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        say!("[{stage}] {line}");
+    }
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        eprintln!("[{stage}] {line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use super::{hook_params, run_hooks};
+    use crate::network::NetworkCtx;
+    use crate::tx_format::RawHooks;
+
+    #[test]
+    fn failing_commands_propagate_unless_allowed() {
+        let hooks: RawHooks = serde_json::from_value(json!({
+            "pre": [{ "run": "exit 3", "allow_failure": true }, { "run": "echo ok" }],
+            "post": [{ "run": "exit 3" }]
+        }))
+        .unwrap();
+        let network = NetworkCtx::default();
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        assert!(run_hooks(&network, "pre", &hooks.pre, dir, &[]).is_ok());
+        assert!(run_hooks(&network, "post", &hooks.post, dir, &[]).is_err());
+    }
+
+    #[test]
+    fn hook_params_resolve_template_params() {
+        let params = ["abc".to_string()];
+        assert_eq!(hook_params(&[json!("$1"), json!(5)], &params), ["abc", "5"]);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{self, Write},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use serde::Serialize;
+use serde_json::{Value, json};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+static QUIET: AtomicBool = AtomicBool::new(false);
+static FIELDS: Mutex<BTreeMap<String, Value>> = Mutex::new(BTreeMap::new());
+
+/// What a command prints on stdout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object with the command's results; text goes to stderr
+    Json,
+}
+
+pub fn set_output_format(format: OutputFormat) {
+    JSON.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn json_output() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Sends human-readable output to stderr from now on, as an output file is
+/// written to stdout (`-`).
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+/// Drops human-readable output from now on, for hosts embedding the engine
+/// (see [`crate::embed`]) whose stdout is not ours.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Prints a line of human-readable output, see [`say!`](crate::say).
+pub fn say(args: fmt::Arguments) {
+    if QUIET.load(Ordering::Relaxed) {
+        return;
+    }
+    if json_output() || STDOUT_RESERVED.load(Ordering::Relaxed) {
+        let _ = writeln!(io::stderr().lock(), "{args}");
+    } else {
+        let _ = writeln!(io::stdout().lock(), "{args}");
+    }
+}
+
+fn fields() -> std::sync::MutexGuard<'static, BTreeMap<String, Value>> {
+    FIELDS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Sets a field of the JSON result.
+pub fn record(key: &str, value: impl Serialize) {
+    if !json_output() {
+        return;
+    }
+    let value = serde_json::to_value(value).unwrap_or(Value::Null);
+    fields().insert(key.to_string(), value);
+}
+
+/// Appends to a list field of the JSON result, e.g. `transactions` or `files`.
+pub fn push(key: &str, value: impl Serialize) {
+    if !json_output() {
+        return;
+    }
+    let value = serde_json::to_value(value).unwrap_or(Value::Null);
+    match fields().entry(key.to_string()).or_insert_with(|| json!([])) {
+        Value::Array(items) => items.push(value),
+        other => *other = json!([other.take(), value]),
+    }
+}
+
+/// The JSON result: `{"command": ..., "ok": ..., "error"?: ..., <fields>}`.
+pub fn result_document(
+    command: Option<&str>,
+    error: Option<String>,
+    fields: BTreeMap<String, Value>,
+) -> Value {
+    let mut document: serde_json::Map<String, Value> = fields.into_iter().collect();
+    document.insert("command".to_string(), json!(command));
+    document.insert("ok".to_string(), json!(error.is_none()));
+    if let Some(error) = error {
+        document.insert("error".to_string(), json!(error));
+    }
+    Value::Object(document)
+}
+
+/// Prints the JSON result of the command on stdout with `--output json`.
+pub fn finish(command: Option<&str>, result: &anyhow::Result<()>) {
+    if !json_output() {
+        return;
+    }
+    let error = result.as_ref().err().map(|error| format!("{error:#}"));
+    let document = result_document(command, error, std::mem::take(&mut *fields()));
+    let _ = writeln!(io::stdout().lock(), "{document:#}");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+
+    use super::result_document;
+
+    #[test]
+    fn results_carry_command_status_and_fields() {
+        let fields = BTreeMap::from([("lamports".to_string(), json!(5))]);
+        assert_eq!(
+            result_document(Some("balance"), None, fields),
+            json!({ "command": "balance", "ok": true, "lamports": 5 })
+        );
+        assert_eq!(
+            result_document(Some("exec-tx"), Some("failed".to_string()), BTreeMap::new()),
+            json!({ "command": "exec-tx", "ok": false, "error": "failed" })
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use regex::Regex;
+use solana_sdk::hash::hashv;
+
+pub const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// What a log assertion looks for.
+#[derive(Clone, Debug)]
+pub enum LogPattern {
+    Contains(String),
+    Regex(Regex),
+    /// Anchor event emitted as `Program data:` with the event discriminator
+    Event(String),
+}
+
+/// Expectation on the logs of an executed transaction, written as
+/// `[!]<contains|regex|event>:<pattern>` (a plain pattern means `contains`).
+/// A leading `!` forbids the pattern instead of requiring it.
+#[derive(Clone, Debug)]
+pub struct LogAssertion {
+    pub forbidden: bool,
+    pub pattern: LogPattern,
+}
+
+impl FromStr for LogAssertion {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let (forbidden, value) = match value.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let pattern = match value.split_once(':') {
+            Some(("contains", text)) => LogPattern::Contains(text.to_string()),
+            Some(("regex", pattern)) => LogPattern::Regex(
+                Regex::new(pattern).map_err(|err| anyhow!("Invalid log regex {pattern}: {err}"))?,
+            ),
+            Some(("event", name)) => LogPattern::Event(name.to_string()),
+            _ => LogPattern::Contains(value.to_string()),
+        };
+        Ok(Self { forbidden, pattern })
+    }
+}
+
+impl LogAssertion {
+    fn matches(&self, logs: &[String]) -> bool {
+        match &self.pattern {
+            LogPattern::Contains(text) => logs.iter().any(|line| line.contains(text.as_str())),
+            LogPattern::Regex(regex) => logs.iter().any(|line| regex.is_match(line)),
+            LogPattern::Event(name) => {
+                let discriminator = event_discriminator(name);
+                logs.iter()
+                    .filter_map(|line| line.strip_prefix(PROGRAM_DATA_PREFIX))
+                    .filter_map(|data| STANDARD.decode(data).ok())
+                    .any(|data| data.starts_with(&discriminator))
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        let pattern = match &self.pattern {
+            LogPattern::Contains(text) => format!("\"{text}\""),
+            LogPattern::Regex(regex) => format!("/{regex}/"),
+            LogPattern::Event(name) => format!("event {name}"),
+        };
+        if self.forbidden {
+            format!("forbidden {pattern} was logged")
+        } else {
+            format!("expected {pattern} was not logged")
+        }
+    }
+}
+
+/// Anchor event discriminator: the first 8 bytes of `sha256("event:<Name>")`.
+pub fn event_discriminator(name: &str) -> [u8; 8] {
+    let hash = hashv(&[b"event:", name.as_bytes()]);
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash.to_bytes()[..8]);
+    discriminator
+}
+
+/// Checks every assertion against the logs, listing all unmet ones in the error.
+pub fn check_log_assertions(assertions: &[LogAssertion], logs: &[String]) -> Result<()> {
+    let failures: Vec<String> = assertions
+        .iter()
+        .filter(|assertion| assertion.matches(logs) == assertion.forbidden)
+        .map(LogAssertion::describe)
+        .collect();
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Log assertions failed:\n  {}",
+        failures.join("\n  ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+    use super::{LogAssertion, check_log_assertions, event_discriminator};
+
+    fn assertions(values: &[&str]) -> Vec<LogAssertion> {
+        values.iter().map(|value| value.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn checks_required_and_forbidden_patterns() {
+        let mut event = event_discriminator("SwapEvent").to_vec();
+        event.extend([1, 2, 3]);
+        let logs = vec![
+            "Program log: Instruction: Swap".to_string(),
+            format!("Program data: {}", STANDARD.encode(event)),
+        ];
+        let passing = assertions(&[
+            "Instruction: Swap",
+            "regex:Instruction: (Swap|Route)",
+            "event:SwapEvent",
+            "!contains:slippage exceeded",
+            "!event:DepositEvent",
+        ]);
+        assert!(check_log_assertions(&passing, &logs).is_ok());
+
+        let failing = assertions(&["event:DepositEvent", "!regex:Swap$"]);
+        let error = check_log_assertions(&failing, &logs)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("expected event DepositEvent was not logged"));
+        assert!(error.contains("forbidden /Swap$/ was logged"));
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::accounts::COMPUTE_BUDGET_PROGRAM_ID;
+use crate::tools::fmt::to_canonical_string;
+use crate::tx_format::{
+    RawHooks, RawInstruction, RawTransaction,
+    data_format::pack_data,
+    json_tx::{expand_shorthand, load_raw_tx_from_json},
+    params::param_index,
+};
+
+/// Most compute units a transaction can request.
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+/// Largest serialized transaction (the packet data size).
+pub const MAX_TX_BYTES: usize = 1232;
+/// Most accounts a transaction can lock.
+pub const MAX_TX_ACCOUNTS: usize = 64;
+
+const SET_CU_LIMIT: u8 = 2;
+const SET_CU_PRICE: u8 = 3;
+
+/// Concatenates the instructions of several templates into one. Params of
+/// each input are shifted past those of the previous inputs (unless
+/// `share_params`, where `$1` means the same value in every input), equal
+/// signers and lookup tables are kept once and compute budget instructions
+/// are merged: limits are summed, the highest price wins.
+pub fn merge_templates(
+    out: impl AsRef<Path>,
+    inputs: &[PathBuf],
+    share_params: bool,
+) -> Result<()> {
+    let out = out.as_ref();
+    let mut templates = Vec::with_capacity(inputs.len());
+    let mut offset = 0;
+    for path in inputs {
+        let tx = load_raw_tx_from_json(path)?;
+        let count = param_count(&tx)?;
+        let tx = if share_params || offset == 0 {
+            tx
+        } else {
+            say!(
+                "{}: params $1..${count} -> ${}..${}",
+                path.display(),
+                offset + 1,
+                offset + count
+            );
+            shift_params(&tx, offset)?
+        };
+        if !share_params {
+            offset += count;
+        }
+        templates.push(tx);
+    }
+
+    let merged = merge_transactions(templates)?;
+    let size = estimate_size(&merged);
+    size.warn();
+    fs::write(out, to_canonical_string(&merged)?)
+        .with_context(|| format!("failed to write {out:?}"))?;
+    say!(
+        "Merged {} templates into {} ({} instructions, {} accounts, ~{} bytes)",
+        inputs.len(),
+        out.display(),
+        merged.instructions.len(),
+        size.accounts,
+        size.bytes
+    );
+    Ok(())
+}
+
+pub fn merge_transactions(templates: Vec<RawTransaction>) -> Result<RawTransaction> {
+    let mut budget = ComputeBudget::default();
+    let mut merged = RawTransaction {
+        instructions: Vec::new(),
+        signers: Vec::new(),
+        lookup_tables: None,
+        hooks: None,
+        version: None,
+        stamp: None,
+    };
+    for tx in templates {
+        for ix in tx.instructions {
+            if !budget.absorb(&ix)? {
+                merged.instructions.push(ix);
+            }
+        }
+        push_unique(&mut merged.signers, tx.signers);
+        if let Some(tables) = tx.lookup_tables {
+            push_unique(merged.lookup_tables.get_or_insert_default(), tables);
+        }
+        if let Some(hooks) = tx.hooks {
+            let merged_hooks = merged.hooks.get_or_insert_with(RawHooks::default);
+            merged_hooks.pre.extend(hooks.pre);
+            merged_hooks.post.extend(hooks.post);
+        }
+    }
+    let mut instructions = budget.into_instructions();
+    instructions.append(&mut merged.instructions);
+    merged.instructions = instructions;
+    Ok(merged)
+}
+
+fn push_unique(into: &mut Vec<Value>, values: Vec<Value>) {
+    for value in values {
+        if !into.contains(&value) {
+            into.push(value);
+        }
+    }
+}
+
+/// Compute budget instructions of the inputs, folded into one per kind.
+#[derive(Default)]
+struct ComputeBudget {
+    limit: Option<u64>,
+    price: Option<(u64, RawInstruction)>,
+    /// Other compute budget instructions (heap frame, loaded data size), first of each kind
+    others: Vec<(u8, RawInstruction)>,
+}
+
+impl ComputeBudget {
+    /// Takes the instruction if it is a compute budget instruction.
+    fn absorb(&mut self, ix: &RawInstruction) -> Result<bool> {
+        let explicit = expand_shorthand(ix)?.unwrap_or_else(|| ix.clone());
+        if explicit.program_id != COMPUTE_BUDGET_PROGRAM_ID.to_string() {
+            return Ok(false);
+        }
+        let Ok(bytes) = pack_data(&explicit.data, &[]) else {
+            eprintln!("Warning: keeping a compute budget instruction with params as is");
+            return Ok(false);
+        };
+        let value = |len: usize| -> Result<u64> {
+            let field = bytes
+                .get(1..1 + len)
+                .ok_or_else(|| anyhow!("Truncated compute budget instruction"))?;
+            let mut le = [0u8; 8];
+            le[..len].copy_from_slice(field);
+            Ok(u64::from_le_bytes(le))
+        };
+        match bytes.first().copied() {
+            Some(SET_CU_LIMIT) => {
+                self.limit = Some(self.limit.unwrap_or(0) + value(4)?);
+            }
+            Some(SET_CU_PRICE) => {
+                let price = value(8)?;
+                if self.price.as_ref().is_none_or(|(max, _)| price > *max) {
+                    self.price = Some((price, ix.clone()));
+                }
+            }
+            Some(kind) => {
+                if !self.others.iter().any(|(other, _)| *other == kind) {
+                    self.others.push((kind, ix.clone()));
+                }
+            }
+            None => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn into_instructions(self) -> Vec<RawInstruction> {
+        let mut out = Vec::new();
+        if let Some(limit) = self.limit {
+            if limit > MAX_COMPUTE_UNIT_LIMIT {
+                eprintln!(
+                    "Warning: summed CU limit {limit} exceeds {MAX_COMPUTE_UNIT_LIMIT}; capping"
+                );
+            }
+            let limit = limit.min(MAX_COMPUTE_UNIT_LIMIT);
+            out.push(RawInstruction {
+                program_id: "set_cu_limit".into(),
+                data: Value::Null,
+                accounts: Vec::new(),
+                extra: json!({ "limit": limit })
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default(),
+            });
+        }
+        out.extend(self.price.map(|(_, ix)| ix));
+        out.extend(self.others.into_iter().map(|(_, ix)| ix));
+        out
+    }
+}
+
+/// Highest `$N` referenced by a template.
+fn param_count(tx: &RawTransaction) -> Result<usize> {
+    fn max_param(value: &Value) -> usize {
+        match value {
+            Value::String(text) => param_index(text).map_or(0, |index| index + 1),
+            Value::Array(items) => items.iter().map(max_param).max().unwrap_or(0),
+            Value::Object(map) => map.values().map(max_param).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+    Ok(max_param(&serde_json::to_value(tx)?))
+}
+
+/// Rewrites every `$N` of a template as `$(N + offset)`.
+fn shift_params(tx: &RawTransaction, offset: usize) -> Result<RawTransaction> {
+    fn shift(value: &mut Value, offset: usize) {
+        match value {
+            Value::String(text) => {
+                if let Some(index) = param_index(text) {
+                    *text = format!("${}", index + 1 + offset);
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| shift(item, offset)),
+            Value::Object(map) => map.values_mut().for_each(|item| shift(item, offset)),
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(tx)?;
+    shift(&mut value, offset);
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Estimated size of a template compiled as a legacy transaction.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TxSize {
+    pub accounts: usize,
+    pub bytes: usize,
+    /// Some data could not be packed without params and was not counted
+    pub approximate: bool,
+}
+
+impl TxSize {
+    pub fn fits(&self) -> bool {
+        self.accounts <= MAX_TX_ACCOUNTS && self.bytes <= MAX_TX_BYTES
+    }
+
+    pub fn warn(&self) {
+        if self.accounts > MAX_TX_ACCOUNTS {
+            eprintln!(
+                "Warning: {} accounts exceed the limit of {MAX_TX_ACCOUNTS}",
+                self.accounts
+            );
+        }
+        if self.bytes > MAX_TX_BYTES {
+            eprintln!(
+                "Warning: ~{} bytes exceed the transaction size limit of {MAX_TX_BYTES} \
+                 (lookup tables may bring it down)",
+                self.bytes
+            );
+        }
+        if self.approximate {
+            eprintln!("Warning: data with params was not counted in the size estimate");
+        }
+    }
+}
+
+/// Counts unique accounts (params and ATAs by their template value) and the
+/// bytes of a legacy message with one signature per signer.
+pub fn estimate_size(tx: &RawTransaction) -> TxSize {
+    let mut keys = HashSet::new();
+    let mut signers = HashSet::new();
+    let mut instruction_bytes = 0;
+    let mut approximate = false;
+    for ix in &tx.instructions {
+        let ix = expand_shorthand(ix)
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| ix.clone());
+        let key = |value: &Value| {
+            value
+                .as_str()
+                .map_or_else(|| value.to_string(), str::to_string)
+        };
+        keys.insert(key(&ix.program_id));
+        for account in &ix.accounts {
+            let key = key(&account.pubkey);
+            if account.is_signer {
+                signers.insert(key.clone());
+            }
+            keys.insert(key);
+        }
+        let data_len = match pack_data(&ix.data, &[]) {
+            Ok(bytes) => bytes.len(),
+            Err(_) => {
+                approximate = true;
+                0
+            }
+        };
+        instruction_bytes += 1
+            + short_vec_len(ix.accounts.len())
+            + ix.accounts.len()
+            + short_vec_len(data_len)
+            + data_len;
+    }
+    let signatures = signers.len().max(1);
+    let bytes = short_vec_len(signatures)
+        + 64 * signatures
+        + 3
+        + short_vec_len(keys.len())
+        + 32 * keys.len()
+        + 32
+        + short_vec_len(tx.instructions.len())
+        + instruction_bytes;
+    TxSize {
+        accounts: keys.len(),
+        bytes,
+        approximate,
+    }
+}
+
+fn short_vec_len(len: usize) -> usize {
+    match len {
+        0..0x80 => 1,
+        0x80..0x4000 => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{merge_transactions, param_count, shift_params};
+    use crate::tx_format::RawTransaction;
+
+    #[test]
+    fn merges_budgets_signers_and_params() {
+        let template = |limit: u64| -> RawTransaction {
+            serde_json::from_value(json!({
+                "instructions": [
+                    { "program_id": "set_cu_limit", "limit": limit },
+                    { "program_id": "transfer", "from": "$1", "to": "$2", "amount": 5 }
+                ],
+                "signers": ["$3"]
+            }))
+            .unwrap()
+        };
+        let first = template(200_000);
+        let second = shift_params(&template(300_000), param_count(&first).unwrap()).unwrap();
+        let merged = merge_transactions(vec![first, second, template(1)]).unwrap();
+
+        let merged = serde_json::to_value(&merged).unwrap();
+        assert_eq!(merged["signers"], json!(["$3", "$6"]));
+        let instructions = merged["instructions"].as_array().unwrap();
+        assert_eq!(instructions.len(), 4);
+        assert_eq!(instructions[0]["limit"], 500_001);
+        assert_eq!(instructions[2]["from"], "$4");
+        assert_eq!(instructions[2]["to"], "$5");
+    }
+}
@@ -0,0 +1,33 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::Value;
+
+use crate::tx_format::migrate::migrate_template;
+
+/// Upgrades a template or batch file to the current template version in
+/// place. With `check`, the file is left untouched and an error reports
+/// whether it needs migrating.
+pub fn migrate_template_file(path: impl AsRef<Path>, check: bool) -> Result<()> {
+    let path = path.as_ref();
+    let original =
+        fs::read_to_string(path).with_context(|| format!("Error reading file {path:?}"))?;
+    let mut value: Value =
+        serde_json::from_str(&original).with_context(|| format!("Invalid JSON in {path:?}"))?;
+    let before = value.clone();
+    let applied = migrate_template(&mut value).with_context(|| format!("In {path:?}"))?;
+    if value == before {
+        return Ok(());
+    }
+    if check {
+        return Err(anyhow!("{} needs migrating", path.display()));
+    }
+    fs::write(path, serde_json::to_string_pretty(&value)?)
+        .with_context(|| format!("failed to write {path:?}"))?;
+    if applied.is_empty() {
+        say!("Migrated {}", path.display());
+    } else {
+        say!("Migrated {} ({})", path.display(), applied.join("; "));
+    }
+    Ok(())
+}
@@ -1,4 +1,40 @@
+pub mod alt;
+pub mod batch;
+pub mod bench;
+pub mod bundle;
+pub mod close_checks;
+pub mod compute;
 pub mod data_format;
+pub mod diff;
 pub mod dump;
+pub mod errors;
+pub mod events;
+pub mod fmt;
+pub mod hooks;
+pub mod json_output;
+pub mod log_assert;
+pub mod merge;
+pub mod migrate;
+pub mod monitor;
+pub mod output;
 pub mod parse;
+pub mod pool;
+pub mod progress;
+pub mod prompt;
+pub mod replay;
+pub mod report;
+pub mod send_many;
+pub mod show;
+pub mod sign;
+pub mod simulate;
+pub mod slots;
+pub mod split;
+pub mod stake;
+pub mod stats;
+pub mod style;
+pub mod sysvars;
+pub mod token;
+pub mod transfer_checks;
 pub mod tx;
+pub mod verify;
+pub mod wallet;
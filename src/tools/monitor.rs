@@ -0,0 +1,186 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use solana_rpc_client::{
+    api::response::RpcConfirmedTransactionStatusWithSignature,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::network::NetworkCtx;
+use crate::tools::dump::dump_accounts_from_tx;
+use crate::tools::output::OutputOptions;
+use crate::tools::parse::{ParseSource, create_json_from_tx};
+
+const SIGNATURES_LIMIT: usize = 100;
+const ACCOUNTS_DIR: &str = "accounts";
+
+/// Signatures of `address` newer than `until`, newest first, paging back with
+/// `before` so a burst of more than one page between polls is not cut off.
+fn new_signatures(
+    network: &NetworkCtx,
+    address: &Pubkey,
+    until: Option<Signature>,
+) -> Result<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    let client = network.source_client();
+    let mut statuses = Vec::new();
+    let mut before = None;
+    loop {
+        let page = client.get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until,
+                limit: Some(SIGNATURES_LIMIT),
+                commitment: Some(network.history_commitment()),
+            },
+        )?;
+        network.pause();
+        let full = page.len() == SIGNATURES_LIMIT;
+        before = page.last().and_then(|status| status.signature.parse().ok());
+        statuses.extend(page);
+        if !full || before.is_none() {
+            return Ok(statuses);
+        }
+    }
+}
+
+/// Polls the source cluster for new transactions touching `addresses` and
+/// writes each one as `<to_path>/<YYYY-MM-DD>/<signature>/` with the parsed
+/// template and an `accounts/` dump, ready for local replays. Transactions
+/// from before the monitor started are skipped. Runs until `max_txs`
+/// transactions were captured, or forever.
+pub fn monitor(
+    network: &NetworkCtx,
+    addresses: &[Pubkey],
+    to_path: impl AsRef<Path>,
+    interval: Duration,
+    max_txs: Option<usize>,
+) -> Result<()> {
+    let to_path = to_path.as_ref();
+    let client = network.source_client();
+    let mut last_seen: HashMap<Pubkey, Option<Signature>> = HashMap::new();
+    for address in addresses {
+        let newest = client
+            .get_signatures_for_address_with_config(
+                address,
+                GetConfirmedSignaturesForAddress2Config {
+                    limit: Some(1),
+                    commitment: Some(network.history_commitment()),
+                    ..Default::default()
+                },
+            )?
+            .first()
+            .and_then(|status| status.signature.parse().ok());
+        last_seen.insert(*address, newest);
+        network.pause();
+    }
+    say!("Watching {} accounts...", addresses.len());
+
+    // A transaction touching several addresses is returned for each of them,
+    // at the latest in the round after it was captured, so only signatures of
+    // the current and the previous round are kept to skip duplicates.
+    let mut captured = HashSet::new();
+    let mut captured_before = HashSet::new();
+    let mut captured_count = 0;
+    loop {
+        let mut all_polled = true;
+        for address in addresses {
+            let until = last_seen.get(address).copied().flatten();
+            let statuses = match new_signatures(network, address, until) {
+                Ok(statuses) => statuses,
+                Err(error) => {
+                    eprintln!("Failed to poll {address}: {error}");
+                    all_polled = false;
+                    continue;
+                }
+            };
+            if let Some(newest) = statuses.first() {
+                last_seen.insert(*address, newest.signature.parse().ok());
+            }
+            // Oldest first, so the corpus follows the chain order.
+            for status in statuses.iter().rev() {
+                if captured_before.contains(&status.signature)
+                    || !captured.insert(status.signature.clone())
+                {
+                    continue;
+                }
+                captured_count += 1;
+                let block_time = status.block_time.unwrap_or_else(|| {
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |now| now.as_secs() as i64)
+                });
+                let dir = to_path.join(utc_date(block_time)).join(&status.signature);
+                if let Err(error) = capture(network, &status.signature, &dir) {
+                    eprintln!("Failed to capture {}: {error:#}", status.signature);
+                }
+                if max_txs.is_some_and(|max| captured_count >= max) {
+                    return Ok(());
+                }
+            }
+        }
+        // After a failed poll the address may still return older signatures.
+        if all_polled {
+            captured_before = std::mem::take(&mut captured);
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn capture(network: &NetworkCtx, signature: &str, dir: &Path) -> Result<()> {
+    say!("New transaction {signature}");
+    fs::create_dir_all(dir)?;
+    create_json_from_tx(
+        network,
+        ParseSource::Rpc(signature),
+        dir,
+        OutputOptions::default(),
+        None,
+    )?;
+    dump_accounts_from_tx(
+        network,
+        signature,
+        dir.join(ACCOUNTS_DIR),
+        OutputOptions::default(),
+        true,
+    )?
+    .print();
+    Ok(())
+}
+
+/// `YYYY-MM-DD` of a unix timestamp in UTC.
+fn utc_date(timestamp: i64) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar).
+    let days = timestamp.div_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::utc_date;
+
+    #[test]
+    fn formats_utc_dates() {
+        assert_eq!(utc_date(0), "1970-01-01");
+        assert_eq!(utc_date(951_782_400), "2000-02-29");
+        assert_eq!(utc_date(1_735_689_599), "2024-12-31");
+        assert_eq!(utc_date(1_760_486_400), "2025-10-15");
+    }
+}
@@ -0,0 +1,420 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow};
+use flate2::{Compression as GzLevel, read::MultiGzDecoder, write::GzEncoder};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{Value, json};
+
+use crate::tools::json_output::{push, reserve_stdout};
+use crate::tools::progress::{FILE_WRITTEN, emit};
+use crate::tx_format::{camel_key, snake_key};
+use crate::utils::{STDIO_PATH, is_stdio, stdin_bytes};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{(\w+)\}").expect("valid placeholder regex"));
+
+/// Writes one compact JSON value per line (JSON Lines), so outputs can be
+/// grepped or piped into `jq` without loading a whole array.
+pub struct JsonLinesWriter<W: Write> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, count: 0 }
+    }
+
+    pub fn write<T: Serialize>(&mut self, value: &T) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Flushes the output and returns the number of lines written.
+    pub fn finish(mut self) -> Result<usize> {
+        self.writer.flush()?;
+        Ok(self.count)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// An output file or stdout, compressed as configured. Compressed streams are
+/// only complete after [`OutputWriter::finish`], which reports write errors
+/// that dropping the writer would lose.
+pub enum OutputWriter {
+    Plain(Box<dyn Write>),
+    Gzip(GzEncoder<Box<dyn Write>>),
+    Zstd(zstd::Encoder<'static, Box<dyn Write>>),
+}
+
+impl OutputWriter {
+    /// Writes the compression trailer and flushes the underlying output.
+    pub fn finish(self) -> Result<()> {
+        let mut inner = match self {
+            OutputWriter::Plain(inner) => inner,
+            OutputWriter::Gzip(encoder) => encoder.finish()?,
+            OutputWriter::Zstd(encoder) => encoder.finish()?,
+        };
+        inner.flush()?;
+        Ok(())
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputWriter::Plain(inner) => inner.write(buf),
+            OutputWriter::Gzip(encoder) => encoder.write(buf),
+            OutputWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputWriter::Plain(inner) => inner.flush(),
+            OutputWriter::Gzip(encoder) => encoder.flush(),
+            OutputWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Codec applied to written output files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Directory layout of dumped accounts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// All files in the output directory
+    #[default]
+    Flat,
+    /// `programs/`, `accounts/`, `mints/` and `lookup_tables/` plus a `manifest.json`
+    Typed,
+}
+
+/// What to do when an output file already exists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Replace the existing file
+    #[default]
+    Force,
+    /// Keep the existing file and skip writing
+    Skip,
+}
+
+/// Casing of the keys of parse output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum KeyStyle {
+    /// `program_id`, `is_signer`, as templates are written
+    Snake,
+    /// `programId`, `isSigner`, as the RPC names them
+    Camel,
+}
+
+/// Renames the keys of [`crate::tx_format::KEY_CASES`] in `value` to `style`;
+/// instruction `data` is left as it was decoded.
+pub fn restyle_keys(value: &mut Value, style: KeyStyle) {
+    match value {
+        Value::Object(map) => {
+            for (key, mut value) in std::mem::take(map) {
+                if key != "data" {
+                    restyle_keys(&mut value, style);
+                }
+                let key = match style {
+                    KeyStyle::Snake => snake_key(&key),
+                    KeyStyle::Camel => camel_key(&key),
+                };
+                map.insert(key.to_string(), value);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| restyle_keys(item, style)),
+        _ => {}
+    }
+}
+
+/// How dump/parse commands write their JSON output.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OutputOptions<'a> {
+    /// One account/transaction per line instead of a pretty-printed document
+    pub jsonl: bool,
+    pub compression: Compression,
+    pub layout: Layout,
+    pub overwrite: Overwrite,
+    /// File name with `{pubkey}`, `{signature}`, `{slot}` or `{timestamp}` placeholders
+    pub name_template: Option<&'a str>,
+    /// Key casing of parse output; each output keeps its own without one
+    pub style: Option<KeyStyle>,
+}
+
+impl OutputOptions<'_> {
+    /// Applies [`OutputOptions::style`] to a parse output.
+    pub fn restyle(&self, value: &mut Value) {
+        if let Some(style) = self.style {
+            restyle_keys(value, style);
+        }
+    }
+
+    /// File stem for an output: `default` unless a name template is set, in
+    /// which case its placeholders are filled from `vars` and the current
+    /// `{timestamp}`. A `.json`/`.jsonl` suffix in the template is dropped so
+    /// the format and codec extensions are applied consistently.
+    pub fn stem(&self, default: &str, vars: &[(&str, String)]) -> Result<String> {
+        let Some(template) = self.name_template else {
+            return Ok(default.to_string());
+        };
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default()
+            .to_string();
+
+        let mut missing = None;
+        let rendered = PLACEHOLDER.replace_all(template, |caps: &regex::Captures<'_>| {
+            let key = &caps[1];
+            if key == "timestamp" {
+                return timestamp.clone();
+            }
+            match vars.iter().find(|(name, _)| *name == key) {
+                Some((_, value)) => value.clone(),
+                None => {
+                    missing.get_or_insert_with(|| key.to_string());
+                    String::new()
+                }
+            }
+        });
+        if let Some(key) = missing {
+            return Err(anyhow!(
+                "Placeholder {{{key}}} is not available for this output"
+            ));
+        }
+
+        let rendered = rendered.as_ref();
+        let stem = rendered
+            .strip_suffix(".jsonl")
+            .or_else(|| rendered.strip_suffix(".json"))
+            .unwrap_or(rendered);
+        Ok(stem.to_string())
+    }
+
+    /// Applies the overwrite policy to `path`: `Ok(false)` means the
+    /// existing file is kept and nothing should be written.
+    pub fn should_write(&self, path: &Path) -> Result<bool> {
+        if !path.exists() {
+            return Ok(true);
+        }
+        match self.overwrite {
+            Overwrite::Force => Ok(true),
+            Overwrite::Skip => {
+                say!("Skipping existing {}", path.display());
+                Ok(false)
+            }
+        }
+    }
+
+    /// Flat account dumps are loaded by file name (`<address>.json`), so a
+    /// name template needs the typed layout, whose manifest maps the files
+    /// back to their addresses.
+    pub fn check_account_names(&self) -> Result<()> {
+        if self.layout == Layout::Flat && self.name_template.is_some() {
+            return Err(anyhow!(
+                "--name-template on account dumps needs --layout typed, flat files are loaded by address"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Output file name for `stem` with the extension matching the format and codec.
+    pub fn file_name(&self, stem: &str) -> String {
+        let extension = if self.jsonl { "jsonl" } else { "json" };
+        match self.compression {
+            Compression::None => format!("{stem}.{extension}"),
+            Compression::Gzip => format!("{stem}.{extension}.gz"),
+            Compression::Zstd => format!("{stem}.{extension}.zst"),
+        }
+    }
+
+    /// Path of the output `stem` in `dir`, which is created if needed, or `-`
+    /// when `dir` is `-` and the output goes to stdout. `None` when an
+    /// existing file is kept.
+    pub fn path_in(&self, dir: &Path, stem: &str) -> Result<Option<PathBuf>> {
+        if is_stdio(dir) {
+            return Ok(Some(PathBuf::from(STDIO_PATH)));
+        }
+        fs::create_dir_all(dir)?;
+        let path = dir.join(self.file_name(stem));
+        Ok(self.should_write(&path)?.then_some(path))
+    }
+
+    /// Creates `path` (stdout for `-`), compressing everything written to it.
+    /// Call [`OutputWriter::finish`] once everything is written.
+    pub fn create(&self, path: &Path) -> Result<OutputWriter> {
+        let file: Box<dyn Write> = if is_stdio(path) {
+            reserve_stdout();
+            Box::new(io::stdout())
+        } else {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            emit(FILE_WRITTEN, json!({ "path": path }));
+            push("files", path);
+            Box::new(BufWriter::new(file))
+        };
+        Ok(match self.compression {
+            Compression::None => OutputWriter::Plain(file),
+            Compression::Gzip => OutputWriter::Gzip(GzEncoder::new(file, GzLevel::default())),
+            Compression::Zstd => OutputWriter::Zstd(zstd::Encoder::new(file, 0)?),
+        })
+    }
+
+    /// Writes `value` as pretty-printed JSON to `path` (stdout for `-`).
+    pub fn write_json<T: Serialize>(&self, path: &Path, value: &T) -> Result<()> {
+        let mut writer = self.create(path)?;
+        serde_json::to_writer_pretty(&mut writer, value)?;
+        writer.finish()
+    }
+}
+
+/// Opens a file (stdin for `-`) for reading, transparently decompressing
+/// gzip or zstd content (detected by its magic bytes).
+pub fn open_input(path: &Path) -> Result<Box<dyn Read>> {
+    let mut file: Box<dyn Read> = if is_stdio(path) {
+        Box::new(stdin_bytes()?)
+    } else {
+        Box::new(BufReader::new(
+            File::open(path).with_context(|| format!("failed to read {}", path.display()))?,
+        ))
+    };
+    let mut magic = [0u8; 4];
+    let read = file.read(&mut magic)?;
+    let head = std::io::Cursor::new(magic[..read].to_vec());
+    let reader = head.chain(file);
+    Ok(if magic[..read].starts_with(&GZIP_MAGIC) {
+        Box::new(MultiGzDecoder::new(reader))
+    } else if magic[..read] == ZSTD_MAGIC {
+        Box::new(zstd::Decoder::new(reader)?)
+    } else {
+        Box::new(reader)
+    })
+}
+
+/// Reads a whole (possibly compressed) file as UTF-8 text.
+pub fn read_to_string(path: &Path) -> Result<String> {
+    let mut data = String::new();
+    open_input(path)?
+        .read_to_string(&mut data)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use serde_json::json;
+
+    use super::{
+        Compression, JsonLinesWriter, KeyStyle, Layout, OutputOptions, Overwrite, read_to_string,
+        restyle_keys,
+    };
+
+    #[test]
+    fn writes_one_value_per_line() {
+        let mut out = Vec::new();
+        let mut writer = JsonLinesWriter::new(&mut out);
+        writer.write(&json!({"a": [1, 2]})).unwrap();
+        writer.write(&json!("b")).unwrap();
+        assert_eq!(writer.finish().unwrap(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"a\":[1,2]}\n\"b\"\n");
+    }
+
+    #[test]
+    fn restyles_schema_keys_outside_data() {
+        let mut tx = json!({
+            "ixs": [{
+                "accounts": [{ "isSigner": true, "isWritable": false, "preBalance": 1 }],
+                "data": { "isSigner": "decoded field" }
+            }]
+        });
+        restyle_keys(&mut tx, KeyStyle::Snake);
+        assert_eq!(
+            tx,
+            json!({
+                "ixs": [{
+                    "accounts": [{ "is_signer": true, "is_writable": false, "pre_balance": 1 }],
+                    "data": { "isSigner": "decoded field" }
+                }]
+            })
+        );
+        let snake = tx.clone();
+        restyle_keys(&mut tx, KeyStyle::Camel);
+        restyle_keys(&mut tx, KeyStyle::Snake);
+        assert_eq!(tx, snake);
+    }
+
+    #[test]
+    fn compressed_output_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            let options = OutputOptions {
+                compression,
+                ..Default::default()
+            };
+            let path = dir.path().join(options.file_name("block"));
+            assert!(options.should_write(&path).unwrap());
+            let mut writer = options.create(&path).unwrap();
+            writer.write_all(b"{\"slot\": 1}").unwrap();
+            writer.finish().unwrap();
+            assert_eq!(read_to_string(&path).unwrap(), "{\"slot\": 1}");
+        }
+    }
+
+    #[test]
+    fn name_template_fills_placeholders() {
+        let options = OutputOptions {
+            name_template: Some("{pubkey}_{slot}.json"),
+            ..Default::default()
+        };
+        let vars = [("pubkey", "abc".to_string()), ("slot", "42".to_string())];
+        assert_eq!(options.stem("abc", &vars).unwrap(), "abc_42");
+        assert!(options.stem("abc", &vars[..1]).is_err());
+        assert_eq!(OutputOptions::default().stem("abc", &[]).unwrap(), "abc");
+        assert!(options.check_account_names().is_err());
+        let typed = OutputOptions {
+            layout: Layout::Typed,
+            ..options
+        };
+        assert!(typed.check_account_names().is_ok());
+    }
+
+    #[test]
+    fn overwrite_policy_applies_to_existing_files() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path();
+        let with = |overwrite| OutputOptions {
+            overwrite,
+            ..Default::default()
+        };
+        assert!(OutputOptions::default().should_write(path).unwrap());
+        assert!(!with(Overwrite::Skip).should_write(path).unwrap());
+        assert!(with(Overwrite::Force).should_write(path).unwrap());
+    }
+}
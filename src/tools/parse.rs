@@ -1,38 +1,154 @@
-use std::{fs, path::Path};
+use std::{fs, io::Write, path::Path};
 
 use anyhow::{Context, Result, anyhow};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 use bs58;
 use serde_json::{Value, json};
-use solana_commitment_config::CommitmentConfig;
 use solana_rpc_client::api::config::RpcBlockConfig;
 use solana_rpc_client::api::config::RpcTransactionConfig;
+use solana_sdk::{instruction::InstructionError, transaction::TransactionError};
 use solana_transaction_status::parse_accounts::ParsedAccount;
 use solana_transaction_status::{
-    EncodedTransaction, TransactionDetails, UiInstruction, UiMessage, UiParsedInstruction,
-    UiTransactionEncoding,
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, TransactionDetails,
+    UiConfirmedBlock, UiInnerInstructions, UiInstruction, UiMessage, UiParsedInstruction,
+    UiParsedMessage, UiTransaction, UiTransactionEncoding,
 };
 
-use crate::tools::tx::{MAINNET_RPC_URL, create_connection};
+use crate::accounts::VOTE_PROGRAM_ID;
+use crate::network::NetworkCtx;
+use crate::tools::events::EventsOutput;
+use crate::tools::output::{JsonLinesWriter, OutputOptions, read_to_string};
+use crate::tools::style::diff_line;
 use crate::tx_format::parse_tx::{parse_native_program, parse_tx_to_json};
+use crate::utils::{STAMP_KEY, json_diff, stamp, without_stamp};
 
-pub fn create_json_from_tx(signature: &str, to_path: impl AsRef<Path>) -> Result<()> {
-    let connection = create_connection(MAINNET_RPC_URL);
+/// Where a parser reads its raw RPC response from.
+#[derive(Clone, Copy)]
+pub enum ParseSource<'a> {
+    /// Fetch by signature or slot from the source cluster.
+    Rpc(&'a str),
+    /// Read a response previously saved by `dump-tx`/`dump-block`.
+    File(&'a Path),
+}
+
+pub fn fetch_transaction(
+    network: &NetworkCtx,
+    signature: &str,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    let connection = network.source_client();
     let config = RpcTransactionConfig {
         encoding: Some(UiTransactionEncoding::JsonParsed),
-        commitment: Some(CommitmentConfig::confirmed()),
+        commitment: Some(network.history_commitment()),
         max_supported_transaction_version: Some(0),
     };
-    let tx = connection
+    connection
         .get_transaction_with_config(&signature.parse()?, config)
-        .with_context(|| format!("Transaction not found: {signature}"))?;
-
-    println!("Parsing transaction {signature}...");
-    let json = parse_tx_to_json(&tx)?;
-    fs::create_dir_all(&to_path)?;
-    let out_path = to_path.as_ref().join(format!("{signature}.json"));
-    fs::write(&out_path, serde_json::to_string_pretty(&json)?)?;
-    println!("Transaction dumped to {}", out_path.display());
+        .with_context(|| format!("Transaction not found: {signature}"))
+}
+
+fn read_raw_json(path: &Path) -> Result<Value> {
+    let data = read_to_string(path)?;
+    serde_json::from_str(&data).with_context(|| format!("invalid JSON in {path:?}"))
+}
+
+pub fn load_raw_transaction(path: &Path) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+    serde_json::from_value(read_raw_json(path)?)
+        .with_context(|| format!("invalid raw transaction in {path:?}"))
+}
+
+/// Loads a raw (optionally compressed) block; the slot comes from the `slot`
+/// field written by `dump-block`, or else from a `<slot>.json` file name.
+/// The parent slot is no fallback, as slots in between may have been skipped.
+pub fn load_raw_block(path: &Path) -> Result<(u64, UiConfirmedBlock)> {
+    let raw = read_raw_json(path)?;
+    let slot = raw.get("slot").and_then(Value::as_u64).or_else(|| {
+        path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.split('.').next())
+            .and_then(|stem| stem.parse().ok())
+    });
+    let slot = slot.ok_or_else(|| {
+        anyhow!(
+            "Unknown slot of the raw block in {path:?}: add a \"slot\" field or name it <slot>.json"
+        )
+    })?;
+    let block: UiConfirmedBlock =
+        serde_json::from_value(raw).with_context(|| format!("invalid raw block in {path:?}"))?;
+    Ok((slot, block))
+}
+
+impl ParseSource<'_> {
+    /// Source recorded in the stamp of the parsed output.
+    fn origin(self, network: &NetworkCtx) -> String {
+        match self {
+            ParseSource::Rpc(_) => network.source.clone(),
+            ParseSource::File(path) => path.display().to_string(),
+        }
+    }
+}
+
+fn recent_blockhash(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Option<String> {
+    match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => match &ui_tx.message {
+            UiMessage::Parsed(msg) => Some(msg.recent_blockhash.clone()),
+            UiMessage::Raw(msg) => Some(msg.recent_blockhash.clone()),
+        },
+        other => other
+            .decode()
+            .map(|decoded| decoded.message.recent_blockhash().to_string()),
+    }
+}
+
+fn transaction_signature(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Option<String> {
+    match &tx.transaction.transaction {
+        EncodedTransaction::Json(ui_tx) => ui_tx.signatures.first().cloned(),
+        other => other
+            .decode()
+            .and_then(|decoded| decoded.signatures.first().map(ToString::to_string)),
+    }
+}
+
+pub fn create_json_from_tx(
+    network: &NetworkCtx,
+    source: ParseSource,
+    to_path: impl AsRef<Path>,
+    output: OutputOptions,
+    events: Option<&EventsOutput>,
+) -> Result<()> {
+    let tx = match source {
+        ParseSource::Rpc(signature) => fetch_transaction(network, signature)?,
+        ParseSource::File(path) => load_raw_transaction(path)?,
+    };
+    let signature =
+        transaction_signature(&tx).ok_or_else(|| anyhow!("Transaction has no signature"))?;
+
+    say!("Parsing transaction {signature}...");
+    let mut json = parse_tx_to_json(&tx)?;
+    json[STAMP_KEY] = stamp(
+        &source.origin(network),
+        Some(tx.slot),
+        recent_blockhash(&tx).as_deref(),
+    );
+    if let Some(events) = events {
+        let logs = tx
+            .transaction
+            .meta
+            .as_ref()
+            .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages.clone()))
+            .unwrap_or_default();
+        events.write(&signature, tx.slot, &logs)?;
+    }
+    let vars = [
+        ("signature", signature.clone()),
+        ("slot", tx.slot.to_string()),
+    ];
+    let stem = output.stem(&signature, &vars)?;
+    let Some(out_path) = output.path_in(to_path.as_ref(), &stem)? else {
+        return Ok(());
+    };
+    output.restyle(&mut json);
+    output.write_json(&out_path, &json)?;
+    say!("Transaction dumped to {}", out_path.display());
     Ok(())
 }
 
@@ -44,13 +160,12 @@ fn find_account_name(pubkey: &str, parsed_info: &Value) -> Option<String> {
             Value::Array(arr) if arr.iter().any(|v| v.as_str() == Some(pubkey)) => {
                 return Some(key.clone());
             }
-            Value::Object(obj) => {
-                if obj.get("pubkey").and_then(Value::as_str) == Some(pubkey)
+            Value::Object(obj)
+                if (obj.get("pubkey").and_then(Value::as_str) == Some(pubkey)
                     || obj.get("wallet").and_then(Value::as_str) == Some(pubkey)
-                    || obj.get("owner").and_then(Value::as_str) == Some(pubkey)
-                {
-                    return Some(key.clone());
-                }
+                    || obj.get("owner").and_then(Value::as_str) == Some(pubkey)) =>
+            {
+                return Some(key.clone());
             }
             _ => {}
         }
@@ -60,7 +175,7 @@ fn find_account_name(pubkey: &str, parsed_info: &Value) -> Option<String> {
 
 fn normalize_ix_accounts(
     accounts: &[Value],
-    account_meta: &Vec<serde_json::Value>,
+    account_meta: &[serde_json::Value],
     account_meta_by_pubkey: &std::collections::HashMap<String, (bool, bool)>,
     parsed_info: Option<&Value>,
 ) -> Vec<Value> {
@@ -89,12 +204,11 @@ fn normalize_ix_accounts(
                 "isWritable": is_writable,
             });
 
-            if let (Some(info), Value::Object(map)) = (parsed_info, &mut entry) {
-                if let Some(pubkey) = map.get("pubkey").and_then(Value::as_str) {
-                    if let Some(name) = find_account_name(pubkey, info) {
-                        map.insert("name".to_string(), Value::String(name));
-                    }
-                }
+            if let (Some(info), Value::Object(map)) = (parsed_info, &mut entry)
+                && let Some(pubkey) = map.get("pubkey").and_then(Value::as_str)
+                && let Some(name) = find_account_name(pubkey, info)
+            {
+                map.insert("name".to_string(), Value::String(name));
             }
 
             entry
@@ -102,28 +216,213 @@ fn normalize_ix_accounts(
         .collect()
 }
 
-pub fn parse_block(slot: &str, to_path: impl AsRef<Path>) -> Result<()> {
+pub fn fetch_block(
+    network: &NetworkCtx,
+    slot: &str,
+    details: DetailLevel,
+) -> Result<(u64, UiConfirmedBlock)> {
     let block_number: u64 = slot.parse().map_err(|_| anyhow!("Invalid slot: {slot}"))?;
 
-    let connection = create_connection(MAINNET_RPC_URL);
+    let connection = network.source_client();
     let config = RpcBlockConfig {
         encoding: Some(UiTransactionEncoding::JsonParsed),
-        transaction_details: Some(TransactionDetails::Full),
+        transaction_details: Some(details.into()),
         rewards: Some(true),
-        commitment: Some(CommitmentConfig::confirmed()),
+        commitment: Some(network.history_commitment()),
         max_supported_transaction_version: Some(0),
     };
 
     let block = connection
         .get_block_with_config(block_number, config)
         .with_context(|| format!("Block not found: {slot}"))?;
+    Ok((block_number, block))
+}
 
-    let transactions = block.transactions.unwrap_or_default();
+pub fn parse_block(
+    network: &NetworkCtx,
+    source: ParseSource,
+    to_path: impl AsRef<Path>,
+    options: &BlockParseOptions,
+    output: OutputOptions,
+) -> Result<()> {
+    let (block_number, block) = match source {
+        ParseSource::Rpc(slot) => fetch_block(network, slot, options.details)?,
+        ParseSource::File(path) => load_raw_block(path)?,
+    };
+    let header = stamp(
+        &source.origin(network),
+        Some(block_number),
+        Some(&block.blockhash),
+    );
+
+    let slot = block_number.to_string();
+    let stem = output.stem(&slot, &[("slot", slot.clone())])?;
+    let Some(file_path) = output.path_in(to_path.as_ref(), &stem)? else {
+        return Ok(());
+    };
+    let mut file = output.create(&file_path)?;
+    let count = if output.jsonl {
+        let mut writer = JsonLinesWriter::new(&mut file);
+        parse_block_transactions(block, options, |mut tx| {
+            if let Value::Object(map) = &mut tx {
+                map.insert("slot".to_string(), json!(block_number.to_string()));
+            }
+            output.restyle(&mut tx);
+            writer.write(&tx)
+        })?;
+        writer.finish()?
+    } else {
+        let mut writer = BlockWriter::new(&mut file, block_number, Some(&header))?;
+        parse_block_transactions(block, options, |mut tx| {
+            output.restyle(&mut tx);
+            writer.write_tx(&tx)
+        })?;
+        writer.finish()?
+    };
+    file.finish()?;
+    say!(
+        "Parsed block saved to {} ({count} transactions)",
+        file_path.display()
+    );
+    Ok(())
+}
+
+/// How much of each block transaction is fetched and written.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DetailLevel {
+    /// Only transaction signatures
+    Signatures,
+    /// Signatures, status and account balances, without instructions
+    Accounts,
+    /// Everything including instructions and logs
+    #[default]
+    Full,
+}
+
+impl From<DetailLevel> for TransactionDetails {
+    fn from(level: DetailLevel) -> Self {
+        match level {
+            DetailLevel::Signatures => TransactionDetails::Signatures,
+            DetailLevel::Accounts => TransactionDetails::Accounts,
+            DetailLevel::Full => TransactionDetails::Full,
+        }
+    }
+}
+
+/// Filters applied while parsing a block.
+#[derive(Debug, Default)]
+pub struct BlockParseOptions {
+    pub details: DetailLevel,
+    pub exclude_votes: bool,
+    pub only_failed: bool,
+    /// Keep only transactions invoking one of these programs (directly or via CPI).
+    pub programs: Vec<String>,
+}
+
+fn instruction_program_id(ix: &UiInstruction, account_keys: &[ParsedAccount]) -> Option<String> {
+    match ix {
+        UiInstruction::Compiled(compiled) => account_keys
+            .get(compiled.program_id_index as usize)
+            .map(|key| key.pubkey.clone()),
+        UiInstruction::Parsed(UiParsedInstruction::Parsed(parsed)) => {
+            Some(parsed.program_id.clone())
+        }
+        UiInstruction::Parsed(UiParsedInstruction::PartiallyDecoded(partial)) => {
+            Some(partial.program_id.clone())
+        }
+    }
+}
+
+fn touches_programs(
+    programs: &[String],
+    account_keys: &[ParsedAccount],
+    instructions: &[UiInstruction],
+    inner_instructions: &[UiInnerInstructions],
+) -> bool {
+    instructions
+        .iter()
+        .chain(
+            inner_instructions
+                .iter()
+                .flat_map(|inner| &inner.instructions),
+        )
+        .filter_map(|ix| instruction_program_id(ix, account_keys))
+        .any(|program_id| programs.contains(&program_id))
+}
+
+/// Describes a transaction error, splitting out the failing instruction index.
+fn transaction_error_json(error: &TransactionError) -> Value {
+    match error {
+        TransactionError::InstructionError(index, ix_error) => {
+            let mut out = json!({
+                "instruction": index,
+                "message": ix_error.to_string(),
+            });
+            if let InstructionError::Custom(code) = ix_error {
+                out["code"] = json!(code);
+            }
+            out
+        }
+        other => json!({ "message": other.to_string() }),
+    }
+}
+
+pub fn parse_block_to_json(
+    block_number: u64,
+    block: UiConfirmedBlock,
+    options: &BlockParseOptions,
+) -> Value {
     let mut parsed_txs = Vec::new();
+    parse_block_transactions(block, options, |tx| {
+        parsed_txs.push(tx);
+        Ok(())
+    })
+    .expect("collecting transactions cannot fail");
+
+    json!({
+        "slot": block_number.to_string(),
+        "txs": parsed_txs,
+    })
+}
+
+/// Parses the transactions of a block one by one, handing each to `emit` so
+/// callers can write them out without holding the whole result in memory.
+pub fn parse_block_transactions(
+    block: UiConfirmedBlock,
+    options: &BlockParseOptions,
+    mut emit: impl FnMut(Value) -> Result<()>,
+) -> Result<()> {
+    let Some(transactions) = block.transactions else {
+        if options.exclude_votes || options.only_failed || !options.programs.is_empty() {
+            eprintln!("Warning: filters are ignored for signature-only blocks");
+        }
+        for signature in block.signatures.unwrap_or_default() {
+            emit(json!({ "signature": signature }))?;
+        }
+        return Ok(());
+    };
 
     for tx in transactions {
+        let error = tx
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.err.clone())
+            .map(TransactionError::from);
+        if options.only_failed && error.is_none() {
+            continue;
+        }
+
         let ui_tx = match tx.transaction {
             EncodedTransaction::Json(tx) => tx,
+            EncodedTransaction::Accounts(list) => UiTransaction {
+                signatures: list.signatures,
+                message: UiMessage::Parsed(UiParsedMessage {
+                    account_keys: list.account_keys,
+                    recent_blockhash: String::new(),
+                    instructions: Vec::new(),
+                    address_table_lookups: None,
+                }),
+            },
             _ => continue,
         };
 
@@ -161,6 +460,35 @@ pub fn parse_block(slot: &str, to_path: impl AsRef<Path>) -> Result<()> {
                 }
             };
 
+        let vote_program = VOTE_PROGRAM_ID.to_string();
+        if options.exclude_votes && account_keys.iter().any(|key| key.pubkey == vote_program) {
+            continue;
+        }
+
+        let accounts_only = options.details == DetailLevel::Accounts || instructions.is_empty();
+        if accounts_only && !options.programs.is_empty() {
+            if !account_keys
+                .iter()
+                .any(|key| options.programs.contains(&key.pubkey))
+            {
+                continue;
+            }
+        } else if !options.programs.is_empty() {
+            let inner_instructions = tx
+                .meta
+                .as_ref()
+                .and_then(|meta| Option::<Vec<_>>::from(meta.inner_instructions.clone()))
+                .unwrap_or_default();
+            if !touches_programs(
+                &options.programs,
+                &account_keys,
+                &instructions,
+                &inner_instructions,
+            ) {
+                continue;
+            }
+        }
+
         let pre_balances = tx
             .meta
             .as_ref()
@@ -233,10 +561,10 @@ pub fn parse_block(slot: &str, to_path: impl AsRef<Path>) -> Result<()> {
                             .unwrap_or_else(|| parsed.clone())
                     })
                 });
-                if data.is_none() {
-                    if let UiInstruction::Compiled(compiled) = ix {
-                        data = Some(Value::String(compiled.data.clone()));
-                    }
+                if data.is_none()
+                    && let UiInstruction::Compiled(compiled) = ix
+                {
+                    data = Some(Value::String(compiled.data.clone()));
                 }
 
                 let mut data_value = data.unwrap_or(Value::Null);
@@ -245,10 +573,10 @@ pub fn parse_block(slot: &str, to_path: impl AsRef<Path>) -> Result<()> {
                         .decode(text)
                         .ok()
                         .or_else(|| bs58::decode(text).into_vec().ok());
-                    if let Some(bytes) = bytes {
-                        if !bytes.is_empty() {
-                            data_value = Value::String(format!("0x{}", hex::encode(bytes)));
-                        }
+                    if let Some(bytes) = bytes
+                        && !bytes.is_empty()
+                    {
+                        data_value = Value::String(format!("0x{}", hex::encode(bytes)));
                     }
                 }
 
@@ -299,23 +627,279 @@ pub fn parse_block(slot: &str, to_path: impl AsRef<Path>) -> Result<()> {
             .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages.clone()))
             .unwrap_or_default();
 
-        parsed_txs.push(json!({
+        let mut parsed = json!({
             "signature": ui_tx.signatures.first().cloned().unwrap_or_default(),
+            "status": if error.is_some() { "failed" } else { "success" },
+            "error": error.as_ref().map(transaction_error_json),
             "ixs": instructions_out,
             "meta": {
                 "logs": logs,
                 "accounts": accounts_meta,
             }
-        }));
+        });
+        if options.details == DetailLevel::Accounts {
+            if let Value::Object(map) = &mut parsed {
+                map.remove("ixs");
+            }
+            if let Some(Value::Object(meta)) = parsed.get_mut("meta") {
+                meta.remove("logs");
+            }
+        }
+        emit(parsed)?;
     }
 
-    fs::create_dir_all(&to_path)?;
-    let file_path = to_path.as_ref().join(format!("{block_number}.json"));
-    let payload = json!({
-        "slot": block_number.to_string(),
-        "txs": parsed_txs,
-    });
-    fs::write(&file_path, serde_json::to_string_pretty(&payload)?)?;
-    println!("Parsed block saved to {}", file_path.display());
     Ok(())
 }
+
+/// Writes a parsed block as pretty JSON, one transaction at a time. The
+/// output is identical to `serde_json::to_string_pretty` of the whole block.
+pub struct BlockWriter<W: Write> {
+    writer: W,
+    count: usize,
+}
+
+impl<W: Write> BlockWriter<W> {
+    /// `stamp` goes first, as `_soltnet` sorts before the other keys.
+    pub fn new(mut writer: W, block_number: u64, stamp: Option<&Value>) -> Result<Self> {
+        writer.write_all(b"{\n")?;
+        if let Some(stamp) = stamp {
+            let pretty = serde_json::to_string_pretty(stamp)?.replace('\n', "\n  ");
+            writeln!(writer, "  \"{STAMP_KEY}\": {pretty},")?;
+        }
+        write!(writer, "  \"slot\": \"{block_number}\",\n  \"txs\": [")?;
+        Ok(Self { writer, count: 0 })
+    }
+
+    pub fn write_tx(&mut self, tx: &Value) -> Result<()> {
+        let separator = if self.count == 0 { "\n" } else { ",\n" };
+        self.writer.write_all(separator.as_bytes())?;
+        let pretty = serde_json::to_string_pretty(tx)?;
+        for (index, line) in pretty.lines().enumerate() {
+            if index > 0 {
+                self.writer.write_all(b"\n")?;
+            }
+            write!(self.writer, "    {line}")?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Closes the JSON document and returns the number of transactions written.
+    pub fn finish(mut self) -> Result<usize> {
+        if self.count > 0 {
+            self.writer.write_all(b"\n  ")?;
+        }
+        self.writer.write_all(b"]\n}")?;
+        self.writer.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// Re-runs the parsers on a raw `dump-tx`/`dump-block` response and compares
+/// the result with a stored expected output (or rewrites it with `update`).
+/// The `_soltnet` stamp of a `parse-tx` output is not compared.
+pub fn verify_parse(
+    raw_path: impl AsRef<Path>,
+    expected_path: impl AsRef<Path>,
+    update: bool,
+) -> Result<()> {
+    let actual = parse_raw_file(&raw_path)?;
+
+    if update {
+        fs::write(&expected_path, serde_json::to_string_pretty(&actual)?)
+            .with_context(|| format!("failed to write {:?}", expected_path.as_ref()))?;
+        say!(
+            "Expected output updated: {}",
+            expected_path.as_ref().display()
+        );
+        return Ok(());
+    }
+
+    let expected: Value = serde_json::from_str(&read_to_string(expected_path.as_ref())?)
+        .with_context(|| format!("invalid JSON in {:?}", expected_path.as_ref()))?;
+
+    let differences = json_diff(&without_stamp(&expected), &without_stamp(&actual));
+    if differences.is_empty() {
+        say!("Parse output matches {}", expected_path.as_ref().display());
+        return Ok(());
+    }
+    for difference in &differences {
+        say!("{}", diff_line(difference));
+    }
+    Err(anyhow!(
+        "Parse output differs from {} ({} differences)",
+        expected_path.as_ref().display(),
+        differences.len()
+    ))
+}
+
+/// Parses a raw transaction or block response stored on disk.
+pub fn parse_raw_file(raw_path: impl AsRef<Path>) -> Result<Value> {
+    let path = raw_path.as_ref();
+    if read_raw_json(path)?.get("blockhash").is_some() {
+        let (slot, block) = load_raw_block(path)?;
+        Ok(parse_block_to_json(
+            slot,
+            block,
+            &BlockParseOptions::default(),
+        ))
+    } else {
+        parse_tx_to_json(&load_raw_transaction(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use serde_json::json;
+    use solana_transaction_status::{
+        UiCompiledInstruction, UiInnerInstructions, UiInstruction, parse_accounts::ParsedAccount,
+    };
+
+    use super::{
+        BlockWriter, ParseSource, create_json_from_tx, parse_raw_file, touches_programs,
+        verify_parse,
+    };
+    use crate::network::NetworkCtx;
+    use crate::rpc::{RpcExchange, RpcMocks};
+    use crate::tools::output::OutputOptions;
+    use crate::utils::{STAMP_KEY, json_diff};
+
+    fn compiled(program_id_index: u8) -> UiInstruction {
+        UiInstruction::Compiled(UiCompiledInstruction {
+            program_id_index,
+            accounts: Vec::new(),
+            data: String::new(),
+            stack_height: None,
+        })
+    }
+
+    #[test]
+    fn block_writer_matches_pretty_output() {
+        let header = json!({"slot": 7, "source": "mainnet"});
+        for (txs, stamp) in [
+            (vec![], None),
+            (
+                vec![json!({"signature": "a", "ixs": [1, 2]}), json!({})],
+                Some(&header),
+            ),
+        ] {
+            let mut out = Vec::new();
+            let mut writer = BlockWriter::new(&mut out, 7, stamp).unwrap();
+            for tx in &txs {
+                writer.write_tx(tx).unwrap();
+            }
+            assert_eq!(writer.finish().unwrap(), txs.len());
+            let mut expected = json!({"slot": "7", "txs": txs});
+            if let Some(stamp) = stamp {
+                expected[STAMP_KEY] = stamp.clone();
+            }
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                serde_json::to_string_pretty(&expected).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn touches_programs_checks_inner_instructions() {
+        let keys: Vec<ParsedAccount> = ["Payer", "Outer", "Inner"]
+            .iter()
+            .map(|key| ParsedAccount {
+                pubkey: key.to_string(),
+                writable: false,
+                signer: false,
+                source: None,
+            })
+            .collect();
+        let inner = vec![UiInnerInstructions {
+            index: 0,
+            instructions: vec![compiled(2)],
+        }];
+        let programs = |id: &str| vec![id.to_string()];
+        assert!(touches_programs(
+            &programs("Outer"),
+            &keys,
+            &[compiled(1)],
+            &[]
+        ));
+        assert!(touches_programs(
+            &programs("Inner"),
+            &keys,
+            &[compiled(1)],
+            &inner
+        ));
+        assert!(!touches_programs(
+            &programs("Inner"),
+            &keys,
+            &[compiled(1)],
+            &[]
+        ));
+    }
+
+    /// Golden tests: every `fixtures/parse/<name>.raw.json` must parse to
+    /// `fixtures/parse/<name>.expected.json`.
+    #[test]
+    fn parse_fixtures_match_expected() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/parse");
+        let mut checked = 0;
+        for entry in fs::read_dir(&dir).expect("fixtures dir") {
+            let path = entry.expect("entry").path();
+            let Some(name) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_suffix(".raw.json"))
+            else {
+                continue;
+            };
+            let expected_path = dir.join(format!("{name}.expected.json"));
+            let expected = serde_json::from_str(
+                &fs::read_to_string(&expected_path).expect("expected fixture"),
+            )
+            .expect("expected JSON");
+            let actual = parse_raw_file(&path).expect("parse fixture");
+            let differences = json_diff(&expected, &actual);
+            assert!(differences.is_empty(), "{name}: {differences:#?}");
+            checked += 1;
+        }
+        assert!(checked > 0, "no parse fixtures found");
+    }
+
+    #[test]
+    fn parse_tx_from_mocked_rpc() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/parse");
+        let read = |name: &str| -> serde_json::Value {
+            serde_json::from_str(&fs::read_to_string(dir.join(name)).unwrap()).unwrap()
+        };
+        let network = NetworkCtx::mocked(RpcMocks::new(vec![RpcExchange {
+            method: "getTransaction".to_string(),
+            result: read("transfer_memo.raw.json"),
+            ..Default::default()
+        }]));
+        let out = tempfile::tempdir().unwrap();
+
+        let signature = read("transfer_memo.raw.json")["transaction"]["signatures"][0]
+            .as_str()
+            .unwrap()
+            .to_string();
+        create_json_from_tx(
+            &network,
+            ParseSource::Rpc(&signature),
+            out.path(),
+            OutputOptions::default(),
+            None,
+        )
+        .unwrap();
+
+        let output = out.path().join(format!("{signature}.json"));
+        verify_parse(dir.join("transfer_memo.raw.json"), &output, false).unwrap();
+        let mut actual: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&output).unwrap()).unwrap();
+        let header = actual.as_object_mut().unwrap().remove(STAMP_KEY).unwrap();
+        assert_eq!(header["slot"], 250_000_000);
+        assert_eq!(header["source"], network.source.as_str());
+        let differences = json_diff(&read("transfer_memo.expected.json"), &actual);
+        assert!(differences.is_empty(), "{differences:#?}");
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::Value;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::network::NetworkCtx;
+use crate::packs::{PoolFields, installed};
+use crate::tools::dump::{DumpSummary, dump_accounts};
+use crate::tools::output::OutputOptions;
+use crate::utils::format_ui_amount;
+
+const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const MINT_DECIMALS_OFFSET: usize = 44;
+
+/// One side of a pool: its token vault and balance.
+#[derive(Debug)]
+pub struct PoolSide {
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub decimals: u8,
+}
+
+/// Pool account decoded with a pack layout, with its reserves.
+#[derive(Debug)]
+pub struct PoolInfo {
+    pub address: Pubkey,
+    pub owner: Pubkey,
+    pub program: String,
+    pub layout: String,
+    pub sides: [PoolSide; 2],
+    /// Fee fraction, e.g. `0.0025`
+    pub fee: Option<f64>,
+    /// Quote per base in UI units
+    pub price: Option<f64>,
+    pub tick: Option<i32>,
+    pub decoded: Value,
+}
+
+/// Fetches a pool account from the source cluster and decodes it with the
+/// pool layout of `pack`, then reads its vaults and mints for the reserves.
+pub fn pool_info(network: &NetworkCtx, address: &Pubkey, pack: &str) -> Result<PoolInfo> {
+    let pack = installed()
+        .and_then(|registry| registry.pack(pack))
+        .ok_or_else(|| anyhow!("Pack not installed: {pack}"))?;
+    let client = network.source_client();
+    let account = client
+        .get_account_with_commitment(address, network.read_commitment())?
+        .value
+        .ok_or_else(|| anyhow!("Account not found: {address}"))?;
+    let program = pack
+        .programs
+        .get(&account.owner.to_string())
+        .ok_or_else(|| anyhow!("Pack {} has no program {}", pack.name, account.owner))?;
+    let (layout, decoded) = program
+        .decode_account(&account.data)
+        .ok_or_else(|| anyhow!("No {} layout matches {address}", program.name))?;
+    let fields = layout
+        .pool
+        .as_ref()
+        .ok_or_else(|| anyhow!("Layout {} is not a pool", layout.name))?;
+
+    let vaults = fields
+        .vaults
+        .iter()
+        .map(|name| {
+            let value = field(&decoded, name)?.as_str()?;
+            value.parse::<Pubkey>().ok()
+        })
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| anyhow!("Pool {address} has no vault fields {:?}", fields.vaults))?;
+    let vault_accounts = client
+        .get_multiple_accounts_with_commitment(&vaults, network.read_commitment())?
+        .value;
+    let mut sides = Vec::new();
+    for (vault, account) in vaults.into_iter().zip(vault_accounts) {
+        let data = account
+            .ok_or_else(|| anyhow!("Vault not found: {vault}"))?
+            .data;
+        let mint = read_pubkey(&data, TOKEN_ACCOUNT_MINT_OFFSET)
+            .ok_or_else(|| anyhow!("Vault {vault} is not a token account"))?;
+        let amount = read_u64(&data, TOKEN_ACCOUNT_AMOUNT_OFFSET)
+            .ok_or_else(|| anyhow!("Vault {vault} is not a token account"))?;
+        let decimals = client
+            .get_account_with_commitment(&mint, network.read_commitment())?
+            .value
+            .and_then(|mint| mint.data.get(MINT_DECIMALS_OFFSET).copied())
+            .with_context(|| format!("Failed to read mint {mint}"))?;
+        sides.push(PoolSide {
+            vault,
+            mint,
+            amount,
+            decimals,
+        });
+    }
+    let sides: [PoolSide; 2] = sides
+        .try_into()
+        .map_err(|_| anyhow!("A pool has two vaults"))?;
+
+    let metrics = pool_metrics(fields, &decoded, &sides)?;
+    Ok(PoolInfo {
+        address: *address,
+        owner: account.owner,
+        program: program.name.clone(),
+        layout: layout.name.clone(),
+        sides,
+        fee: metrics.fee,
+        price: metrics.price,
+        tick: metrics.tick,
+        decoded,
+    })
+}
+
+impl PoolInfo {
+    pub fn print(&self) {
+        say!("Pool {} ({} {})", self.address, self.program, self.layout);
+        for (label, side) in ["base ", "quote"].iter().zip(&self.sides) {
+            say!(
+                "  {label} {}: {} (vault {})",
+                side.mint,
+                format_ui_amount(side.amount, side.decimals.into()),
+                side.vault
+            );
+        }
+        if let Some(price) = self.price {
+            say!("  price: 1 base = {price} quote");
+        }
+        if let Some(fee) = self.fee {
+            say!("  fee:   {}%", fee * 100.0);
+        }
+        if let Some(tick) = self.tick {
+            say!("  tick:  {tick}");
+        }
+    }
+
+    /// Dumps the pool, its program, vaults and mints for forking.
+    pub fn dump(
+        &self,
+        network: &NetworkCtx,
+        to_path: impl AsRef<Path>,
+        output: OutputOptions,
+    ) -> Result<DumpSummary> {
+        let mut accounts = BTreeSet::from([self.address.to_string(), self.owner.to_string()]);
+        for side in &self.sides {
+            accounts.insert(side.vault.to_string());
+            accounts.insert(side.mint.to_string());
+        }
+        dump_accounts(
+            network,
+            accounts,
+            to_path,
+            &self.address.to_string(),
+            output,
+        )
+    }
+}
+
+struct PoolMetrics {
+    fee: Option<f64>,
+    price: Option<f64>,
+    tick: Option<i32>,
+}
+
+/// Fee, price and tick of a decoded pool. The price comes from the square
+/// root price when the layout has one, otherwise from the reserves ratio.
+fn pool_metrics(
+    fields: &PoolFields,
+    decoded: &Value,
+    sides: &[PoolSide; 2],
+) -> Result<PoolMetrics> {
+    let number = |name: &Option<String>| -> Result<Option<u128>> {
+        name.as_deref()
+            .map(|name| {
+                field(decoded, name)
+                    .and_then(read_number)
+                    .ok_or_else(|| anyhow!("Pool field {name} is missing or not a number"))
+            })
+            .transpose()
+    };
+    let fee = number(&fields.fee_rate)?.map(|rate| rate as f64 / fields.fee_denominator as f64);
+    let tick = number(&fields.tick)?.map(|tick| tick as u32 as i32);
+    let decimals = sides[0].decimals as i32 - sides[1].decimals as i32;
+    let price = match number(&fields.sqrt_price_x64)? {
+        Some(sqrt_price) => {
+            let sqrt_price = sqrt_price as f64 / 2f64.powi(64);
+            Some(sqrt_price * sqrt_price * 10f64.powi(decimals))
+        }
+        None if sides[0].amount > 0 => {
+            let ratio = sides[1].amount as f64 / sides[0].amount as f64;
+            Some(ratio * 10f64.powi(decimals))
+        }
+        None => None,
+    };
+    Ok(PoolMetrics { fee, price, tick })
+}
+
+/// Value of the first field called `name` in a decoded data format.
+fn field<'a>(decoded: &'a Value, name: &str) -> Option<&'a Value> {
+    if decoded["name"].as_str() == Some(name) {
+        return decoded.get("data");
+    }
+    let children = match decoded {
+        Value::Array(items) => items,
+        Value::Object(map) => map.get("data")?.as_array()?,
+        _ => return None,
+    };
+    children.iter().find_map(|child| field(child, name))
+}
+
+/// Reads a number, a decimal string (large `u64`) or little-endian `bytes`
+/// (e.g. a `u128` declared as 16 bytes).
+fn read_number(value: &Value) -> Option<u128> {
+    match value {
+        Value::Number(number) => number.as_u64().map(u128::from),
+        Value::String(text) => text.parse().ok().or_else(|| {
+            let bytes = STANDARD.decode(text).ok()?;
+            let mut buffer = [0u8; 16];
+            buffer.get_mut(..bytes.len())?.copy_from_slice(&bytes);
+            Some(u128::from_le_bytes(buffer))
+        }),
+        _ => None,
+    }
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    Some(Pubkey::new_from_array(
+        data.get(offset..offset + 32)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine as _, engine::general_purpose::STANDARD};
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::{PoolSide, pool_metrics};
+    use crate::packs::PoolFields;
+
+    #[test]
+    fn computes_price_fee_and_tick_in_ui_units() {
+        let side = |amount, decimals| PoolSide {
+            vault: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount,
+            decimals,
+        };
+        // 100 SOL (9 decimals) against 15_000 USDC (6 decimals)
+        let sides = [side(100_000_000_000, 9), side(15_000_000_000, 6)];
+        let fields: PoolFields = serde_json::from_value(json!({
+            "vaults": ["vault_a", "vault_b"],
+            "fee_rate": "fee",
+        }))
+        .unwrap();
+        let decoded = json!({ "type": "object", "data": [
+            { "type": "u32", "name": "fee", "data": 2500 },
+        ]});
+        let metrics = pool_metrics(&fields, &decoded, &sides).unwrap();
+        assert_eq!(metrics.fee, Some(0.0025));
+        assert!((metrics.price.unwrap() - 150.0).abs() < 1e-9);
+
+        let fields: PoolFields = serde_json::from_value(json!({
+            "vaults": ["vault_a", "vault_b"],
+            "sqrt_price_x64": "sqrt_price",
+            "tick": "tick",
+        }))
+        .unwrap();
+        // sqrt(150 * 10^-3) in Q64.64
+        let sqrt_price = (0.15f64.sqrt() * 2f64.powi(64)) as u128;
+        let decoded = json!({ "type": "object", "data": [
+            { "type": "bytes", "name": "sqrt_price", "data": STANDARD.encode(sqrt_price.to_le_bytes()) },
+            { "type": "u32", "name": "tick", "data": (-18971i32) as u32 },
+        ]});
+        let metrics = pool_metrics(&fields, &decoded, &sides).unwrap();
+        assert!((metrics.price.unwrap() - 150.0).abs() < 1e-6);
+        assert_eq!(metrics.tick, Some(-18971));
+        assert_eq!(metrics.fee, None);
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Map, Value, json};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Progress events emitted with `--event-stream`.
+pub const STEP_STARTED: &str = "step_started";
+pub const RPC_RETRY: &str = "rpc_retry";
+pub const TX_SENT: &str = "tx_sent";
+pub const TX_CONFIRMED: &str = "tx_confirmed";
+pub const FILE_WRITTEN: &str = "file_written";
+
+pub fn enable_event_stream(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// One event line: `{"event": ..., "time_ms": ..., <fields>}`.
+pub fn event_line(event: &str, time_ms: u128, fields: Value) -> String {
+    let mut line = match fields {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+    line.insert("event".to_string(), json!(event));
+    line.insert("time_ms".to_string(), json!(time_ms));
+    Value::Object(line).to_string()
+}
+
+/// Writes a newline-delimited JSON event to stderr when the stream is enabled.
+pub fn emit(event: &str, fields: Value) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let time_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    let mut stderr = io::stderr().lock();
+    let _ = writeln!(stderr, "{}", event_line(event, time_ms, fields));
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Value, json};
+
+    use super::{TX_SENT, event_line};
+
+    #[test]
+    fn event_lines_are_flat_json() {
+        let line = event_line(TX_SENT, 42, json!({ "signature": "abc" }));
+        assert!(!line.contains('\n'));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            value,
+            json!({ "event": "tx_sent", "time_ms": 42, "signature": "abc" })
+        );
+    }
+}
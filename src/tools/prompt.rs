@@ -0,0 +1,136 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, BufRead, IsTerminal, Write},
+    path::Path,
+};
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::tx_format::{RawTransaction, json_tx::load_raw_tx_from_json, params::param_index};
+
+/// What each `$N` of a template is used as, e.g. `keypair` for signers,
+/// `u64 amount` for typed data or the field name otherwise.
+pub fn param_hints(tx: &RawTransaction) -> Result<BTreeMap<usize, BTreeSet<String>>> {
+    let mut hints = BTreeMap::new();
+    collect_hints(&serde_json::to_value(tx)?, None, None, &mut hints);
+    Ok(hints)
+}
+
+fn collect_hints(
+    value: &Value,
+    key: Option<&str>,
+    parent: Option<&serde_json::Map<String, Value>>,
+    hints: &mut BTreeMap<usize, BTreeSet<String>>,
+) {
+    match value {
+        Value::String(text) => {
+            let Some(index) = param_index(text) else {
+                return;
+            };
+            let hint = match (key, parent) {
+                (Some("signers"), _) => "keypair".to_string(),
+                (Some("data"), Some(object))
+                    if object.get("type").is_some_and(Value::is_string) =>
+                {
+                    let kind = object["type"].as_str().unwrap_or_default();
+                    match object.get("name").and_then(Value::as_str) {
+                        Some(name) => format!("{kind} {name}"),
+                        None => kind.to_string(),
+                    }
+                }
+                (Some(key), _) => key.to_string(),
+                (None, _) => "value".to_string(),
+            };
+            hints.entry(index).or_default().insert(hint);
+        }
+        Value::Array(items) => {
+            for item in items {
+                collect_hints(item, key, parent, hints);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                collect_hints(item, Some(key), Some(map), hints);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Asks for the params a template references beyond those given. Fails
+/// with the expected params instead when stdin is not a terminal.
+pub fn prompt_missing_params(template: &Path, params: &mut Vec<String>) -> Result<()> {
+    let Ok(tx) = load_raw_tx_from_json(template) else {
+        return Ok(());
+    };
+    let hints = param_hints(&tx)?;
+    let missing: Vec<_> = hints
+        .iter()
+        .filter(|(index, _)| **index >= params.len())
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let describe = |index: usize, hint: &BTreeSet<String>| {
+        let hint: Vec<&str> = hint.iter().map(String::as_str).collect();
+        format!("${} ({})", index + 1, hint.join(", "))
+    };
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        let expected: Vec<String> = missing
+            .iter()
+            .map(|(index, hint)| describe(**index, hint))
+            .collect();
+        return Err(anyhow!(
+            "Template {template:?} expects more params than given: {}",
+            expected.join(", ")
+        ));
+    }
+    let last = missing.last().map_or(0, |(index, _)| **index);
+    let mut lines = stdin.lock().lines();
+    for index in params.len()..=last {
+        let hint = hints.get(&index).cloned().unwrap_or_default();
+        eprint!("{}: ", describe(index, &hint));
+        io::stderr().flush()?;
+        let value = lines
+            .next()
+            .transpose()?
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .ok_or_else(|| anyhow!("Missing value for ${}", index + 1))?;
+        params.push(value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::param_hints;
+    use crate::tx_format::RawTransaction;
+
+    #[test]
+    fn hints_describe_param_usage() {
+        let tx: RawTransaction = serde_json::from_value(json!({
+            "instructions": [
+                { "program_id": "transfer", "from": "$1", "to": "$2", "amount": "$3" },
+                {
+                    "program_id": "11111111111111111111111111111111",
+                    "data": { "type": "u64", "name": "amount", "data": "$4" },
+                    "accounts": [{ "pubkey": "$1", "is_signer": true }]
+                }
+            ],
+            "signers": ["$5"]
+        }))
+        .unwrap();
+        let hints = param_hints(&tx).unwrap();
+
+        let hint = |index: usize| hints[&index].iter().cloned().collect::<Vec<_>>();
+        assert_eq!(hint(0), ["from", "pubkey"]);
+        assert_eq!(hint(2), ["amount"]);
+        assert_eq!(hint(3), ["u64 amount"]);
+        assert_eq!(hint(4), ["keypair"]);
+    }
+}
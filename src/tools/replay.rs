@@ -0,0 +1,241 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::Value;
+use solana_address_lookup_table_interface::state::AddressLookupTable;
+use solana_rpc_client::api::config::{RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_sdk::transaction::VersionedTransaction;
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::network::NetworkCtx;
+use crate::testnet::restart_testnet;
+use crate::tools::compute::{is_invoke_end, parse_invoke};
+use crate::tools::dump::dump_accounts_from_tx;
+use crate::tools::output::{OutputOptions, Overwrite};
+use crate::tools::style::{failure, success, warning};
+
+/// How a program invocation of the replayed route ended.
+#[derive(Debug, PartialEq)]
+pub enum LegOutcome {
+    Success,
+    Failed(String),
+    /// The logs end (or were truncated) before the invocation returned
+    Incomplete,
+}
+
+/// A top-level instruction (depth 1) or one of the programs it invoked
+/// directly (depth 2), typically a DEX leg of an aggregator route.
+#[derive(Debug)]
+pub struct RouteLeg {
+    pub instruction: usize,
+    pub program: String,
+    pub depth: usize,
+    pub outcome: LegOutcome,
+}
+
+/// Dumps every account of a swap transaction (lookup tables included),
+/// patches the lookup tables to stay active on a fresh validator, optionally
+/// restarts the local testnet with the dump and simulates the original
+/// transaction there, reporting the outcome of each route leg.
+pub fn replay_route(
+    network: &NetworkCtx,
+    signature: &str,
+    to_path: impl AsRef<Path>,
+    restart: bool,
+) -> Result<()> {
+    let to_path = to_path.as_ref();
+    // Refresh a previous replay of the same route in place.
+    let output = OutputOptions {
+        overwrite: Overwrite::Force,
+        ..Default::default()
+    };
+    let summary = dump_accounts_from_tx(network, signature, to_path, output, true)?;
+    summary.print();
+
+    let tx = fetch_versioned_transaction(network, signature)?;
+    if let Some(lookups) = tx.message.address_table_lookups() {
+        for lookup in lookups {
+            patch_lookup_table(&to_path.join(format!("{}.json", lookup.account_key)))?;
+        }
+    }
+
+    if restart {
+        restart_testnet(network, to_path)?;
+    }
+
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(network.read_commitment()),
+        ..Default::default()
+    };
+    let result = network
+        .target_client()
+        .simulate_transaction_with_config(&tx, config)
+        .context("Failed to simulate the route")?
+        .value;
+    let logs = result.logs.unwrap_or_default();
+    let legs = route_legs(&logs);
+    print_legs(&legs);
+
+    match result.err {
+        None => {
+            say!(
+                "{}",
+                success(format!(
+                    "Route replayed successfully ({} CU)",
+                    result.units_consumed.unwrap_or_default()
+                ))
+            );
+            Ok(())
+        }
+        Some(err) => {
+            let leg = legs
+                .iter()
+                .rev()
+                .find(|leg| matches!(leg.outcome, LegOutcome::Failed(_)));
+            match leg {
+                Some(leg) => Err(anyhow!(
+                    "Route failed in instruction #{} at {}: {err}",
+                    leg.instruction,
+                    leg.program
+                )),
+                None => Err(anyhow!("Route failed: {err}")),
+            }
+        }
+    }
+}
+
+fn fetch_versioned_transaction(
+    network: &NetworkCtx,
+    signature: &str,
+) -> Result<VersionedTransaction> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(network.history_commitment()),
+        max_supported_transaction_version: Some(0),
+    };
+    network
+        .source_client()
+        .get_transaction_with_config(&signature.parse()?, config)
+        .with_context(|| format!("Transaction not found: {signature}"))?
+        .transaction
+        .transaction
+        .decode()
+        .ok_or_else(|| anyhow!("Failed to decode transaction {signature}"))
+}
+
+/// Rewrites a dumped lookup table so every address is usable from slot 0:
+/// the table is marked active and its last extension moved to slot 0.
+fn patch_lookup_table(path: &Path) -> Result<()> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Lookup table not dumped: {path:?}"))?;
+    let mut account: Value = serde_json::from_str(&contents)?;
+    let encoded = account["account"]["data"][0]
+        .as_str()
+        .ok_or_else(|| anyhow!("No account data in {path:?}"))?;
+    let mut data = STANDARD.decode(encoded)?;
+    let mut meta = AddressLookupTable::deserialize(&data)
+        .map_err(|err| anyhow!("Invalid lookup table in {path:?}: {err}"))?
+        .meta;
+    meta.deactivation_slot = u64::MAX;
+    meta.last_extended_slot = 0;
+    AddressLookupTable::overwrite_meta_data(&mut data, meta)
+        .map_err(|err| anyhow!("Failed to patch {path:?}: {err}"))?;
+    account["account"]["data"][0] = Value::String(STANDARD.encode(&data));
+    fs::write(path, serde_json::to_string_pretty(&account)?)
+        .with_context(|| format!("failed to write {path:?}"))?;
+    say!("Patched lookup table {}", path.display());
+    Ok(())
+}
+
+/// Collects depth 1 and 2 invocations with their outcome from the logs.
+pub fn route_legs(logs: &[String]) -> Vec<RouteLeg> {
+    let mut legs: Vec<RouteLeg> = Vec::new();
+    let mut stack: Vec<Option<usize>> = Vec::new();
+    let mut instruction = 0;
+    let mut invoked = false;
+
+    for line in logs {
+        if let Some((program, depth)) = parse_invoke(line) {
+            if depth == 1 && invoked {
+                instruction += 1;
+            }
+            invoked = true;
+            if depth <= 2 {
+                stack.push(Some(legs.len()));
+                legs.push(RouteLeg {
+                    instruction,
+                    program: program.to_string(),
+                    depth,
+                    outcome: LegOutcome::Incomplete,
+                });
+            } else {
+                stack.push(None);
+            }
+        } else if is_invoke_end(line)
+            && let Some(Some(index)) = stack.pop()
+        {
+            legs[index].outcome = match line.split_once(" failed: ") {
+                Some((_, reason)) => LegOutcome::Failed(reason.to_string()),
+                None => LegOutcome::Success,
+            };
+        }
+    }
+    legs
+}
+
+fn print_legs(legs: &[RouteLeg]) {
+    for leg in legs {
+        let outcome = match &leg.outcome {
+            LegOutcome::Success => success("success"),
+            LegOutcome::Failed(reason) => failure(format!("FAILED: {reason}")),
+            LegOutcome::Incomplete => warning("did not complete"),
+        };
+        if leg.depth == 1 {
+            say!(
+                "Instruction #{} {}: {outcome}",
+                leg.instruction,
+                leg.program
+            );
+        } else {
+            say!("  leg {}: {outcome}", leg.program);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LegOutcome, route_legs};
+
+    #[test]
+    fn reports_the_failing_leg() {
+        let logs: Vec<String> = [
+            "Program Comp1111111111111111111111111111111111 invoke [1]",
+            "Program Comp1111111111111111111111111111111111 success",
+            "Program Jup11111111111111111111111111111111111 invoke [1]",
+            "Program Whir1111111111111111111111111111111111 invoke [2]",
+            "Program Tokn1111111111111111111111111111111111 invoke [3]",
+            "Program Tokn1111111111111111111111111111111111 success",
+            "Program Whir1111111111111111111111111111111111 success",
+            "Program Ray11111111111111111111111111111111111 invoke [2]",
+            "Program Ray11111111111111111111111111111111111 failed: custom program error: 0x1e",
+            "Program Jup11111111111111111111111111111111111 failed: custom program error: 0x1e",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+
+        let legs = route_legs(&logs);
+        assert_eq!(legs.len(), 4);
+        assert_eq!(legs[1].instruction, 1);
+        assert_eq!(legs[2].program, "Whir1111111111111111111111111111111111");
+        assert_eq!(legs[2].outcome, LegOutcome::Success);
+        assert_eq!(legs[3].depth, 2);
+        assert_eq!(
+            legs[3].outcome,
+            LegOutcome::Failed("custom program error: 0x1e".to_string())
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::network::NetworkCtx;
+use crate::tools::bundle::list_files;
+use crate::tools::style::{failure, success};
+use crate::tx_format::{
+    RawTransaction,
+    json_tx::{expand_shorthand, load_raw_tx_from_json},
+    pubkey::parse_pubkey,
+};
+
+/// Most addresses a lookup table can hold.
+const MAX_LOOKUP_TABLE_ACCOUNTS: usize = 256;
+const ACCOUNTS_CHUNK: usize = 100;
+
+/// How one account is used across a set of templates.
+#[derive(Debug, Default)]
+pub struct AccountUsage {
+    pub templates: BTreeSet<PathBuf>,
+    pub uses: usize,
+    pub writable: usize,
+    pub signer: usize,
+    pub program: bool,
+    /// Whether the account exists on the target, when it could be checked
+    pub exists: Option<bool>,
+}
+
+impl AccountUsage {
+    /// Lookup tables can hold accounts that are neither signers nor invoked
+    /// programs; only those shared by several templates are worth adding.
+    pub fn alt_candidate(&self) -> bool {
+        self.signer == 0 && !self.program && self.templates.len() > 1
+    }
+}
+
+/// Account usage of every template under a directory.
+#[derive(Debug, Default)]
+pub struct AccountsReport {
+    pub templates: usize,
+    /// Files that are not templates
+    pub skipped: usize,
+    /// Accounts given by params, which differ per execution
+    pub unresolved: usize,
+    pub accounts: HashMap<Pubkey, AccountUsage>,
+}
+
+impl AccountsReport {
+    pub fn add(&mut self, path: &Path, tx: &RawTransaction) {
+        self.templates += 1;
+        for ix in &tx.instructions {
+            let ix = expand_shorthand(ix)
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| ix.clone());
+            if let Ok(program) = parse_pubkey(&ix.program_id, &[]) {
+                let usage = self.accounts.entry(program).or_default();
+                usage.templates.insert(path.to_path_buf());
+                usage.uses += 1;
+                usage.program = true;
+            }
+            for account in &ix.accounts {
+                let Ok(pubkey) = parse_pubkey(&account.pubkey, &[]) else {
+                    self.unresolved += 1;
+                    continue;
+                };
+                let usage = self.accounts.entry(pubkey).or_default();
+                usage.templates.insert(path.to_path_buf());
+                usage.uses += 1;
+                usage.writable += usize::from(account.is_writable);
+                usage.signer += usize::from(account.is_signer);
+            }
+        }
+    }
+
+    /// Accounts by number of templates, then uses.
+    pub fn sorted(&self) -> Vec<(&Pubkey, &AccountUsage)> {
+        let mut accounts: Vec<_> = self.accounts.iter().collect();
+        accounts.sort_by(|(a_key, a), (b_key, b)| {
+            b.templates
+                .len()
+                .cmp(&a.templates.len())
+                .then(b.uses.cmp(&a.uses))
+                .then(a_key.cmp(b_key))
+        });
+        accounts
+    }
+
+    /// Suggested lookup table members, most shared first.
+    pub fn alt_candidates(&self) -> Vec<Pubkey> {
+        self.sorted()
+            .into_iter()
+            .filter(|(_, usage)| usage.alt_candidate())
+            .map(|(pubkey, _)| *pubkey)
+            .take(MAX_LOOKUP_TABLE_ACCOUNTS)
+            .collect()
+    }
+
+    /// Marks which accounts exist on the target; left unknown when it is unreachable.
+    pub fn check_target(&mut self, network: &NetworkCtx) {
+        let client = network.target_client();
+        let pubkeys: Vec<Pubkey> = self.accounts.keys().copied().collect();
+        for chunk in pubkeys.chunks(ACCOUNTS_CHUNK) {
+            match client.get_multiple_accounts_with_commitment(chunk, network.read_commitment()) {
+                Ok(response) => {
+                    for (pubkey, account) in chunk.iter().zip(response.value) {
+                        if let Some(usage) = self.accounts.get_mut(pubkey) {
+                            usage.exists = Some(account.is_some());
+                        }
+                    }
+                }
+                Err(error) => {
+                    eprintln!("Could not check accounts on the target: {error}");
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn print(&self) {
+        say!(
+            "{} templates, {} accounts",
+            self.templates,
+            self.accounts.len()
+        );
+        say!(
+            "{:<44}  {:>9}  {:>5}  {:>8}  {:>6}  {:>5}",
+            "account",
+            "templates",
+            "uses",
+            "writable",
+            "signer",
+            "local"
+        );
+        for (pubkey, usage) in self.sorted() {
+            let local = match usage.exists {
+                Some(true) => success(format!("{:>5}", "yes")),
+                Some(false) => failure(format!("{:>5}", "no")),
+                None => format!("{:>5}", "?"),
+            };
+            let program = if usage.program { " (program)" } else { "" };
+            say!(
+                "{:<44}  {:>9}  {:>5}  {:>8}  {:>6}  {}{program}",
+                pubkey.to_string(),
+                usage.templates.len(),
+                usage.uses,
+                usage.writable,
+                usage.signer,
+                local
+            );
+        }
+        if self.unresolved > 0 {
+            say!(
+                "{} account references use params and are not included",
+                self.unresolved
+            );
+        }
+        if self.skipped > 0 {
+            say!("{} files are not templates and were skipped", self.skipped);
+        }
+        let candidates = self.alt_candidates();
+        if !candidates.is_empty() {
+            say!(
+                "Suggested lookup table ({} non-signer accounts shared by several templates):",
+                candidates.len()
+            );
+            for pubkey in candidates {
+                say!("  {pubkey}");
+            }
+        }
+    }
+}
+
+/// Writes the suggested lookup table as the accounts JSON read by `create-lookup-table`.
+pub fn emit_alt(report: &AccountsReport, path: &Path) -> Result<usize> {
+    let candidates: Vec<String> = report
+        .alt_candidates()
+        .iter()
+        .map(Pubkey::to_string)
+        .collect();
+    fs::write(path, serde_json::to_string_pretty(&candidates)?)
+        .with_context(|| format!("failed to write {path:?}"))?;
+    Ok(candidates.len())
+}
+
+/// Scans every `.json` template under `dir` and reports the accounts they
+/// reference, whether those exist on the target and which would be worth
+/// putting in a lookup table.
+pub fn accounts_report(network: &NetworkCtx, dir: impl AsRef<Path>) -> Result<AccountsReport> {
+    let mut report = AccountsReport::default();
+    for path in list_files(dir.as_ref())? {
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        match load_raw_tx_from_json(&path) {
+            Ok(tx) => report.add(&path, &tx),
+            Err(_) => report.skipped += 1,
+        }
+    }
+    report.check_target(network);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::AccountsReport;
+    use crate::accounts::SYSTEM_PROGRAM_ID;
+    use crate::tx_format::RawTransaction;
+
+    #[test]
+    fn counts_usage_and_suggests_shared_accounts() {
+        let (payer, pool, other) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let template = |extra: &Pubkey| -> RawTransaction {
+            serde_json::from_value(json!({
+                "instructions": [
+                    { "program_id": "transfer", "from": payer.to_string(), "to": "$1", "amount": 1 },
+                    {
+                        "program_id": SYSTEM_PROGRAM_ID.to_string(),
+                        "accounts": [
+                            { "pubkey": pool.to_string(), "is_writable": true },
+                            { "pubkey": extra.to_string() }
+                        ]
+                    }
+                ],
+                "signers": ["$2"]
+            }))
+            .unwrap()
+        };
+        let mut report = AccountsReport::default();
+        report.add(Path::new("a.json"), &template(&other));
+        report.add(Path::new("b.json"), &template(&Pubkey::new_unique()));
+
+        assert_eq!(report.templates, 2);
+        assert_eq!(report.unresolved, 2);
+        assert_eq!(report.accounts[&pool].writable, 2);
+        assert_eq!(report.accounts[&payer].signer, 2);
+        assert_eq!(report.accounts[&other].templates.len(), 1);
+        assert_eq!(report.alt_candidates(), [pool]);
+    }
+}
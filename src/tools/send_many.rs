@@ -0,0 +1,185 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signer::Signer,
+    transaction::Transaction,
+};
+
+use crate::accounts::SYSTEM_PROGRAM_ID;
+use crate::network::NetworkCtx;
+use crate::tools::json_output::push;
+use crate::tools::merge::MAX_TX_BYTES;
+use crate::tools::style::{failure, success};
+use crate::tools::transfer_checks::{SYSTEM_TRANSFER, Transfer, TransferChecks, check_transfers};
+use crate::tx_format::json_tx::parse_keypair;
+use crate::utils::format_amount;
+
+/// Shortvec length and signature of the single signer
+const SIGNATURE_BYTES: usize = 1 + 64;
+
+/// One entry of a recipients file: `{ "pubkey": ..., "amount": <lamports> }`.
+#[derive(Debug, Deserialize)]
+struct RecipientEntry {
+    pubkey: String,
+    amount: Value,
+}
+
+/// Reads `[{ "pubkey": ..., "amount": <lamports> }]`; amounts may be numbers
+/// or strings with `_` separators.
+pub fn load_recipients(path: &Path) -> Result<Vec<(Pubkey, u64)>> {
+    let data = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+    let entries: Vec<RecipientEntry> =
+        serde_json::from_str(&data).with_context(|| format!("invalid JSON in {path:?}"))?;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let pubkey = entry
+                .pubkey
+                .parse()
+                .map_err(|_| anyhow!("Invalid recipient: {}", entry.pubkey))?;
+            let amount = match &entry.amount {
+                Value::Number(number) => number.as_u64(),
+                Value::String(text) => text.replace('_', "").parse().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| anyhow!("Invalid amount for {}: {}", entry.pubkey, entry.amount))?;
+            Ok((pubkey, amount))
+        })
+        .collect()
+}
+
+fn transfer_instruction(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+    let mut data = SYSTEM_TRANSFER.to_le_bytes().to_vec();
+    data.extend(lamports.to_le_bytes());
+    Instruction::new_with_bytes(
+        SYSTEM_PROGRAM_ID,
+        &data,
+        vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+    )
+}
+
+fn transfers_size(from: &Pubkey, recipients: &[(Pubkey, u64)]) -> usize {
+    let instructions: Vec<Instruction> = recipients
+        .iter()
+        .map(|(to, lamports)| transfer_instruction(from, to, *lamports))
+        .collect();
+    SIGNATURE_BYTES + Message::new(&instructions, Some(from)).serialize().len()
+}
+
+/// Splits `recipients` into batches of as many transfers as fit into one
+/// transaction signed by `from`.
+pub fn pack_transfers(from: &Pubkey, recipients: &[(Pubkey, u64)]) -> Vec<Vec<(Pubkey, u64)>> {
+    let mut batches: Vec<Vec<(Pubkey, u64)>> = Vec::new();
+    let mut batch = Vec::new();
+    for recipient in recipients {
+        batch.push(*recipient);
+        if batch.len() > 1 && transfers_size(from, &batch) > MAX_TX_BYTES {
+            batch.pop();
+            batches.push(std::mem::take(&mut batch));
+            batch.push(*recipient);
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+/// Sends SOL from `signer` to every recipient of `recipients_path`, packing
+/// the transfers into as few transactions as possible. Batches that fail are
+/// reported and the remaining ones still sent.
+pub fn send_sol_many(
+    network: &NetworkCtx,
+    recipients_path: &Path,
+    signer: &str,
+    checks: TransferChecks,
+) -> Result<()> {
+    let client = network.target_client();
+    let keypair = parse_keypair(&Value::String(signer.to_string()), &[])?;
+    let from = keypair.pubkey();
+    let recipients = load_recipients(recipients_path)?;
+    let transfers: Vec<Transfer> = recipients
+        .iter()
+        .map(|(to, lamports)| Transfer {
+            from,
+            to: *to,
+            lamports: *lamports,
+        })
+        .collect();
+    check_transfers(&client, &transfers, &[from], checks)?;
+
+    let batches = pack_transfers(&from, &recipients);
+    let mut failed = 0;
+    for (index, batch) in batches.iter().enumerate() {
+        let instructions: Vec<Instruction> = batch
+            .iter()
+            .map(|(to, lamports)| transfer_instruction(&from, to, *lamports))
+            .collect();
+        let blockhash = client.get_latest_blockhash()?;
+        let tx =
+            Transaction::new_signed_with_payer(&instructions, Some(&from), &[&keypair], blockhash);
+        let result = client.send_and_confirm_transaction(&tx);
+        let label = format!("Batch {}/{}", index + 1, batches.len());
+        match &result {
+            Ok(sig) => say!("{}", success(format!("{label} sent: {sig}"))),
+            Err(err) => {
+                failed += batch.len();
+                say!("{}", failure(format!("{label} failed: {err}")));
+            }
+        }
+        for (to, lamports) in batch {
+            let signature = result.as_ref().ok().map(ToString::to_string);
+            say!(
+                "  {to}: {} lamports {}",
+                format_amount(*lamports),
+                signature.as_deref().unwrap_or("failed")
+            );
+            push(
+                "transfers",
+                json!({ "recipient": to.to_string(), "lamports": lamports, "signature": signature }),
+            );
+        }
+    }
+
+    say!(
+        "Sent to {} of {} recipients in {} transactions",
+        recipients.len() - failed,
+        recipients.len(),
+        batches.len()
+    );
+    if failed > 0 {
+        return Err(anyhow!("{failed} transfers failed"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::{pack_transfers, transfers_size};
+    use crate::tools::merge::MAX_TX_BYTES;
+
+    #[test]
+    fn packs_transfers_into_full_transactions() {
+        let from = Pubkey::new_unique();
+        let recipients: Vec<(Pubkey, u64)> = (0..50).map(|i| (Pubkey::new_unique(), i)).collect();
+        let batches = pack_transfers(&from, &recipients);
+
+        assert!(batches.len() > 1);
+        assert_eq!(batches.concat(), recipients);
+        for batch in &batches {
+            assert!(transfers_size(&from, batch) <= MAX_TX_BYTES);
+        }
+        let first = &batches[0];
+        let mut overfull = first.clone();
+        overfull.push(batches[1][0]);
+        assert!(transfers_size(&from, &overfull) > MAX_TX_BYTES);
+    }
+}
@@ -0,0 +1,192 @@
+use std::str::FromStr;
+
+use anyhow::{Result, anyhow};
+use solana_rpc_client::api::config::{RpcAccountInfoConfig, UiAccountEncoding};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::network::NetworkCtx;
+use crate::tools::json_output::record;
+use crate::tools::parse::{DetailLevel, fetch_block, fetch_transaction};
+use crate::tools::style::{failure, success};
+use crate::tx_format::parse_tx::parse_tx_to_json;
+use crate::utils::{format_amount, format_ui_amount};
+
+/// Decimals of SOL amounts
+const SOL_DECIMALS: u32 = 9;
+
+/// What the argument of `show` refers to.
+#[derive(Debug, PartialEq)]
+pub enum ShowTarget {
+    Account(Pubkey),
+    Transaction(Signature),
+    Slot(u64),
+}
+
+/// Slots are numbers, pubkeys decode to 32 bytes and signatures to 64.
+pub fn detect(id: &str) -> Result<ShowTarget> {
+    if let Ok(slot) = id.parse() {
+        Ok(ShowTarget::Slot(slot))
+    } else if let Ok(pubkey) = Pubkey::from_str(id) {
+        Ok(ShowTarget::Account(pubkey))
+    } else if let Ok(signature) = Signature::from_str(id) {
+        Ok(ShowTarget::Transaction(signature))
+    } else {
+        Err(anyhow!(
+            "`{id}` is neither a pubkey, a transaction signature nor a slot"
+        ))
+    }
+}
+
+/// Prints a summary of the account, transaction or block `id` refers to on
+/// the source cluster.
+pub fn show(network: &NetworkCtx, id: &str) -> Result<()> {
+    let target = detect(id)?;
+    record("id", id);
+    match target {
+        ShowTarget::Account(pubkey) => show_account(network, &pubkey),
+        ShowTarget::Transaction(signature) => show_transaction(network, &signature),
+        ShowTarget::Slot(slot) => show_block(network, slot),
+    }
+}
+
+fn show_account(network: &NetworkCtx, pubkey: &Pubkey) -> Result<()> {
+    record("kind", "account");
+    let account = network
+        .source_client()
+        .get_ui_account_with_config(
+            pubkey,
+            RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::JsonParsed),
+                commitment: Some(network.read_commitment()),
+                ..Default::default()
+            },
+        )?
+        .value
+        .ok_or_else(|| anyhow!("Account {pubkey} not found on {}", network.source))?;
+    let data = serde_json::to_value(&account.data)?;
+    say!("Account {pubkey}");
+    say!(
+        "  Balance: {} lamports ({} SOL)",
+        format_amount(account.lamports),
+        format_ui_amount(account.lamports, SOL_DECIMALS)
+    );
+    say!("  Owner: {}", account.owner);
+    say!("  Executable: {}", account.executable);
+    if let Some(space) = account.space {
+        say!("  Data: {space} bytes");
+    }
+    record("lamports", account.lamports);
+    record("owner", &account.owner);
+    record("executable", account.executable);
+    record("space", account.space);
+    if data.get("parsed").is_some() {
+        say!(
+            "  Parsed ({}):",
+            data["program"].as_str().unwrap_or("unknown")
+        );
+        for line in serde_json::to_string_pretty(&data["parsed"])?.lines() {
+            say!("    {line}");
+        }
+        record("parsed", &data["parsed"]);
+    }
+    Ok(())
+}
+
+fn show_transaction(network: &NetworkCtx, signature: &Signature) -> Result<()> {
+    record("kind", "transaction");
+    let tx = fetch_transaction(network, &signature.to_string())?;
+    say!("Transaction {signature}");
+    say!("  Slot: {}", tx.slot);
+    record("slot", tx.slot);
+    if let Some(time) = tx.block_time {
+        say!("  Block time: {time}");
+        record("block_time", time);
+    }
+    if let Some(meta) = &tx.transaction.meta {
+        match &meta.err {
+            None => say!("  Status: {}", success("success")),
+            Some(err) => say!("  Status: {}", failure(format!("failed: {err}"))),
+        }
+        say!("  Fee: {} lamports", format_amount(meta.fee));
+        let compute_units: Option<u64> = meta.compute_units_consumed.clone().into();
+        if let Some(units) = compute_units {
+            say!("  CUs: {units}");
+        }
+        record("success", meta.err.is_none());
+        record("fee", meta.fee);
+        record("compute_units", compute_units);
+        let logs: Vec<String> = Option::from(meta.log_messages.clone()).unwrap_or_default();
+        if !logs.is_empty() {
+            say!("  Logs:");
+            for log in &logs {
+                say!("    {log}");
+            }
+        }
+    }
+    let parsed = parse_tx_to_json(&tx)?;
+    let programs: Vec<&str> = parsed["instructions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|ix| ix["program_id"].as_str())
+        .collect();
+    say!("  Instructions: {}", programs.join(", "));
+    say!("Save it as a template with `soltnet parse-tx {signature}`");
+    record("template", &parsed);
+    Ok(())
+}
+
+fn show_block(network: &NetworkCtx, slot: u64) -> Result<()> {
+    record("kind", "block");
+    let (slot, block) = fetch_block(network, &slot.to_string(), DetailLevel::Accounts)?;
+    let transactions = block.transactions.unwrap_or_default();
+    let failed = transactions
+        .iter()
+        .filter(|tx| tx.meta.as_ref().is_some_and(|meta| meta.err.is_some()))
+        .count();
+    let fees: u64 = transactions
+        .iter()
+        .filter_map(|tx| tx.meta.as_ref())
+        .map(|meta| meta.fee)
+        .sum();
+    say!("Block {slot}");
+    say!("  Blockhash: {}", block.blockhash);
+    say!("  Parent slot: {}", block.parent_slot);
+    if let Some(time) = block.block_time {
+        say!("  Block time: {time}");
+    }
+    say!("  Transactions: {} ({failed} failed)", transactions.len());
+    say!("  Fees: {} lamports", format_amount(fees));
+    say!("Parse its transactions with `soltnet parse-block {slot}`");
+    record("slot", slot);
+    record("blockhash", &block.blockhash);
+    record("parent_slot", block.parent_slot);
+    record("block_time", block.block_time);
+    record("transaction_count", transactions.len());
+    record("failed", failed);
+    record("fees", fees);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+    use super::{ShowTarget, detect};
+
+    #[test]
+    fn detects_slots_pubkeys_and_signatures() {
+        let pubkey = Pubkey::new_unique();
+        let signature = Signature::from([7; 64]);
+        assert_eq!(detect("250000000").unwrap(), ShowTarget::Slot(250_000_000));
+        assert_eq!(
+            detect(&pubkey.to_string()).unwrap(),
+            ShowTarget::Account(pubkey)
+        );
+        assert_eq!(
+            detect(&signature.to_string()).unwrap(),
+            ShowTarget::Transaction(signature)
+        );
+        assert!(detect("not-an-id").is_err());
+    }
+}
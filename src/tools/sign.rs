@@ -0,0 +1,418 @@
+use std::{fs, path::Path, str::FromStr};
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::{api::config::RpcSendTransactionConfig, rpc_client::RpcClient};
+use solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::{Signer, keypair::Keypair},
+    transaction::VersionedTransaction,
+};
+
+use crate::accounts::{SYSTEM_PROGRAM_ID, sysvar_id};
+use crate::network::NetworkCtx;
+use crate::tools::json_output::record;
+use crate::tools::style::success;
+use crate::tools::tx::{compile_message, fetch_lookup_tables};
+use crate::tx_format::json_tx::{ParsedTransaction, parse_keypair};
+
+fn message_bytes(message: &str) -> Result<Vec<u8>> {
+    if let Some(hex) = message.strip_prefix("0x") {
+        hex::decode(hex).map_err(|_| anyhow!("Invalid hex message"))
+    } else {
+        Ok(message.as_bytes().to_vec())
+    }
+}
+
+pub fn sign_message(message: &str, signer: &str) -> Result<()> {
+    let keypair = parse_keypair(&serde_json::Value::String(signer.to_string()), &[])?;
+    let signature = keypair.sign_message(&message_bytes(message)?);
+    say!("Signer: {}", keypair.pubkey());
+    say!("Signature: {signature}");
+    record("signer", keypair.pubkey().to_string());
+    record("signature", signature.to_string());
+    Ok(())
+}
+
+pub fn verify_signature(pubkey: &str, message: &str, signature: &str) -> Result<()> {
+    let pubkey = Pubkey::from_str(pubkey).map_err(|_| anyhow!("Invalid pubkey: {pubkey}"))?;
+    let signature =
+        Signature::from_str(signature).map_err(|_| anyhow!("Invalid signature: {signature}"))?;
+    if signature.verify(pubkey.as_ref(), &message_bytes(message)?) {
+        say!("Signature is valid for {pubkey}");
+        record("valid", true);
+        Ok(())
+    } else {
+        Err(anyhow!("Signature verification failed for {pubkey}"))
+    }
+}
+
+fn required_signers(message: &VersionedMessage) -> &[Pubkey] {
+    &message.static_account_keys()[..message.header().num_required_signatures as usize]
+}
+
+/// Required signers of `tx` whose signature is still missing.
+pub fn missing_signers(tx: &VersionedTransaction) -> Vec<Pubkey> {
+    required_signers(&tx.message)
+        .iter()
+        .zip(&tx.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(pubkey, _)| *pubkey)
+        .collect()
+}
+
+/// Signs `message` with `signers`, leaving the signatures of the other
+/// required signers empty.
+pub fn partially_sign(
+    message: VersionedMessage,
+    signers: &[Keypair],
+) -> Result<VersionedTransaction> {
+    let required = required_signers(&message);
+    let bytes = message.serialize();
+    let mut signatures = vec![Signature::default(); required.len()];
+    for signer in signers {
+        let pubkey = signer.pubkey();
+        let index = required
+            .iter()
+            .position(|key| *key == pubkey)
+            .ok_or_else(|| anyhow!("Signer {pubkey} is not required by the transaction message"))?;
+        signatures[index] = signer.sign_message(&bytes);
+    }
+    Ok(VersionedTransaction {
+        signatures,
+        message,
+    })
+}
+
+/// Copies the signatures `tx` is missing from `other`, a copy of the same
+/// transaction signed by someone else.
+pub fn merge_signatures(tx: &mut VersionedTransaction, other: &VersionedTransaction) -> Result<()> {
+    if tx.message.serialize() != other.message.serialize() {
+        return Err(anyhow!(
+            "Transactions have different messages; sign the same sign-tx output"
+        ));
+    }
+    for (signature, theirs) in tx.signatures.iter_mut().zip(&other.signatures) {
+        if *signature == Signature::default() {
+            *signature = *theirs;
+        }
+    }
+    Ok(())
+}
+
+/// Adds the signature an external signer made over the transaction message.
+pub fn add_signature(
+    tx: &mut VersionedTransaction,
+    pubkey: &Pubkey,
+    signature: Signature,
+) -> Result<()> {
+    let index = required_signers(&tx.message)
+        .iter()
+        .position(|key| key == pubkey)
+        .ok_or_else(|| anyhow!("{pubkey} is not a signer of the transaction"))?;
+    if !signature.verify(pubkey.as_ref(), &tx.message.serialize()) {
+        return Err(anyhow!(
+            "Signature of {pubkey} does not match the transaction message"
+        ));
+    }
+    tx.signatures[index] = signature;
+    Ok(())
+}
+
+/// Prints a (partially) signed transaction in base64 with the signers it
+/// still needs and, with `output`, writes it to that file.
+fn export_transaction(tx: &VersionedTransaction, output: Option<&Path>) -> Result<()> {
+    let encoded = STANDARD.encode(bincode::serialize(tx)?);
+    let missing: Vec<String> = missing_signers(tx)
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    say!("{encoded}");
+    if missing.is_empty() {
+        say!("All {} signatures present", tx.signatures.len());
+    } else {
+        say!("Missing signatures: {}", missing.join(", "));
+    }
+    record("transaction", &encoded);
+    record("missing_signers", &missing);
+    if let Some(path) = output {
+        fs::write(path, encoded + "\n").with_context(|| format!("failed to write {path:?}"))?;
+        say!("Transaction written to {}", path.display());
+        record("file", path.display().to_string());
+    }
+    Ok(())
+}
+
+/// System instruction index of `AdvanceNonceAccount`
+const ADVANCE_NONCE_ACCOUNT: u32 = 4;
+/// Size of the nonce account state (versions, state, authority, nonce, fee calculator)
+const NONCE_ACCOUNT_SIZE: usize = 80;
+
+/// The `AdvanceNonceAccount` instruction a durable nonce transaction starts with.
+fn advance_nonce_instruction(nonce: &Pubkey, authority: &Pubkey) -> Instruction {
+    let recent_blockhashes = sysvar_id("recent_blockhashes").expect("known sysvar");
+    Instruction::new_with_bytes(
+        SYSTEM_PROGRAM_ID,
+        &ADVANCE_NONCE_ACCOUNT.to_le_bytes(),
+        vec![
+            AccountMeta::new(*nonce, false),
+            AccountMeta::new_readonly(recent_blockhashes, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+    )
+}
+
+/// Authority and stored nonce of an initialized nonce account.
+fn nonce_state(data: &[u8]) -> Result<(Pubkey, Hash)> {
+    if data.len() != NONCE_ACCOUNT_SIZE {
+        return Err(anyhow!("Not a nonce account"));
+    }
+    // `Versions` and `State` tags; state 1 is initialized
+    if data[4..8] != 1u32.to_le_bytes() {
+        return Err(anyhow!("Nonce account is not initialized"));
+    }
+    let authority = Pubkey::try_from(&data[8..40]).expect("32 bytes");
+    let nonce = Hash::new_from_array(data[40..72].try_into().expect("32 bytes"));
+    Ok((authority, nonce))
+}
+
+/// Reads the nonce stored in `nonce`, checking it is advanced by `authority`.
+fn fetch_nonce(client: &RpcClient, nonce: &Pubkey, authority: &Pubkey) -> Result<Hash> {
+    let account = client
+        .get_account_with_commitment(nonce, CommitmentConfig::confirmed())?
+        .value
+        .ok_or_else(|| anyhow!("Nonce account {nonce} not found"))?;
+    if account.owner != SYSTEM_PROGRAM_ID {
+        return Err(anyhow!("{nonce} is not a nonce account"));
+    }
+    let (stored_authority, hash) =
+        nonce_state(&account.data).with_context(|| format!("Invalid nonce account {nonce}"))?;
+    if stored_authority != *authority {
+        return Err(anyhow!(
+            "Nonce account {nonce} is advanced by {stored_authority}, not {authority}"
+        ));
+    }
+    Ok(hash)
+}
+
+/// Compiles a template with a fresh (or the given) blockhash, signs it with
+/// the keypairs it holds and exports it for the remaining signers. With a
+/// durable `nonce` account and its authority, the transaction starts with
+/// `AdvanceNonceAccount` and uses the stored nonce as its blockhash, so it
+/// does not expire.
+pub fn sign_transaction(
+    network: &NetworkCtx,
+    mut json_tx: ParsedTransaction,
+    payer: Option<Pubkey>,
+    blockhash: Option<Hash>,
+    nonce: Option<(Pubkey, Option<Pubkey>)>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let payer = match payer {
+        Some(payer) => payer,
+        None => json_tx
+            .signers
+            .first()
+            .ok_or_else(|| anyhow!("Missing transaction signer; pass the fee payer with --payer"))?
+            .pubkey(),
+    };
+    let client = network.target_client();
+    let lookup_accounts = fetch_lookup_tables(&client, &json_tx.lookup_tables)?;
+    let blockhash = match (nonce, blockhash) {
+        (Some((nonce, authority)), blockhash) => {
+            let authority = authority.unwrap_or(payer);
+            let advance = advance_nonce_instruction(&nonce, &authority);
+            json_tx.instructions.insert(0, advance);
+            record("nonce_account", nonce.to_string());
+            match blockhash {
+                Some(blockhash) => blockhash,
+                None => fetch_nonce(&client, &nonce, &authority)?,
+            }
+        }
+        (None, Some(blockhash)) => blockhash,
+        (None, None) => {
+            client
+                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?
+                .0
+        }
+    };
+    let message = compile_message(&json_tx, &payer, &lookup_accounts, blockhash)?;
+    let tx = partially_sign(message, &json_tx.signers)?;
+    record("blockhash", blockhash.to_string());
+    export_transaction(&tx, output)
+}
+
+/// An input of `combine-sigs`: the contents of the file at `input`, or
+/// `input` itself.
+fn read_input_arg(input: &str) -> Result<String> {
+    if Path::new(input).is_file() {
+        let text =
+            fs::read_to_string(input).with_context(|| format!("Error reading file {input:?}"))?;
+        return Ok(text.trim().to_string());
+    }
+    Ok(input.trim().to_string())
+}
+
+/// A `<pubkey>=<signature>` input; base64 transactions may also contain `=`.
+fn signature_pair(input: &str) -> Option<(Pubkey, Signature)> {
+    let (pubkey, signature) = input.split_once('=')?;
+    Some((
+        Pubkey::from_str(pubkey).ok()?,
+        Signature::from_str(signature).ok()?,
+    ))
+}
+
+/// Merges partially signed copies of a transaction and `<pubkey>=<signature>`
+/// pairs, then sends it with the `send` config (confirmed at
+/// `confirm_commitment`, confirmed by default) or exports it for the next signer.
+pub fn combine_signatures(
+    network: &NetworkCtx,
+    inputs: &[String],
+    send: Option<RpcSendTransactionConfig>,
+    confirm_commitment: Option<CommitmentConfig>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let mut combined: Option<VersionedTransaction> = None;
+    let mut pairs = Vec::new();
+    for input in inputs {
+        let text = read_input_arg(input)?;
+        if let Some(pair) = signature_pair(&text) {
+            pairs.push(pair);
+            continue;
+        }
+        let bytes = STANDARD.decode(&text).map_err(|_| {
+            anyhow!("`{input}` is neither a base64 transaction nor <pubkey>=<signature>")
+        })?;
+        let tx: VersionedTransaction = bincode::deserialize(&bytes)
+            .with_context(|| format!("Invalid transaction in `{input}`"))?;
+        match &mut combined {
+            Some(combined) => merge_signatures(combined, &tx)?,
+            None => combined = Some(tx),
+        }
+    }
+    let mut tx = combined.ok_or_else(|| anyhow!("No partially signed transaction given"))?;
+    for (pubkey, signature) in pairs {
+        add_signature(&mut tx, &pubkey, signature)?;
+    }
+    let signers = required_signers(&tx.message).to_vec();
+    for ((pubkey, signature), valid) in signers
+        .iter()
+        .zip(&tx.signatures)
+        .zip(tx.verify_with_results())
+    {
+        if *signature != Signature::default() && !valid {
+            return Err(anyhow!("Invalid signature of {pubkey}"));
+        }
+    }
+
+    let Some(send_config) = send else {
+        return export_transaction(&tx, output);
+    };
+    let missing = missing_signers(&tx);
+    if !missing.is_empty() {
+        let missing: Vec<String> = missing.iter().map(ToString::to_string).collect();
+        return Err(anyhow!("Missing signatures: {}", missing.join(", ")));
+    }
+    let client = network.target_client();
+    let send_config = RpcSendTransactionConfig {
+        preflight_commitment: send_config
+            .preflight_commitment
+            .or(Some(client.commitment().commitment)),
+        ..send_config
+    };
+    let signature = client.send_transaction_with_config(&tx, send_config)?;
+    client.poll_for_signature_with_commitment(
+        &signature,
+        confirm_commitment.unwrap_or_else(CommitmentConfig::confirmed),
+    )?;
+    say!("{}", success(format!("Transaction sent: {signature}")));
+    record("signature", signature.to_string());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::{Message, VersionedMessage},
+        pubkey::Pubkey,
+        signer::{Signer, keypair::Keypair},
+    };
+
+    use super::{
+        add_signature, advance_nonce_instruction, merge_signatures, missing_signers, nonce_state,
+        partially_sign,
+    };
+    use crate::accounts::{SYSTEM_PROGRAM_ID, sysvar_id};
+
+    #[test]
+    fn combines_partial_signatures() {
+        let payer = Keypair::new();
+        let owner = Keypair::new();
+        let multisig = Keypair::new();
+        let ix = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1],
+            vec![
+                AccountMeta::new(owner.pubkey(), true),
+                AccountMeta::new_readonly(multisig.pubkey(), true),
+            ],
+        );
+        let message = VersionedMessage::Legacy(Message::new_with_blockhash(
+            &[ix],
+            Some(&payer.pubkey()),
+            &Hash::new_unique(),
+        ));
+
+        let mut tx = partially_sign(message.clone(), &[payer.insecure_clone()]).unwrap();
+        assert_eq!(missing_signers(&tx), [owner.pubkey(), multisig.pubkey()]);
+        let theirs = partially_sign(message.clone(), &[owner.insecure_clone()]).unwrap();
+        merge_signatures(&mut tx, &theirs).unwrap();
+        assert_eq!(missing_signers(&tx), [multisig.pubkey()]);
+
+        let forged = payer.sign_message(&message.serialize());
+        assert!(add_signature(&mut tx, &multisig.pubkey(), forged).is_err());
+        let signature = multisig.sign_message(&message.serialize());
+        add_signature(&mut tx, &multisig.pubkey(), signature).unwrap();
+        assert!(missing_signers(&tx).is_empty());
+        assert!(tx.verify_with_results().iter().all(|valid| *valid));
+
+        let other = VersionedMessage::Legacy(Message::new(&[], Some(&payer.pubkey())));
+        let other = partially_sign(other, &[]).unwrap();
+        assert!(merge_signatures(&mut tx, &other).is_err());
+        assert!(partially_sign(message, &[Keypair::new()]).is_err());
+    }
+
+    #[test]
+    fn reads_durable_nonces() {
+        let nonce = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let stored = Hash::new_unique();
+        let mut data = [1u32.to_le_bytes(), 1u32.to_le_bytes()].concat();
+        data.extend_from_slice(authority.as_ref());
+        data.extend_from_slice(stored.as_ref());
+        data.extend_from_slice(&5_000u64.to_le_bytes());
+        assert_eq!(nonce_state(&data).unwrap(), (authority, stored));
+        data[4] = 0;
+        assert!(nonce_state(&data).is_err());
+        assert!(nonce_state(&data[..72]).is_err());
+
+        let ix = advance_nonce_instruction(&nonce, &authority);
+        assert_eq!(ix.program_id, SYSTEM_PROGRAM_ID);
+        assert_eq!(ix.data, [4, 0, 0, 0]);
+        assert_eq!(
+            ix.accounts,
+            [
+                AccountMeta::new(nonce, false),
+                AccountMeta::new_readonly(sysvar_id("recent_blockhashes").unwrap(), false),
+                AccountMeta::new_readonly(authority, true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    fs,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+use solana_rpc_client::{api::config::RpcSimulateTransactionConfig, rpc_client::RpcClient};
+use solana_sdk::{
+    native_token::LAMPORTS_PER_SOL, pubkey::Pubkey, signer::Signer, transaction::TransactionError,
+};
+use tempfile::TempDir;
+
+use crate::accounts::SYSTEM_PROGRAM_ID;
+use crate::network::NetworkCtx;
+use crate::tools::compute::print_cu_breakdown;
+use crate::tools::dump::dump_accounts_for_tx;
+use crate::tools::errors::{ErrorMap, report_failure};
+use crate::tools::json_output::record;
+use crate::tools::output::{OutputOptions, Overwrite};
+use crate::tools::style::success;
+use crate::tools::tx::{compile_transaction, fetch_lookup_tables};
+use crate::tx_format::json_tx::ParsedTransaction;
+
+/// Balance of the payer written into a fork when the source has no account for it
+const FORK_PAYER_LAMPORTS: u64 = 1_000 * LAMPORTS_PER_SOL;
+const FORK_VALIDATOR: &str = "solana-test-validator";
+const FORK_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+const FORK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Account state injected into a simulation, in the format written by `dump`.
+#[derive(Debug)]
+pub struct AccountOverride {
+    pub pubkey: Pubkey,
+    pub path: PathBuf,
+}
+
+impl AccountOverride {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read_to_string(path).with_context(|| format!("failed to read {path:?}"))?;
+        let account: Value =
+            serde_json::from_str(&data).with_context(|| format!("invalid JSON in {path:?}"))?;
+        let pubkey = account["pubkey"]
+            .as_str()
+            .ok_or_else(|| anyhow!("{path:?} has no pubkey"))?
+            .parse()
+            .with_context(|| format!("invalid pubkey in {path:?}"))?;
+        if !account["account"].is_object() {
+            return Err(anyhow!("{path:?} is not a dumped account"));
+        }
+        Ok(Self {
+            pubkey,
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+/// Throwaway `solana-test-validator` on a temporary ledger and free ports,
+/// holding the template accounts with the overrides written over them. It
+/// never touches the local testnet and is killed when dropped.
+struct ForkValidator {
+    child: Child,
+    network: NetworkCtx,
+    _dir: TempDir,
+}
+
+impl ForkValidator {
+    fn start(
+        network: &NetworkCtx,
+        tx_json: &Path,
+        params: &[String],
+        payer: &Pubkey,
+        overrides: &[AccountOverride],
+    ) -> Result<Self> {
+        let dir = tempfile::tempdir().context("failed to create the fork directory")?;
+        let accounts = dir.path().join("accounts");
+        prepare_fork(network, tx_json, params, &accounts, payer, overrides)?;
+
+        let rpc_port = free_port_pair()?;
+        let mut command = Command::new(FORK_VALIDATOR);
+        command
+            .arg("--ledger")
+            .arg(dir.path().join("ledger"))
+            .args(["--reset", "--quiet", "--bind-address", "127.0.0.1"])
+            .args(["--rpc-port", &rpc_port.to_string()])
+            .args(["--faucet-port", &free_port()?.to_string()])
+            .args(["--gossip-port", &free_port()?.to_string()]);
+        for entry in fs::read_dir(&accounts)? {
+            let path = entry?.path();
+            let Some(address) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some("so") => command.arg("--bpf-program"),
+                Some("json") => command.arg("--account"),
+                _ => continue,
+            };
+            command.arg(address).arg(&path);
+        }
+        say!("Starting a throwaway validator for the overrides...");
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| {
+                format!("failed to run {FORK_VALIDATOR}; simulating overrides needs the Solana CLI")
+            })?;
+
+        let url = format!("http://127.0.0.1:{rpc_port}");
+        let mut fork = Self {
+            child,
+            network: NetworkCtx::new(&network.source, &url),
+            _dir: dir,
+        };
+        fork.wait_healthy()?;
+        Ok(fork)
+    }
+
+    fn wait_healthy(&mut self) -> Result<()> {
+        let started = Instant::now();
+        let client = self.client();
+        while client.get_health().is_err() {
+            if let Some(status) = self.child.try_wait()? {
+                return Err(anyhow!("{FORK_VALIDATOR} exited with {status}"));
+            }
+            if started.elapsed() > FORK_STARTUP_TIMEOUT {
+                return Err(anyhow!(
+                    "{FORK_VALIDATOR} did not become healthy within {}s",
+                    FORK_STARTUP_TIMEOUT.as_secs()
+                ));
+            }
+            thread::sleep(FORK_POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    fn client(&self) -> Arc<RpcClient> {
+        self.network.target_client()
+    }
+}
+
+impl Drop for ForkValidator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn free_port() -> Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// A free port whose successor is free too, as the validator serves
+/// WebSocket subscriptions on the port after the RPC one.
+fn free_port_pair() -> Result<u16> {
+    for _ in 0..32 {
+        let port = free_port()?;
+        if port < u16::MAX && TcpListener::bind(("127.0.0.1", port + 1)).is_ok() {
+            return Ok(port);
+        }
+    }
+    Err(anyhow!("No free port pair for the throwaway validator"))
+}
+
+/// Simulates a template on the target without sending it, printing logs and
+/// compute units. With overrides, the accounts of the template are dumped
+/// from the source, the overrides written over them and the simulation run
+/// on a throwaway validator holding the result, so what-if states (e.g. a
+/// different oracle price) can be tried against mainnet data without
+/// touching any chain or the local testnet.
+pub fn simulate_json_transaction(
+    network: &NetworkCtx,
+    tx_json: &Path,
+    params: &[String],
+    json_tx: ParsedTransaction,
+    payer: Option<Pubkey>,
+    overrides: &[AccountOverride],
+    error_map: &ErrorMap,
+) -> Result<()> {
+    let payer = match payer {
+        Some(payer) => payer,
+        None => json_tx
+            .signers
+            .first()
+            .ok_or_else(|| anyhow!("Missing transaction signer"))?
+            .pubkey(),
+    };
+    let fork = match overrides {
+        [] => None,
+        _ => Some(ForkValidator::start(
+            network, tx_json, params, &payer, overrides,
+        )?),
+    };
+    let client = match &fork {
+        Some(fork) => fork.client(),
+        None => network.target_client(),
+    };
+    let lookup_accounts = fetch_lookup_tables(&client, &json_tx.lookup_tables)?;
+    let blockhash = client.get_latest_blockhash()?;
+    let tx = compile_transaction(&json_tx, &payer, &lookup_accounts, blockhash)?;
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        commitment: Some(network.read_commitment()),
+        ..Default::default()
+    };
+    let result = client.simulate_transaction_with_config(&tx, config)?.value;
+    let logs = result.logs.unwrap_or_default();
+    if let Some(err) = result.err {
+        let tx_error = TransactionError::from(err);
+        report_failure(&tx_error, &logs, &json_tx.instructions, error_map);
+        return Err(anyhow!("Simulation failed: {tx_error}"));
+    }
+    for log in &logs {
+        say!("{log}");
+    }
+    print_cu_breakdown(&logs);
+    match result.units_consumed {
+        Some(units) => say!("Total CUs used: {units}"),
+        None => say!("Total CUs used: n/a"),
+    }
+    if let Some(return_data) = &result.return_data {
+        let (data, _) = &return_data.data;
+        say!("Return data from {}: {data}", return_data.program_id);
+        record(
+            "return_data",
+            json!({ "program_id": return_data.program_id, "data": data }),
+        );
+    }
+    say!("{}", success("Simulation succeeded"));
+    record("compute_units", result.units_consumed);
+    record("logs", &logs);
+    Ok(())
+}
+
+/// Dumps the template accounts into `fork` and writes the overrides over
+/// them, adding a funded payer when the source has none.
+fn prepare_fork(
+    network: &NetworkCtx,
+    tx_json: &Path,
+    params: &[String],
+    fork: &Path,
+    payer: &Pubkey,
+    overrides: &[AccountOverride],
+) -> Result<()> {
+    let output = OutputOptions {
+        overwrite: Overwrite::Force,
+        ..Default::default()
+    };
+    dump_accounts_for_tx(network, tx_json, fork, params, output)?.print();
+    for account in overrides {
+        let path = fork.join(format!("{}.json", account.pubkey));
+        fs::copy(&account.path, &path)
+            .with_context(|| format!("failed to copy {:?}", account.path))?;
+        say!("Overriding account {}", account.pubkey);
+    }
+    let payer_path = fork.join(format!("{payer}.json"));
+    if !payer_path.exists() {
+        fs::write(&payer_path, funded_account(payer).to_string())?;
+    }
+    Ok(())
+}
+
+fn funded_account(pubkey: &Pubkey) -> Value {
+    json!({
+        "pubkey": pubkey.to_string(),
+        "account": {
+            "lamports": FORK_PAYER_LAMPORTS,
+            "data": ["", "base64"],
+            "owner": SYSTEM_PROGRAM_ID.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": 0,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::{AccountOverride, funded_account};
+
+    #[test]
+    fn loads_overrides_in_dump_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let pubkey = Pubkey::new_unique();
+        let valid = dir.path().join("valid.json");
+        fs::write(&valid, funded_account(&pubkey).to_string()).unwrap();
+        let invalid = dir.path().join("invalid.json");
+        fs::write(
+            &invalid,
+            json!({ "pubkey": pubkey.to_string() }).to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(AccountOverride::load(&valid).unwrap().pubkey, pubkey);
+        assert!(AccountOverride::load(&invalid).is_err());
+    }
+}
@@ -0,0 +1,51 @@
+use std::{thread, time::Duration};
+
+use anyhow::Result;
+use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::epoch_info::EpochInfo;
+
+const SLOT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// First slot of the epoch after the current one.
+pub fn next_epoch_start(epoch_info: &EpochInfo) -> u64 {
+    epoch_info.absolute_slot - epoch_info.slot_index + epoch_info.slots_in_epoch
+}
+
+pub fn fetch_next_epoch_start(client: &RpcClient) -> Result<u64> {
+    let epoch_info = client.get_epoch_info_with_commitment(CommitmentConfig::processed())?;
+    Ok(next_epoch_start(&epoch_info))
+}
+
+/// Blocks until the processed slot reaches `slot`, returning the slot seen.
+pub fn wait_for_slot(client: &RpcClient, slot: u64) -> Result<u64> {
+    let mut current = client.get_slot_with_commitment(CommitmentConfig::processed())?;
+    if current < slot {
+        say!("Waiting for slot {slot} (current {current})...");
+    }
+    while current < slot {
+        thread::sleep(SLOT_POLL_INTERVAL);
+        current = client.get_slot_with_commitment(CommitmentConfig::processed())?;
+    }
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::epoch_info::EpochInfo;
+
+    use super::next_epoch_start;
+
+    #[test]
+    fn next_epoch_starts_after_the_current_one() {
+        let epoch_info = EpochInfo {
+            epoch: 3,
+            slot_index: 100,
+            slots_in_epoch: 432,
+            absolute_slot: 1_396,
+            block_height: 1_390,
+            transaction_count: None,
+        };
+        assert_eq!(next_epoch_start(&epoch_info), 1_728);
+    }
+}
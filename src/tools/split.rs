@@ -0,0 +1,201 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+
+use crate::accounts::COMPUTE_BUDGET_PROGRAM_ID;
+use crate::tools::fmt::to_canonical_value;
+use crate::tools::merge::estimate_size;
+use crate::tx_format::{
+    RawHooks, RawInstruction, RawTransaction,
+    json_tx::{expand_shorthand, load_raw_tx_from_json},
+};
+
+/// Instruction field listing the indices of earlier instructions that must
+/// land in the same transaction.
+pub const DEPENDS_ON: &str = "depends_on";
+
+/// Splits a template exceeding the account or size limits into the fewest
+/// ordered transactions, written as `{"transactions": [...]}`. Instructions
+/// joined by `depends_on` (and everything between them) stay together,
+/// compute budget instructions and signers are repeated in every transaction.
+pub fn split_template(input: impl AsRef<Path>, out: impl AsRef<Path>) -> Result<()> {
+    let (input, out) = (input.as_ref(), out.as_ref());
+    let tx = load_raw_tx_from_json(input)?;
+    let parts = split_transaction(&tx)?;
+    for (index, part) in parts.iter().enumerate() {
+        let size = estimate_size(part);
+        say!(
+            "Transaction {index}: {} instructions, {} accounts, ~{} bytes",
+            part.instructions.len(),
+            size.accounts,
+            size.bytes
+        );
+    }
+    let transactions = parts
+        .iter()
+        .map(to_canonical_value)
+        .collect::<Result<Vec<_>>>()?;
+    let text = serde_json::to_string_pretty(&json!({ "transactions": transactions }))?;
+    fs::write(out, text).with_context(|| format!("failed to write {out:?}"))?;
+    say!(
+        "Split {} into {} transactions in {}",
+        input.display(),
+        parts.len(),
+        out.display()
+    );
+    Ok(())
+}
+
+pub fn split_transaction(tx: &RawTransaction) -> Result<Vec<RawTransaction>> {
+    let (budget, units) = atomic_units(tx)?;
+    let part = |instructions: Vec<RawInstruction>| RawTransaction {
+        instructions: budget.iter().cloned().chain(instructions).collect(),
+        signers: tx.signers.clone(),
+        lookup_tables: tx.lookup_tables.clone(),
+        hooks: None,
+        version: None,
+        stamp: None,
+    };
+
+    let mut parts: Vec<RawTransaction> = Vec::new();
+    let mut current: Vec<RawInstruction> = Vec::new();
+    for (first, unit) in units {
+        let mut candidate = current.clone();
+        candidate.extend(unit.iter().cloned());
+        if estimate_size(&part(candidate.clone())).fits() {
+            current = candidate;
+            continue;
+        }
+        if !estimate_size(&part(unit.clone())).fits() {
+            return Err(anyhow!(
+                "Instructions #{first}..#{} do not fit in one transaction",
+                first + unit.len() - 1
+            ));
+        }
+        parts.push(part(std::mem::replace(&mut current, unit)));
+    }
+    if !current.is_empty() || parts.is_empty() {
+        parts.push(part(current));
+    }
+
+    if let Some(hooks) = &tx.hooks {
+        if let Some(first) = parts.first_mut() {
+            first.hooks = Some(RawHooks {
+                pre: hooks.pre.clone(),
+                post: Vec::new(),
+            });
+        }
+        if let Some(last) = parts.last_mut() {
+            last.hooks.get_or_insert_default().post = hooks.post.clone();
+        }
+    }
+    Ok(parts)
+}
+
+/// A run of instructions that must not be split, with the index of its first one.
+type Unit = (usize, Vec<RawInstruction>);
+
+/// Compute budget instructions, and the other instructions grouped into units.
+fn atomic_units(tx: &RawTransaction) -> Result<(Vec<RawInstruction>, Vec<Unit>)> {
+    let mut budget = Vec::new();
+    // Start of the run each instruction belongs to
+    let mut run_start: Vec<usize> = Vec::with_capacity(tx.instructions.len());
+    for (index, ix) in tx.instructions.iter().enumerate() {
+        let mut start = index;
+        for dependency in dependencies(ix)? {
+            if dependency >= index {
+                return Err(anyhow!(
+                    "Instruction #{index} depends on #{dependency}, which does not come before it"
+                ));
+            }
+            start = start.min(run_start[dependency]);
+        }
+        // Everything from the start of the run on joins it
+        for entry in &mut run_start[start..] {
+            *entry = (*entry).min(start);
+        }
+        run_start.push(start);
+    }
+
+    let mut units: Vec<Unit> = Vec::new();
+    for (index, ix) in tx.instructions.iter().enumerate() {
+        let mut ix = ix.clone();
+        ix.extra.remove(DEPENDS_ON);
+        if is_compute_budget(&ix)? && run_start[index] == index {
+            budget.push(ix);
+            continue;
+        }
+        match units.last_mut() {
+            Some((_, unit)) if run_start[index] < index => unit.push(ix),
+            _ => units.push((index, vec![ix])),
+        }
+    }
+    Ok((budget, units))
+}
+
+fn dependencies(ix: &RawInstruction) -> Result<Vec<usize>> {
+    let index = |value: &Value| {
+        value
+            .as_u64()
+            .map(|index| index as usize)
+            .ok_or_else(|| anyhow!("`{DEPENDS_ON}` must be an instruction index or a list of them"))
+    };
+    match ix.extra.get(DEPENDS_ON) {
+        None => Ok(Vec::new()),
+        Some(Value::Array(items)) => items.iter().map(index).collect(),
+        Some(value) => Ok(vec![index(value)?]),
+    }
+}
+
+fn is_compute_budget(ix: &RawInstruction) -> Result<bool> {
+    let explicit = expand_shorthand(ix)?;
+    let program_id = explicit
+        .as_ref()
+        .map_or(&ix.program_id, |ix| &ix.program_id);
+    Ok(*program_id == COMPUTE_BUDGET_PROGRAM_ID.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::split_transaction;
+    use crate::tx_format::RawTransaction;
+
+    #[test]
+    fn splits_at_dependency_boundaries() {
+        let memo = |index: usize| {
+            json!({
+                "program_id": "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr",
+                "data": format!("0x{}", "ab".repeat(400)),
+                "accounts": [{ "pubkey": format!("Account{index}1111111111111111111111111111111") }]
+            })
+        };
+        let mut dependent = memo(3);
+        dependent["depends_on"] = json!([2]);
+        let tx: RawTransaction = serde_json::from_value(json!({
+            "instructions": [
+                { "program_id": "set_cu_limit", "limit": 200000 },
+                memo(1),
+                memo(2),
+                dependent,
+                memo(4)
+            ],
+            "signers": ["$1"]
+        }))
+        .unwrap();
+
+        let parts = split_transaction(&tx).unwrap();
+        let lengths: Vec<usize> = parts.iter().map(|part| part.instructions.len()).collect();
+        assert_eq!(lengths, [2, 3, 2]);
+        for part in &parts {
+            assert_eq!(part.instructions[0].program_id, "set_cu_limit");
+            assert!(
+                part.instructions
+                    .iter()
+                    .all(|ix| !ix.extra.contains_key("depends_on"))
+            );
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::Result;
+use solana_rpc_client::api::{
+    config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, UiAccountEncoding, UiDataSliceConfig,
+    },
+    filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::accounts::STAKE_PROGRAM_ID;
+use crate::network::NetworkCtx;
+use crate::tools::dump::{DumpSummary, dump_accounts};
+use crate::tools::output::OutputOptions;
+
+/// Stake accounts start with the state tag and rent exempt reserve, then the
+/// staker and withdrawer authorities
+const STAKER_OFFSET: usize = 12;
+const WITHDRAWER_OFFSET: usize = 44;
+
+/// Authority a stake account is matched by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum StakeAuthority {
+    Staker,
+    Withdrawer,
+}
+
+impl StakeAuthority {
+    fn offset(self) -> usize {
+        match self {
+            StakeAuthority::Staker => STAKER_OFFSET,
+            StakeAuthority::Withdrawer => WITHDRAWER_OFFSET,
+        }
+    }
+}
+
+/// Memcmp filter matching stake accounts whose `role` is `authority`.
+fn authority_filter(authority: &Pubkey, role: StakeAuthority) -> RpcFilterType {
+    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+        role.offset(),
+        authority.as_ref(),
+    ))
+}
+
+/// Addresses of the stake accounts of `authority`, as staker and/or withdrawer
+/// (both when `role` is not given).
+pub fn find_stake_accounts(
+    network: &NetworkCtx,
+    authority: &Pubkey,
+    role: Option<StakeAuthority>,
+) -> Result<BTreeSet<String>> {
+    let roles = match role {
+        Some(role) => vec![role],
+        None => vec![StakeAuthority::Staker, StakeAuthority::Withdrawer],
+    };
+    let client = network.source_client();
+    let mut accounts = BTreeSet::new();
+    for role in roles {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![authority_filter(authority, role)]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                // Only the addresses are needed, the accounts are fetched by the dump
+                data_slice: Some(UiDataSliceConfig {
+                    offset: 0,
+                    length: 0,
+                }),
+                commitment: Some(network.read_commitment()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        let found = client.get_program_ui_accounts_with_config(&STAKE_PROGRAM_ID, config)?;
+        accounts.extend(found.into_iter().map(|(address, _)| address.to_string()));
+    }
+    Ok(accounts)
+}
+
+/// Dumps every stake account of `authority` to `to_path`.
+pub fn dump_stake_accounts(
+    network: &NetworkCtx,
+    authority: &Pubkey,
+    role: Option<StakeAuthority>,
+    to_path: impl AsRef<Path>,
+    output: OutputOptions,
+) -> Result<DumpSummary> {
+    let accounts = find_stake_accounts(network, authority, role)?;
+    say!("Found {} stake accounts of {authority}", accounts.len());
+    dump_accounts(
+        network,
+        accounts,
+        to_path,
+        &format!("stake-{authority}"),
+        output,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_rpc_client::api::filter::RpcFilterType;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::{StakeAuthority, authority_filter};
+
+    #[test]
+    fn filters_match_authority_offsets() {
+        let authority = Pubkey::new_unique();
+        let mut data = vec![0u8; 200];
+        data[44..76].copy_from_slice(authority.as_ref());
+
+        let RpcFilterType::Memcmp(withdrawer) =
+            authority_filter(&authority, StakeAuthority::Withdrawer)
+        else {
+            panic!("expected a memcmp filter");
+        };
+        assert!(withdrawer.bytes_match(&data));
+        let RpcFilterType::Memcmp(staker) = authority_filter(&authority, StakeAuthority::Staker)
+        else {
+            panic!("expected a memcmp filter");
+        };
+        assert!(!staker.bytes_match(&data));
+    }
+}
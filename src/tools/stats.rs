@@ -0,0 +1,202 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, anyhow};
+use serde_json::json;
+
+use crate::network::NetworkCtx;
+use crate::tools::json_output::record;
+use crate::tools::tx::{ExecOptions, ExecReport, execute_json_transaction};
+use crate::tx_format::json_tx::ParsedTransaction;
+use crate::utils::format_amount;
+
+/// Aggregated results of running the same template several times.
+#[derive(Debug, Default)]
+pub struct RunStats {
+    latencies: Vec<Duration>,
+    slots: Vec<u64>,
+    compute_units: Vec<u64>,
+    failures: usize,
+}
+
+fn percentile<T: Copy + Ord>(values: &[T], percent: usize) -> Option<T> {
+    let mut sorted = values.to_vec();
+    sorted.sort();
+    let last = sorted.len().checked_sub(1)?;
+    sorted.get(last * percent / 100).copied()
+}
+
+impl RunStats {
+    pub fn record(&mut self, report: &ExecReport) {
+        self.record_latency(report.latency);
+        self.slots.push(report.slot);
+        if let Some(units) = report.compute_units {
+            self.compute_units.push(units);
+        }
+    }
+
+    pub fn record_latency(&mut self, latency: Duration) {
+        self.latencies.push(latency);
+    }
+
+    pub fn record_failure(&mut self) {
+        self.failures += 1;
+    }
+
+    /// Fails when any run failed, after the stats have been reported.
+    pub fn check(&self) -> Result<()> {
+        if self.failures > 0 {
+            return Err(anyhow!(
+                "{} of {} runs failed",
+                self.failures,
+                self.latencies.len() + self.failures
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn max_compute_units(&self) -> Option<u64> {
+        self.compute_units.iter().max().copied()
+    }
+
+    pub fn print_latency(&self) {
+        if let (Some(min), Some(p50), Some(p90), Some(max)) = (
+            percentile(&self.latencies, 0),
+            percentile(&self.latencies, 50),
+            percentile(&self.latencies, 90),
+            percentile(&self.latencies, 100),
+        ) {
+            let avg = self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32;
+            say!("Latency: min {min:.2?} avg {avg:.2?} p50 {p50:.2?} p90 {p90:.2?} max {max:.2?}");
+        }
+    }
+
+    pub fn print(&self) {
+        let runs = self.latencies.len() + self.failures;
+        say!("Runs: {runs} ({} failed)", self.failures);
+        record(
+            "stats",
+            json!({
+                "runs": runs,
+                "failures": self.failures,
+                "latency_ms": self.latencies.iter().map(Duration::as_millis).collect::<Vec<_>>(),
+                "slots": self.slots,
+                "compute_units": self.compute_units,
+            }),
+        );
+        self.print_latency();
+
+        if let (Some(first), Some(last)) = (self.slots.iter().min(), self.slots.iter().max()) {
+            let mut distinct = self.slots.clone();
+            distinct.sort();
+            distinct.dedup();
+            say!(
+                "Slots: {first}..{last} (spread {}, {} distinct)",
+                last - first,
+                distinct.len()
+            );
+        }
+
+        if let (Some(min), Some(max)) = (
+            percentile(&self.compute_units, 0),
+            percentile(&self.compute_units, 100),
+        ) {
+            let avg = self.compute_units.iter().sum::<u64>() / self.compute_units.len() as u64;
+            say!(
+                "CUs: min {} avg {} max {}",
+                format_amount(min),
+                format_amount(avg),
+                format_amount(max)
+            );
+        }
+    }
+}
+
+/// Executes a freshly loaded template `repeat` times, each with a new blockhash.
+/// Failed runs are recorded when `repeat > 1`; check them with `RunStats::check`.
+pub fn run_repeated(
+    network: &NetworkCtx,
+    repeat: u64,
+    load: impl Fn() -> Result<ParsedTransaction>,
+    options: &mut ExecOptions,
+) -> Result<RunStats> {
+    let mut stats = RunStats::default();
+    for run in 1..=repeat {
+        match load().and_then(|parsed| execute_json_transaction(network, parsed, options)) {
+            Ok(report) => {
+                options.previous_blockhash = Some(report.blockhash);
+                stats.record(&report);
+            }
+            Err(err) if repeat > 1 => {
+                eprintln!("Run {run} failed: {err}");
+                stats.record_failure();
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(stats)
+}
+
+/// Runs between aggregate reports when a periodic run has no end.
+const PERIODIC_REPORT_RUNS: u64 = 10;
+
+/// Executes a freshly loaded template every `every`, `times` times or until
+/// interrupted, like a crank. Failed runs are recorded and the schedule goes
+/// on; runs start on a fixed grid so slow sends do not drift the period.
+pub fn run_periodic(
+    network: &NetworkCtx,
+    every: Duration,
+    times: Option<u64>,
+    load: impl Fn() -> Result<ParsedTransaction>,
+    options: &mut ExecOptions,
+) -> Result<RunStats> {
+    let mut stats = RunStats::default();
+    let started = Instant::now();
+    let mut run = 0;
+    while times.is_none_or(|times| run < times) {
+        let deadline = started + every * run as u32;
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        run += 1;
+        say!("Run {run}");
+        match load().and_then(|parsed| execute_json_transaction(network, parsed, options)) {
+            Ok(report) => {
+                options.previous_blockhash = Some(report.blockhash);
+                stats.record(&report);
+            }
+            Err(err) => {
+                eprintln!("Run {run} failed: {err}");
+                stats.record_failure();
+            }
+        }
+        if times.is_none() && run % PERIODIC_REPORT_RUNS == 0 {
+            stats.print();
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RunStats, percentile};
+
+    #[test]
+    fn percentile_picks_sorted_rank() {
+        let values = [5u64, 1, 4, 2, 3];
+        assert_eq!(percentile(&values, 0), Some(1));
+        assert_eq!(percentile(&values, 50), Some(3));
+        assert_eq!(percentile(&values, 100), Some(5));
+        assert_eq!(percentile::<u64>(&[], 50), None);
+    }
+
+    #[test]
+    fn check_fails_when_any_run_failed() {
+        let mut stats = RunStats::default();
+        stats.record_latency(std::time::Duration::from_millis(5));
+        assert!(stats.check().is_ok());
+        stats.record_failure();
+        let err = stats.check().expect_err("failed run");
+        assert_eq!(err.to_string(), "1 of 2 runs failed");
+    }
+}
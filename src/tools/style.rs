@@ -0,0 +1,81 @@
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anstyle::{AnsiColor, Style};
+
+/// Disables colors like `--no-color`, see <https://no-color.org>
+pub const NO_COLOR_ENV: &str = "NO_COLOR";
+
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Enables colors when stdout is a terminal, unless disabled by `--no-color`
+/// or `NO_COLOR`.
+pub fn init_color(no_color: bool) {
+    let enabled = !no_color
+        && env::var_os(NO_COLOR_ENV).is_none_or(|value| value.is_empty())
+        && io::stdout().is_terminal();
+    set_color(enabled);
+}
+
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
+}
+
+fn paint(color: AnsiColor, text: impl Display) -> String {
+    if !COLOR.load(Ordering::Relaxed) {
+        return text.to_string();
+    }
+    let style = Style::new().fg_color(Some(color.into()));
+    format!("{style}{text}{style:#}")
+}
+
+pub fn success(text: impl Display) -> String {
+    paint(AnsiColor::Green, text)
+}
+
+pub fn failure(text: impl Display) -> String {
+    paint(AnsiColor::Red, text)
+}
+
+pub fn warning(text: impl Display) -> String {
+    paint(AnsiColor::Yellow, text)
+}
+
+/// Green for gains, red for losses.
+pub fn delta(amount: i128, text: impl Display) -> String {
+    match amount {
+        0 => text.to_string(),
+        amount if amount > 0 => success(text),
+        _ => failure(text),
+    }
+}
+
+/// Colors a `+`/`-`/`~` diff line by its marker.
+pub fn diff_line(line: &str) -> String {
+    match line.chars().next() {
+        Some('+') => success(line),
+        Some('-') => failure(line),
+        Some('~') => warning(line),
+        _ => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{delta, diff_line, set_color};
+
+    #[test]
+    fn colors_only_when_enabled() {
+        set_color(false);
+        assert_eq!(diff_line("+ $.a: 1"), "+ $.a: 1");
+        set_color(true);
+        assert_eq!(diff_line("- $.a: 1"), "\u{1b}[31m- $.a: 1\u{1b}[0m");
+        assert_eq!(delta(5, "5"), "\u{1b}[32m5\u{1b}[0m");
+        assert_eq!(delta(0, "0"), "0");
+        set_color(false);
+    }
+}
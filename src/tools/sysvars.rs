@@ -0,0 +1,208 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{message::Message, pubkey::Pubkey};
+
+use crate::accounts::RENT_SYSVAR_ID;
+use crate::config::{ValidatorSysvars, set_validator_sysvars};
+use crate::network::NetworkCtx;
+use crate::tools::json_output::record;
+use crate::tools::style::warning;
+
+/// `Rent` sysvar: lamports per byte-year, exemption threshold, burn percent
+const RENT_LEN: usize = 17;
+
+/// Rent, epoch and fee parameters of a cluster that change how cloned
+/// accounts and replayed transactions behave.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ClusterParams {
+    pub lamports_per_byte_year: u64,
+    pub exemption_threshold: f64,
+    pub burn_percent: u8,
+    pub slots_per_epoch: u64,
+    pub warmup: bool,
+    pub lamports_per_signature: Option<u64>,
+}
+
+/// A parameter that differs between the source and the target.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Divergence {
+    pub name: &'static str,
+    pub source: String,
+    pub target: String,
+    /// Whether the local validator can be started with the source value
+    pub alignable: bool,
+}
+
+fn parse_rent(data: &[u8]) -> Result<(u64, f64, u8)> {
+    if data.len() < RENT_LEN {
+        return Err(anyhow!("Rent sysvar has {} bytes", data.len()));
+    }
+    let lamports = u64::from_le_bytes(data[0..8].try_into()?);
+    let threshold = f64::from_le_bytes(data[8..16].try_into()?);
+    Ok((lamports, threshold, data[16]))
+}
+
+fn fetch_params(client: &RpcClient, commitment: CommitmentConfig) -> Result<ClusterParams> {
+    let rent = client
+        .get_account_with_commitment(&RENT_SYSVAR_ID, commitment)?
+        .value
+        .ok_or_else(|| anyhow!("Rent sysvar not found"))?;
+    let (lamports_per_byte_year, exemption_threshold, burn_percent) = parse_rent(&rent.data)?;
+    let schedule = client.get_epoch_schedule()?;
+    // Fee of a message with a single signature and no instructions
+    let blockhash = client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(&[], Some(&Pubkey::new_unique()), &blockhash);
+    let lamports_per_signature = client.get_fee_for_message(&message).ok();
+    Ok(ClusterParams {
+        lamports_per_byte_year,
+        exemption_threshold,
+        burn_percent,
+        slots_per_epoch: schedule.slots_per_epoch,
+        warmup: schedule.warmup,
+        lamports_per_signature,
+    })
+}
+
+fn fee_text(fee: Option<u64>) -> String {
+    fee.map_or_else(|| "n/a".to_string(), |fee| fee.to_string())
+}
+
+/// Parameters of `target` that differ from `source`.
+pub fn divergences(source: &ClusterParams, target: &ClusterParams) -> Vec<Divergence> {
+    let fields: [(&'static str, String, String, bool); 6] = [
+        (
+            "lamports_per_byte_year",
+            source.lamports_per_byte_year.to_string(),
+            target.lamports_per_byte_year.to_string(),
+            false,
+        ),
+        (
+            "exemption_threshold",
+            source.exemption_threshold.to_string(),
+            target.exemption_threshold.to_string(),
+            false,
+        ),
+        (
+            "burn_percent",
+            source.burn_percent.to_string(),
+            target.burn_percent.to_string(),
+            false,
+        ),
+        (
+            "slots_per_epoch",
+            source.slots_per_epoch.to_string(),
+            target.slots_per_epoch.to_string(),
+            true,
+        ),
+        (
+            "warmup",
+            source.warmup.to_string(),
+            target.warmup.to_string(),
+            // `--slots-per-epoch` disables warmup, as on mainnet
+            !source.warmup,
+        ),
+        (
+            "lamports_per_signature",
+            fee_text(source.lamports_per_signature),
+            fee_text(target.lamports_per_signature),
+            false,
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, source, target, _)| source != target)
+        .map(|(name, source, target, alignable)| Divergence {
+            name,
+            source,
+            target,
+            alignable,
+        })
+        .collect()
+}
+
+/// Compares the Rent, EpochSchedule and fee parameters of the source and the
+/// target. With `align`, the local validator config takes the source's epoch
+/// schedule; rent and fees cannot be set on `solana-test-validator`.
+pub fn check_sysvars(network: &NetworkCtx, align: bool) -> Result<Vec<Divergence>> {
+    let commitment = network.read_commitment();
+    let source = fetch_params(&network.source_client(), commitment)?;
+    let target = fetch_params(&network.target_client(), commitment)?;
+    let divergences = divergences(&source, &target);
+    record("source", &source);
+    record("target", &target);
+    record("divergences", &divergences);
+
+    if divergences.is_empty() {
+        say!(
+            "{} and {} use the same sysvars",
+            network.source,
+            network.target
+        );
+        return Ok(divergences);
+    }
+    say!(
+        "Sysvars of {} differ from {}:",
+        network.target,
+        network.source
+    );
+    for divergence in &divergences {
+        let note = if divergence.alignable {
+            "alignable with --align"
+        } else {
+            "fixed by the validator"
+        };
+        say!(
+            "  {}",
+            warning(format!(
+                "{}: {} (source {}), {note}",
+                divergence.name, divergence.target, divergence.source
+            ))
+        );
+    }
+    if align && divergences.iter().any(|divergence| divergence.alignable) {
+        set_validator_sysvars(&ValidatorSysvars {
+            slots_per_epoch: Some(source.slots_per_epoch),
+        })?;
+        say!(
+            "Testnet config uses {} slots per epoch; restart the testnet to apply it",
+            source.slots_per_epoch
+        );
+    }
+    Ok(divergences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClusterParams, divergences, parse_rent};
+
+    #[test]
+    fn reports_differing_params() {
+        let mut rent = 3480u64.to_le_bytes().to_vec();
+        rent.extend(2.0f64.to_le_bytes());
+        rent.push(50);
+        let (lamports_per_byte_year, exemption_threshold, burn_percent) =
+            parse_rent(&rent).unwrap();
+        let mainnet = ClusterParams {
+            lamports_per_byte_year,
+            exemption_threshold,
+            burn_percent,
+            slots_per_epoch: 432_000,
+            warmup: false,
+            lamports_per_signature: Some(5000),
+        };
+        let local = ClusterParams {
+            slots_per_epoch: 32,
+            warmup: true,
+            ..mainnet.clone()
+        };
+
+        assert!(divergences(&mainnet, &mainnet.clone()).is_empty());
+        let diff = divergences(&mainnet, &local);
+        let names: Vec<_> = diff.iter().map(|divergence| divergence.name).collect();
+        assert_eq!(names, ["slots_per_epoch", "warmup"]);
+        assert!(diff.iter().all(|divergence| divergence.alignable));
+        assert!(parse_rent(&rent[..10]).is_err());
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::Result;
+use serde_json::json;
+use solana_sdk::{pubkey::Pubkey, signer::Signer, signer::keypair::Keypair};
+
+use crate::accounts::{METADATA_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::network::NetworkCtx;
+use crate::state::{STATE_MINT, STATE_NFT, record_state};
+use crate::tools::json_output::record;
+use crate::tools::tx::{ExecOptions, execute_json_transaction};
+use crate::tx_format::{
+    RawInstruction, RawTransaction,
+    json_tx::{parse_keypair, parse_tx_from_json},
+    raw_tx::{
+        create_account_tx, create_ata_with_payer_tx, create_master_edition_tx, create_metadata_tx,
+        initialize_mint_tx, mint_to_tx,
+    },
+};
+use crate::utils::format_amount;
+
+const MINT_ACCOUNT_SIZE: u64 = 82;
+
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+}
+
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    let seeds = [
+        b"metadata".as_ref(),
+        METADATA_PROGRAM_ID.as_ref(),
+        mint.as_ref(),
+    ];
+    Pubkey::find_program_address(&seeds, &METADATA_PROGRAM_ID).0
+}
+
+pub fn master_edition_address(mint: &Pubkey) -> Pubkey {
+    let seeds = [
+        b"metadata".as_ref(),
+        METADATA_PROGRAM_ID.as_ref(),
+        mint.as_ref(),
+        b"edition".as_ref(),
+    ];
+    Pubkey::find_program_address(&seeds, &METADATA_PROGRAM_ID).0
+}
+
+struct MintPlan<'a> {
+    authority: &'a str,
+    owner: &'a str,
+    mint: &'a Pubkey,
+    decimals: u8,
+    supply: u64,
+    metadata: Option<&'a TokenMetadata>,
+    master_edition: bool,
+}
+
+fn mint_instructions(plan: &MintPlan, rent: u64) -> Vec<RawInstruction> {
+    let mint = plan.mint.to_string();
+    let metadata = metadata_address(plan.mint).to_string();
+    let mut instructions = vec![
+        create_account_tx(
+            plan.authority,
+            &mint,
+            rent,
+            MINT_ACCOUNT_SIZE,
+            &TOKEN_PROGRAM_ID.to_string(),
+        ),
+        initialize_mint_tx(&mint, plan.decimals, plan.authority),
+        create_ata_with_payer_tx(plan.authority, plan.owner, &mint),
+    ];
+    if plan.supply > 0 {
+        instructions.push(mint_to_tx(&mint, plan.owner, plan.authority, plan.supply));
+    }
+    if let Some(data) = plan.metadata {
+        instructions.push(create_metadata_tx(
+            &metadata,
+            &mint,
+            plan.authority,
+            &data.name,
+            &data.symbol,
+            &data.uri,
+        ));
+    }
+    if plan.master_edition {
+        instructions.push(create_master_edition_tx(
+            &master_edition_address(plan.mint).to_string(),
+            &metadata,
+            &mint,
+            plan.authority,
+        ));
+    }
+    instructions
+}
+
+fn execute_mint_plan(
+    network: &NetworkCtx,
+    signer: &str,
+    mint_keypair: &Keypair,
+    plan: &MintPlan,
+) -> Result<()> {
+    let client = network.target_client();
+    let rent = client.get_minimum_balance_for_rent_exemption(MINT_ACCOUNT_SIZE as usize)?;
+    let raw = RawTransaction {
+        instructions: mint_instructions(plan, rent),
+        signers: vec![
+            serde_json::Value::String(signer.to_string()),
+            json!(mint_keypair.to_bytes().to_vec()),
+        ],
+        lookup_tables: None,
+        hooks: None,
+        version: None,
+        stamp: None,
+    };
+    let parsed = parse_tx_from_json(&raw, &[])?;
+    execute_json_transaction(network, parsed, &ExecOptions::default())?;
+    Ok(())
+}
+
+pub fn create_token(
+    network: &NetworkCtx,
+    signer: &str,
+    decimals: u8,
+    supply: u64,
+    metadata: Option<TokenMetadata>,
+) -> Result<()> {
+    let signer_keypair = parse_keypair(&serde_json::Value::String(signer.to_string()), &[])?;
+    let authority = signer_keypair.pubkey().to_string();
+    let mint_keypair = Keypair::new();
+    let mint = mint_keypair.pubkey();
+
+    execute_mint_plan(
+        network,
+        signer,
+        &mint_keypair,
+        &MintPlan {
+            authority: &authority,
+            owner: &authority,
+            mint: &mint,
+            decimals,
+            supply,
+            metadata: metadata.as_ref(),
+            master_edition: false,
+        },
+    )?;
+
+    record_state(network, STATE_MINT, &mint.to_string())?;
+
+    say!(
+        "Token {mint} created with {decimals} decimals, minted {} to {authority}",
+        format_amount(supply)
+    );
+    record("mint", mint.to_string());
+    if let Some(metadata) = metadata {
+        say!("Metadata {} ({})", metadata.name, metadata_address(&mint));
+        record("metadata", metadata_address(&mint).to_string());
+    }
+    Ok(())
+}
+
+pub fn mint_nft(
+    network: &NetworkCtx,
+    signer: &str,
+    owner: Option<&str>,
+    metadata: TokenMetadata,
+) -> Result<()> {
+    let signer_keypair = parse_keypair(&serde_json::Value::String(signer.to_string()), &[])?;
+    let authority = signer_keypair.pubkey().to_string();
+    let owner = owner.unwrap_or(&authority);
+    let mint_keypair = Keypair::new();
+    let mint = mint_keypair.pubkey();
+
+    execute_mint_plan(
+        network,
+        signer,
+        &mint_keypair,
+        &MintPlan {
+            authority: &authority,
+            owner,
+            mint: &mint,
+            decimals: 0,
+            supply: 1,
+            metadata: Some(&metadata),
+            master_edition: true,
+        },
+    )?;
+
+    record_state(network, STATE_NFT, &mint.to_string())?;
+
+    say!("NFT {mint} ({}) minted to {owner}", metadata.name);
+    say!("Metadata: {}", metadata_address(&mint));
+    say!("Master edition: {}", master_edition_address(&mint));
+    record("mint", mint.to_string());
+    record("metadata", metadata_address(&mint).to_string());
+    record("master_edition", master_edition_address(&mint).to_string());
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::accounts::SYSTEM_PROGRAM_ID;
+use crate::tools::json_output::record;
+
+pub const CHECK_RECIPIENT_EXISTS: &str = "recipient_exists";
+pub const CHECK_SENDER_RENT_EXEMPT: &str = "sender_rent_exempt";
+pub const CHECK_RECIPIENT_NOT_SIGNER: &str = "recipient_not_signer";
+/// System program `Transfer` instruction tag
+pub const SYSTEM_TRANSFER: u32 = 2;
+
+/// Checks run before sending SOL transfers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TransferChecks {
+    pub enabled: bool,
+    /// Allow transfers to accounts that do not exist yet
+    pub allow_unfunded_recipient: bool,
+}
+
+/// A system program transfer of `lamports` from `from` to `to`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transfer {
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub lamports: u64,
+}
+
+/// What was checked, in the structured result.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct CheckReport {
+    pub passed: Vec<&'static str>,
+    pub skipped: Vec<&'static str>,
+}
+
+impl CheckReport {
+    fn pass(&mut self, check: &'static str) {
+        if !self.passed.contains(&check) {
+            self.passed.push(check);
+        }
+    }
+
+    fn skip(&mut self, check: &'static str) {
+        if !self.skipped.contains(&check) {
+            self.skipped.push(check);
+        }
+    }
+}
+
+/// System transfers among `instructions`, e.g. expanded `transfer` shorthands.
+pub fn system_transfers(instructions: &[Instruction]) -> Vec<Transfer> {
+    instructions
+        .iter()
+        .filter(|ix| ix.program_id == SYSTEM_PROGRAM_ID && ix.accounts.len() >= 2)
+        .filter(|ix| ix.data.len() == 12 && ix.data[..4] == SYSTEM_TRANSFER.to_le_bytes())
+        .map(|ix| Transfer {
+            from: ix.accounts[0].pubkey,
+            to: ix.accounts[1].pubkey,
+            lamports: u64::from_le_bytes(ix.data[4..12].try_into().unwrap_or_default()),
+        })
+        .collect()
+}
+
+/// Balance of the sender after its transfers must be zero or rent exempt.
+fn check_sender_rent(sender: &Pubkey, balance: u64, sent: u64, min_rent: u64) -> Result<()> {
+    let remaining = balance
+        .checked_sub(sent)
+        .ok_or_else(|| anyhow!("{sender} has {balance} lamports, cannot send {sent}"))?;
+    if remaining > 0 && remaining < min_rent {
+        return Err(anyhow!(
+            "{sender} would keep {remaining} lamports, below the rent-exempt minimum of {min_rent}"
+        ));
+    }
+    Ok(())
+}
+
+fn check_recipient_not_signer(transfer: &Transfer, signers: &[Pubkey]) -> Result<()> {
+    if transfer.to == transfer.from || signers.contains(&transfer.to) {
+        return Err(anyhow!(
+            "Transfer recipient {} is a signer of the transaction",
+            transfer.to
+        ));
+    }
+    Ok(())
+}
+
+/// Runs the enabled checks on `transfers` against the target and records
+/// which ran and which were skipped.
+pub fn check_transfers(
+    client: &RpcClient,
+    transfers: &[Transfer],
+    signers: &[Pubkey],
+    checks: TransferChecks,
+) -> Result<CheckReport> {
+    let mut report = CheckReport::default();
+    if transfers.is_empty() {
+        return Ok(report);
+    }
+    if !checks.enabled {
+        report.skip(CHECK_RECIPIENT_NOT_SIGNER);
+        report.skip(CHECK_RECIPIENT_EXISTS);
+        report.skip(CHECK_SENDER_RENT_EXEMPT);
+        record("transfer_checks", &report);
+        return Ok(report);
+    }
+
+    for transfer in transfers {
+        check_recipient_not_signer(transfer, signers)?;
+        report.pass(CHECK_RECIPIENT_NOT_SIGNER);
+        if checks.allow_unfunded_recipient {
+            report.skip(CHECK_RECIPIENT_EXISTS);
+        } else {
+            let exists = client
+                .get_account_with_commitment(&transfer.to, client.commitment())?
+                .value
+                .is_some();
+            if !exists {
+                return Err(anyhow!(
+                    "Recipient {} does not exist (allow it with --allow-unfunded-recipient)",
+                    transfer.to
+                ));
+            }
+            report.pass(CHECK_RECIPIENT_EXISTS);
+        }
+    }
+
+    let mut sent: BTreeMap<Pubkey, u64> = BTreeMap::new();
+    for transfer in transfers {
+        *sent.entry(transfer.from).or_default() += transfer.lamports;
+    }
+    for (sender, amount) in sent {
+        let account = client
+            .get_account_with_commitment(&sender, client.commitment())?
+            .value
+            .ok_or_else(|| anyhow!("Sender {sender} does not exist"))?;
+        let min_rent = client.get_minimum_balance_for_rent_exemption(account.data.len())?;
+        check_sender_rent(&sender, account.lamports, amount, min_rent)?;
+    }
+    report.pass(CHECK_SENDER_RENT_EXEMPT);
+    record("transfer_checks", &report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::{Transfer, check_recipient_not_signer, check_sender_rent, system_transfers};
+    use crate::tx_format::{RawTransaction, json_tx::parse_tx_from_json};
+
+    #[test]
+    fn finds_and_checks_transfers() {
+        let (from, to) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let raw: RawTransaction = serde_json::from_value(json!({
+            "instructions": [{
+                "program_id": "transfer",
+                "from": from.to_string(),
+                "to": to.to_string(),
+                "amount": 42
+            }],
+            "signers": []
+        }))
+        .unwrap();
+        let parsed = parse_tx_from_json(&raw, &[]).unwrap();
+        let transfers = system_transfers(&parsed.instructions);
+        assert_eq!(
+            transfers,
+            [Transfer {
+                from,
+                to,
+                lamports: 42
+            }]
+        );
+
+        assert!(check_recipient_not_signer(&transfers[0], &[from]).is_ok());
+        assert!(check_recipient_not_signer(&transfers[0], &[from, to]).is_err());
+        assert!(check_sender_rent(&from, 1_000_000, 1_000_000, 890_880).is_ok());
+        assert!(check_sender_rent(&from, 1_000_000, 500_000, 890_880).is_err());
+        assert!(check_sender_rent(&from, 1_000_000, 2_000_000, 890_880).is_err());
+    }
+}
@@ -1,27 +1,49 @@
-use std::{fs, path::Path, str::FromStr};
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result, anyhow};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::json;
 use solana_address_lookup_table_interface::{
     instruction::{create_lookup_table as create_lookup_table_instruction, extend_lookup_table},
     state::AddressLookupTable,
 };
 use solana_commitment_config::CommitmentConfig;
-use solana_rpc_client::api::config::RpcTransactionConfig;
+use solana_rpc_client::api::config::{RpcSendTransactionConfig, RpcTransactionConfig};
 use solana_rpc_client::rpc_client::RpcClient;
 use solana_sdk::message::{
     AddressLookupTableAccount, Message, VersionedMessage, v0::Message as V0Message,
 };
 use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
     pubkey::Pubkey,
     signature::Signature,
-    signer::Signer,
-    slot_hashes::SlotHashes,
-    sysvar,
-    transaction::VersionedTransaction,
+    signer::{Signer, keypair::Keypair},
+    slot_hashes,
+    transaction::{TransactionError, VersionedTransaction},
 };
 use solana_system_transaction as system_transaction;
 use solana_transaction_status::UiTransactionEncoding;
 
+use crate::accounts::{ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::network::NetworkCtx;
+use crate::state::{STATE_LOOKUP_TABLE, record_state};
+use crate::tools::close_checks::check_token_closes;
+use crate::tools::compute::print_cu_breakdown;
+use crate::tools::errors::{ErrorMap, report_failure, send_failure_details};
+use crate::tools::events::EventsOutput;
+use crate::tools::json_output::{push, record};
+use crate::tools::log_assert::{LogAssertion, check_log_assertions};
+use crate::tools::progress::{TX_CONFIRMED, TX_SENT, emit};
+use crate::tools::slots::wait_for_slot;
+use crate::tools::style::{delta, success};
+use crate::tools::transfer_checks::{Transfer, TransferChecks, check_transfers, system_transfers};
 use crate::tx_format::{
     RawTransaction,
     json_tx::{ParsedTransaction, parse_keypair, parse_tx_from_json},
@@ -30,32 +52,35 @@ use crate::tx_format::{
 };
 use crate::utils::format_amount;
 
-pub const LOCAL_RPC_URL: &str = "http://127.0.0.1:8899";
-pub const MAINNET_RPC_URL: &str = "http://api.mainnet-beta.solana.com";
-
-pub fn create_connection(network: &str) -> RpcClient {
-    RpcClient::new_with_commitment(network.to_string(), CommitmentConfig::confirmed())
-}
+/// Slots before a `send_at_slot` target at which the transaction is built
+const SEND_AT_LEAD_SLOTS: u64 = 4;
+/// Addresses added per extend transaction, which stays under the size limit
+const LOOKUP_TABLE_EXTEND_CHUNK: usize = 20;
 
 fn confirm_signature(client: &RpcClient, signature: &Signature) -> Result<()> {
     client.poll_for_signature_with_commitment(signature, CommitmentConfig::confirmed())?;
     Ok(())
 }
 
-fn fetch_slot_hashes(client: &RpcClient) -> Result<SlotHashes> {
-    let account = client.get_account(&sysvar::slot_hashes::id())?;
-    let hashes: SlotHashes =
-        bincode::deserialize(&account.data).map_err(|_| anyhow!("Invalid slot hashes data"))?;
-    Ok(hashes)
+/// Whether a lookup table can be used at `current_slot`. A deactivated table
+/// stays usable while its deactivation slot is among the slot hashes, which
+/// always cover the last `MAX_ENTRIES` slots; beyond that it is treated as
+/// closed, without reading the slot hashes sysvar.
+pub fn lookup_table_active(deactivation_slot: u64, current_slot: u64) -> bool {
+    deactivation_slot == u64::MAX
+        || current_slot.saturating_sub(deactivation_slot) < slot_hashes::MAX_ENTRIES as u64
 }
 
-fn fetch_lookup_table(client: &RpcClient, key: &Pubkey) -> Result<AddressLookupTableAccount> {
-    let account = client.get_account(key)?;
-    let table = AddressLookupTable::deserialize(&account.data)
+fn lookup_table_account(
+    key: &Pubkey,
+    data: &[u8],
+    current_slot: impl FnOnce() -> Result<u64>,
+) -> Result<AddressLookupTableAccount> {
+    let table = AddressLookupTable::deserialize(data)
         .map_err(|_| anyhow!("Failed to deserialize address lookup table"))?;
-    let current_slot = client.get_slot_with_commitment(CommitmentConfig::confirmed())?;
-    let slot_hashes = fetch_slot_hashes(client)?;
-    if !table.meta.is_active(current_slot, &slot_hashes) {
+    let deactivation_slot = table.meta.deactivation_slot;
+    // Only deactivated tables need the current slot.
+    if deactivation_slot != u64::MAX && !lookup_table_active(deactivation_slot, current_slot()?) {
         return Err(anyhow!("ALT {key} not found / not active"));
     }
     Ok(AddressLookupTableAccount {
@@ -64,101 +89,405 @@ fn fetch_lookup_table(client: &RpcClient, key: &Pubkey) -> Result<AddressLookupT
     })
 }
 
-pub fn execute_json_transaction(
-    json_tx: ParsedTransaction,
-    payer_pubkey: Option<Pubkey>,
-) -> Result<()> {
-    let client = create_connection(LOCAL_RPC_URL);
-    let payer = match payer_pubkey {
-        Some(payer) => payer,
-        None => json_tx
-            .signers
-            .first()
-            .ok_or_else(|| anyhow!("Missing transaction signer"))?
-            .pubkey(),
-    };
+pub fn fetch_lookup_table(client: &RpcClient, key: &Pubkey) -> Result<AddressLookupTableAccount> {
+    let account = client.get_account(key)?;
+    lookup_table_account(key, &account.data, || {
+        Ok(client.get_slot_with_commitment(CommitmentConfig::confirmed())?)
+    })
+}
 
-    let mut lookup_accounts = Vec::new();
-    for table in &json_tx.lookup_tables {
-        lookup_accounts.push(fetch_lookup_table(&client, table)?);
+fn check_signers(message: &VersionedMessage, signers: &[Keypair]) -> Result<()> {
+    let num_required = message.header().num_required_signatures as usize;
+    let required = &message.static_account_keys()[..num_required];
+    for signer in signers {
+        let pubkey = signer.pubkey();
+        if !required.contains(&pubkey) {
+            return Err(anyhow!(
+                "Signer {pubkey} is not required by the transaction message"
+            ));
+        }
+    }
+    for pubkey in required {
+        if !signers.iter().any(|signer| signer.pubkey() == *pubkey) {
+            return Err(anyhow!("Missing keypair for required signer {pubkey}"));
+        }
     }
+    Ok(())
+}
+
+/// Fetches all tables with one request; the slot is read at most once.
+pub fn fetch_lookup_tables(
+    client: &RpcClient,
+    tables: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>> {
+    if tables.is_empty() {
+        return Ok(Vec::new());
+    }
+    let accounts = client
+        .get_multiple_accounts_with_commitment(tables, CommitmentConfig::confirmed())?
+        .value;
+    let mut current_slot = None;
+    tables
+        .iter()
+        .zip(accounts)
+        .map(|(key, account)| {
+            let account = account.ok_or_else(|| anyhow!("ALT {key} not found / not active"))?;
+            lookup_table_account(key, &account.data, || match current_slot {
+                Some(slot) => Ok(slot),
+                None => {
+                    let slot = client.get_slot_with_commitment(CommitmentConfig::confirmed())?;
+                    current_slot = Some(slot);
+                    Ok(slot)
+                }
+            })
+        })
+        .collect()
+}
 
-    let (blockhash, _) =
-        client.get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?;
+/// Tables worth attaching to a transaction: each table that can load at
+/// least two of its accounts, which saves more than the table key costs.
+/// Signers and invoked programs cannot be loaded from a table.
+pub fn useful_lookup_tables(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    candidates: Vec<AddressLookupTableAccount>,
+) -> Vec<AddressLookupTableAccount> {
+    let programs: HashSet<Pubkey> = instructions.iter().map(|ix| ix.program_id).collect();
+    let loadable: HashSet<Pubkey> = instructions
+        .iter()
+        .flat_map(|ix| &ix.accounts)
+        .filter(|meta| !meta.is_signer && meta.pubkey != *payer && !programs.contains(&meta.pubkey))
+        .map(|meta| meta.pubkey)
+        .collect();
+    candidates
+        .into_iter()
+        .filter(|table| {
+            table
+                .addresses
+                .iter()
+                .filter(|address| loadable.contains(address))
+                .count()
+                >= 2
+        })
+        .collect()
+}
 
-    let versioned_message = if lookup_accounts.is_empty() {
-        let message = Message::new_with_blockhash(&json_tx.instructions, Some(&payer), &blockhash);
-        VersionedMessage::Legacy(message)
+/// Compiles the message of the transaction, a v0 message when lookup tables are present.
+pub fn compile_message(
+    json_tx: &ParsedTransaction,
+    payer: &Pubkey,
+    lookup_accounts: &[AddressLookupTableAccount],
+    blockhash: Hash,
+) -> Result<VersionedMessage> {
+    if lookup_accounts.is_empty() {
+        let message = Message::new_with_blockhash(&json_tx.instructions, Some(payer), &blockhash);
+        Ok(VersionedMessage::Legacy(message))
     } else {
         let message =
-            V0Message::try_compile(&payer, &json_tx.instructions, &lookup_accounts, blockhash)?;
-        VersionedMessage::V0(message)
+            V0Message::try_compile(payer, &json_tx.instructions, lookup_accounts, blockhash)?;
+        Ok(VersionedMessage::V0(message))
+    }
+}
+
+/// Compiles and signs the transaction, using a v0 message when lookup tables are present.
+pub fn compile_transaction(
+    json_tx: &ParsedTransaction,
+    payer: &Pubkey,
+    lookup_accounts: &[AddressLookupTableAccount],
+    blockhash: Hash,
+) -> Result<VersionedTransaction> {
+    let versioned_message = compile_message(json_tx, payer, lookup_accounts, blockhash)?;
+    check_signers(&versioned_message, &json_tx.signers)?;
+    Ok(VersionedTransaction::try_new(
+        versioned_message,
+        &json_tx.signers,
+    )?)
+}
+
+/// Options controlling how `execute_json_transaction` sends and reports a transaction.
+#[derive(Default)]
+pub struct ExecOptions {
+    pub payer: Option<Pubkey>,
+    pub error_map: ErrorMap,
+    /// Wait for a blockhash newer than this one, so repeated sends are not deduplicated.
+    pub previous_blockhash: Option<Hash>,
+    /// `sendTransaction` config; the preflight commitment defaults to the client's.
+    pub send_config: RpcSendTransactionConfig,
+    /// Commitment the transaction is confirmed at; defaults to confirmed
+    pub confirm_commitment: Option<CommitmentConfig>,
+    /// Checked against the logs of a successful transaction
+    pub log_assertions: Vec<LogAssertion>,
+    /// Writes the events of a successful transaction
+    pub events: Option<EventsOutput>,
+    /// Hold the transaction until the target reaches this slot
+    pub send_at_slot: Option<u64>,
+    /// Lookup tables of the instance (`soltnet.toml`), attached when they shorten the transaction
+    pub default_lookup_tables: Vec<Pubkey>,
+    /// Checks on the SOL transfers of the transaction before it is sent
+    pub transfer_checks: TransferChecks,
+    /// Burn the tokens left in token accounts the transaction closes instead
+    /// of refusing to send it
+    pub burn_remaining: bool,
+}
+
+/// Outcome of a confirmed transaction sent by `execute_json_transaction`.
+#[derive(Debug)]
+pub struct ExecReport {
+    pub blockhash: Hash,
+    pub slot: u64,
+    pub latency: Duration,
+    pub compute_units: Option<u64>,
+}
+
+/// The fee payer: `options.payer`, or the first signer of the template.
+fn fee_payer(json_tx: &ParsedTransaction, options: &ExecOptions) -> Result<Pubkey> {
+    match options.payer {
+        Some(payer) => Ok(payer),
+        None => Ok(json_tx
+            .signers
+            .first()
+            .ok_or_else(|| anyhow!("Missing transaction signer"))?
+            .pubkey()),
+    }
+}
+
+/// Compiles and signs the transaction with its lookup tables, including the
+/// configured ones that shorten it, and a fresh blockhash.
+fn build_transaction(
+    client: &RpcClient,
+    json_tx: &ParsedTransaction,
+    payer: &Pubkey,
+    options: &ExecOptions,
+) -> Result<(VersionedTransaction, Hash)> {
+    let mut lookup_accounts = fetch_lookup_tables(client, &json_tx.lookup_tables)?;
+    let mut defaults = Vec::new();
+    for table in &options.default_lookup_tables {
+        if json_tx.lookup_tables.contains(table) {
+            continue;
+        }
+        match fetch_lookup_table(client, table) {
+            Ok(table) => defaults.push(table),
+            Err(err) => eprintln!("Warning: skipping configured lookup table {table}: {err}"),
+        }
+    }
+    for table in useful_lookup_tables(&json_tx.instructions, payer, defaults) {
+        say!("Attaching lookup table {}", table.key);
+        lookup_accounts.push(table);
+    }
+
+    let blockhash = match &options.previous_blockhash {
+        Some(previous) => client.get_new_latest_blockhash(previous)?,
+        None => {
+            client
+                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?
+                .0
+        }
     };
 
-    let tx = VersionedTransaction::try_new(versioned_message, &json_tx.signers)?;
+    let tx = compile_transaction(json_tx, payer, &lookup_accounts, blockhash)?;
+    Ok((tx, blockhash))
+}
+
+/// Compiles and signs the transaction as `execute_json_transaction` would and
+/// returns its wire format in base64, without sending it.
+pub fn encode_json_transaction(
+    network: &NetworkCtx,
+    json_tx: &ParsedTransaction,
+    options: &ExecOptions,
+) -> Result<String> {
+    let payer = fee_payer(json_tx, options)?;
+    let (tx, _) = build_transaction(&network.target_client(), json_tx, &payer, options)?;
+    Ok(STANDARD.encode(bincode::serialize(&tx)?))
+}
+
+/// Prints the encoded transactions one per line and, with `output`, writes
+/// them to that file.
+pub fn encode_transactions(
+    network: &NetworkCtx,
+    transactions: &[ParsedTransaction],
+    options: &ExecOptions,
+    output: Option<&Path>,
+) -> Result<()> {
+    let mut encoded = Vec::with_capacity(transactions.len());
+    for json_tx in transactions {
+        encoded.push(encode_json_transaction(network, json_tx, options)?);
+    }
+    for tx in &encoded {
+        say!("{tx}");
+        push("encoded", tx);
+    }
+    if let Some(path) = output {
+        fs::write(path, encoded.join("\n") + "\n")
+            .with_context(|| format!("failed to write {path:?}"))?;
+        say!("Encoded transactions written to {}", path.display());
+        push("files", path.display().to_string());
+    }
+    Ok(())
+}
+
+pub fn execute_json_transaction(
+    network: &NetworkCtx,
+    mut json_tx: ParsedTransaction,
+    options: &ExecOptions,
+) -> Result<ExecReport> {
+    let client = network.target_client();
+    let payer = fee_payer(&json_tx, options)?;
+    check_token_closes(&client, &mut json_tx.instructions, options.burn_remaining)?;
+
+    // Build the transaction shortly before the target slot so its blockhash
+    // is still valid, then hold it until the slot is reached.
+    if let Some(slot) = options.send_at_slot {
+        wait_for_slot(&client, slot.saturating_sub(SEND_AT_LEAD_SLOTS))?;
+    }
+
+    let (tx, blockhash) = build_transaction(&client, &json_tx, &payer, options)?;
+    let signers: Vec<Pubkey> = json_tx.signers.iter().map(Signer::pubkey).collect();
+    check_transfers(
+        &client,
+        &system_transfers(&json_tx.instructions),
+        &signers,
+        options.transfer_checks,
+    )?;
 
     let balance_before = client.get_balance(&payer)? as i128;
-    let sig = client.send_transaction(&tx)?;
-    confirm_signature(&client, &sig)?;
+    if let Some(slot) = options.send_at_slot {
+        let current = wait_for_slot(&client, slot)?;
+        say!("Sending at slot {current}");
+    }
+    let started = Instant::now();
+    let send_config = RpcSendTransactionConfig {
+        preflight_commitment: options
+            .send_config
+            .preflight_commitment
+            .or(Some(client.commitment().commitment)),
+        ..options.send_config
+    };
+    let sig = match client.send_transaction_with_config(&tx, send_config) {
+        Ok(sig) => sig,
+        Err(err) => {
+            if let Some((tx_error, logs)) = send_failure_details(&err) {
+                report_failure(&tx_error, &logs, &json_tx.instructions, &options.error_map);
+            }
+            return Err(err.into());
+        }
+    };
+    emit(TX_SENT, json!({ "signature": sig.to_string() }));
+    let confirm_commitment = options
+        .confirm_commitment
+        .unwrap_or_else(CommitmentConfig::confirmed);
+    client.poll_for_signature_with_commitment(&sig, confirm_commitment)?;
+    let latency = started.elapsed();
+    // Transactions cannot be read back at `processed`
+    if !confirm_commitment.is_at_least_confirmed() {
+        confirm_signature(&client, &sig)?;
+    }
 
-    println!("Transaction sent: {sig}");
+    say!("{}", success(format!("Transaction sent: {sig}")));
 
     let parsed_tx = client.get_transaction_with_config(
         &sig,
         RpcTransactionConfig {
             encoding: Some(UiTransactionEncoding::JsonParsed),
-            commitment: Some(CommitmentConfig::confirmed()),
+            commitment: Some(if confirm_commitment.is_finalized() {
+                confirm_commitment
+            } else {
+                CommitmentConfig::confirmed()
+            }),
             max_supported_transaction_version: Some(0),
         },
     )?;
 
+    emit(
+        TX_CONFIRMED,
+        json!({ "signature": sig.to_string(), "slot": parsed_tx.slot }),
+    );
+
+    let mut report = ExecReport {
+        blockhash,
+        slot: parsed_tx.slot,
+        latency,
+        compute_units: None,
+    };
     if let Some(meta) = parsed_tx.transaction.meta {
-        let logs: Option<Vec<String>> = meta.log_messages.into();
-        if let Some(logs) = logs {
-            for log in logs {
-                println!("{log}");
-            }
+        let logs: Vec<String> = Option::<Vec<String>>::from(meta.log_messages).unwrap_or_default();
+        if let Some(err) = meta.err {
+            let tx_error = TransactionError::from(err);
+            report_failure(&tx_error, &logs, &json_tx.instructions, &options.error_map);
+            return Err(anyhow!("Transaction {sig} failed: {tx_error}"));
+        }
+        for log in &logs {
+            say!("{log}");
         }
+        print_cu_breakdown(&logs);
         let compute_units: Option<u64> = meta.compute_units_consumed.into();
+        report.compute_units = compute_units;
         if let Some(units) = compute_units {
-            println!("Total CUs used: {units}");
+            say!("Total CUs used: {units}");
         } else {
-            println!("Total CUs used: n/a");
+            say!("Total CUs used: n/a");
         }
+        if let Some(events) = &options.events {
+            events.write(&sig.to_string(), parsed_tx.slot, &logs)?;
+        }
+        check_log_assertions(&options.log_assertions, &logs)
+            .with_context(|| format!("Transaction {sig}"))?;
     }
 
     let balance_after = client.get_balance(&payer)? as i128;
     let amount_changed = balance_after - balance_before;
-    println!(
+    say!(
         "Balance changed: {} lamports",
-        format_amount(amount_changed)
+        delta(amount_changed, format_amount(amount_changed))
+    );
+    push(
+        "transactions",
+        json!({
+            "signature": sig.to_string(),
+            "slot": report.slot,
+            "compute_units": report.compute_units,
+            "balance_change": amount_changed as i64,
+            "latency_ms": latency.as_millis() as u64,
+        }),
     );
 
-    Ok(())
+    Ok(report)
 }
 
-pub fn get_balance(address: &str) -> Result<()> {
-    let client = create_connection(LOCAL_RPC_URL);
+pub fn get_balance(network: &NetworkCtx, address: &str) -> Result<()> {
+    let client = network.target_client();
     let pubkey = Pubkey::from_str(address)?;
-    let balance = client.get_balance(&pubkey)?;
-    println!("Balance of {address}: {} lamports", format_amount(balance));
+    let balance = client
+        .get_balance_with_commitment(&pubkey, network.read_commitment())?
+        .value;
+    say!("Balance of {address}: {} lamports", format_amount(balance));
+    record("address", address);
+    record("lamports", balance);
     Ok(())
 }
 
-pub fn airdrop_sol(address: &str, amount: u64) -> Result<()> {
-    let client = create_connection(LOCAL_RPC_URL);
+pub fn airdrop_sol(network: &NetworkCtx, address: &str, amount: u64) -> Result<()> {
+    let client = network.target_client();
     let pubkey = Pubkey::from_str(address)?;
     let sig = client.request_airdrop(&pubkey, amount)?;
     confirm_signature(&client, &sig)?;
-    println!(
+    say!(
         "Airdrop successful: {} lamports to {address}",
         format_amount(amount)
     );
+    record("signature", sig.to_string());
+    record("lamports", amount);
     Ok(())
 }
 
-pub fn send_sol(from: &str, to: &str, amount: u64, signer: &str) -> Result<()> {
-    let client = create_connection(LOCAL_RPC_URL);
+pub fn send_sol(
+    network: &NetworkCtx,
+    from: &str,
+    to: &str,
+    amount: u64,
+    signer: &str,
+    checks: TransferChecks,
+) -> Result<()> {
+    let client = network.target_client();
     let from_pubkey = Pubkey::from_str(from)?;
     let to_pubkey = Pubkey::from_str(to)?;
     let signer_value = serde_json::Value::String(signer.to_string());
@@ -167,11 +496,17 @@ pub fn send_sol(from: &str, to: &str, amount: u64, signer: &str) -> Result<()> {
     if signer_keypair.pubkey() != from_pubkey {
         return Err(anyhow!("Signer does not match from pubkey"));
     }
+    let transfer = Transfer {
+        from: from_pubkey,
+        to: to_pubkey,
+        lamports: amount,
+    };
+    check_transfers(&client, &[transfer], &[from_pubkey], checks)?;
     let blockhash = client.get_latest_blockhash()?;
     let tx = system_transaction::transfer(&signer_keypair, &to_pubkey, amount, blockhash);
 
     let sig = client.send_and_confirm_transaction(&tx)?;
-    println!("Transaction sent: {sig}");
+    say!("Transaction sent: {sig}");
 
     let parsed_tx = client.get_transaction_with_config(
         &sig,
@@ -186,89 +521,250 @@ pub fn send_sol(from: &str, to: &str, amount: u64, signer: &str) -> Result<()> {
         let logs: Option<Vec<String>> = meta.log_messages.into();
         if let Some(logs) = logs {
             for log in logs {
-                println!("{log}");
+                say!("{log}");
             }
         }
     }
 
-    println!("Sent {} SOL from {from} to {to}", format_amount(amount));
+    say!("Sent {} SOL from {from} to {to}", format_amount(amount));
+    push("transactions", json!({ "signature": sig.to_string() }));
+    record("lamports", amount);
     Ok(())
 }
 
-pub fn create_ata(owner: &str, mint: &str, signer: &str) -> Result<()> {
+pub fn create_ata(network: &NetworkCtx, owner: &str, mint: &str, signer: &str) -> Result<()> {
     let raw = RawTransaction {
         instructions: vec![create_ata_tx(owner, mint)],
         signers: vec![serde_json::Value::String(signer.to_string())],
         lookup_tables: None,
+        hooks: None,
+        version: None,
+        stamp: None,
     };
     let parsed = parse_tx_from_json(&raw, &[])?;
-    execute_json_transaction(parsed, None)
+    execute_json_transaction(network, parsed, &ExecOptions::default())?;
+    Ok(())
 }
 
-pub fn close_ata(owner: &str, mint: &str, signer: &str) -> Result<()> {
+/// Closes the owner's ATA of `mint`. An ATA still holding tokens is refused,
+/// or emptied with a burn first when `burn_remaining` (see `check_token_closes`).
+pub fn close_ata(
+    network: &NetworkCtx,
+    owner: &str,
+    mint: &str,
+    signer: &str,
+    burn_remaining: bool,
+) -> Result<()> {
+    let client = network.target_client();
+    let ata = parse_pubkey(
+        &serde_json::json!({ "type": "ata", "owner": owner, "mint": mint }),
+        &[],
+    )?;
+    if client
+        .get_account_with_commitment(&ata, network.read_commitment())?
+        .value
+        .is_none()
+    {
+        return Err(anyhow!(
+            "Token account {ata} of {owner} for {mint} does not exist"
+        ));
+    }
     let raw = RawTransaction {
         instructions: vec![close_ata_tx(owner, mint)],
         signers: vec![serde_json::Value::String(signer.to_string())],
         lookup_tables: None,
+        hooks: None,
+        version: None,
+        stamp: None,
     };
     let parsed = parse_tx_from_json(&raw, &[])?;
-    execute_json_transaction(parsed, None)
+    let options = ExecOptions {
+        burn_remaining,
+        ..Default::default()
+    };
+    execute_json_transaction(network, parsed, &options)?;
+    Ok(())
 }
 
-pub fn get_token_balance(owner: &str, mint: &str) -> Result<()> {
-    let client = create_connection(LOCAL_RPC_URL);
-    let ata = parse_pubkey(
-        &serde_json::json!({
-            "type": "ata",
-            "owner": owner,
-            "mint": mint,
-        }),
-        &[],
-    )?;
-    let balance = client.get_token_account_balance(&ata)?;
+/// Prints the balance of the owner's ATA of `mint`, for the token program
+/// owning the mint (SPL Token or Token-2022).
+pub fn get_token_balance(network: &NetworkCtx, owner: &str, mint: &str) -> Result<()> {
+    let client = network.target_client();
+    let owner_key = parse_pubkey(&json!(owner), &[])?;
+    let mint_key = parse_pubkey(&json!(mint), &[])?;
+    let token_program = client
+        .get_account_with_commitment(&mint_key, network.read_commitment())?
+        .value
+        .ok_or_else(|| anyhow!("Mint {mint_key} does not exist"))?
+        .owner;
+    if token_program != TOKEN_PROGRAM_ID && token_program != TOKEN_2022_PROGRAM_ID {
+        return Err(anyhow!(
+            "{mint_key} is not a token mint (owned by {token_program})"
+        ));
+    }
+    let ata = token_account_address(&owner_key, &mint_key, &token_program);
+    let balance = client
+        .get_token_account_balance_with_commitment(&ata, network.read_commitment())?
+        .value;
     let amount = balance
         .ui_amount
         .map(|v| v.to_string())
         .unwrap_or_else(|| balance.ui_amount_string.clone());
-    println!(
-        "Balance of {owner} for token {mint}: {} tokens",
+    say!(
+        "Balance of {owner_key} for token {mint_key}: {} tokens",
         format_amount(amount)
     );
+    say!(
+        "  Raw amount: {} ({} decimals)",
+        format_amount(&balance.amount),
+        balance.decimals
+    );
+    say!("  Token account: {ata} (program {token_program})");
+    let fields = token_balance_fields(
+        &ata,
+        &token_program,
+        &balance.amount,
+        balance.decimals,
+        &balance.ui_amount_string,
+    );
+    for (key, value) in fields.as_object().into_iter().flatten() {
+        record(key, value);
+    }
     Ok(())
 }
 
-pub fn create_lookup_table(accounts_path: &Path, signer: &str) -> Result<()> {
+/// Associated token account of `owner` for `mint` under `token_program`
+/// (SPL Token or Token-2022).
+fn token_account_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    let seeds = [owner.as_ref(), token_program.as_ref(), mint.as_ref()];
+    Pubkey::find_program_address(&seeds, &ASSOCIATED_TOKEN_PROGRAM_ID).0
+}
+
+/// JSON result fields of `get-token-balance`.
+fn token_balance_fields(
+    ata: &Pubkey,
+    token_program: &Pubkey,
+    amount: &str,
+    decimals: u8,
+    ui_amount: &str,
+) -> serde_json::Value {
+    json!({
+        "token_account": ata.to_string(),
+        "token_program": token_program.to_string(),
+        "amount": amount,
+        "decimals": decimals,
+        "ui_amount": ui_amount,
+    })
+}
+
+pub fn create_lookup_table(
+    network: &NetworkCtx,
+    accounts_path: &Path,
+    signer: &str,
+) -> Result<Pubkey> {
     let data = fs::read_to_string(accounts_path)
         .with_context(|| format!("failed to read {accounts_path:?}"))?;
     let accounts: Vec<String> = serde_json::from_str(&data)
         .with_context(|| format!("invalid JSON in {accounts_path:?}"))?;
-
-    let signer_keypair = parse_keypair(&serde_json::Value::String(signer.to_string()), &[])?;
-    let payer_pubkey = signer_keypair.pubkey();
-    let client = create_connection(LOCAL_RPC_URL);
-    let slot = client.get_slot_with_commitment(CommitmentConfig::finalized())?;
-    let recent_slot = slot.saturating_sub(1);
-
-    let (create_ix, table_addr) =
-        create_lookup_table_instruction(payer_pubkey, payer_pubkey, recent_slot);
     let addresses = accounts
         .iter()
         .map(|acc| Pubkey::from_str(acc))
         .collect::<Result<Vec<_>, _>>()
         .map_err(|_| anyhow!("Invalid account in lookup table list"))?;
-    let extend_ix = extend_lookup_table(table_addr, payer_pubkey, Some(payer_pubkey), addresses);
 
-    let parsed = ParsedTransaction {
-        instructions: vec![create_ix, extend_ix],
-        signers: vec![signer_keypair],
-        lookup_tables: Vec::new(),
-    };
-    execute_json_transaction(parsed, None)?;
-
-    println!(
+    let signer_keypair = parse_keypair(&serde_json::Value::String(signer.to_string()), &[])?;
+    let table_addr = create_lookup_table_with_addresses(network, &addresses, &signer_keypair)?;
+    say!(
         "Lookup table created at {} with {} accounts",
         table_addr,
         accounts.len()
     );
-    Ok(())
+    record("lookup_table", table_addr.to_string());
+    Ok(table_addr)
+}
+
+/// Creates a lookup table owned by `authority` on the target holding `addresses`.
+pub fn create_lookup_table_with_addresses(
+    network: &NetworkCtx,
+    addresses: &[Pubkey],
+    authority: &Keypair,
+) -> Result<Pubkey> {
+    let payer_pubkey = authority.pubkey();
+    let client = network.target_client();
+    let slot = client.get_slot_with_commitment(CommitmentConfig::finalized())?;
+    let recent_slot = slot.saturating_sub(1);
+
+    let (create_ix, table_addr) =
+        create_lookup_table_instruction(payer_pubkey, payer_pubkey, recent_slot);
+    // Large lists do not fit in one transaction, so the table is extended in chunks.
+    let extend = |chunk: &[Pubkey]| {
+        extend_lookup_table(table_addr, payer_pubkey, Some(payer_pubkey), chunk.to_vec())
+    };
+    let send = |instructions| -> Result<()> {
+        let parsed = ParsedTransaction {
+            instructions,
+            signers: vec![authority.insecure_clone()],
+            lookup_tables: Vec::new(),
+        };
+        execute_json_transaction(network, parsed, &ExecOptions::default())?;
+        Ok(())
+    };
+    let mut chunks = addresses.chunks(LOOKUP_TABLE_EXTEND_CHUNK);
+    send(
+        [create_ix]
+            .into_iter()
+            .chain(chunks.next().map(extend))
+            .collect(),
+    )?;
+    for chunk in chunks {
+        send(vec![extend(chunk)])?;
+    }
+    record_state(network, STATE_LOOKUP_TABLE, &table_addr.to_string())?;
+    Ok(table_addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use solana_sdk::{pubkey::Pubkey, slot_hashes::MAX_ENTRIES};
+
+    use super::{lookup_table_active, token_account_address, token_balance_fields};
+    use crate::accounts::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+    use crate::tx_format::pubkey::parse_pubkey;
+
+    #[test]
+    fn deactivated_tables_stay_usable_for_recent_slots() {
+        assert!(lookup_table_active(u64::MAX, 10));
+        assert!(lookup_table_active(1_000, 1_000));
+        assert!(lookup_table_active(1_000, 1_000 + MAX_ENTRIES as u64 - 1));
+        assert!(!lookup_table_active(1_000, 1_000 + MAX_ENTRIES as u64));
+    }
+
+    #[test]
+    fn token_balances_use_the_mint_program() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let classic = parse_pubkey(
+            &json!({ "type": "ata", "owner": owner.to_string(), "mint": mint.to_string() }),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            token_account_address(&owner, &mint, &TOKEN_PROGRAM_ID),
+            classic
+        );
+        let token_2022 = token_account_address(&owner, &mint, &TOKEN_2022_PROGRAM_ID);
+        assert_ne!(token_2022, classic);
+
+        let fields = token_balance_fields(&token_2022, &TOKEN_2022_PROGRAM_ID, "1500000", 6, "1.5");
+        assert_eq!(
+            fields,
+            json!({
+                "token_account": token_2022.to_string(),
+                "token_program": TOKEN_2022_PROGRAM_ID.to_string(),
+                "amount": "1500000",
+                "decimals": 6,
+                "ui_amount": "1.5",
+            })
+        );
+    }
 }
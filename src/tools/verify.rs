@@ -0,0 +1,59 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use solana_sdk::hash::{Hash, hash};
+
+use crate::network::NetworkCtx;
+use crate::tools::dump::fetch_program_elf;
+use crate::tools::json_output::record;
+use crate::tools::style::{failure, success};
+
+/// Hash of an ELF without trailing zero bytes, since program data accounts
+/// are usually allocated larger than the deployed ELF.
+pub fn program_hash(elf: &[u8]) -> Hash {
+    let len = elf
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |last| last + 1);
+    hash(&elf[..len])
+}
+
+/// Compares the ELF of `program` on the source with a local build.
+pub fn verify_program(network: &NetworkCtx, program: &str, so: &Path) -> Result<()> {
+    let local = fs::read(so).with_context(|| format!("failed to read {so:?}"))?;
+    let on_chain = fetch_program_elf(network, program)?;
+    let (on_chain_hash, local_hash) = (program_hash(&on_chain), program_hash(&local));
+    say!(
+        "On-chain {program}: {} ({} bytes)",
+        hex::encode(on_chain_hash),
+        on_chain.len()
+    );
+    say!(
+        "Local {}: {} ({} bytes)",
+        so.display(),
+        hex::encode(local_hash),
+        local.len()
+    );
+    record("on_chain_hash", hex::encode(on_chain_hash));
+    record("local_hash", hex::encode(local_hash));
+    record("matches", on_chain_hash == local_hash);
+    if on_chain_hash != local_hash {
+        say!("{}", failure("Mismatch"));
+        return Err(anyhow!("{} differs from program {program}", so.display()));
+    }
+    say!("{}", success("Match"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::program_hash;
+
+    #[test]
+    fn hash_ignores_zero_padding() {
+        let elf = [0x7f, b'E', b'L', b'F', 1, 2];
+        let padded = [0x7f, b'E', b'L', b'F', 1, 2, 0, 0, 0];
+        assert_eq!(program_hash(&elf), program_hash(&padded));
+        assert_ne!(program_hash(&elf), program_hash(&elf[..5]));
+    }
+}
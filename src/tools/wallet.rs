@@ -0,0 +1,173 @@
+use std::{
+    fs,
+    num::NonZero,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::json;
+use solana_sdk::{signer::Signer, signer::keypair::Keypair};
+
+use crate::network::NetworkCtx;
+use crate::state::{STATE_WALLET, STATE_WALLET_KEYPAIR, record_state};
+use crate::tools::json_output::{push, record};
+use crate::tools::tx::airdrop_sol;
+
+pub fn save_keypair(keypair: &Keypair, path: impl AsRef<Path>) -> Result<()> {
+    let bytes = serde_json::to_string(&keypair.to_bytes().to_vec())?;
+    fs::write(&path, bytes).with_context(|| format!("failed to write {:?}", path.as_ref()))
+}
+
+/// Characters of base58 pubkeys.
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Grinds keypairs on every core until one's pubkey starts with `prefix`.
+fn vanity_keypair(prefix: &str) -> Keypair {
+    let found = Mutex::new(None);
+    let done = AtomicBool::new(false);
+    let threads = thread::available_parallelism().map_or(1, NonZero::get);
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let keypair = Keypair::new();
+                    if keypair.pubkey().to_string().starts_with(prefix)
+                        && !done.swap(true, Ordering::Relaxed)
+                    {
+                        *found.lock().unwrap() = Some(keypair);
+                    }
+                }
+            });
+        }
+    });
+    found
+        .into_inner()
+        .unwrap()
+        .expect("a thread found the keypair")
+}
+
+/// Generates `count` keypair files, optionally with pubkeys starting with
+/// `vanity`. A single keypair goes to `outfile`; several go to
+/// `<pubkey>.json` files in the `outfile` directory (default: the current one).
+pub fn keygen(outfile: Option<&Path>, vanity: Option<&str>, count: usize) -> Result<()> {
+    if let Some(prefix) = vanity
+        && let Some(invalid) = prefix.chars().find(|c| !BASE58_ALPHABET.contains(*c))
+    {
+        return Err(anyhow!(
+            "`{invalid}` never appears in a pubkey (base58 has no 0, O, I or l)"
+        ));
+    }
+    if let Some(dir) = outfile.filter(|_| count > 1) {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut keypairs = Vec::with_capacity(count);
+    for _ in 0..count {
+        let keypair = match vanity {
+            Some(prefix) => vanity_keypair(prefix),
+            None => Keypair::new(),
+        };
+        let pubkey = keypair.pubkey().to_string();
+        let path = match outfile {
+            Some(file) if count == 1 => file.to_path_buf(),
+            Some(dir) => dir.join(format!("{pubkey}.json")),
+            None => PathBuf::from(format!("{pubkey}.json")),
+        };
+        if path.exists() {
+            return Err(anyhow!("{} already exists", path.display()));
+        }
+        save_keypair(&keypair, &path)?;
+        say!("{pubkey} -> {}", path.display());
+        push("files", &path);
+        keypairs.push(json!({
+            "pubkey": pubkey,
+            "path": path.display().to_string(),
+        }));
+    }
+    record("keypairs", &keypairs);
+    Ok(())
+}
+
+pub fn make_wallets(
+    network: &NetworkCtx,
+    count: usize,
+    lamports: u64,
+    to_path: impl AsRef<Path>,
+) -> Result<()> {
+    fs::create_dir_all(&to_path)?;
+
+    let mut manifest = Vec::with_capacity(count);
+    for _ in 0..count {
+        let keypair = Keypair::new();
+        let pubkey = keypair.pubkey().to_string();
+        let path = to_path.as_ref().join(format!("{pubkey}.json"));
+        save_keypair(&keypair, &path)?;
+        if lamports > 0 {
+            airdrop_sol(network, &pubkey, lamports)?;
+        }
+        record_state(network, STATE_WALLET, &pubkey)?;
+        record_state(network, STATE_WALLET_KEYPAIR, &path.display().to_string())?;
+        manifest.push(json!({
+            "pubkey": pubkey,
+            "path": path.display().to_string(),
+        }));
+    }
+
+    let manifest_path = to_path.as_ref().join("wallets.json");
+    fs::write(&manifest_path, serde_json::to_string_pretty(&manifest)?)?;
+    record("wallets", &manifest);
+    push("files", &manifest_path);
+    say!(
+        "Generated {count} wallets, manifest saved to {}",
+        manifest_path.display()
+    );
+    Ok(())
+}
+
+/// Entry of the `wallets.json` manifest written by `make_wallets`.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct WalletEntry {
+    pub pubkey: String,
+    pub path: String,
+}
+
+pub fn load_wallet_manifest(path: impl AsRef<Path>) -> Result<Vec<WalletEntry>> {
+    let data =
+        fs::read_to_string(&path).with_context(|| format!("failed to read {:?}", path.as_ref()))?;
+    serde_json::from_str(&data).with_context(|| format!("invalid JSON in {:?}", path.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use solana_sdk::signer::Signer;
+
+    use super::{keygen, vanity_keypair};
+    use crate::tx_format::json_tx::parse_keypair;
+
+    #[test]
+    fn keygen_writes_loadable_vanity_keypairs() {
+        assert!(vanity_keypair("A").pubkey().to_string().starts_with('A'));
+        assert!(keygen(None, Some("0x"), 1).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        keygen(Some(dir.path()), Some("z"), 2).unwrap();
+        let files: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 2);
+        for path in files {
+            let keypair = parse_keypair(&path.display().to_string().into(), &[]).unwrap();
+            let pubkey = keypair.pubkey().to_string();
+            assert!(pubkey.starts_with('z'));
+            assert_eq!(path.file_stem().unwrap().to_str(), Some(pubkey.as_str()));
+        }
+    }
+}
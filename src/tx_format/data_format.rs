@@ -2,7 +2,10 @@ use anyhow::{Result, anyhow};
 use base64::{Engine as _, engine::general_purpose::STANDARD};
 use serde_json::Value;
 
-use crate::tx_format::{params::resolve_value, pubkey::parse_pubkey};
+use crate::tx_format::{
+    params::resolve_value,
+    pubkey::{find_pda, parse_pubkey},
+};
 
 const MAX_SAFE_INTEGER: u64 = 9_007_199_254_740_991;
 
@@ -25,6 +28,22 @@ fn parse_bool(value: &Value) -> Result<bool> {
     }
 }
 
+/// Bytes of `{"type": "raw", "encoding": "hex|base58|base64", "data": "..."}`
+/// data; hex may have a `0x` prefix.
+fn decode_raw(text: &str, encoding: &str) -> Result<Vec<u8>> {
+    match encoding {
+        "hex" => hex::decode(text.strip_prefix("0x").unwrap_or(text))
+            .map_err(|_| anyhow!("Invalid hex string")),
+        "base58" => bs58::decode(text)
+            .into_vec()
+            .map_err(|_| anyhow!("Invalid base58 string")),
+        "base64" => STANDARD
+            .decode(text)
+            .map_err(|_| anyhow!("Invalid base64 string")),
+        other => Err(anyhow!("Unsupported raw data encoding: {other}")),
+    }
+}
+
 pub fn pack_data(value: &Value, params: &[String]) -> Result<Vec<u8>> {
     let resolved = resolve_value(value, params);
 
@@ -38,8 +57,7 @@ pub fn pack_data(value: &Value, params: &[String]) -> Result<Vec<u8>> {
             Ok(vec![0u8; length])
         }
         Value::String(_) => {
-            let data = resolve_value(&resolved, params);
-            let data_str = data
+            let data_str = resolved
                 .as_str()
                 .ok_or_else(|| anyhow!("Invalid string data"))?;
             if let Some(hex) = data_str.strip_prefix("0x") {
@@ -107,6 +125,18 @@ pub fn pack_data(value: &Value, params: &[String]) -> Result<Vec<u8>> {
                     let pubkey = parse_pubkey(data, params)?;
                     Ok(pubkey.to_bytes().to_vec())
                 }
+                "pda_bump" => Ok(vec![find_pda(&map, params)?.1]),
+                "raw" => {
+                    let data = map.get("data").ok_or_else(|| anyhow!("Missing data"))?;
+                    let encoding = map
+                        .get("encoding")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| anyhow!("Missing encoding"))?;
+                    match resolve_value(data, params) {
+                        Value::String(text) => decode_raw(&text, encoding),
+                        _ => Err(anyhow!("Raw data must be a string")),
+                    }
+                }
                 "string" | "bytes" => {
                     let data = map.get("data").ok_or_else(|| anyhow!("Missing data"))?;
                     pack_data(data, params)
@@ -340,6 +370,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pack_raw_data_with_encoding() {
+        let raw = |encoding, data| json!({ "type": "raw", "encoding": encoding, "data": data });
+        assert_eq!(pack_data(&raw("hex", "0x0102"), &[]).unwrap(), [1, 2]);
+        assert_eq!(pack_data(&raw("hex", "0102"), &[]).unwrap(), [1, 2]);
+        assert_eq!(pack_data(&raw("base58", "5"), &[]).unwrap(), [4]);
+        assert_eq!(pack_data(&raw("base64", "AQI="), &[]).unwrap(), [1, 2]);
+        let params = ["3yZe7d".to_string()];
+        assert_eq!(
+            pack_data(&raw("base58", "$1"), &params).unwrap(),
+            bs58::decode("3yZe7d").into_vec().unwrap()
+        );
+        assert!(pack_data(&raw("base32", "AA"), &[]).is_err());
+        assert!(pack_data(&json!({ "type": "raw", "data": "00" }), &[]).is_err());
+    }
+
     #[test]
     fn pack_object_from_param_string() {
         let params = vec![r#"[{"type":"u8","data":7}]"#.to_string()];
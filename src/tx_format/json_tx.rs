@@ -1,24 +1,72 @@
-use std::{fs, path::Path, str::FromStr};
+use std::{env, fs, path::Path};
 
 use anyhow::{Context, Result, anyhow};
-use serde_json::Value;
+use serde_json::{Map, Value};
+use solana_derivation_path::DerivationPath;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signer::keypair::Keypair,
+    signer::{
+        Signer,
+        keypair::{Keypair, keypair_from_seed_and_derivation_path},
+    },
 };
+use solana_seed_phrase::generate_seed_from_seed_phrase_and_passphrase;
 
 use crate::tx_format::{
     RawInstruction, RawTransaction,
     data_format::pack_data,
-    params::resolve_value,
+    migrate::migrate_tx,
+    params::{anchor_file_refs, check_external_refs, resolve_value},
     pubkey::parse_pubkey,
-    raw_tx::{close_ata_tx, create_ata_tx, set_cu_limit_tx, transfer_tx},
+    raw_tx::{close_ata_tx, create_ata_tx, set_cu_limit_tx, set_cu_price_tx, transfer_tx},
+    strict::{check_fields, is_strict},
 };
+use crate::utils::{read_input, warn_if_stale};
+
+/// Environment variable holding the phrase of a `mnemonic` signer without one.
+pub const MNEMONIC_ENV: &str = "SOLTNET_MNEMONIC";
+
+/// Derivation path of a `mnemonic` signer without one: the first account of
+/// Phantom, Solflare and Ledger.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+/// Keypair derived from a BIP39 seed phrase like wallets do:
+/// `{"type": "mnemonic", "phrase": "...", "path": "m/44'/501'/0'/0'", "passphrase": "..."}`.
+fn mnemonic_keypair(map: &Map<String, Value>, params: &[String]) -> Result<Keypair> {
+    let field = |key: &str| -> Result<Option<String>> {
+        match map.get(key).map(|value| resolve_value(value, params)) {
+            None => Ok(None),
+            Some(Value::String(text)) => Ok(Some(text)),
+            Some(_) => Err(anyhow!("Mnemonic `{key}` must be a string")),
+        }
+    };
+    let phrase = match field("phrase")? {
+        Some(phrase) => phrase,
+        None => env::var(MNEMONIC_ENV)
+            .map_err(|_| anyhow!("Mnemonic signer needs a `phrase` or {MNEMONIC_ENV}"))?,
+    };
+    let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Any text seeds a keypair, so a typo would silently give another wallet.
+    bip39::Mnemonic::parse_in_normalized(bip39::Language::English, &phrase)
+        .map_err(|err| anyhow!("Invalid mnemonic phrase: {err}"))?;
+    let path = field("path")?.unwrap_or_else(|| DEFAULT_DERIVATION_PATH.to_string());
+    let derivation_path = DerivationPath::from_absolute_path_str(&path)
+        .map_err(|err| anyhow!("Invalid derivation path {path}: {err}"))?;
+    let seed = generate_seed_from_seed_phrase_and_passphrase(
+        &phrase,
+        &field("passphrase")?.unwrap_or_default(),
+    );
+    keypair_from_seed_and_derivation_path(&seed, Some(derivation_path))
+        .map_err(|err| anyhow!("Invalid mnemonic keypair: {err}"))
+}
 
 pub fn parse_keypair(value: &Value, params: &[String]) -> Result<Keypair> {
     let resolved = resolve_value(value, params);
     match resolved {
+        Value::Object(map) if map.get("type").and_then(Value::as_str) == Some("mnemonic") => {
+            mnemonic_keypair(&map, params)
+        }
         Value::String(path) => {
             let data = fs::read_to_string(&path)
                 .with_context(|| format!("failed to read keypair file {path}"))?;
@@ -47,16 +95,26 @@ fn value_as_string(value: &Value, label: &str) -> Result<String> {
         .ok_or_else(|| anyhow!("Missing or invalid {label}"))
 }
 
-fn parse_ix_from_json(ix: &RawInstruction, params: &[String]) -> Result<Instruction> {
-    match ix.program_id.as_str() {
+/// Expands a shorthand instruction (`set_cu_limit`, `set_cu_price`, `transfer`,
+/// `create_ata`, `close_ata`) into its explicit form; `None` for explicit
+/// instructions.
+pub fn expand_shorthand(ix: &RawInstruction) -> Result<Option<RawInstruction>> {
+    let raw = match ix.program_id.as_str().unwrap_or_default() {
         "set_cu_limit" => {
             let limit = ix
                 .extra
                 .get("limit")
                 .and_then(Value::as_u64)
                 .ok_or_else(|| anyhow!("Missing limit"))? as u32;
-            let raw = set_cu_limit_tx(limit);
-            parse_ix_from_json(&raw, params)
+            set_cu_limit_tx(limit)
+        }
+        "set_cu_price" => {
+            let micro_lamports = ix
+                .extra
+                .get("micro_lamports")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("Missing micro_lamports"))?;
+            set_cu_price_tx(micro_lamports)
         }
         "transfer" => {
             let from = ix
@@ -68,12 +126,11 @@ fn parse_ix_from_json(ix: &RawInstruction, params: &[String]) -> Result<Instruct
                 .extra
                 .get("amount")
                 .ok_or_else(|| anyhow!("Missing amount"))?;
-            let raw = transfer_tx(
+            transfer_tx(
                 &value_as_string(from, "from")?,
                 &value_as_string(to, "to")?,
                 amount,
-            );
-            parse_ix_from_json(&raw, params)
+            )
         }
         "create_ata" => {
             let owner = ix
@@ -84,11 +141,10 @@ fn parse_ix_from_json(ix: &RawInstruction, params: &[String]) -> Result<Instruct
                 .extra
                 .get("mint")
                 .ok_or_else(|| anyhow!("Missing mint"))?;
-            let raw = create_ata_tx(
+            create_ata_tx(
                 &value_as_string(owner, "owner")?,
                 &value_as_string(mint, "mint")?,
-            );
-            parse_ix_from_json(&raw, params)
+            )
         }
         "close_ata" => {
             let owner = ix
@@ -99,32 +155,61 @@ fn parse_ix_from_json(ix: &RawInstruction, params: &[String]) -> Result<Instruct
                 .extra
                 .get("mint")
                 .ok_or_else(|| anyhow!("Missing mint"))?;
-            let raw = close_ata_tx(
+            close_ata_tx(
                 &value_as_string(owner, "owner")?,
                 &value_as_string(mint, "mint")?,
-            );
-            parse_ix_from_json(&raw, params)
+            )
         }
-        _ => {
-            let program_id = Pubkey::from_str(&ix.program_id)
-                .map_err(|err| anyhow!("Invalid program id {}: {err}", ix.program_id))?;
-            let mut accounts = Vec::new();
-            for acc in &ix.accounts {
-                let pubkey = parse_pubkey(&acc.pubkey, params)?;
-                accounts.push(AccountMeta {
-                    pubkey,
-                    is_signer: acc.is_signer,
-                    is_writable: acc.is_writable,
-                });
+        _ => return Ok(None),
+    };
+    Ok(Some(raw))
+}
+
+/// Index of a `{"type":"signer","index":N}` account into the `signers` of
+/// the template.
+fn signer_index(value: &Value) -> Option<Result<usize>> {
+    (value.get("type")? == "signer").then(|| {
+        value
+            .get("index")
+            .and_then(Value::as_u64)
+            .map(|index| index as usize)
+            .ok_or_else(|| anyhow!("Missing index for signer pubkey"))
+    })
+}
+
+fn parse_ix_from_json(
+    ix: &RawInstruction,
+    params: &[String],
+    signers: &[Pubkey],
+) -> Result<Instruction> {
+    if let Some(raw) = expand_shorthand(ix)? {
+        return parse_ix_from_json(&raw, params, signers);
+    }
+    let program_id = parse_pubkey(&ix.program_id, params)
+        .with_context(|| format!("Invalid program id {}", ix.program_id))?;
+    let mut accounts = Vec::new();
+    for acc in &ix.accounts {
+        let pubkey = match signer_index(&acc.pubkey) {
+            Some(index) => {
+                let index = index?;
+                *signers.get(index).ok_or_else(|| {
+                    anyhow!("Signer {index} is out of range ({} signers)", signers.len())
+                })?
             }
-            let data = pack_data(&ix.data, params)?;
-            Ok(Instruction {
-                program_id,
-                accounts,
-                data,
-            })
-        }
+            None => parse_pubkey(&acc.pubkey, params)?,
+        };
+        accounts.push(AccountMeta {
+            pubkey,
+            is_signer: acc.is_signer,
+            is_writable: acc.is_writable,
+        });
     }
+    let data = pack_data(&ix.data, params)?;
+    Ok(Instruction {
+        program_id,
+        accounts,
+        data,
+    })
 }
 
 #[derive(Debug)]
@@ -134,15 +219,48 @@ pub struct ParsedTransaction {
     pub lookup_tables: Vec<Pubkey>,
 }
 
+impl ParsedTransaction {
+    /// Adds a signer unless a keypair with the same pubkey is already present.
+    pub fn add_signer(&mut self, keypair: Keypair) -> Pubkey {
+        let pubkey = keypair.pubkey();
+        if !self.signers.iter().any(|signer| signer.pubkey() == pubkey) {
+            self.signers.push(keypair);
+        }
+        pubkey
+    }
+
+    /// Sets the compute unit price of the transaction, replacing any price
+    /// instruction of the template.
+    pub fn set_cu_price(&mut self, micro_lamports: u64) -> Result<()> {
+        let price = parse_ix_from_json(&set_cu_price_tx(micro_lamports), &[], &[])?;
+        self.instructions.retain(|ix| {
+            ix.program_id != price.program_id || ix.data.first() != price.data.first()
+        });
+        self.instructions.insert(0, price);
+        Ok(())
+    }
+
+    /// Returns true if any instruction marks `pubkey` as a signer.
+    pub fn requires_signature(&self, pubkey: &Pubkey) -> bool {
+        self.instructions.iter().any(|ix| {
+            ix.accounts
+                .iter()
+                .any(|acc| acc.is_signer && acc.pubkey == *pubkey)
+        })
+    }
+}
+
 pub fn parse_tx_from_json(tx: &RawTransaction, params: &[String]) -> Result<ParsedTransaction> {
+    check_external_refs(&serde_json::to_value(tx)?, params)?;
+    let keypairs = tx
+        .signers
+        .iter()
+        .map(|signer| parse_keypair(signer, params))
+        .collect::<Result<Vec<_>>>()?;
+    let signer_keys: Vec<Pubkey> = keypairs.iter().map(Signer::pubkey).collect();
     let mut instructions = Vec::with_capacity(tx.instructions.len());
     for ix in &tx.instructions {
-        instructions.push(parse_ix_from_json(ix, params)?);
-    }
-
-    let mut signers = Vec::with_capacity(tx.signers.len());
-    for signer in &tx.signers {
-        signers.push(parse_keypair(signer, params)?);
+        instructions.push(parse_ix_from_json(ix, params, &signer_keys)?);
     }
 
     let mut lookup_tables = Vec::new();
@@ -152,17 +270,86 @@ pub fn parse_tx_from_json(tx: &RawTransaction, params: &[String]) -> Result<Pars
         }
     }
 
-    Ok(ParsedTransaction {
+    let mut parsed = ParsedTransaction {
         instructions,
-        signers,
+        signers: Vec::with_capacity(signer_keys.len()),
         lookup_tables,
-    })
+    };
+    for keypair in keypairs {
+        let pubkey = keypair.pubkey();
+        if parsed.signers.iter().any(|s| s.pubkey() == pubkey) {
+            eprintln!("Warning: duplicate signer {pubkey} ignored");
+            continue;
+        }
+        // The first signer is the default fee payer, so it is always used.
+        if !parsed.signers.is_empty() && !parsed.requires_signature(&pubkey) {
+            eprintln!("Warning: signer {pubkey} is not required by any instruction");
+        }
+        parsed.add_signer(keypair);
+    }
+
+    Ok(parsed)
+}
+
+/// Transactions of a template value: a single transaction, an array of them
+/// or an object with a `transactions` array.
+pub fn raw_txs_from_value(value: Value) -> Result<Vec<RawTransaction>> {
+    let batch = match value {
+        Value::Array(batch) => batch,
+        Value::Object(mut map) if map.contains_key("transactions") => {
+            match map.remove("transactions") {
+                Some(Value::Array(batch)) => batch,
+                _ => return Err(anyhow!("`transactions` must be an array")),
+            }
+        }
+        single => return Ok(vec![raw_tx_from_value(single)?]),
+    };
+    if batch.is_empty() {
+        return Err(anyhow!("Transaction batch is empty"));
+    }
+    batch
+        .into_iter()
+        .enumerate()
+        .map(|(index, tx)| {
+            raw_tx_from_value(tx).with_context(|| format!("Invalid transaction {}", index + 1))
+        })
+        .collect()
+}
+
+/// Upgrades one transaction of a template to the current version and, in
+/// strict mode, rejects fields soltnet does not read.
+fn raw_tx_from_value(mut tx: Value) -> Result<RawTransaction> {
+    migrate_tx(&mut tx)?;
+    if is_strict() {
+        check_fields(&tx)?;
+    }
+    Ok(serde_json::from_value(tx)?)
+}
+
+/// Loads every transaction of a template or batch file, in order.
+pub fn load_raw_txs_from_json(path: impl AsRef<Path>) -> Result<Vec<RawTransaction>> {
+    let data = read_input(path.as_ref())?;
+    serde_json::from_str(&data)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut value| {
+            warn_if_stale(&value, &path.as_ref().display().to_string());
+            if let Some(dir) = path.as_ref().parent() {
+                anchor_file_refs(&mut value, dir);
+            }
+            raw_txs_from_value(value)
+        })
+        .with_context(|| format!("Invalid JSON in {:?}", path.as_ref()))
 }
 
 pub fn load_raw_tx_from_json(path: impl AsRef<Path>) -> Result<RawTransaction> {
-    let data = fs::read_to_string(&path)
-        .with_context(|| format!("Error reading file {:?}", path.as_ref()))?;
-    serde_json::from_str(&data).with_context(|| format!("Invalid JSON in {:?}", path.as_ref()))
+    let mut batch = load_raw_txs_from_json(&path)?;
+    match batch.len() {
+        1 => Ok(batch.remove(0)),
+        count => Err(anyhow!(
+            "{:?} holds {count} transactions; only exec-tx runs batch files",
+            path.as_ref()
+        )),
+    }
 }
 
 pub fn load_parsed_tx_from_json(
@@ -172,3 +359,163 @@ pub fn load_parsed_tx_from_json(
     let tx = load_raw_tx_from_json(path)?;
     parse_tx_from_json(&tx, params)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_keypair, parse_tx_from_json, raw_txs_from_value};
+    use crate::tx_format::RawTransaction;
+    use serde_json::json;
+    use solana_sdk::signer::{Signer, keypair::Keypair};
+
+    #[test]
+    fn parse_tx_deduplicates_signers() {
+        let keypair = Keypair::new();
+        let bytes = json!(keypair.to_bytes().to_vec());
+        let raw = RawTransaction {
+            instructions: Vec::new(),
+            signers: vec![bytes.clone(), bytes],
+            lookup_tables: None,
+            hooks: None,
+            version: None,
+            stamp: None,
+        };
+        let parsed = parse_tx_from_json(&raw, &[]).expect("parse");
+        assert_eq!(parsed.signers.len(), 1);
+        assert_eq!(parsed.signers[0].pubkey(), keypair.pubkey());
+    }
+
+    #[test]
+    fn cu_price_shorthand_and_override() {
+        let raw: RawTransaction = serde_json::from_value(json!({
+            "instructions": [
+                { "program_id": "set_cu_limit", "limit": 200000 },
+                { "program_id": "set_cu_price", "micro_lamports": 1000 }
+            ],
+            "signers": []
+        }))
+        .unwrap();
+        let mut parsed = parse_tx_from_json(&raw, &[]).expect("parse");
+        let mut price = vec![3];
+        price.extend(1000u64.to_le_bytes());
+        assert_eq!(parsed.instructions[1].data, price);
+
+        parsed.set_cu_price(5).unwrap();
+        let mut price = vec![3];
+        price.extend(5u64.to_le_bytes());
+        assert_eq!(parsed.instructions.len(), 2);
+        assert_eq!(parsed.instructions[0].data, price);
+        assert_eq!(parsed.instructions[1].data[0], 2);
+    }
+
+    #[test]
+    fn accepts_camel_case_keys() {
+        let authority = Keypair::new();
+        let raw: RawTransaction = serde_json::from_value(json!({
+            "instructions": [
+                { "programId": "set_cu_price", "microLamports": 1000 },
+                {
+                    "programId": "11111111111111111111111111111111",
+                    "accounts": [{ "pubkey": "$1", "isSigner": true, "isWritable": true }]
+                }
+            ],
+            "signers": [authority.to_bytes().to_vec()],
+            "lookupTables": []
+        }))
+        .unwrap();
+        assert_eq!(raw.lookup_tables, Some(Vec::new()));
+        let parsed = parse_tx_from_json(&raw, &[authority.pubkey().to_string()]).expect("parse");
+        assert_eq!(parsed.instructions[0].data[0], 3);
+        let account = &parsed.instructions[1].accounts[0];
+        assert!(account.is_signer && account.is_writable);
+    }
+
+    #[test]
+    fn resolves_program_ids_like_pubkeys() {
+        let program = solana_sdk::pubkey::Pubkey::new_unique();
+        let raw: RawTransaction = serde_json::from_value(json!({
+            "instructions": [
+                { "program_id": "$1" },
+                { "program_id": { "type": "system_program" } },
+                { "program_id": program.to_string() }
+            ],
+            "signers": []
+        }))
+        .unwrap();
+        let parsed = parse_tx_from_json(&raw, &[program.to_string()]).expect("parse");
+        let programs: Vec<_> = parsed.instructions.iter().map(|ix| ix.program_id).collect();
+        assert_eq!(
+            programs,
+            [program, crate::accounts::SYSTEM_PROGRAM_ID, program]
+        );
+    }
+
+    #[test]
+    fn reads_single_and_batch_templates() {
+        let tx = json!({ "instructions": [], "signers": [] });
+        assert_eq!(raw_txs_from_value(tx.clone()).unwrap().len(), 1);
+        assert_eq!(raw_txs_from_value(json!([tx, tx])).unwrap().len(), 2);
+        let keyed = json!({ "transactions": [tx, tx, tx] });
+        assert_eq!(raw_txs_from_value(keyed).unwrap().len(), 3);
+        assert!(raw_txs_from_value(json!([])).is_err());
+        let error = raw_txs_from_value(json!([tx, { "signers": [] }])).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid transaction 2");
+    }
+
+    #[test]
+    fn accounts_reference_signers_and_keypair_files() {
+        let payer = Keypair::new();
+        let authority = Keypair::new();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("authority.json");
+        std::fs::write(&path, json!(authority.to_bytes().to_vec()).to_string()).unwrap();
+        let raw: RawTransaction = serde_json::from_value(json!({
+            "instructions": [{
+                "program_id": "11111111111111111111111111111111",
+                "accounts": [
+                    { "pubkey": { "type": "signer", "index": 1 }, "is_signer": true },
+                    { "pubkey": { "type": "keypair", "path": "$1" } }
+                ]
+            }],
+            "signers": [payer.to_bytes().to_vec(), "$1"]
+        }))
+        .unwrap();
+        let params = [path.display().to_string()];
+        let parsed = parse_tx_from_json(&raw, &params).expect("parse");
+        let accounts = &parsed.instructions[0].accounts;
+        assert_eq!(accounts[0].pubkey, authority.pubkey());
+        assert_eq!(accounts[1].pubkey, authority.pubkey());
+
+        let mut out_of_range = raw.clone();
+        out_of_range.signers.pop();
+        assert!(parse_tx_from_json(&out_of_range, &params).is_err());
+    }
+
+    #[test]
+    fn derives_mnemonic_signers() {
+        let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let mnemonic = |path: &str| json!({ "type": "mnemonic", "phrase": "$1", "path": path });
+        let params = [phrase.to_string()];
+        let first = parse_keypair(&mnemonic("m/44'/501'/0'/0'"), &params).unwrap();
+        let second = parse_keypair(&mnemonic("m/44'/501'/1'/0'"), &params).unwrap();
+        assert_eq!(
+            first.pubkey().to_string(),
+            "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+        );
+        assert_ne!(first.pubkey(), second.pubkey());
+        let default_path = json!({ "type": "mnemonic", "phrase": format!("  {phrase}\n") });
+        assert_eq!(
+            parse_keypair(&default_path, &[]).unwrap().pubkey(),
+            first.pubkey()
+        );
+        assert!(parse_keypair(&mnemonic("m/x"), &params).is_err());
+
+        let typo = [phrase.replace("about", "abuot")];
+        let error = parse_keypair(&mnemonic("m/44'/501'/0'/0'"), &typo).unwrap_err();
+        assert!(
+            error.to_string().contains("Invalid mnemonic phrase"),
+            "{error}"
+        );
+        let bad_checksum = [phrase.replace("about", "abandon")];
+        assert!(parse_keypair(&mnemonic("m/44'/501'/0'/0'"), &bad_checksum).is_err());
+    }
+}
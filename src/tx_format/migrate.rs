@@ -0,0 +1,136 @@
+use anyhow::{Result, anyhow};
+use serde_json::{Map, Value};
+
+/// Version of the template layout written by this soltnet; templates without
+/// a `version` are version 0.
+pub const TEMPLATE_VERSION: u64 = 1;
+
+/// Upgrade of a template from the version at its index to the next one.
+struct Migration {
+    description: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+const MIGRATIONS: [Migration; TEMPLATE_VERSION as usize] = [Migration {
+    description: "renamed camelCase programId, isSigner, isWritable and lookupTables keys",
+    apply: snake_case_keys,
+}];
+
+fn rename(map: &mut Map<String, Value>, from: &str, to: &str) {
+    if !map.contains_key(to)
+        && let Some(value) = map.remove(from)
+    {
+        map.insert(to.to_string(), value);
+    }
+}
+
+/// Version 0 templates were often copied from RPC or `parse-block` output,
+/// which names keys in camelCase.
+fn snake_case_keys(tx: &mut Map<String, Value>) {
+    rename(tx, "lookupTables", "lookup_tables");
+    let instructions = tx.get_mut("instructions").and_then(Value::as_array_mut);
+    for ix in instructions
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+    {
+        rename(ix, "programId", "program_id");
+        let accounts = ix.get_mut("accounts").and_then(Value::as_array_mut);
+        for account in accounts
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_object_mut)
+        {
+            rename(account, "isSigner", "is_signer");
+            rename(account, "isWritable", "is_writable");
+        }
+    }
+}
+
+/// Upgrades one transaction of a template to [`TEMPLATE_VERSION`] and
+/// returns what changed.
+pub fn migrate_tx(tx: &mut Value) -> Result<Vec<&'static str>> {
+    let Some(map) = tx.as_object_mut() else {
+        return Ok(Vec::new());
+    };
+    let version = match map.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow!("Invalid template version {version}"))?,
+    };
+    if version > TEMPLATE_VERSION {
+        return Err(anyhow!(
+            "Template version {version} is newer than this soltnet supports ({TEMPLATE_VERSION})"
+        ));
+    }
+    let applied = MIGRATIONS[version as usize..]
+        .iter()
+        .map(|migration| {
+            (migration.apply)(map);
+            migration.description
+        })
+        .collect();
+    map.insert("version".to_string(), TEMPLATE_VERSION.into());
+    Ok(applied)
+}
+
+/// Upgrades every transaction of a template or batch (see
+/// [`crate::tx_format::json_tx::raw_txs_from_value`]).
+pub fn migrate_template(value: &mut Value) -> Result<Vec<&'static str>> {
+    let batch = match value {
+        Value::Array(batch) => batch,
+        Value::Object(map) if map.contains_key("transactions") => {
+            match map.get_mut("transactions") {
+                Some(Value::Array(batch)) => batch,
+                _ => return Err(anyhow!("`transactions` must be an array")),
+            }
+        }
+        single => return migrate_tx(single),
+    };
+    let mut applied = Vec::new();
+    for tx in batch {
+        for description in migrate_tx(tx)? {
+            if !applied.contains(&description) {
+                applied.push(description);
+            }
+        }
+    }
+    Ok(applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{TEMPLATE_VERSION, migrate_template};
+
+    #[test]
+    fn upgrades_unversioned_templates() {
+        let mut tx = json!({
+            "instructions": [{
+                "programId": "11111111111111111111111111111111",
+                "accounts": [{ "pubkey": "$1", "isSigner": true, "isWritable": true }]
+            }],
+            "signers": ["$1"]
+        });
+        let mut batch = json!([tx.clone(), tx.clone()]);
+        assert_eq!(migrate_template(&mut tx).unwrap().len(), 1);
+        assert_eq!(tx["version"], json!(TEMPLATE_VERSION));
+        assert_eq!(
+            tx["instructions"][0]["program_id"],
+            json!("11111111111111111111111111111111")
+        );
+        assert_eq!(
+            tx["instructions"][0]["accounts"][0]["is_signer"],
+            json!(true)
+        );
+        assert!(migrate_template(&mut tx).unwrap().is_empty());
+
+        assert_eq!(migrate_template(&mut batch).unwrap().len(), 1);
+        assert_eq!(batch[1]["version"], json!(TEMPLATE_VERSION));
+        let mut future =
+            json!({ "version": TEMPLATE_VERSION + 1, "instructions": [], "signers": [] });
+        assert!(migrate_template(&mut future).is_err());
+    }
+}
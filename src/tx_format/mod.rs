@@ -1,37 +1,117 @@
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
 
 pub mod data_format;
 pub mod json_tx;
+pub mod migrate;
 pub mod params;
+#[cfg(feature = "cli")]
 pub mod parse_tx;
 pub mod pubkey;
 pub mod raw_tx;
+pub mod strict;
+
+/// Keys soltnet reads or writes, in snake_case and camelCase; templates may
+/// use either.
+pub const KEY_CASES: [(&str, &str); 10] = [
+    ("program_id", "programId"),
+    ("is_signer", "isSigner"),
+    ("is_writable", "isWritable"),
+    ("lookup_tables", "lookupTables"),
+    ("allow_failure", "allowFailure"),
+    ("micro_lamports", "microLamports"),
+    ("depends_on", "dependsOn"),
+    ("balance_change", "balanceChange"),
+    ("pre_balance", "preBalance"),
+    ("post_balance", "postBalance"),
+];
+
+/// snake_case spelling of a key of [`KEY_CASES`]; other keys are returned as they are.
+pub fn snake_key(key: &str) -> &str {
+    KEY_CASES
+        .iter()
+        .find(|(_, camel)| *camel == key)
+        .map_or(key, |(snake, _)| snake)
+}
+
+/// camelCase spelling of a key of [`KEY_CASES`]; other keys are returned as they are.
+pub fn camel_key(key: &str) -> &str {
+    KEY_CASES
+        .iter()
+        .find(|(snake, _)| *snake == key)
+        .map_or(key, |(_, camel)| camel)
+}
+
+/// Shorthand fields with their snake_case keys, whichever casing the template uses.
+fn snake_case_extra<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Map<String, Value>, D::Error> {
+    let extra = Map::deserialize(deserializer)?;
+    Ok(extra
+        .into_iter()
+        .map(|(key, value)| (snake_key(&key).to_string(), value))
+        .collect())
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RawAccountMeta {
     pub pubkey: Value,
-    #[serde(default)]
+    #[serde(default, alias = "isSigner")]
     pub is_signer: bool,
-    #[serde(default)]
+    #[serde(default, alias = "isWritable")]
     pub is_writable: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RawInstruction {
-    pub program_id: String,
+    /// A pubkey in any form accounts take (see [`pubkey::parse_pubkey`]) or
+    /// the name of a shorthand
+    #[serde(alias = "programId")]
+    pub program_id: Value,
     #[serde(default)]
     pub data: Value,
     #[serde(default)]
     pub accounts: Vec<RawAccountMeta>,
-    #[serde(flatten)]
-    pub extra: serde_json::Map<String, Value>,
+    #[serde(flatten, deserialize_with = "snake_case_extra")]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RawTransaction {
     pub instructions: Vec<RawInstruction>,
     pub signers: Vec<Value>,
-    #[serde(default)]
+    #[serde(default, alias = "lookupTables")]
     pub lookup_tables: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<RawHooks>,
+    /// Layout version (see [`migrate::TEMPLATE_VERSION`]); absent in old templates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    /// Header stamped by `parse-tx` (see [`crate::utils::stamp`])
+    #[serde(rename = "_soltnet", default, skip_serializing_if = "Option::is_none")]
+    pub stamp: Option<Value>,
+}
+
+/// Commands or templates run around the execution of a template.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RawHooks {
+    #[serde(default)]
+    pub pre: Vec<RawHook>,
+    #[serde(default)]
+    pub post: Vec<RawHook>,
+}
+
+/// A hook either runs a shell command (`run`) or executes another template
+/// (`template`, relative to the declaring template) with `params`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RawHook {
+    #[serde(default)]
+    pub run: Option<String>,
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub params: Vec<Value>,
+    /// Report a failing hook instead of aborting
+    #[serde(default, alias = "allowFailure")]
+    pub allow_failure: bool,
 }
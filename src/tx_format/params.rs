@@ -1,23 +1,278 @@
+use std::{collections::HashMap, env, fs, path::Path, sync::OnceLock};
+
+use anyhow::{Context, Result, anyhow};
 use serde_json::Value;
 
+/// Prefix of built-in variables describing the current testnet, e.g. `$local:payer`.
+pub const LOCAL_VAR_PREFIX: &str = "$local:";
+
+/// Prefix of values recorded by earlier commands, e.g. `$state:lookup_table.latest`.
+pub const STATE_VAR_PREFIX: &str = "$state:";
+
+/// `${env:VAR}` resolves to an environment variable.
+pub const ENV_VAR_PREFIX: &str = "${env:";
+
+/// `${file:path}` resolves to the contents of a file: the JSON array or object
+/// it holds (e.g. a keypair or byte array), otherwise its text. Relative paths
+/// in a template are relative to the template's directory.
+pub const FILE_VAR_PREFIX: &str = "${file:";
+
+static LOCAL_VARS: OnceLock<HashMap<String, String>> = OnceLock::new();
+static STATE_VARS: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Sets the values of `$local:<name>` variables; only the first call has an effect.
+pub fn set_local_vars(vars: HashMap<String, String>) {
+    let _ = LOCAL_VARS.set(vars);
+}
+
+pub fn local_var(name: &str) -> Option<&'static str> {
+    LOCAL_VARS.get()?.get(name).map(String::as_str)
+}
+
+/// Sets the values of `$state:<kind>.<selector>` variables; only the first call has an effect.
+pub fn set_state_vars(vars: HashMap<String, String>) {
+    let _ = STATE_VARS.set(vars);
+}
+
+pub fn state_var(name: &str) -> Option<&'static str> {
+    STATE_VARS.get()?.get(name).map(String::as_str)
+}
+
+/// A leading `$$` stands for a literal `$`: `$$1` is the string `$1`.
+pub const ESCAPED_DOLLAR: &str = "$$";
+
+/// Zero-based index of a `$N` param: `N` is all decimal digits without a
+/// leading zero, so `$10` is the tenth param and `$01`, `$+1` or `$1a` are
+/// not params.
 pub fn param_index(value: &str) -> Option<usize> {
-    if let Some(stripped) = value.strip_prefix('$') {
-        if let Ok(index) = stripped.parse::<usize>() {
-            if index > 0 {
-                return Some(index - 1);
+    let digits = value.strip_prefix('$')?;
+    if digits.starts_with('0') || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse::<usize>().ok()?.checked_sub(1)
+}
+
+fn file_value(path: &str) -> Result<Value> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Error reading `${{file:{path}}}`"))?;
+    let text = text.trim_end_matches(['\r', '\n']);
+    if text.starts_with(['[', '{']) {
+        return serde_json::from_str(text)
+            .with_context(|| format!("Invalid JSON in `${{file:{path}}}`"));
+    }
+    Ok(Value::String(text.to_string()))
+}
+
+/// Value at `path` in `value`: `.`-separated keys, each optionally followed
+/// by `[index]`s, e.g. `instructions[0].accounts[3].pubkey`.
+pub fn json_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        let (key, indexes) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+        if !key.is_empty() {
+            current = current.get(key)?;
+        }
+        for index in indexes.split('[').skip(1) {
+            current = current.get(index.strip_suffix(']')?.parse::<usize>().ok()?)?;
+        }
+    }
+    Some(current)
+}
+
+/// Param taken from a JSON file, e.g. a `parse-tx` output, given as
+/// `<file>:<path>` (see [`json_path`]). Strings are used as they are, other
+/// values as compact JSON.
+pub fn param_from_json(spec: &str) -> Result<String> {
+    let (file, path) = spec
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Expected <file>:<path>, got `{spec}`"))?;
+    let text = fs::read_to_string(file).with_context(|| format!("Error reading file {file:?}"))?;
+    let value: Value =
+        serde_json::from_str(&text).with_context(|| format!("Invalid JSON in {file:?}"))?;
+    match json_path(&value, path) {
+        Some(Value::String(text)) => Ok(text.clone()),
+        Some(Value::Null) | None => Err(anyhow!("No value at `{path}` in {file}")),
+        Some(other) => Ok(other.to_string()),
+    }
+}
+
+/// Value of an `${env:VAR}` or `${file:path}` reference, which may also be
+/// passed as a param; `None` when `s` is no such reference.
+fn external_value(s: &str) -> Option<Result<Value>> {
+    if let Some(name) = s
+        .strip_prefix(ENV_VAR_PREFIX)
+        .and_then(|s| s.strip_suffix('}'))
+    {
+        return Some(
+            env::var(name)
+                .map(Value::String)
+                .with_context(|| format!("Environment variable {name} of `{s}` is not set")),
+        );
+    }
+    let path = s
+        .strip_prefix(FILE_VAR_PREFIX)
+        .and_then(|s| s.strip_suffix('}'))?;
+    Some(file_value(path))
+}
+
+/// Makes the relative `${file:path}` references of a template relative to
+/// the template's directory `dir`.
+pub fn anchor_file_refs(value: &mut Value, dir: &Path) {
+    match value {
+        Value::String(s) => {
+            if let Some(path) = s
+                .strip_prefix(FILE_VAR_PREFIX)
+                .and_then(|s| s.strip_suffix('}'))
+                && Path::new(path).is_relative()
+            {
+                *s = format!("{FILE_VAR_PREFIX}{}}}", dir.join(path).display());
             }
         }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| anchor_file_refs(item, dir)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| anchor_file_refs(item, dir)),
+        _ => {}
+    }
+}
+
+/// Fails on the first `${env:VAR}` or `${file:path}` reference in `value` or
+/// `params` that does not resolve, so a missing variable or file is reported
+/// instead of ending up in the transaction as text.
+pub fn check_external_refs(value: &Value, params: &[String]) -> Result<()> {
+    for param in params {
+        if let Some(resolved) = external_value(param) {
+            resolved?;
+        }
+    }
+    check_value_refs(value)
+}
+
+fn check_value_refs(value: &Value) -> Result<()> {
+    match value {
+        Value::String(s) => match external_value(s) {
+            Some(resolved) => resolved.map(drop),
+            None => Ok(()),
+        },
+        Value::Array(items) => items.iter().try_for_each(check_value_refs),
+        Value::Object(map) => map.values().try_for_each(check_value_refs),
+        _ => Ok(()),
     }
-    None
 }
 
 pub fn resolve_value(value: &Value, params: &[String]) -> Value {
     if let Value::String(s) = value {
-        if let Some(index) = param_index(s) {
-            if let Some(param) = params.get(index) {
-                return Value::String(param.clone());
-            }
+        if s.starts_with(ESCAPED_DOLLAR) {
+            return Value::String(s[1..].to_string());
+        }
+        if let Some(index) = param_index(s)
+            && let Some(param) = params.get(index)
+        {
+            return match external_value(param) {
+                Some(Ok(value)) => value,
+                _ => Value::String(param.clone()),
+            };
+        }
+        if let Some(name) = s.strip_prefix(LOCAL_VAR_PREFIX)
+            && let Some(var) = local_var(name)
+        {
+            return Value::String(var.to_string());
+        }
+        if let Some(name) = s.strip_prefix(STATE_VAR_PREFIX)
+            && let Some(var) = state_var(name)
+        {
+            return Value::String(var.to_string());
+        }
+        if let Some(Ok(value)) = external_value(s) {
+            return value;
         }
     }
     value.clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use serde_json::json;
+
+    use super::{anchor_file_refs, check_external_refs, json_path, param_index, resolve_value};
+
+    #[test]
+    fn resolves_env_and_file_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let bytes = dir.path().join("bytes.json");
+        let text = dir.path().join("pubkey.txt");
+        fs::write(&bytes, "[1, 2, 3]\n").unwrap();
+        fs::write(&text, "11111111111111111111111111111111\n").unwrap();
+
+        let file = |path: &std::path::Path| json!(format!("${{file:{}}}", path.display()));
+        assert_eq!(resolve_value(&file(&bytes), &[]), json!([1, 2, 3]));
+        assert_eq!(
+            resolve_value(&file(&text), &[]),
+            json!("11111111111111111111111111111111")
+        );
+        let path = std::env::var("PATH").unwrap();
+        assert_eq!(resolve_value(&json!("${env:PATH}"), &[]), json!(path));
+        let param = ["${env:PATH}".to_string()];
+        assert_eq!(resolve_value(&json!("$1"), &param), json!(path));
+        assert!(check_external_refs(&json!({ "a": [file(&bytes)] }), &param).is_ok());
+
+        let missing = json!("${env:SOLTNET_UNSET_TEST_VAR}");
+        let error = check_external_refs(&json!({ "data": [missing] }), &[]).unwrap_err();
+        assert!(
+            error.to_string().contains("SOLTNET_UNSET_TEST_VAR"),
+            "{error}"
+        );
+        let missing_file = file(&dir.path().join("missing.json"));
+        assert!(check_external_refs(&missing_file, &[]).is_err());
+        assert!(
+            check_external_refs(&json!("$1"), &["${env:SOLTNET_UNSET_TEST_VAR}".into()]).is_err()
+        );
+    }
+
+    #[test]
+    fn file_refs_are_relative_to_the_template() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("amount.txt"), "42\n").unwrap();
+        let mut template = json!({ "data": ["${file:amount.txt}", "${file:/abs/path}"] });
+        anchor_file_refs(&mut template, dir.path());
+        assert_eq!(resolve_value(&template["data"][0], &[]), json!("42"));
+        assert_eq!(template["data"][1], json!("${file:/abs/path}"));
+    }
+
+    #[test]
+    fn looks_up_json_paths() {
+        let parsed = json!({
+            "instructions": [{ "accounts": [{ "pubkey": "a" }, { "pubkey": "b" }] }],
+            "matrix": [[1, 2], [3, 4]]
+        });
+        let at = |path| json_path(&parsed, path).cloned();
+        assert_eq!(at("instructions[0].accounts[1].pubkey"), Some(json!("b")));
+        assert_eq!(at("matrix[1][0]"), Some(json!(3)));
+        assert_eq!(at("instructions[1]"), None);
+        assert_eq!(at("instructions[x]"), None);
+    }
+
+    #[test]
+    fn parses_params_and_escapes() {
+        assert_eq!(param_index("$1"), Some(0));
+        assert_eq!(param_index("$10"), Some(9));
+        assert_eq!(param_index("$123"), Some(122));
+        for value in ["$0", "$01", "$+1", "$1a", "$", "1", "$$1"] {
+            assert_eq!(param_index(value), None, "{value}");
+        }
+
+        let params: Vec<String> = (1..=12).map(|n| format!("p{n}")).collect();
+        assert_eq!(resolve_value(&json!("$12"), &params), json!("p12"));
+        assert_eq!(resolve_value(&json!("$$1"), &params), json!("$1"));
+        assert_eq!(resolve_value(&json!("$$$1"), &params), json!("$$1"));
+        assert_eq!(
+            resolve_value(&json!("$$local:payer"), &params),
+            json!("$local:payer")
+        );
+        assert_eq!(resolve_value(&json!("$13"), &params), json!("$13"));
+    }
+}
@@ -10,12 +10,22 @@ use solana_transaction_status::{
 };
 
 use crate::accounts::{ASSOCIATED_TOKEN_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::packs::installed;
+use crate::tx_format::migrate::TEMPLATE_VERSION;
 
-fn decode_base58_to_hex(data: &str) -> Result<String> {
+/// Base58 RPC data as explicit hex `raw` data, which short strings cannot be
+/// mistaken for.
+fn decode_base58_to_raw(data: &str) -> Result<Value> {
     let bytes = bs58::decode(data)
         .into_vec()
         .map_err(|_| anyhow!("Invalid base58 data"))?;
-    Ok(format!("0x{}", hex::encode(bytes)))
+    Ok(json!({ "type": "raw", "encoding": "hex", "data": hex::encode(bytes) }))
+}
+
+/// Instruction data decoded with the layouts of installed packs.
+fn decode_with_packs(program_id: &str, data: &str) -> Option<Value> {
+    let bytes = bs58::decode(data).into_vec().ok()?;
+    installed()?.decode_instruction(program_id, &bytes)
 }
 
 #[derive(Debug, Clone)]
@@ -61,7 +71,7 @@ fn accounts_from_raw(message: &solana_transaction_status::UiRawMessage) -> Vec<A
 }
 
 fn find_ata_accounts(accounts: &[String]) -> Vec<Value> {
-    println!("Finding ATA accounts...");
+    say!("Finding ATA accounts...");
     let mut ata_accounts = Vec::new();
     for owner in accounts {
         for mint in accounts {
@@ -80,7 +90,7 @@ fn find_ata_accounts(accounts: &[String]) -> Vec<Value> {
             let (ata, _) = Pubkey::find_program_address(&seeds, &ASSOCIATED_TOKEN_PROGRAM_ID);
             let ata_str = ata.to_string();
             if accounts.contains(&ata_str) {
-                println!("Found ATA: {ata_str} for owner: {owner} and mint: {mint}");
+                say!("Found ATA: {ata_str} for owner: {owner} and mint: {mint}");
                 ata_accounts.push(json!({
                     "type": "ata",
                     "owner": owner,
@@ -102,30 +112,30 @@ pub fn parse_native_program(program_id: &str, parsed: &Value) -> (Vec<String>, O
     let info = parsed.get("info");
 
     if program_id == SYSTEM_PROGRAM_ID.to_string() {
-        if parsed_type == Some("transfer") {
-            if let Some(info) = info.and_then(Value::as_object) {
-                let lamports = info.get("lamports").cloned().unwrap_or(Value::Null);
-                let source = info
-                    .get("source")
-                    .and_then(Value::as_str)
-                    .unwrap_or("")
-                    .to_string();
-                let destination = info
-                    .get("destination")
-                    .and_then(Value::as_str)
-                    .unwrap_or("")
-                    .to_string();
-                return (
-                    vec![source, destination],
-                    Some(json!({
-                        "type": "object",
-                        "data": [
-                            {"type": "u32", "data": 2},
-                            {"type": "u64", "data": lamports}
-                        ]
-                    })),
-                );
-            }
+        if parsed_type == Some("transfer")
+            && let Some(info) = info.and_then(Value::as_object)
+        {
+            let lamports = info.get("lamports").cloned().unwrap_or(Value::Null);
+            let source = info
+                .get("source")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string();
+            let destination = info
+                .get("destination")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_string();
+            return (
+                vec![source, destination],
+                Some(json!({
+                    "type": "object",
+                    "data": [
+                        {"type": "u32", "data": 2},
+                        {"type": "u64", "data": lamports}
+                    ]
+                })),
+            );
         }
         let accounts = info
             .and_then(Value::as_object)
@@ -193,6 +203,15 @@ pub fn parse_tx_to_json(raw_tx: &EncodedConfirmedTransactionWithStatusMeta) -> R
             ),
         };
 
+    let lookup_tables: Vec<String> = match &transaction.message {
+        UiMessage::Parsed(msg) => msg.address_table_lookups.as_ref(),
+        UiMessage::Raw(msg) => msg.address_table_lookups.as_ref(),
+    }
+    .into_iter()
+    .flatten()
+    .map(|lookup| lookup.account_key.clone())
+    .collect();
+
     let signers_accounts: Vec<String> = account_infos
         .iter()
         .filter(|k| k.signer)
@@ -204,7 +223,7 @@ pub fn parse_tx_to_json(raw_tx: &EncodedConfirmedTransactionWithStatusMeta) -> R
         .map(|k| k.pubkey.clone())
         .collect();
 
-    println!("Signers accounts: {}", signers_accounts.join(", "));
+    say!("Signers accounts: {}", signers_accounts.join(", "));
 
     let accounts: Vec<String> = account_infos.iter().map(|k| k.pubkey.clone()).collect();
     let ata_accounts = find_ata_accounts(&accounts);
@@ -249,10 +268,13 @@ pub fn parse_tx_to_json(raw_tx: &EncodedConfirmedTransactionWithStatusMeta) -> R
             ),
         };
 
-        println!("Parsing instruction for program {}...", program_id);
+        say!("Parsing instruction for program {}...", program_id);
 
         if let Value::String(s) = &data {
-            data = Value::String(decode_base58_to_hex(s)?);
+            data = match decode_with_packs(&program_id, s) {
+                Some(decoded) => decoded,
+                None => decode_base58_to_raw(s)?,
+            };
         }
 
         let mut accounts_output = Vec::new();
@@ -273,15 +295,14 @@ pub fn parse_tx_to_json(raw_tx: &EncodedConfirmedTransactionWithStatusMeta) -> R
                 if let Some(index) = signers_accounts.iter().position(|x| x == pk) {
                     pubkey_value = Value::String(format!("${}", index + 1));
                 }
-            } else if let Value::Object(map) = &mut pubkey_value {
-                if let Some(owner) = map.get("owner").and_then(Value::as_str) {
-                    if let Some(index) = signers_accounts.iter().position(|x| x == owner) {
-                        map.insert(
-                            "owner".to_string(),
-                            Value::String(format!("${}", index + 1)),
-                        );
-                    }
-                }
+            } else if let Value::Object(map) = &mut pubkey_value
+                && let Some(owner) = map.get("owner").and_then(Value::as_str)
+                && let Some(index) = signers_accounts.iter().position(|x| x == owner)
+            {
+                map.insert(
+                    "owner".to_string(),
+                    Value::String(format!("${}", index + 1)),
+                );
             }
 
             accounts_output.push(json!({
@@ -309,8 +330,14 @@ pub fn parse_tx_to_json(raw_tx: &EncodedConfirmedTransactionWithStatusMeta) -> R
         .map(|(index, _)| Value::String(format!("${}", signers_accounts.len() + index + 1)))
         .collect();
 
-    Ok(json!({
+    let mut tx_json = json!({
         "instructions": instructions_json,
         "signers": signers_json,
-    }))
+        "version": TEMPLATE_VERSION,
+    });
+    // The original tables, so `exec-tx --auto-alt` can recreate them locally.
+    if !lookup_tables.is_empty() {
+        tx_json["lookup_tables"] = json!(lookup_tables);
+    }
+    Ok(tx_json)
 }
@@ -1,13 +1,25 @@
+#[cfg(feature = "cli")]
+use std::process::Command;
 use std::str::FromStr;
 
-use anyhow::{Result, anyhow};
-use serde_json::Value;
-use solana_sdk::pubkey::Pubkey;
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Map, Value};
+use solana_sdk::{
+    pubkey::{MAX_SEED_LEN, MAX_SEEDS, Pubkey},
+    signer::Signer,
+};
 
 use crate::accounts::{
-    ASSOCIATED_TOKEN_PROGRAM_ID, COMPUTE_BUDGET_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
+    ASSOCIATED_TOKEN_PROGRAM_ID, COMPUTE_BUDGET_PROGRAM_ID, METADATA_PROGRAM_ID, SYSTEM_PROGRAM_ID,
+    SYSVARS, TOKEN_PROGRAM_ID, sysvar_id,
+};
+#[cfg(feature = "cli")]
+use crate::packs::installed;
+use crate::tx_format::{
+    data_format::pack_data,
+    json_tx::parse_keypair,
+    params::{ESCAPED_DOLLAR, resolve_value},
 };
-use crate::tx_format::params::resolve_value;
 
 pub fn parse_pubkey(value: &Value, params: &[String]) -> Result<Pubkey> {
     match value {
@@ -31,6 +43,59 @@ pub fn parse_pubkey(value: &Value, params: &[String]) -> Result<Pubkey> {
                         Pubkey::find_program_address(&seeds, &ASSOCIATED_TOKEN_PROGRAM_ID);
                     Ok(ata)
                 }
+                "metadata" | "master_edition" => {
+                    let mint = map
+                        .get("mint")
+                        .ok_or_else(|| anyhow!("Missing mint for {kind}"))?;
+                    let mint = parse_pubkey(mint, params)?;
+                    let mut seeds = vec![
+                        b"metadata".as_slice(),
+                        METADATA_PROGRAM_ID.as_ref(),
+                        mint.as_ref(),
+                    ];
+                    if kind == "master_edition" {
+                        seeds.push(b"edition");
+                    }
+                    let (address, _) = Pubkey::find_program_address(&seeds, &METADATA_PROGRAM_ID);
+                    Ok(address)
+                }
+                "keypair" => {
+                    let path = map
+                        .get("path")
+                        .ok_or_else(|| anyhow!("Missing path for keypair pubkey"))?;
+                    Ok(parse_keypair(path, params)?.pubkey())
+                }
+                "signer" => Err(anyhow!(
+                    "Signer pubkeys can only be used as instruction accounts"
+                )),
+                "pda" => Ok(find_pda(map, params)?.0),
+                "sysvar" => {
+                    let name = map
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| anyhow!("Missing name for sysvar"))?;
+                    sysvar_id(name).ok_or_else(|| {
+                        let names: Vec<&str> = SYSVARS.iter().map(|(name, _)| *name).collect();
+                        anyhow!("Unknown sysvar {name} (expected {})", names.join(", "))
+                    })
+                }
+                #[cfg(feature = "cli")]
+                "external" => {
+                    let cmd = map
+                        .get("cmd")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| anyhow!("Missing cmd for external pubkey"))?;
+                    let args = map.get("args").and_then(Value::as_array);
+                    let args: Vec<String> = args
+                        .into_iter()
+                        .flatten()
+                        .map(|arg| match resolve_value(arg, params) {
+                            Value::String(text) => text,
+                            other => other.to_string(),
+                        })
+                        .collect();
+                    run_resolver(cmd, &args)
+                }
                 "compute_budget_program" => Ok(COMPUTE_BUDGET_PROGRAM_ID),
                 "system_program" => Ok(SYSTEM_PROGRAM_ID),
                 "token_program" => Ok(TOKEN_PROGRAM_ID),
@@ -40,22 +105,114 @@ pub fn parse_pubkey(value: &Value, params: &[String]) -> Result<Pubkey> {
         }
         Value::String(_) => {
             let resolved = resolve_value(value, params);
-            if resolved != *value {
+            let escaped = value
+                .as_str()
+                .is_some_and(|text| text.starts_with(ESCAPED_DOLLAR));
+            if resolved != *value && !escaped {
                 return parse_pubkey(&resolved, params);
             }
             let s = resolved
                 .as_str()
                 .ok_or_else(|| anyhow!("Invalid pubkey value"))?;
-            Pubkey::from_str(s).map_err(|err| anyhow!("Invalid pubkey {s}: {err}"))
+            parse_pubkey_str(s)
         }
         _ => Err(anyhow!("Unsupported pubkey value")),
     }
 }
 
+/// Seed of a `pda` pubkey: `string` seeds are UTF-8 text, other seeds are
+/// packed like instruction data.
+fn seed_bytes(seed: &Value, params: &[String]) -> Result<Vec<u8>> {
+    if let Value::Object(map) = seed
+        && map.get("type").and_then(Value::as_str) == Some("string")
+    {
+        let data = map
+            .get("data")
+            .ok_or_else(|| anyhow!("Missing data for string seed"))?;
+        return match resolve_value(data, params) {
+            Value::String(text) => Ok(text.into_bytes()),
+            _ => Err(anyhow!("String seed must be a string")),
+        };
+    }
+    pack_data(seed, params)
+}
+
+/// Address and bump of a `pda` object (`program` and `seeds`), also used by
+/// the `pda_bump` data type.
+pub fn find_pda(map: &Map<String, Value>, params: &[String]) -> Result<(Pubkey, u8)> {
+    let program = map
+        .get("program")
+        .ok_or_else(|| anyhow!("Missing program for pda"))?;
+    let program = parse_pubkey(program, params)?;
+    let seeds = map
+        .get("seeds")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("Missing seeds for pda"))?;
+    if seeds.len() >= MAX_SEEDS {
+        return Err(anyhow!("A pda takes at most {} seeds", MAX_SEEDS - 1));
+    }
+    let mut packed = Vec::with_capacity(seeds.len());
+    for (index, seed) in seeds.iter().enumerate() {
+        let bytes =
+            seed_bytes(seed, params).with_context(|| format!("Invalid pda seed {}", index + 1))?;
+        if bytes.len() > MAX_SEED_LEN {
+            return Err(anyhow!(
+                "Pda seed {} is {} bytes, the limit is {MAX_SEED_LEN}",
+                index + 1,
+                bytes.len()
+            ));
+        }
+        packed.push(bytes);
+    }
+    let seeds: Vec<&[u8]> = packed.iter().map(Vec::as_slice).collect();
+    Pubkey::try_find_program_address(&seeds, &program)
+        .ok_or_else(|| anyhow!("No pda found for program {program}"))
+}
+
+/// Runs an external resolver (no shell) and parses the base58 pubkey it
+/// prints on stdout.
+#[cfg(feature = "cli")]
+fn run_resolver(cmd: &str, args: &[String]) -> Result<Pubkey> {
+    let output = Command::new(cmd)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run pubkey resolver `{cmd}`"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Pubkey resolver `{cmd}` exited with status {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Pubkey::from_str(stdout.trim())
+        .map_err(|err| anyhow!("Pubkey resolver `{cmd}` printed an invalid pubkey: {err}"))
+}
+
+/// Parses a base58 pubkey or an alias of an installed pack.
+pub fn parse_pubkey_str(value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value)
+        .or_else(|err| pack_alias(value).ok_or_else(|| anyhow!("Invalid pubkey {value}: {err}")))
+}
+
+#[cfg(feature = "cli")]
+fn pack_alias(name: &str) -> Option<Pubkey> {
+    installed().and_then(|packs| packs.alias(name))
+}
+
+/// Packs are loaded from disk, so the core has no aliases.
+#[cfg(not(feature = "cli"))]
+fn pack_alias(_name: &str) -> Option<Pubkey> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use super::parse_pubkey;
     use crate::accounts::{ASSOCIATED_TOKEN_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID};
+    use crate::tx_format::{data_format::pack_data, params::set_local_vars};
     use serde_json::json;
     use solana_sdk::pubkey::Pubkey;
 
@@ -66,6 +223,14 @@ mod tests {
         assert_eq!(pk, SYSTEM_PROGRAM_ID);
     }
 
+    #[test]
+    fn parse_pubkey_resolves_local_vars() {
+        let payer = Pubkey::new_unique();
+        set_local_vars(HashMap::from([("payer".to_string(), payer.to_string())]));
+        assert_eq!(parse_pubkey(&json!("$local:payer"), &[]).unwrap(), payer);
+        assert!(parse_pubkey(&json!("$local:unknown"), &[]).is_err());
+    }
+
     #[test]
     fn parse_pubkey_ata_matches_pda() {
         let owner = Pubkey::new_unique();
@@ -80,4 +245,71 @@ mod tests {
         let derived = parse_pubkey(&value, &[]).expect("ata");
         assert_eq!(derived, expected);
     }
+
+    #[cfg(all(unix, feature = "cli"))]
+    #[test]
+    fn parse_pubkey_runs_external_resolvers() {
+        let expected = Pubkey::new_unique();
+        let value = json!({ "type": "external", "cmd": "echo", "args": ["$1"] });
+        let resolved = parse_pubkey(&value, &[expected.to_string()]).unwrap();
+        assert_eq!(resolved, expected);
+        assert!(parse_pubkey(&json!({ "type": "external", "cmd": "false" }), &[]).is_err());
+    }
+
+    #[test]
+    fn parse_pubkey_derives_pdas() {
+        let program = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let value = json!({
+            "type": "pda",
+            "program": program.to_string(),
+            "seeds": [
+                { "type": "string", "data": "vault" },
+                { "type": "pubkey", "data": "$1" },
+                { "type": "u64", "data": 7 }
+            ]
+        });
+        let (expected, bump) =
+            Pubkey::find_program_address(&[b"vault", user.as_ref(), &7u64.to_le_bytes()], &program);
+        let params = [user.to_string()];
+        assert_eq!(parse_pubkey(&value, &params).unwrap(), expected);
+        let mut bump_data = value.clone();
+        bump_data["type"] = json!("pda_bump");
+        assert_eq!(pack_data(&bump_data, &params).unwrap(), vec![bump]);
+
+        let long = json!({ "type": "pda", "program": program.to_string(), "seeds": [vec![0; 33]] });
+        assert!(parse_pubkey(&long, &[]).is_err());
+    }
+
+    #[test]
+    fn parse_pubkey_names_sysvars() {
+        use solana_sdk::sysvar;
+
+        let sysvar = |name| parse_pubkey(&json!({ "type": "sysvar", "name": name }), &[]);
+        assert_eq!(sysvar("clock").unwrap(), sysvar::clock::ID);
+        assert_eq!(sysvar("rent").unwrap(), sysvar::rent::ID);
+        assert_eq!(sysvar("instructions").unwrap(), sysvar::instructions::ID);
+        assert_eq!(sysvar("slot_hashes").unwrap(), sysvar::slot_hashes::ID);
+        assert_eq!(
+            sysvar("epoch_schedule").unwrap(),
+            sysvar::epoch_schedule::ID
+        );
+        assert!(sysvar("clocks").is_err());
+    }
+
+    #[test]
+    fn parse_pubkey_derives_metaplex_accounts() {
+        let mint = "F9Lw3ki3hJKVU9FP4fbmC8ri4gxygHsm5kq8xYazoFBR";
+        let metadata = json!({ "type": "metadata", "mint": mint });
+        let edition = json!({ "type": "master_edition", "mint": "$1" });
+        let metadata = parse_pubkey(&metadata, &[]).unwrap();
+        let edition = parse_pubkey(&edition, &[mint.to_string()]).unwrap();
+        let program = Pubkey::from_str_const("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+        let mint = Pubkey::from_str_const(mint);
+        let seeds = [b"metadata".as_slice(), program.as_ref(), mint.as_ref()];
+        assert_eq!(metadata, Pubkey::find_program_address(&seeds, &program).0);
+        let seeds = [seeds[0], seeds[1], seeds[2], b"edition"];
+        assert_eq!(edition, Pubkey::find_program_address(&seeds, &program).0);
+        assert_ne!(metadata, edition);
+    }
 }
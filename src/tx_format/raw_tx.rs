@@ -2,14 +2,15 @@ use serde_json::json;
 
 use crate::{
     accounts::{
-        ASSOCIATED_TOKEN_PROGRAM_ID, COMPUTE_BUDGET_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
+        ASSOCIATED_TOKEN_PROGRAM_ID, COMPUTE_BUDGET_PROGRAM_ID, METADATA_PROGRAM_ID,
+        RENT_SYSVAR_ID, SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
     },
     tx_format::{RawAccountMeta, RawInstruction},
 };
 
 pub fn set_cu_limit_tx(limit: u32) -> RawInstruction {
     RawInstruction {
-        program_id: COMPUTE_BUDGET_PROGRAM_ID.to_string(),
+        program_id: COMPUTE_BUDGET_PROGRAM_ID.to_string().into(),
         accounts: Vec::new(),
         data: json!({
             "type": "object",
@@ -22,12 +23,32 @@ pub fn set_cu_limit_tx(limit: u32) -> RawInstruction {
     }
 }
 
+/// Compute unit price (priority fee) in micro-lamports per CU.
+pub fn set_cu_price_tx(micro_lamports: u64) -> RawInstruction {
+    RawInstruction {
+        program_id: COMPUTE_BUDGET_PROGRAM_ID.to_string().into(),
+        accounts: Vec::new(),
+        data: json!({
+            "type": "object",
+            "data": [
+                {"type": "u8", "data": 3},
+                {"type": "u64", "data": micro_lamports}
+            ]
+        }),
+        extra: serde_json::Map::new(),
+    }
+}
+
 pub fn create_ata_tx(owner: &str, mint: &str) -> RawInstruction {
+    create_ata_with_payer_tx(owner, owner, mint)
+}
+
+pub fn create_ata_with_payer_tx(payer: &str, owner: &str, mint: &str) -> RawInstruction {
     RawInstruction {
-        program_id: ASSOCIATED_TOKEN_PROGRAM_ID.to_string(),
+        program_id: ASSOCIATED_TOKEN_PROGRAM_ID.to_string().into(),
         accounts: vec![
             RawAccountMeta {
-                pubkey: json!(owner),
+                pubkey: json!(payer),
                 is_signer: true,
                 is_writable: true,
             },
@@ -42,8 +63,8 @@ pub fn create_ata_tx(owner: &str, mint: &str) -> RawInstruction {
             },
             RawAccountMeta {
                 pubkey: json!(owner),
-                is_signer: true,
-                is_writable: true,
+                is_signer: payer == owner,
+                is_writable: payer == owner,
             },
             RawAccountMeta {
                 pubkey: json!(mint),
@@ -68,7 +89,7 @@ pub fn create_ata_tx(owner: &str, mint: &str) -> RawInstruction {
 
 pub fn close_ata_tx(owner: &str, mint: &str) -> RawInstruction {
     RawInstruction {
-        program_id: TOKEN_PROGRAM_ID.to_string(),
+        program_id: TOKEN_PROGRAM_ID.to_string().into(),
         accounts: vec![
             RawAccountMeta {
                 pubkey: json!({
@@ -100,7 +121,7 @@ pub fn close_ata_tx(owner: &str, mint: &str) -> RawInstruction {
 
 pub fn transfer_tx(from: &str, to: &str, amount: &serde_json::Value) -> RawInstruction {
     RawInstruction {
-        program_id: SYSTEM_PROGRAM_ID.to_string(),
+        program_id: SYSTEM_PROGRAM_ID.to_string().into(),
         data: json!({
             "type": "object",
             "data": [
@@ -123,3 +144,162 @@ pub fn transfer_tx(from: &str, to: &str, amount: &serde_json::Value) -> RawInstr
         extra: serde_json::Map::new(),
     }
 }
+
+fn account(pubkey: serde_json::Value, is_signer: bool, is_writable: bool) -> RawAccountMeta {
+    RawAccountMeta {
+        pubkey,
+        is_signer,
+        is_writable,
+    }
+}
+
+fn borsh_string(value: &str) -> serde_json::Value {
+    json!({
+        "type": "object",
+        "data": [
+            {"type": "u32", "data": value.len()},
+            {"type": "bytes", "data": format!("0x{}", hex::encode(value))}
+        ]
+    })
+}
+
+pub fn create_account_tx(
+    from: &str,
+    to: &str,
+    lamports: u64,
+    space: u64,
+    owner: &str,
+) -> RawInstruction {
+    RawInstruction {
+        program_id: SYSTEM_PROGRAM_ID.to_string().into(),
+        data: json!({
+            "type": "object",
+            "data": [
+                {"type": "u32", "data": 0},
+                {"type": "u64", "data": lamports},
+                {"type": "u64", "data": space},
+                {"type": "pubkey", "data": owner}
+            ]
+        }),
+        accounts: vec![
+            account(json!(from), true, true),
+            account(json!(to), true, true),
+        ],
+        extra: serde_json::Map::new(),
+    }
+}
+
+pub fn initialize_mint_tx(mint: &str, decimals: u8, authority: &str) -> RawInstruction {
+    RawInstruction {
+        program_id: TOKEN_PROGRAM_ID.to_string().into(),
+        data: json!({
+            "type": "object",
+            "data": [
+                {"type": "u8", "data": 20},
+                {"type": "u8", "data": decimals},
+                {"type": "pubkey", "data": authority},
+                {"type": "u8", "data": 0}
+            ]
+        }),
+        accounts: vec![account(json!(mint), false, true)],
+        extra: serde_json::Map::new(),
+    }
+}
+
+pub fn mint_to_tx(mint: &str, owner: &str, authority: &str, amount: u64) -> RawInstruction {
+    RawInstruction {
+        program_id: TOKEN_PROGRAM_ID.to_string().into(),
+        data: json!({
+            "type": "object",
+            "data": [
+                {"type": "u8", "data": 7},
+                {"type": "u64", "data": amount}
+            ]
+        }),
+        accounts: vec![
+            account(json!(mint), false, true),
+            account(
+                json!({
+                    "type": "ata",
+                    "owner": owner,
+                    "mint": mint
+                }),
+                false,
+                true,
+            ),
+            account(json!(authority), true, false),
+        ],
+        extra: serde_json::Map::new(),
+    }
+}
+
+pub fn create_metadata_tx(
+    metadata: &str,
+    mint: &str,
+    authority: &str,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+) -> RawInstruction {
+    RawInstruction {
+        program_id: METADATA_PROGRAM_ID.to_string().into(),
+        // CreateMetadataAccountV3 with no creators, collection, uses or collection details.
+        data: json!({
+            "type": "object",
+            "data": [
+                {"type": "u8", "data": 33},
+                borsh_string(name),
+                borsh_string(symbol),
+                borsh_string(uri),
+                {"type": "u16", "data": 0},
+                {"type": "u8", "data": 0},
+                {"type": "u8", "data": 0},
+                {"type": "u8", "data": 0},
+                {"type": "u8", "data": 1},
+                {"type": "u8", "data": 0}
+            ]
+        }),
+        accounts: vec![
+            account(json!(metadata), false, true),
+            account(json!(mint), false, false),
+            account(json!(authority), true, false),
+            account(json!(authority), true, true),
+            account(json!(authority), true, false),
+            account(json!(SYSTEM_PROGRAM_ID.to_string()), false, false),
+            account(json!(RENT_SYSVAR_ID.to_string()), false, false),
+        ],
+        extra: serde_json::Map::new(),
+    }
+}
+
+pub fn create_master_edition_tx(
+    edition: &str,
+    metadata: &str,
+    mint: &str,
+    authority: &str,
+) -> RawInstruction {
+    RawInstruction {
+        program_id: METADATA_PROGRAM_ID.to_string().into(),
+        // CreateMasterEditionV3 with max_supply = Some(0).
+        data: json!({
+            "type": "object",
+            "data": [
+                {"type": "u8", "data": 17},
+                {"type": "u8", "data": 1},
+                {"type": "u64", "data": 0}
+            ]
+        }),
+        accounts: vec![
+            account(json!(edition), false, true),
+            account(json!(mint), false, true),
+            account(json!(authority), true, false),
+            account(json!(authority), true, false),
+            account(json!(authority), true, true),
+            account(json!(metadata), false, true),
+            account(json!(TOKEN_PROGRAM_ID.to_string()), false, false),
+            account(json!(SYSTEM_PROGRAM_ID.to_string()), false, false),
+            account(json!(RENT_SYSVAR_ID.to_string()), false, false),
+        ],
+        extra: serde_json::Map::new(),
+    }
+}
@@ -0,0 +1,121 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+
+use crate::tx_format::{camel_key, snake_key};
+use crate::utils::STAMP_KEY;
+
+static STRICT: AtomicBool = AtomicBool::new(false);
+
+/// Top-level fields of a template.
+pub const TEMPLATE_FIELDS: [&str; 6] = [
+    "instructions",
+    "signers",
+    "lookup_tables",
+    "hooks",
+    "version",
+    STAMP_KEY,
+];
+
+/// Fields of every instruction; `depends_on` is read by `split-tx`.
+const INSTRUCTION_FIELDS: [&str; 4] = ["program_id", "data", "accounts", "depends_on"];
+
+const ACCOUNT_FIELDS: [&str; 3] = ["pubkey", "is_signer", "is_writable"];
+
+/// Makes loading a template fail on fields soltnet does not read.
+pub fn set_strict(strict: bool) {
+    STRICT.store(strict, Ordering::Relaxed);
+}
+
+pub fn is_strict() -> bool {
+    STRICT.load(Ordering::Relaxed)
+}
+
+/// Fields read by a shorthand instruction besides `program_id`.
+fn shorthand_fields(program_id: &str) -> &'static [&'static str] {
+    match program_id {
+        "set_cu_limit" => &["limit"],
+        "set_cu_price" => &["micro_lamports"],
+        "transfer" => &["from", "to", "amount"],
+        "create_ata" | "close_ata" => &["owner", "mint"],
+        _ => &[],
+    }
+}
+
+fn push_unknown(value: &Value, known: &[&str], prefix: &str, unknown: &mut Vec<String>) {
+    let keys = value.as_object().into_iter().flat_map(|map| map.keys());
+    for key in keys.filter(|key| !known.contains(&snake_key(key))) {
+        unknown.push(format!("{prefix}{key}"));
+    }
+}
+
+/// Field `key` (snake_case) of `value` in either casing, `Null` if absent.
+fn field<'a>(value: &'a Value, key: &str) -> &'a Value {
+    value
+        .get(key)
+        .or_else(|| value.get(camel_key(key)))
+        .unwrap_or(&Value::Null)
+}
+
+/// Paths of the fields of a transaction soltnet does not read, e.g.
+/// `instructions[0].accounts[1].isSinger`.
+pub fn unknown_fields(tx: &Value) -> Vec<String> {
+    let mut unknown = Vec::new();
+    push_unknown(tx, &TEMPLATE_FIELDS, "", &mut unknown);
+    let instructions = field(tx, "instructions").as_array().into_iter().flatten();
+    for (index, ix) in instructions.enumerate() {
+        let program_id = field(ix, "program_id").as_str().unwrap_or_default();
+        let known = [&INSTRUCTION_FIELDS[..], shorthand_fields(program_id)].concat();
+        let prefix = format!("instructions[{index}].");
+        push_unknown(ix, &known, &prefix, &mut unknown);
+        let accounts = field(ix, "accounts").as_array().into_iter().flatten();
+        for (position, account) in accounts.enumerate() {
+            let prefix = format!("{prefix}accounts[{position}].");
+            push_unknown(account, &ACCOUNT_FIELDS, &prefix, &mut unknown);
+        }
+    }
+    unknown
+}
+
+/// Fails with the offending keys if `tx` has fields soltnet does not read.
+pub fn check_fields(tx: &Value) -> Result<()> {
+    let unknown = unknown_fields(tx);
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!("Unknown template fields: {}", unknown.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::unknown_fields;
+
+    #[test]
+    fn lists_unknown_fields_outside_shorthands() {
+        let tx = json!({
+            "instructions": [
+                { "program_id": "transfer", "from": "$1", "to": "$2", "amount": 1, "memo": "x" },
+                { "programId": "set_cu_price", "microLamports": 5 },
+                {
+                    "program_id": "11111111111111111111111111111111",
+                    "accounts": [{ "pubkey": "$1", "isSinger": true }],
+                    "depends_on": 0
+                }
+            ],
+            "signers": [],
+            "lookupTables": [],
+            "singers": []
+        });
+        assert_eq!(
+            unknown_fields(&tx),
+            [
+                "singers",
+                "instructions[0].memo",
+                "instructions[2].accounts[0].isSinger"
+            ]
+        );
+    }
+}
@@ -1,3 +1,105 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    io::{self, Read},
+    path::Path,
+    sync::{Mutex, OnceLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde_json::{Value, json};
+
+/// Input or output path standing for stdin or stdout.
+pub const STDIO_PATH: &str = "-";
+
+static STDIN: OnceLock<Vec<u8>> = OnceLock::new();
+
+pub fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO_PATH)
+}
+
+/// Everything piped into stdin, read on first use so that every load of `-`
+/// sees the same input.
+pub fn stdin_bytes() -> Result<&'static [u8]> {
+    if let Some(bytes) = STDIN.get() {
+        return Ok(bytes);
+    }
+    let mut bytes = Vec::new();
+    io::stdin()
+        .lock()
+        .read_to_end(&mut bytes)
+        .context("failed to read stdin")?;
+    Ok(STDIN.get_or_init(|| bytes))
+}
+
+/// Reads a text file, or stdin for `-`.
+pub fn read_input(path: &Path) -> Result<String> {
+    if is_stdio(path) {
+        return String::from_utf8(stdin_bytes()?.to_vec()).context("stdin is not UTF-8");
+    }
+    fs::read_to_string(path).with_context(|| format!("Error reading file {path:?}"))
+}
+
+/// Key of the header stamped into parse and dump artifacts.
+pub const STAMP_KEY: &str = "_soltnet";
+
+/// Stamped artifacts older than this are reported as possibly stale.
+pub const STALE_AFTER: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Header of an artifact captured from `source`: the soltnet version, the
+/// slot and blockhash it reflects and when it was written (unix seconds).
+pub fn stamp(source: &str, slot: Option<u64>, blockhash: Option<&str>) -> Value {
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "source": source,
+        "slot": slot,
+        "blockhash": blockhash,
+        "created_at": unix_time(),
+    })
+}
+
+/// `value` without its stamp, for comparing artifacts captured at different
+/// times.
+pub fn without_stamp(value: &Value) -> Value {
+    let mut value = value.clone();
+    if let Some(map) = value.as_object_mut() {
+        map.remove(STAMP_KEY);
+    }
+    value
+}
+
+/// Time since the stamp of `value` was written, if it has one.
+pub fn stamp_age(value: &Value) -> Option<Duration> {
+    let created = value.get(STAMP_KEY)?.get("created_at")?.as_u64()?;
+    Some(Duration::from_secs(unix_time().saturating_sub(created)))
+}
+
+/// Warns, once per artifact, that `value` was stamped more than
+/// [`STALE_AFTER`] ago.
+pub fn warn_if_stale(value: &Value, label: &str) {
+    static WARNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    let Some(age) = stamp_age(value).filter(|age| *age > STALE_AFTER) else {
+        return;
+    };
+    if !WARNED.lock().unwrap().insert(label.to_string()) {
+        return;
+    }
+    let stamp = &value[STAMP_KEY];
+    eprintln!(
+        "Warning: {label} was captured {} days ago from {} at slot {}; the state it reflects may have changed",
+        age.as_secs() / 86_400,
+        stamp["source"].as_str().unwrap_or("an unknown source"),
+        stamp["slot"]
+    );
+}
+
 fn remove_underscores(s: &str) -> String {
     s.replace('_', "")
 }
@@ -43,9 +145,144 @@ pub fn format_amount<T: ToString>(value: T) -> String {
     format_amount_str(&value.to_string())
 }
 
+/// Parses a duration like `500ms`, `5s`, `2m` or `1h`; a bare number is seconds.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+    let value: f64 = value
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration: {input}"))?;
+    let seconds = match unit {
+        "ms" => value / 1000.0,
+        "" | "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => {
+            return Err(anyhow!(
+                "Invalid duration unit in {input} (use ms, s, m or h)"
+            ));
+        }
+    };
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Formats base units as a decimal UI amount with `decimals` places, trimming trailing zeros.
+pub fn format_ui_amount(amount: u64, decimals: u32) -> String {
+    let scale = 10u128.pow(decimals);
+    let whole = u128::from(amount) / scale;
+    let fraction = u128::from(amount) % scale;
+    let fraction = format!("{fraction:0width$}", width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format_amount(whole)
+    } else {
+        format_amount(format!("{whole}.{fraction}"))
+    }
+}
+
+/// Converts a decimal UI amount (e.g. `1.5`) into base units with `decimals` places.
+pub fn parse_ui_amount(input: &str, decimals: u32) -> Result<u64> {
+    let cleaned = remove_underscores(input.trim());
+    if cleaned.is_empty() {
+        return Err(anyhow!("Invalid amount: empty string"));
+    }
+    if cleaned.starts_with('-') {
+        return Err(anyhow!("Amount must be non-negative"));
+    }
+
+    let mut parts = cleaned.split('.');
+    let whole_str = parts.next().unwrap_or("");
+    let frac_str = parts.next().unwrap_or("");
+    if parts.next().is_some() {
+        return Err(anyhow!("Invalid amount: too many decimal points"));
+    }
+
+    if !whole_str.is_empty() && !whole_str.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("Invalid amount: {input}"));
+    }
+    if !frac_str.is_empty() && !frac_str.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("Invalid amount: {input}"));
+    }
+    if frac_str.len() > decimals as usize {
+        return Err(anyhow!("Invalid amount: max {decimals} decimal places"));
+    }
+
+    let whole: u64 = if whole_str.is_empty() {
+        0
+    } else {
+        whole_str.parse()?
+    };
+
+    let mut frac_padded = frac_str.to_string();
+    while frac_padded.len() < decimals as usize {
+        frac_padded.push('0');
+    }
+    let frac: u64 = if frac_padded.is_empty() {
+        0
+    } else {
+        frac_padded.parse()?
+    };
+
+    let amount = 10u64
+        .checked_pow(decimals)
+        .and_then(|unit| whole.checked_mul(unit))
+        .and_then(|v| v.checked_add(frac))
+        .ok_or_else(|| anyhow!("Amount is too large"))?;
+
+    Ok(amount)
+}
+
+fn collect_json_diff(expected: &Value, actual: &Value, path: &str, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (key, value) in left {
+                let child = format!("{path}.{key}");
+                match right.get(key) {
+                    Some(other) => collect_json_diff(value, other, &child, out),
+                    None => out.push(format!("- {child}: {value}")),
+                }
+            }
+            for (key, value) in right {
+                if !left.contains_key(key) {
+                    out.push(format!("+ {path}.{key}: {value}"));
+                }
+            }
+        }
+        (Value::Array(left), Value::Array(right)) => {
+            for idx in 0..left.len().max(right.len()) {
+                let child = format!("{path}[{idx}]");
+                match (left.get(idx), right.get(idx)) {
+                    (Some(a), Some(b)) => collect_json_diff(a, b, &child, out),
+                    (Some(a), None) => out.push(format!("- {child}: {a}")),
+                    (None, Some(b)) => out.push(format!("+ {child}: {b}")),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if expected != actual => out.push(format!("~ {path}: {expected} -> {actual}")),
+        _ => {}
+    }
+}
+
+/// Lists the differences between two JSON values as `-`/`+`/`~` lines keyed by path.
+pub fn json_diff(expected: &Value, actual: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    collect_json_diff(expected, actual, "$", &mut out);
+    out
+}
+
 #[cfg(test)]
 mod tests {
-    use super::format_amount;
+    use std::time::Duration;
+
+    use super::{
+        STALE_AFTER, STAMP_KEY, format_amount, format_ui_amount, json_diff, parse_duration,
+        parse_ui_amount, stamp, stamp_age,
+    };
+    use serde_json::json;
 
     #[test]
     fn format_amount_inserts_underscores() {
@@ -59,4 +296,53 @@ mod tests {
     fn format_amount_handles_fractional() {
         assert_eq!(format_amount("1234567.8901"), "1_234_567.8_901");
     }
+
+    #[test]
+    fn parse_ui_amount_scales_by_decimals() {
+        assert_eq!(parse_ui_amount("1", 9).unwrap(), 1_000_000_000);
+        assert_eq!(parse_ui_amount("1.5", 6).unwrap(), 1_500_000);
+        assert_eq!(parse_ui_amount("42", 0).unwrap(), 42);
+        assert!(parse_ui_amount("1.5", 0).is_err());
+    }
+
+    #[test]
+    fn format_ui_amount_trims_trailing_zeros() {
+        assert_eq!(format_ui_amount(1_500_000, 6), "1.5");
+        assert_eq!(format_ui_amount(2_000_000_000, 9), "2");
+        assert_eq!(format_ui_amount(1_234_000_001, 3), "1_234_000.001");
+    }
+
+    #[test]
+    fn parse_duration_accepts_units() {
+        assert_eq!(parse_duration("5s").unwrap(), Duration::from_secs(5));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("1.5").unwrap(), Duration::from_millis(1500));
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("s").is_err());
+    }
+
+    #[test]
+    fn json_diff_reports_paths() {
+        let expected = json!({"a": 1, "b": [1, 2], "c": "x"});
+        let actual = json!({"a": 2, "b": [1], "d": true, "c": "x"});
+        assert_eq!(
+            json_diff(&expected, &actual),
+            vec!["~ $.a: 1 -> 2", "- $.b[1]: 2", "+ $.d: true"]
+        );
+    }
+
+    #[test]
+    fn stamps_age_from_creation_time() {
+        let fresh = json!({ STAMP_KEY: stamp("mainnet", Some(7), Some("hash")) });
+        assert!(stamp_age(&fresh).unwrap() < STALE_AFTER);
+        assert_eq!(fresh[STAMP_KEY]["slot"], json!(7));
+        assert_eq!(
+            fresh[STAMP_KEY]["version"],
+            json!(env!("CARGO_PKG_VERSION"))
+        );
+        let old = json!({ STAMP_KEY: { "created_at": 0 } });
+        assert!(stamp_age(&old).unwrap() > STALE_AFTER);
+        assert_eq!(stamp_age(&json!({ "slot": 7 })), None);
+    }
 }